@@ -0,0 +1,61 @@
+use std::{convert::TryFrom, num::NonZeroUsize};
+
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use vector::{
+    config::log_schema,
+    event::{Event, LogEvent},
+    sinks::s3_common::partitioner::S3KeyPartitioner,
+    template::Template,
+};
+use vector_lib::partition::Partitioner;
+
+/// Events of a few services, such that consecutive events mostly share a partition.
+fn events() -> Vec<Event> {
+    let timestamp = Utc::now();
+    (0..1000)
+        .map(|i| {
+            let mut log = LogEvent::from("hello world");
+            log.insert("service", format!("service-{}", i / 250));
+            log.insert("region", "us-east-1");
+            log.insert(log_schema().timestamp_key_target_path().unwrap(), timestamp);
+            Event::Log(log)
+        })
+        .collect()
+}
+
+fn partitioner(cache_size: Option<NonZeroUsize>) -> S3KeyPartitioner {
+    let key_prefix =
+        Template::try_from("service={{ service }}/region={{ region }}/date=%F/hour=%H/").unwrap();
+    S3KeyPartitioner::new(key_prefix, None)
+        .with_lowercase_keys(true)
+        .with_key_cache(cache_size)
+}
+
+fn bench_partition_key_cache(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aws_s3/partition");
+    let events = events();
+
+    for (name, cache_size) in [("uncached", None), ("cached", NonZeroUsize::new(16))] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || partitioner(cache_size),
+                |partitioner| {
+                    for event in &events {
+                        criterion::black_box(partitioner.partition(event));
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().noise_threshold(0.05);
+    targets = bench_partition_key_cache
+);
+criterion_main!(benches);
@@ -1,5 +1,7 @@
+use std::{collections::hash_map::RandomState, hash::BuildHasher};
+
 use crate::encoding::BuildError;
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use serde::{Deserialize, Serialize};
 use tokio_util::codec::Encoder;
 use vector_config::configurable_component;
@@ -10,6 +12,13 @@ use vector_core::{config::DataType, event::Event, schema};
 pub struct AvroSerializerConfig {
     /// Options for the Avro serializer.
     pub avro: AvroSerializerOptions,
+
+    /// Whether or not to write events as the data blocks of an Avro Object Container File.
+    ///
+    /// This is not configurable through the codec, as only sinks writing the batch prefix of the
+    /// encoder, holding the header of the file, produce readable files.
+    #[serde(skip)]
+    object_container_file: bool,
 }
 
 impl AvroSerializerConfig {
@@ -17,14 +26,25 @@ impl AvroSerializerConfig {
     pub const fn new(schema: String) -> Self {
         Self {
             avro: AvroSerializerOptions { schema },
+            object_container_file: false,
         }
     }
 
+    /// Writes events as the data blocks of an Avro Object Container File, whose header is the batch
+    /// prefix of the encoder.
+    pub const fn with_object_container_file(mut self, object_container_file: bool) -> Self {
+        self.object_container_file = object_container_file;
+        self
+    }
+
     /// Build the `AvroSerializer` from this configuration.
     pub fn build(&self) -> Result<AvroSerializer, BuildError> {
         let schema = apache_avro::Schema::parse_str(&self.avro.schema)
             .map_err(|error| format!("Failed building Avro serializer: {}", error))?;
-        Ok(AvroSerializer { schema })
+        let container = self
+            .object_container_file
+            .then(|| ObjectContainer::new(&self.avro.schema));
+        Ok(AvroSerializer { schema, container })
     }
 
     /// The data type of events that are accepted by `AvroSerializer`.
@@ -51,16 +71,80 @@ pub struct AvroSerializerOptions {
     pub schema: String,
 }
 
+/// The header and sync marker of the Object Container Files written by a serializer.
+#[derive(Debug, Clone)]
+struct ObjectContainer {
+    header: Bytes,
+    sync_marker: [u8; 16],
+}
+
+impl ObjectContainer {
+    fn new(schema: &str) -> Self {
+        // The sync marker only needs to be unlikely to appear in the data blocks, so it is drawn
+        // from the randomly keyed hasher of the standard library.
+        let random = RandomState::new();
+        let mut sync_marker = [0; 16];
+        sync_marker[..8].copy_from_slice(&random.hash_one(schema).to_le_bytes());
+        sync_marker[8..].copy_from_slice(&random.hash_one(sync_marker).to_le_bytes());
+
+        let mut header = BytesMut::new();
+        header.put_slice(b"Obj\x01");
+        put_long(&mut header, 2);
+        for (key, value) in [
+            ("avro.codec", b"null".as_slice()),
+            ("avro.schema", schema.as_bytes()),
+        ] {
+            put_bytes(&mut header, key.as_bytes());
+            put_bytes(&mut header, value);
+        }
+        put_long(&mut header, 0);
+        header.put_slice(&sync_marker);
+
+        Self {
+            header: header.freeze(),
+            sync_marker,
+        }
+    }
+}
+
+/// Writes an Avro `long`, as a zigzag-encoded variable-length integer.
+fn put_long(buffer: &mut BytesMut, value: i64) {
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+    while value >= 0x80 {
+        buffer.put_u8(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.put_u8(value as u8);
+}
+
+/// Writes Avro `bytes`, as their length followed by the bytes themselves.
+fn put_bytes(buffer: &mut BytesMut, bytes: &[u8]) {
+    put_long(buffer, bytes.len() as i64);
+    buffer.put_slice(bytes);
+}
+
 /// Serializer that converts an `Event` to bytes using the Apache Avro format.
 #[derive(Debug, Clone)]
 pub struct AvroSerializer {
     schema: apache_avro::Schema,
+    container: Option<ObjectContainer>,
 }
 
 impl AvroSerializer {
     /// Creates a new `AvroSerializer`.
     pub const fn new(schema: apache_avro::Schema) -> Self {
-        Self { schema }
+        Self {
+            schema,
+            container: None,
+        }
+    }
+
+    /// The header of the Object Container File, embedding the schema, to write before the events
+    /// of each batch, if events are written as its data blocks.
+    pub fn container_header(&self) -> &[u8] {
+        self.container
+            .as_ref()
+            .map_or(&[], |container| &container.header)
     }
 }
 
@@ -72,7 +156,14 @@ impl Encoder<Event> for AvroSerializer {
         let value = apache_avro::to_value(log)?;
         let value = value.resolve(&self.schema)?;
         let bytes = apache_avro::to_avro_datum(&self.schema, value)?;
-        buffer.put_slice(&bytes);
+        match &self.container {
+            Some(container) => {
+                put_long(buffer, 1);
+                put_bytes(buffer, &bytes);
+                buffer.put_slice(&container.sync_marker);
+            }
+            None => buffer.put_slice(&bytes),
+        }
         Ok(())
     }
 }
@@ -112,4 +203,42 @@ mod tests {
 
         assert_eq!(bytes.freeze(), b"\0\x06bar".as_slice());
     }
+
+    #[test]
+    fn serialize_avro_object_container_file() {
+        let schema = indoc! {r#"
+            {
+                "type": "record",
+                "name": "Log",
+                "fields": [
+                    {
+                        "name": "foo",
+                        "type": "string"
+                    }
+                ]
+            }
+        "#}
+        .to_owned();
+        let config = AvroSerializerConfig::new(schema).with_object_container_file(true);
+        let mut serializer = config.build().unwrap();
+        let mut bytes = BytesMut::from(serializer.container_header());
+        for foo in ["bar", "baz"] {
+            let event = Event::Log(LogEvent::from(btreemap! {
+                "foo" => Value::from(foo)
+            }));
+            serializer.encode(event, &mut bytes).unwrap();
+        }
+
+        let reader = apache_avro::Reader::new(&bytes[..]).unwrap();
+        let values = reader
+            .map(|value| apache_avro::from_value::<serde_json::Value>(&value.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                serde_json::json!({ "foo": "bar" }),
+                serde_json::json!({ "foo": "baz" }),
+            ]
+        );
+    }
 }
@@ -0,0 +1,97 @@
+use bytes::{BufMut, BytesMut};
+use serde::{Deserialize, Serialize};
+use tokio_util::codec::Encoder;
+use vector_core::{config::DataType, event::Event, schema};
+
+/// Config used to build a `CborSerializer`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CborSerializerConfig;
+
+impl CborSerializerConfig {
+    /// Creates a new `CborSerializerConfig`.
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Build the `CborSerializer` from this configuration.
+    pub const fn build(&self) -> CborSerializer {
+        CborSerializer
+    }
+
+    /// The data type of events that are accepted by `CborSerializer`.
+    pub fn input_type(&self) -> DataType {
+        DataType::all_bits()
+    }
+
+    /// The schema required by the serializer.
+    pub fn schema_requirement(&self) -> schema::Requirement {
+        schema::Requirement::empty()
+    }
+}
+
+/// Serializer that converts an `Event` to bytes using the CBOR format.
+///
+/// Events are represented as they would be by the JSON serializer, such that each event is a
+/// self-describing CBOR data item.
+#[derive(Debug, Clone)]
+pub struct CborSerializer;
+
+impl Encoder<Event> for CborSerializer {
+    type Error = vector_common::Error;
+
+    fn encode(&mut self, event: Event, buffer: &mut BytesMut) -> Result<(), Self::Error> {
+        let writer = buffer.writer();
+        match event {
+            Event::Log(log) => ciborium::into_writer(&log, writer),
+            Event::Metric(metric) => ciborium::into_writer(&metric, writer),
+            Event::Trace(trace) => ciborium::into_writer(&trace, writer),
+        }
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use vector_core::event::{LogEvent, Metric, MetricKind, MetricValue, Value};
+    use vrl::btreemap;
+
+    use super::*;
+
+    fn serialize(event: Event) -> serde_json::Value {
+        let mut bytes = BytesMut::new();
+        CborSerializer.encode(event, &mut bytes).unwrap();
+        ciborium::from_reader(&bytes[..]).unwrap()
+    }
+
+    #[test]
+    fn serialize_cbor_log() {
+        let event = Event::Log(LogEvent::from(btreemap! {
+            "foo" => Value::from("bar"),
+            "count" => Value::from(3),
+        }));
+
+        assert_eq!(
+            serialize(event),
+            serde_json::json!({ "foo": "bar", "count": 3 })
+        );
+    }
+
+    #[test]
+    fn serialize_cbor_metric() {
+        let event = Event::Metric(Metric::new(
+            "foos",
+            MetricKind::Incremental,
+            MetricValue::Counter { value: 100.0 },
+        ));
+
+        assert_eq!(
+            serialize(event),
+            serde_json::json!({
+                "name": "foos",
+                "kind": "incremental",
+                "counter": { "value": 100.0 },
+            })
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use bytes::{BufMut, BytesMut};
+use serde::{Deserialize, Serialize};
+use tokio_util::codec::Encoder;
+use vector_core::{config::DataType, event::Event, schema};
+
+/// Config used to build a `MessagePackSerializer`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MessagePackSerializerConfig;
+
+impl MessagePackSerializerConfig {
+    /// Creates a new `MessagePackSerializerConfig`.
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Build the `MessagePackSerializer` from this configuration.
+    pub const fn build(&self) -> MessagePackSerializer {
+        MessagePackSerializer
+    }
+
+    /// The data type of events that are accepted by `MessagePackSerializer`.
+    pub fn input_type(&self) -> DataType {
+        DataType::all_bits()
+    }
+
+    /// The schema required by the serializer.
+    pub fn schema_requirement(&self) -> schema::Requirement {
+        schema::Requirement::empty()
+    }
+}
+
+/// Serializer that converts an `Event` to bytes using the MessagePack format.
+///
+/// Events are represented as they would be by the JSON serializer, with structs written as maps
+/// keyed by field name rather than as arrays, such that each event is a self-describing
+/// MessagePack object.
+#[derive(Debug, Clone)]
+pub struct MessagePackSerializer;
+
+impl Encoder<Event> for MessagePackSerializer {
+    type Error = vector_common::Error;
+
+    fn encode(&mut self, event: Event, buffer: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut writer = buffer.writer();
+        match event {
+            Event::Log(log) => rmp_serde::encode::write_named(&mut writer, &log),
+            Event::Metric(metric) => rmp_serde::encode::write_named(&mut writer, &metric),
+            Event::Trace(trace) => rmp_serde::encode::write_named(&mut writer, &trace),
+        }
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use vector_core::event::{LogEvent, Metric, MetricKind, MetricValue, Value};
+    use vrl::btreemap;
+
+    use super::*;
+
+    fn serialize(event: Event) -> serde_json::Value {
+        let mut bytes = BytesMut::new();
+        MessagePackSerializer.encode(event, &mut bytes).unwrap();
+        rmp_serde::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn serialize_message_pack_log() {
+        let event = Event::Log(LogEvent::from(btreemap! {
+            "foo" => Value::from("bar"),
+            "count" => Value::from(3),
+        }));
+
+        assert_eq!(
+            serialize(event),
+            serde_json::json!({ "foo": "bar", "count": 3 })
+        );
+    }
+
+    #[test]
+    fn serialize_message_pack_metric() {
+        let event = Event::Metric(Metric::new(
+            "foos",
+            MetricKind::Incremental,
+            MetricValue::Counter { value: 100.0 },
+        ));
+
+        assert_eq!(
+            serialize(event),
+            serde_json::json!({
+                "name": "foos",
+                "kind": "incremental",
+                "counter": { "value": 100.0 },
+            })
+        );
+    }
+}
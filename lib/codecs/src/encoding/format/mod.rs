@@ -4,11 +4,13 @@
 #![deny(missing_docs)]
 
 mod avro;
+mod cbor;
 mod common;
 mod csv;
 mod gelf;
 mod json;
 mod logfmt;
+mod message_pack;
 mod native;
 mod native_json;
 mod protobuf;
@@ -19,10 +21,12 @@ use std::fmt::Debug;
 
 pub use self::csv::{CsvSerializer, CsvSerializerConfig};
 pub use avro::{AvroSerializer, AvroSerializerConfig, AvroSerializerOptions};
+pub use cbor::{CborSerializer, CborSerializerConfig};
 use dyn_clone::DynClone;
 pub use gelf::{GelfSerializer, GelfSerializerConfig};
 pub use json::{JsonSerializer, JsonSerializerConfig, JsonSerializerOptions};
 pub use logfmt::{LogfmtSerializer, LogfmtSerializerConfig};
+pub use message_pack::{MessagePackSerializer, MessagePackSerializerConfig};
 pub use native::{NativeSerializer, NativeSerializerConfig};
 pub use native_json::{NativeJsonSerializer, NativeJsonSerializerConfig};
 pub use protobuf::{ProtobufSerializer, ProtobufSerializerConfig, ProtobufSerializerOptions};
@@ -34,4 +34,13 @@ pub trait KeyedTimer<K> {
     ///
     /// Used primarily for property testing vis-á-vis `vector_stream::batcher::Batcher`.
     fn poll_expired(&mut self, cx: &mut Context) -> Poll<Option<K>>;
+
+    /// Pulls out the next subtimer which should expire right away, regardless of its timeout.
+    ///
+    /// Unlike `poll_expired`, which is only polled once the input is idle, this is checked after
+    /// every item, so that batches which can no longer receive items are flushed even under
+    /// sustained input. By default, no subtimer expires right away.
+    fn take_immediate(&mut self) -> Option<K> {
+        None
+    }
 }
@@ -225,7 +225,6 @@ where
     }
 }
 
-#[cfg(test)]
 impl<St, Prt, KT, C, F, B> PartitionedBatcher<St, Prt, KT, C, F, B>
 where
     St: Stream<Item = Prt::Item>,
@@ -235,6 +234,9 @@ where
     C: BatchConfig<Prt::Item>,
     F: Fn() -> C + Send,
 {
+    /// Creates a new `PartitionedBatcher` using the given [`KeyedTimer`] to drive batch expirations.
+    ///
+    /// This allows callers to expire batches for reasons other than their timeout elapsing.
     pub fn with_timer(stream: St, partitioner: Prt, timer: KT, settings: F) -> Self {
         Self {
             state: settings,
@@ -338,6 +340,14 @@ where
                         this.batches.remove(&item_key);
                         this.timer.remove(&item_key);
                     }
+
+                    // Batches the timer expires right away are flushed without waiting for the
+                    // input to be idle.
+                    while let Some(item_key) = this.timer.take_immediate() {
+                        if let Some(mut batch) = this.batches.remove(&item_key) {
+                            this.closed_batches.push((item_key, batch.take_batch()));
+                        }
+                    }
                 }
             }
         }
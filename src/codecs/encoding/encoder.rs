@@ -76,18 +76,27 @@ impl Encoder<Framer> {
         &self.framer
     }
 
+    /// Frame an event serialized on its own into the provided buffer.
+    pub fn frame(&mut self, buffer: &mut BytesMut) -> Result<(), Error> {
+        self.framer.encode((), buffer).map_err(|error| {
+            emit!(EncoderFramingError { error: &error });
+            Error::FramingError(error)
+        })
+    }
+
     /// Get the serializer.
     pub const fn serializer(&self) -> &Serializer {
         &self.serializer
     }
 
     /// Get the prefix that encloses a batch of events.
-    pub const fn batch_prefix(&self) -> &[u8] {
+    pub fn batch_prefix(&self) -> &[u8] {
         match (&self.framer, &self.serializer) {
             (
                 Framer::CharacterDelimited(CharacterDelimitedEncoder { delimiter: b',' }),
                 Serializer::Json(_) | Serializer::NativeJson(_),
             ) => b"[",
+            (_, Serializer::Avro(avro)) => avro.container_header(),
             _ => &[],
         }
     }
@@ -103,6 +112,18 @@ impl Encoder<Framer> {
         }
     }
 
+    /// Get the separator placed between the batches of events of the same stream, when they are
+    /// written one after the other without their prefix and suffix.
+    pub const fn batch_separator(&self) -> &[u8] {
+        match &self.framer {
+            Framer::CharacterDelimited(CharacterDelimitedEncoder { delimiter }) => {
+                std::slice::from_ref(delimiter)
+            }
+            Framer::NewlineDelimited(_) => b"\n",
+            _ => &[],
+        }
+    }
+
     /// Get the HTTP content type.
     pub const fn content_type(&self) -> &'static str {
         match (&self.serializer, &self.framer) {
@@ -113,6 +134,8 @@ impl Encoder<Framer> {
                 Serializer::Gelf(_) | Serializer::Json(_) | Serializer::NativeJson(_),
                 Framer::CharacterDelimited(CharacterDelimitedEncoder { delimiter: b',' }),
             ) => "application/json",
+            (Serializer::Cbor(_), _) => "application/cbor",
+            (Serializer::MessagePack(_), _) => "application/msgpack",
             (Serializer::Native(_), _) | (Serializer::Protobuf(_), _) => "application/octet-stream",
             (
                 Serializer::Avro(_)
@@ -155,10 +178,7 @@ impl tokio_util::codec::Encoder<Event> for Encoder<Framer> {
         self.serialize_at_start(event, &mut payload)?;
 
         // Frame the serialized event.
-        self.framer.encode((), &mut payload).map_err(|error| {
-            emit!(EncoderFramingError { error: &error });
-            Error::FramingError(error)
-        })?;
+        self.frame(&mut payload)?;
 
         buffer.unsplit(payload);
 
@@ -7,7 +7,7 @@ use std::collections::BTreeMap;
 use ordered_float::NotNan;
 use serde::{Deserialize, Deserializer};
 use vector_lib::configurable::configurable_component;
-use vector_lib::event::{LogEvent, MaybeAsLogMut};
+use vector_lib::event::{LogEvent, MaybeAsLogMut, ObjectMap};
 use vector_lib::lookup::lookup_v2::ConfigValuePath;
 use vector_lib::lookup::{event_path, PathPrefix};
 use vector_lib::schema::meaning;
@@ -31,6 +31,23 @@ pub struct Transformer {
     /// Format used for timestamp fields.
     #[serde(default, skip_serializing_if = "is_default")]
     timestamp_format: Option<TimestampFormat>,
+
+    /// Whether to flatten nested fields into top-level fields.
+    ///
+    /// The name of each flattened field joins the path of the field with `flatten_separator`,
+    /// with the index of array elements as a segment, such that `{"a": {"b": [1]}}` is encoded as
+    /// `{"a.b.0": 1}`. Fields are flattened after `only_fields` and `except_fields` are applied,
+    /// which refer to fields by their nested path. When the names of flattened fields collide,
+    /// such as for a field `a.b` alongside a field `a` containing `b`, the field whose name sorts
+    /// last is kept. Empty objects and arrays are kept as they are.
+    #[serde(default, skip_serializing_if = "is_default")]
+    flatten: bool,
+
+    /// The separator joining the segments of the names of flattened fields.
+    ///
+    /// Defaults to `.`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    flatten_separator: Option<String>,
 }
 
 impl<'de> Deserialize<'de> for Transformer {
@@ -47,9 +64,14 @@ impl<'de> Deserialize<'de> for Transformer {
             except_fields: Option<Vec<OwnedValuePath>>,
             #[serde(default)]
             timestamp_format: Option<TimestampFormat>,
+            #[serde(default)]
+            flatten: bool,
+            #[serde(default)]
+            flatten_separator: Option<String>,
         }
 
         let inner: TransformerInner = Deserialize::deserialize(deserializer)?;
+        let (flatten, flatten_separator) = (inner.flatten, inner.flatten_separator);
         Self::new(
             inner
                 .only_fields
@@ -59,6 +81,7 @@ impl<'de> Deserialize<'de> for Transformer {
                 .map(|v| v.iter().map(|p| ConfigValuePath(p.clone())).collect()),
             inner.timestamp_format,
         )
+        .map(|transformer| transformer.with_flatten(flatten, flatten_separator))
         .map_err(serde::de::Error::custom)
     }
 }
@@ -79,9 +102,19 @@ impl Transformer {
             only_fields,
             except_fields,
             timestamp_format,
+            flatten: false,
+            flatten_separator: None,
         })
     }
 
+    /// Flattens nested fields into top-level fields, whose names are joined with the given
+    /// separator, or `.` by default.
+    pub fn with_flatten(mut self, flatten: bool, flatten_separator: Option<String>) -> Self {
+        self.flatten = flatten;
+        self.flatten_separator = flatten_separator;
+        self
+    }
+
     /// Get the `Transformer`'s `only_fields`.
     #[cfg(test)]
     pub const fn only_fields(&self) -> &Option<Vec<ConfigValuePath>> {
@@ -126,6 +159,8 @@ impl Transformer {
             self.apply_except_fields(log);
             self.apply_only_fields(log);
             self.apply_timestamp_format(log);
+            // Fields are flattened last, as the other rules refer to them by their nested path.
+            self.apply_flatten(log);
         }
     }
 
@@ -224,6 +259,22 @@ impl Transformer {
         }
     }
 
+    fn apply_flatten(&self, log: &mut LogEvent) {
+        if !self.flatten {
+            return;
+        }
+        if let Value::Object(fields) = log.value_mut() {
+            let separator = self.flatten_separator.as_deref().unwrap_or(".");
+            let mut flattened = ObjectMap::new();
+            // Fields are visited in the order of their names, so that collisions are resolved
+            // deterministically.
+            for (name, value) in std::mem::take(fields) {
+                flatten_into(&mut flattened, name.into(), value, separator);
+            }
+            *fields = flattened;
+        }
+    }
+
     /// Set the `except_fields` value.
     ///
     /// Returns `Err` if the new `except_fields` fail validation, i.e. are not mutually exclusive
@@ -239,6 +290,36 @@ impl Transformer {
     }
 }
 
+/// Inserts the value into `flattened` under the given name, or its nested fields and elements under
+/// their own names, joined to it with the separator.
+fn flatten_into(flattened: &mut ObjectMap, name: String, value: Value, separator: &str) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (field, value) in fields {
+                flatten_into(
+                    flattened,
+                    format!("{name}{separator}{field}"),
+                    value,
+                    separator,
+                );
+            }
+        }
+        Value::Array(elements) if !elements.is_empty() => {
+            for (index, value) in elements.into_iter().enumerate() {
+                flatten_into(
+                    flattened,
+                    format!("{name}{separator}{index}"),
+                    value,
+                    separator,
+                );
+            }
+        }
+        value => {
+            flattened.insert(name.into(), value);
+        }
+    }
+}
+
 #[configurable_component]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -370,6 +451,40 @@ mod tests {
         assert!(!event.as_mut_log().contains("i"));
     }
 
+    #[test]
+    fn deserialize_and_transform_flatten() {
+        let transformer: Transformer = toml::from_str(indoc! {r#"
+            except_fields = ["a.skip"]
+            flatten = true
+            flatten_separator = "_"
+        "#})
+        .unwrap();
+        let mut log = LogEvent::default();
+        {
+            log.insert("a.b", 1);
+            log.insert("a.skip", 1);
+            log.insert("a_b", 2);
+            log.insert("c[0]", 3);
+            log.insert("c[1].d", 4);
+            log.insert("e", BTreeMap::new());
+        }
+        let mut event = Event::from(log);
+        transformer.transform(&mut event);
+        let log = event.as_log();
+        assert_eq!(
+            log.as_map()
+                .unwrap()
+                .keys()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["a_b", "c_0", "c_1_d", "e"]
+        );
+        assert_eq!(log["a_b"], Value::from(2));
+        assert_eq!(log["c_0"], Value::from(3));
+        assert_eq!(log["c_1_d"], Value::from(4));
+        assert_eq!(log["e"], Value::Object(ObjectMap::new()));
+    }
+
     #[test]
     fn deserialize_and_transform_timestamp() {
         let mut base = Event::Log(LogEvent::from("Demo"));
@@ -239,6 +239,8 @@ dyn_clone::clone_trait_object!(SinkConfig);
 
 #[derive(Clone)]
 pub struct SinkContext {
+    /// The key of the sink component being built.
+    pub key: ComponentKey,
     pub healthcheck: SinkHealthcheckOptions,
     pub globals: GlobalOptions,
     pub proxy: ProxyConfig,
@@ -254,6 +256,7 @@ pub struct SinkContext {
 impl Default for SinkContext {
     fn default() -> Self {
         Self {
+            key: ComponentKey::from("default"),
             healthcheck: Default::default(),
             globals: Default::default(),
             proxy: Default::default(),
@@ -38,8 +38,11 @@ use crate::{
 };
 
 pub mod status {
+    pub const MOVED_PERMANENTLY: u16 = 301;
+    pub const BAD_REQUEST: u16 = 400;
     pub const FORBIDDEN: u16 = 403;
     pub const NOT_FOUND: u16 = 404;
+    pub const CONFLICT: u16 = 409;
     pub const TOO_MANY_REQUESTS: u16 = 429;
 }
 
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use metrics::{histogram, Histogram};
+use metrics::{gauge, histogram, Gauge, Histogram};
 
 #[derive(Clone, Copy)]
 pub struct AdaptiveConcurrencyLimitData {
@@ -21,10 +21,14 @@ registered_event! {
         reached_limit: Histogram = histogram!("adaptive_concurrency_reached_limit"),
         back_pressure: Histogram = histogram!("adaptive_concurrency_back_pressure"),
         past_rtt_mean: Histogram = histogram!("adaptive_concurrency_past_rtt_mean"),
+        // The limit as of the last window, for dashboards and alerts following the limit itself
+        // rather than its distribution.
+        current_limit: Gauge = gauge!("adaptive_concurrency_current_limit"),
     }
 
     fn emit(&self, data: AdaptiveConcurrencyLimitData) {
         self.limit.record(data.concurrency as f64);
+        self.current_limit.set(data.concurrency as f64);
         let reached_limit = data.reached_limit.then_some(1.0).unwrap_or_default();
         self.reached_limit.record(reached_limit);
         let back_pressure = data.had_back_pressure.then_some(1.0).unwrap_or_default();
@@ -0,0 +1,600 @@
+use std::time::Duration;
+
+use metrics::{counter, gauge, histogram};
+use vector_lib::internal_event::{
+    error_stage, error_type, ComponentEventsDropped, InternalEvent, INTENTIONAL, UNINTENTIONAL,
+};
+
+use crate::sinks::util::Compression;
+
+#[derive(Debug)]
+pub struct S3KeyPrefixFallback;
+
+impl InternalEvent for S3KeyPrefixFallback {
+    fn emit(self) {
+        debug!(message = "Partitioning event under the fallback key prefix.");
+        counter!("s3_key_prefix_fallback_events_total").increment(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3LateEventDropped;
+
+impl InternalEvent for S3LateEventDropped {
+    fn emit(self) {
+        emit!(ComponentEventsDropped::<INTENTIONAL> {
+            count: 1,
+            reason: "Event arrived after its window was closed.",
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct S3FlushRequested {
+    pub batch_count: usize,
+}
+
+impl InternalEvent for S3FlushRequested {
+    fn emit(self) {
+        info!(
+            message = "Flushing all batches on request.",
+            batch_count = self.batch_count,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3DistinctPartitionKeys {
+    pub count: usize,
+}
+
+impl InternalEvent for S3DistinctPartitionKeys {
+    fn emit(self) {
+        gauge!("aws_s3_distinct_partition_keys").set(self.count as f64);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3InvalidStorageClass<'a> {
+    pub storage_class: &'a str,
+}
+
+impl InternalEvent for S3InvalidStorageClass<'_> {
+    fn emit(self) {
+        warn!(
+            message = "Invalid storage class rendered, using the configured storage class instead.",
+            storage_class = %self.storage_class,
+            internal_log_rate_limit = true,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3StartupProfileCompleted<'a> {
+    pub event_count: usize,
+    pub elapsed: Duration,
+    pub uncompressed_byte_size: usize,
+    pub compressed_byte_size: usize,
+    pub codec: &'a str,
+    pub compression: Compression,
+}
+
+impl InternalEvent for S3StartupProfileCompleted<'_> {
+    fn emit(self) {
+        let seconds = self.elapsed.as_secs_f64();
+        info!(
+            message = "Profiled encoding of the first events.",
+            codec = %self.codec,
+            compression = %self.compression,
+            events = self.event_count,
+            events_per_sec = self.event_count as f64 / seconds,
+            megabytes_per_sec = self.uncompressed_byte_size as f64 / 1_000_000.0 / seconds,
+            uncompressed_bytes = self.uncompressed_byte_size,
+            compressed_bytes = self.compressed_byte_size,
+            compression_ratio =
+                self.uncompressed_byte_size as f64 / self.compressed_byte_size.max(1) as f64,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3StartupProfileError {
+    pub error: std::io::Error,
+}
+
+impl InternalEvent for S3StartupProfileError {
+    fn emit(self) {
+        warn!(
+            message = "Failed to profile encoding of the first events.",
+            error = %self.error,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3StagingError {
+    pub error: std::io::Error,
+}
+
+impl InternalEvent for S3StagingError {
+    fn emit(self) {
+        warn!(
+            message = "Failed to access the staging directory.",
+            error = %self.error,
+            internal_log_rate_limit = true,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3OrphanUploaded<'a> {
+    pub key: &'a str,
+}
+
+impl InternalEvent for S3OrphanUploaded<'_> {
+    fn emit(self) {
+        info!(
+            message = "Uploaded object staged by a previous run.",
+            key = %self.key,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3OrphanUploadError<'a, E> {
+    pub key: &'a str,
+    pub error: E,
+}
+
+impl<E: std::fmt::Display> InternalEvent for S3OrphanUploadError<'_, E> {
+    fn emit(self) {
+        warn!(
+            message = "Failed to upload object staged by a previous run, keeping it for the next run.",
+            key = %self.key,
+            error = %self.error,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3ObjectSpilled<'a, E> {
+    pub key: &'a str,
+    pub byte_size: usize,
+    pub error: E,
+}
+
+impl<E: std::fmt::Display> InternalEvent for S3ObjectSpilled<'_, E> {
+    fn emit(self) {
+        warn!(
+            message = "Failed to upload object, spilled it to be uploaded again later.",
+            key = %self.key,
+            error = %self.error,
+            internal_log_rate_limit = true,
+        );
+        counter!("aws_s3_spilled_bytes_total").increment(self.byte_size as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3SpillDropped {
+    pub byte_size: u64,
+}
+
+impl InternalEvent for S3SpillDropped {
+    fn emit(self) {
+        error!(
+            message = "Spill directory is full, dropped its oldest object.",
+            byte_size = self.byte_size,
+            error_code = "spill_dir_full",
+            error_type = error_type::CONDITION_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_limit = true,
+        );
+        counter!("aws_s3_spill_dropped_bytes_total").increment(self.byte_size);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3SpillRecovered<'a> {
+    pub key: &'a str,
+    pub byte_size: usize,
+}
+
+impl InternalEvent for S3SpillRecovered<'_> {
+    fn emit(self) {
+        info!(message = "Uploaded spilled object.", key = %self.key);
+        counter!("aws_s3_spill_recovered_bytes_total").increment(self.byte_size as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3SpillRecoveryError<'a, E> {
+    pub key: &'a str,
+    pub error: E,
+}
+
+impl<E: std::fmt::Display> InternalEvent for S3SpillRecoveryError<'_, E> {
+    fn emit(self) {
+        warn!(
+            message = "Failed to upload spilled object, keeping it for the next attempt.",
+            key = %self.key,
+            error = %self.error,
+            internal_log_rate_limit = true,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3SpillError {
+    pub error: std::io::Error,
+}
+
+impl InternalEvent for S3SpillError {
+    fn emit(self) {
+        warn!(
+            message = "Failed to access the spill directory.",
+            error = %self.error,
+            internal_log_rate_limit = true,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3HeartbeatWritten<'a> {
+    pub key: &'a str,
+}
+
+impl InternalEvent for S3HeartbeatWritten<'_> {
+    fn emit(self) {
+        trace!(message = "Wrote heartbeat object.", key = %self.key);
+        counter!("aws_s3_heartbeats_written_total").increment(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3HeartbeatError<'a, E> {
+    pub key: &'a str,
+    pub error: E,
+}
+
+impl<E: std::fmt::Display> InternalEvent for S3HeartbeatError<'_, E> {
+    fn emit(self) {
+        warn!(
+            message = "Failed to write heartbeat object, retrying at the next heartbeat.",
+            key = %self.key,
+            error = %self.error,
+        );
+        counter!("aws_s3_heartbeat_errors_total").increment(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3EmfSummaryWritten<'a> {
+    pub key: &'a str,
+}
+
+impl InternalEvent for S3EmfSummaryWritten<'_> {
+    fn emit(self) {
+        trace!(message = "Wrote EMF summary object.", key = %self.key);
+        counter!("aws_s3_emf_summaries_written_total").increment(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3EmfSummaryError<'a, E> {
+    pub key: &'a str,
+    pub error: E,
+}
+
+impl<E: std::fmt::Display> InternalEvent for S3EmfSummaryError<'_, E> {
+    fn emit(self) {
+        warn!(
+            message = "Failed to write EMF summary object, its activity is lost.",
+            key = %self.key,
+            error = %self.error,
+        );
+        counter!("aws_s3_emf_summary_errors_total").increment(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3ObjectUploaded {
+    pub latency: Duration,
+    pub upload_slo: Option<Duration>,
+}
+
+impl InternalEvent for S3ObjectUploaded {
+    fn emit(self) {
+        histogram!("aws_s3_object_upload_latency_seconds").record(self.latency);
+        if let Some(upload_slo) = self
+            .upload_slo
+            .filter(|upload_slo| self.latency > *upload_slo)
+        {
+            debug!(
+                message = "Object was uploaded later than the upload SLO.",
+                latency_secs = self.latency.as_secs_f64(),
+                upload_slo_secs = upload_slo.as_secs_f64(),
+            );
+            counter!("aws_s3_slo_violations_total").increment(1);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct S3ObjectVersionCreated<'a> {
+    pub bucket: &'a str,
+    pub key: &'a str,
+    pub version_id: &'a str,
+}
+
+impl InternalEvent for S3ObjectVersionCreated<'_> {
+    fn emit(self) {
+        info!(
+            message = "Created object version.",
+            bucket = %self.bucket,
+            key = %self.key,
+            version_id = %self.version_id,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3BucketRegionDetected<'a> {
+    pub bucket: &'a str,
+    pub region: &'a str,
+}
+
+impl InternalEvent for S3BucketRegionDetected<'_> {
+    fn emit(self) {
+        warn!(
+            message = "Bucket is in another region than configured, sending requests to its region instead.",
+            bucket = %self.bucket,
+            region = %self.region,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3MultipartPartSizeIncreased<'a> {
+    pub key: &'a str,
+    pub part_size: usize,
+    pub configured_part_size: usize,
+}
+
+impl InternalEvent for S3MultipartPartSizeIncreased<'_> {
+    fn emit(self) {
+        warn!(
+            message = "Object is too large to be uploaded in 10,000 parts of the configured size, uploading it in larger parts instead.",
+            key = %self.key,
+            part_size = %self.part_size,
+            configured_part_size = %self.configured_part_size,
+            internal_log_rate_limit = true,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3MultipartPartRetried<'a, E> {
+    pub key: &'a str,
+    pub part_number: i32,
+    pub error: E,
+}
+
+impl<E: std::fmt::Display> InternalEvent for S3MultipartPartRetried<'_, E> {
+    fn emit(self) {
+        warn!(
+            message = "Retrying part of multipart upload.",
+            key = %self.key,
+            part_number = %self.part_number,
+            error = %self.error,
+            internal_log_rate_limit = true,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3WrongRegionError<'a> {
+    pub bucket: &'a str,
+    pub region: &'a str,
+}
+
+impl InternalEvent for S3WrongRegionError<'_> {
+    fn emit(self) {
+        error!(
+            message = format!(
+                "Bucket {:?} is in region {:?}, configure `region = {:?}` or enable `auto_region`.",
+                self.bucket, self.region, self.region
+            ),
+            error_code = "wrong_region",
+            error_type = error_type::CONFIGURATION_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_limit = true,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3UploadVerificationFailed<'a> {
+    pub bucket: &'a str,
+    pub key: &'a str,
+    pub reason: &'a str,
+}
+
+impl InternalEvent for S3UploadVerificationFailed<'_> {
+    fn emit(self) {
+        error!(
+            message = "Uploaded object does not match its upload.",
+            bucket = %self.bucket,
+            key = %self.key,
+            reason = %self.reason,
+            error_code = "upload_verification_failed",
+            error_type = error_type::CONDITION_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_limit = true,
+        );
+        counter!("aws_s3_upload_verification_failures_total").increment(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3EventCountMismatch<'a> {
+    pub key: &'a str,
+    pub event_count: usize,
+    pub record_count: usize,
+}
+
+impl InternalEvent for S3EventCountMismatch<'_> {
+    fn emit(self) {
+        error!(
+            message = "Number of records encoded in the object does not match its event count.",
+            key = %self.key,
+            event_count = self.event_count,
+            record_count = self.record_count,
+            error_code = "event_count_mismatch",
+            error_type = error_type::ENCODER_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_limit = true,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3ClockSkewDetected {
+    pub skew: Duration,
+    pub threshold: Duration,
+}
+
+impl InternalEvent for S3ClockSkewDetected {
+    fn emit(self) {
+        warn!(
+            message = "System clock disagrees with the event timestamps of the object by more than the clock skew threshold.",
+            skew_secs = self.skew.as_secs_f64(),
+            threshold_secs = self.threshold.as_secs(),
+            internal_log_rate_limit = true,
+        );
+        counter!("aws_s3_clock_skew_detected_total").increment(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3CompressionSelected {
+    pub compression: Compression,
+}
+
+impl InternalEvent for S3CompressionSelected {
+    fn emit(self) {
+        trace!(message = "Selected the smallest compression.", compression = %self.compression);
+        counter!(
+            "aws_s3_compression_selected_total",
+            "compression" => self.compression.to_string(),
+        )
+        .increment(1);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3EventTooLarge {
+    pub byte_size: usize,
+    pub max_event_bytes: usize,
+}
+
+impl InternalEvent for S3EventTooLarge {
+    fn emit(self) {
+        let reason = "Event exceeds the maximum event size.";
+        error!(
+            message = reason,
+            byte_size = self.byte_size,
+            max_event_bytes = self.max_event_bytes,
+            error_code = "event_too_large",
+            error_type = error_type::CONDITION_FAILED,
+            stage = error_stage::PROCESSING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total",
+            "error_code" => "event_too_large",
+            "error_type" => error_type::CONDITION_FAILED,
+            "stage" => error_stage::PROCESSING,
+        )
+        .increment(1);
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> { count: 1, reason });
+    }
+}
+
+#[derive(Debug)]
+pub struct S3PersistentErrorsExceeded {
+    pub consecutive_failures: usize,
+}
+
+impl InternalEvent for S3PersistentErrorsExceeded {
+    fn emit(self) {
+        error!(
+            message = "Stopping the sink after consecutive requests failed.",
+            consecutive_failures = self.consecutive_failures,
+            error_code = "persistent_errors",
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3RetentionObjectsDeleted<'a> {
+    pub key: &'a str,
+    pub count: usize,
+}
+
+impl InternalEvent for S3RetentionObjectsDeleted<'_> {
+    fn emit(self) {
+        debug!(
+            message = "Deleted objects beyond the retention limits.",
+            key = %self.key,
+            count = self.count,
+        );
+        counter!("aws_s3_retention_deleted_objects_total").increment(self.count as u64);
+    }
+}
+
+#[derive(Debug)]
+pub struct S3RetentionError<'a, E> {
+    pub key: &'a str,
+    pub error: E,
+}
+
+impl<E: std::fmt::Display> InternalEvent for S3RetentionError<'_, E> {
+    fn emit(self) {
+        warn!(
+            message = "Failed to delete objects beyond the retention limits, retrying on the next upload.",
+            key = %self.key,
+            error = %self.error,
+            internal_log_rate_limit = true,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3CompanionObjectError<'a, E> {
+    pub key: &'a str,
+    pub error: E,
+}
+
+impl<E: std::fmt::Display> InternalEvent for S3CompanionObjectError<'_, E> {
+    fn emit(self) {
+        error!(
+            message = "Failed to write companion object of an uploaded object.",
+            key = %self.key,
+            error = %self.error,
+            error_code = "companion_object_failed",
+            error_type = error_type::REQUEST_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total",
+            "error_code" => "companion_object_failed",
+            "error_type" => error_type::REQUEST_FAILED,
+            "stage" => error_stage::SENDING,
+        )
+        .increment(1);
+    }
+}
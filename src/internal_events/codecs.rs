@@ -79,6 +79,30 @@ impl<'a> InternalEvent for EncoderFramingError<'a> {
     }
 }
 
+#[derive(Debug)]
+pub struct EncoderInvalidUtf8Error;
+
+impl InternalEvent for EncoderInvalidUtf8Error {
+    fn emit(self) {
+        let reason = "Event was encoded to invalid UTF-8.";
+        error!(
+            message = reason,
+            error_code = "encoder_invalid_utf8",
+            error_type = error_type::ENCODER_FAILED,
+            stage = error_stage::SENDING,
+            internal_log_rate_limit = true,
+        );
+        counter!(
+            "component_errors_total",
+            "error_code" => "encoder_invalid_utf8",
+            "error_type" => error_type::ENCODER_FAILED,
+            "stage" => error_stage::SENDING,
+        )
+        .increment(1);
+        emit!(ComponentEventsDropped::<UNINTENTIONAL> { count: 1, reason });
+    }
+}
+
 #[derive(Debug)]
 pub struct EncoderSerializeError<'a> {
     pub error: &'a crate::Error,
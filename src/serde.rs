@@ -1,6 +1,8 @@
 #![allow(missing_docs)]
+use std::fmt;
+
 use indexmap::map::IndexMap;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use vector_lib::codecs::{
     decoding::{DeserializerConfig, FramingConfig},
     BytesDecoderConfig, BytesDeserializerConfig,
@@ -23,6 +25,102 @@ pub fn default_max_length() -> usize {
     bytesize::kib(100u64) as usize
 }
 
+/// Parses a size in bytes given as an integer with an optional unit, such as `128MiB` or `1.5GB`.
+///
+/// Units ending in `iB` are powers of 1024, and the others are powers of 1000, such that `1MiB` is
+/// 1048576 bytes while `1MB` is 1000000 bytes. Units are case-insensitive, and may be separated
+/// from the number by whitespace.
+pub fn parse_byte_size(size: &str) -> Result<usize, String> {
+    let size = size.trim();
+    let unit_start = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = (&size[..unit_start], size[unit_start..].trim());
+    let multiplier = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => bytesize::KB,
+        "mb" => bytesize::MB,
+        "gb" => bytesize::GB,
+        "tb" => bytesize::TB,
+        "kib" => bytesize::KIB,
+        "mib" => bytesize::MIB,
+        "gib" => bytesize::GIB,
+        "tib" => bytesize::TIB,
+        _ => {
+            return Err(format!(
+                "unknown unit {:?} in byte size {:?}, expected one of {}",
+                unit, size, "B, kB, MB, GB, TB, KiB, MiB, GiB, or TiB"
+            ))
+        }
+    };
+    let invalid = || format!("invalid byte size {:?}", size);
+    let bytes = if number.contains('.') {
+        let bytes = number.parse::<f64>().map_err(|_| invalid())? * multiplier as f64;
+        if bytes.fract() != 0.0 || bytes > usize::MAX as f64 {
+            return Err(invalid());
+        }
+        bytes as u64
+    } else {
+        number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(multiplier))
+            .ok_or_else(invalid)?
+    };
+    usize::try_from(bytes).map_err(|_| invalid())
+}
+
+/// Deserializes an optional size in bytes from either an integer number of bytes or a string with
+/// a unit, as parsed by [`parse_byte_size`].
+pub fn optional_byte_size<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct OptionalByteSize;
+
+    impl<'de> de::Visitor<'de> for OptionalByteSize {
+        type Value = Option<usize>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter
+                .write_str(r#"integer number of bytes, or string with a unit such as "128MiB""#)
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_any(self)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+            usize::try_from(value)
+                .map(Some)
+                .map_err(|_| de::Error::invalid_value(de::Unexpected::Unsigned(value), &self))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+            usize::try_from(value)
+                .map(Some)
+                .map_err(|_| de::Error::invalid_value(de::Unexpected::Signed(value), &self))
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            parse_byte_size(value).map(Some).map_err(de::Error::custom)
+        }
+    }
+
+    deserializer.deserialize_option(OptionalByteSize)
+}
+
 pub fn default_framing_message_based() -> FramingConfig {
     BytesDecoderConfig::new().into()
 }
@@ -126,3 +224,38 @@ impl<T> From<Vec<T>> for OneOrMany<T> {
         Self::Many(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_sizes_parse_with_units() {
+        assert_eq!(parse_byte_size("100000000"), Ok(100_000_000));
+        assert_eq!(parse_byte_size("128MiB"), Ok(128 * 1024 * 1024));
+        assert_eq!(parse_byte_size("128MB"), Ok(128_000_000));
+        assert_eq!(parse_byte_size("1 GB"), Ok(1_000_000_000));
+        assert_eq!(parse_byte_size("1.5kib"), Ok(1536));
+        assert_eq!(parse_byte_size("10B"), Ok(10));
+        assert!(parse_byte_size("1.5B").is_err());
+        assert!(parse_byte_size("128Mb/s").is_err());
+        assert!(parse_byte_size("MiB").is_err());
+        assert!(parse_byte_size("-1MiB").is_err());
+    }
+
+    #[test]
+    fn optional_byte_sizes_deserialize_from_integers_and_strings() {
+        #[derive(Deserialize)]
+        struct Config {
+            #[serde(default, deserialize_with = "optional_byte_size")]
+            max_bytes: Option<usize>,
+        }
+
+        let parse = |config: &str| toml::from_str::<Config>(config).map(|config| config.max_bytes);
+        assert_eq!(parse("max_bytes = 1048576").unwrap(), Some(1_048_576));
+        assert_eq!(parse(r#"max_bytes = "1MiB""#).unwrap(), Some(1_048_576));
+        assert_eq!(parse("").unwrap(), None);
+        assert!(parse(r#"max_bytes = "1 parsec""#).is_err());
+        assert!(parse("max_bytes = -1").is_err());
+    }
+}
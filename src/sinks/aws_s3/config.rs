@@ -1,14 +1,31 @@
-use aws_sdk_s3::Client as S3Client;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    num::{NonZeroU32, NonZeroU64, NonZeroUsize},
+    path::PathBuf,
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
+
+use aws_sdk_s3::{
+    config::{AppName, Region},
+    Client as S3Client,
+};
+use futures::{FutureExt, TryFutureExt};
+use snafu::Snafu;
 use tower::ServiceBuilder;
 use vector_lib::codecs::{
-    encoding::{Framer, FramingConfig},
-    TextSerializerConfig,
+    encoding::{AvroSerializerConfig, Framer, FramingConfig, Serializer, SerializerConfig},
+    BytesEncoder, LengthDelimitedEncoderConfig, TextSerializerConfig,
 };
 use vector_lib::configurable::configurable_component;
+use vector_lib::lookup::lookup_v2::ConfigValuePath;
 use vector_lib::sink::VectorSink;
 use vector_lib::TimeZone;
 
-use super::sink::S3RequestOptions;
+use super::sink::{
+    expires_at, ObjectEncoder, S3RequestOptions, COLLAPSED_COUNT_TAG, MAX_EVENT_TIME_TAG,
+    MIN_EVENT_TIME_TAG,
+};
 use crate::{
     aws::{AwsAuthentication, RegionOrEndpoint},
     codecs::{Encoder, EncodingConfigWithFraming, SinkType},
@@ -16,12 +33,27 @@ use crate::{
     sinks::{
         s3_common::{
             self,
-            config::{S3Options, S3RetryLogic},
-            partitioner::S3KeyPartitioner,
+            config::{LateEvents, PartitionCase, S3Options, S3RetryLogic},
+            emf::{EmfStats, EmfStatsService, EmfSummary},
+            fail_fast::{FailFast, FailFastService},
+            generation::Generation,
+            heartbeat::Heartbeat,
+            lineage::Lineage,
+            ordering::OrderedPartitions,
+            partitioner::{
+                EventTimeWindows, FlushRequests, HashBuckets, LagSegments, PrefixMapping,
+                RangeBuckets, S3KeyPartitioner, SegmentNormalizer, DISTINCT_KEYS_WINDOW,
+            },
+            profile::StartupProfile,
+            retention::Retention,
             service::S3Service,
-            sink::S3Sink,
+            sink::{S3Sink, MAX_MULTIPART_REQUEST_BYTES, MAX_REQUEST_BYTES},
+            spill::{SpillDir, SpillService},
+            staging::StagingDir,
+            verification::UploadVerification,
         },
         util::{
+            encoding::{BatchFraming, EmbeddedNewlines, InvalidUtf8},
             timezone_to_offset, BatchConfig, BulkSizeBasedDefaultBatchSettings, Compression,
             ServiceBuilderExt, TowerRequestConfig,
         },
@@ -31,6 +63,121 @@ use crate::{
     tls::TlsConfig,
 };
 
+#[derive(Debug, Snafu)]
+pub(super) enum BuildError {
+    #[snafu(display(
+        "Invalid `framing.length_delimited.length_field_length` of {} bytes: the length field must be between 1 and 8 bytes wide.",
+        length_field_length
+    ))]
+    InvalidLengthFieldLength { length_field_length: usize },
+    #[snafu(display(
+        "`framing.length_delimited.max_frame_length` of {} bytes does not fit in a {}-byte length field, which can only represent frames of up to {} bytes.",
+        max_frame_length,
+        length_field_length,
+        max_representable
+    ))]
+    MaxFrameLengthTooLarge {
+        max_frame_length: usize,
+        length_field_length: usize,
+        max_representable: u64,
+    },
+    #[snafu(display(
+        "`min_events_per_object` requires `max_age_secs` to be set, to bound how long objects are held open."
+    ))]
+    MinEventsWithoutMaxAge,
+    #[snafu(display("Invalid `cache_control` {:?}: {}.", cache_control, reason))]
+    InvalidCacheControl {
+        cache_control: String,
+        reason: &'static str,
+    },
+    #[snafu(display(
+        "`expires_secs` of {} is too far in the future to be represented as an HTTP date.",
+        expires_secs
+    ))]
+    ExpiresTooLate { expires_secs: u64 },
+    #[snafu(display(
+        "Invalid `checksum_footer.format` {:?}: the footer must contain `{{checksum}}` and fit on a single line.",
+        format
+    ))]
+    InvalidChecksumFooterFormat { format: String },
+    #[snafu(display("`heartbeat.key` must not be empty."))]
+    EmptyHeartbeatKey,
+    #[snafu(display(
+        "`byte_order_mark` only applies to text-based codecs, not to the `{}` codec.",
+        codec
+    ))]
+    ByteOrderMarkWithBinaryCodec { codec: String },
+    #[snafu(display(
+        "`invalid_utf8` only applies to text-based codecs, not to the `{}` codec.",
+        codec
+    ))]
+    InvalidUtf8WithBinaryCodec { codec: String },
+    #[snafu(display(
+        "`key_segment_normalizers` field {:?} is not referenced in `key_prefix` or `key_prefix_fallbacks`.",
+        field
+    ))]
+    UnreferencedKeySegmentField { field: String },
+    #[snafu(display(
+        "`bloom_filter.false_positive_rate` of {} must be strictly between 0 and 1.",
+        false_positive_rate
+    ))]
+    InvalidBloomFilterFalsePositiveRate { false_positive_rate: f64 },
+    #[snafu(display("`write_gzip_index` requires `compression` to be set to `gzip`."))]
+    GzipIndexWithoutGzip,
+    #[snafu(display("`lineage` requires `write_object_metadata` to be set."))]
+    LineageWithoutObjectMetadata,
+    #[snafu(display("`gzip_header_os` requires `compression` to be set to `gzip`."))]
+    GzipHeaderOsWithoutGzip,
+    #[snafu(display(
+        "`gzip_header_os` can't be combined with `write_gzip_index`, as BGZF blocks have a fixed header."
+    ))]
+    GzipHeaderOsWithGzipIndex,
+    #[snafu(display("Invalid `bucket` ARN {:?}: {}.", bucket, reason))]
+    InvalidBucketArn {
+        bucket: String,
+        reason: &'static str,
+    },
+    #[snafu(display("`region_routing.targets` must not be empty."))]
+    EmptyRegionRoutingTargets,
+    #[snafu(display(
+        "`avro_object_container_file` requires `framing.method` to be `bytes`, as events are framed by the data blocks of the file."
+    ))]
+    AvroObjectContainerWithFraming,
+    #[snafu(display("`avro_object_container_file` requires the `avro` codec."))]
+    AvroObjectContainerWithoutAvro,
+    #[snafu(display("`spill.path` must not be the same directory as `staging_dir`."))]
+    SpillDirIsStagingDir,
+    #[snafu(display("`flush_signal` is only supported on Unix."))]
+    FlushSignalUnsupported,
+    #[snafu(display("`retention` requires `max_objects` or `max_bytes` to be set."))]
+    RetentionWithoutLimits,
+    #[snafu(display(
+        "`retention` can't be combined with `{}`, as it would delete the objects it writes under the key prefix of objects.",
+        option
+    ))]
+    RetentionWithCompanionObjects { option: &'static str },
+    #[snafu(display(
+        "`{}` framing can't separate events encoded by the binary `{}` codec, as they may contain the delimiter. Use `length_delimited` framing instead.",
+        framing,
+        codec
+    ))]
+    DelimitedFramingWithBinaryCodec {
+        framing: &'static str,
+        codec: String,
+    },
+    #[snafu(display(
+        "`multipart_part_size_bytes` of {} must be between 5 MiB and 5 GiB, the part sizes S3 allows.",
+        part_size
+    ))]
+    InvalidMultipartPartSize { part_size: usize },
+    #[snafu(display(
+        "Objects may be tagged with {} tags, but S3 allows at most {} tags per object. Tags are set by `tags`, `component_id_tag`, `partition_tags`, `event_time_tags`, and `collapse_identical_events`.",
+        count,
+        MAX_OBJECT_TAGS
+    ))]
+    TooManyTags { count: usize },
+}
+
 /// Configuration for the `aws_s3` sink.
 #[configurable_component(sink(
     "aws_s3",
@@ -39,10 +186,18 @@ use crate::{
 #[derive(Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct S3SinkConfig {
-    /// The S3 bucket name.
+    /// The S3 bucket name, or the ARN of an access point.
     ///
     /// This must not include a leading `s3://` or a trailing `/`.
+    ///
+    /// Both S3 access point ARNs, such as `arn:aws:s3:<region>:<account-id>:accesspoint/<name>`,
+    /// and S3 on Outposts access point ARNs, such as
+    /// `arn:aws:s3-outposts:<region>:<account-id>:outpost/<outpost-id>/accesspoint/<name>`, are
+    /// supported. Requests are then sent to the region of the ARN, and `auto_region` has no effect.
     #[configurable(metadata(docs::examples = "my-bucket"))]
+    #[configurable(metadata(
+        docs::examples = "arn:aws:s3:us-east-1:123456789012:accesspoint/my-access-point"
+    ))]
     pub bucket: String,
 
     /// A prefix to apply to all object keys.
@@ -57,6 +212,17 @@ pub struct S3SinkConfig {
     #[configurable(metadata(docs::examples = "application_id={{ application_id }}/date=%F"))]
     pub key_prefix: String,
 
+    /// Templates to render the key prefix of events with when `key_prefix` fails to render, or
+    /// renders empty.
+    ///
+    /// The templates are tried in order, and the first to render a non-empty prefix is used, such
+    /// that events from sources with different schemas are each partitioned by the best field they
+    /// have. Events for which none of them render are handled according to `on_missing_key`.
+    #[serde(default)]
+    #[configurable(metadata(docs::templateable))]
+    #[configurable(metadata(docs::examples = "app={{ app }}/"))]
+    pub key_prefix_fallbacks: Vec<Template>,
+
     /// The timestamp format for the time component of the object key.
     ///
     /// By default, object keys are appended with a timestamp that reflects when the objects are
@@ -76,6 +242,38 @@ pub struct S3SinkConfig {
     #[serde(default = "default_filename_time_format")]
     pub filename_time_format: String,
 
+    /// The timestamp format for a date partition between the key prefix and the filename.
+    ///
+    /// When set, the time the filename is formatted from is also formatted with this format, and
+    /// inserted between the rendered `key_prefix` and the filename, such that a format of
+    /// `year=%Y/month=%m/day=%d/` with a `filename_time_format` of `%H%M%S` results in object keys
+    /// such as `year=2024/month=01/day=15/143022`. Unlike specifiers in `key_prefix`, which are
+    /// rendered from the timestamps of events, the date partition is always consistent with the
+    /// filename, following `filename_time_source` and `timezone`.
+    ///
+    /// Supports the same [`strftime`][chrono_strftime_specifiers] specifiers as
+    /// `filename_time_format`.
+    ///
+    /// [chrono_strftime_specifiers]: https://docs.rs/chrono/latest/chrono/format/strftime/index.html#specifiers
+    #[configurable(metadata(docs::examples = "year=%Y/month=%m/day=%d/"))]
+    pub prefix_time_format: Option<String>,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub filename_time_source: FilenameTimeSource,
+
+    /// The difference between the system clock and event timestamps, in seconds, above which clock
+    /// skew is reported.
+    ///
+    /// When set, the latest event timestamp of each object is compared to the system clock when
+    /// the object is named, and objects for which they differ by more than this are counted by the
+    /// `aws_s3_clock_skew_detected_total` counter, with a warning. This surfaces hosts whose clock
+    /// would date keys in the wrong partition, though delayed events are reported as well. By
+    /// default, clock skew is not checked.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 300))]
+    pub clock_skew_threshold_secs: Option<NonZeroU64>,
+
     /// Whether or not to append a UUID v4 token to the end of the object key.
     ///
     /// The UUID is appended to the timestamp portion of the object key, such that if the object key
@@ -88,12 +286,517 @@ pub struct S3SinkConfig {
     #[configurable(metadata(docs::human_name = "Append UUID to Filename"))]
     pub filename_append_uuid: bool,
 
+    #[configurable(derived)]
+    pub unique_suffix: Option<UniqueSuffix>,
+
+    /// The sequence number of the first object, when prefixing filenames with a sequence number.
+    ///
+    /// When set, the filename of each object is prefixed with a sequence number, zero-padded to 20
+    /// digits, such that an object key of `date=2022-07-18/1658176486` becomes
+    /// `date=2022-07-18/00000000000000000042-1658176486`. Object keys within a key prefix then sort
+    /// in the order objects were built, regardless of clock skew, and retried uploads keep their
+    /// sequence number.
+    ///
+    /// The sequence is kept in memory, restarting at this value whenever Vector restarts, and is
+    /// only monotonic for a single instance of this sink. Writing to the same key prefix from
+    /// several sinks or Vector instances requires keeping their sequences apart, for example with
+    /// distinct key prefixes. Concurrent requests may complete out of order, so set
+    /// `request.concurrency` to `1` if objects must also be written in sequence order.
+    #[configurable(metadata(docs::examples = 0))]
+    pub filename_sequence_start: Option<u64>,
+
     /// The filename extension to use in the object key.
     ///
-    /// This overrides setting the extension based on the configured `compression`.
+    /// This overrides setting the extension based on the configured `compression`, and on the
+    /// codec when it is `cbor` or `message_pack`.
     #[configurable(metadata(docs::examples = "json"))]
     pub filename_extension: Option<String>,
 
+    /// A template used to split objects whenever its rendered value changes.
+    ///
+    /// When set, the template is rendered for each event and compared with the value rendered for
+    /// the previous event of the same partition. Whenever the value changes, the batch for that
+    /// partition is closed and a new one is started, such that each object contains a contiguous
+    /// run of events sharing the same value, such as a session ID.
+    ///
+    /// Unlike partitioning with `key_prefix`, events sharing a value are not grouped together
+    /// if other values are interleaved between them. Batch size limits still apply, so a single run
+    /// may span multiple objects.
+    #[configurable(metadata(docs::templateable))]
+    #[configurable(metadata(docs::examples = "{{ session_id }}"))]
+    pub split_on_change: Option<Template>,
+
+    /// An application name to include in the `User-Agent` header of requests sent to S3.
+    ///
+    /// This allows attributing requests to a specific Vector deployment in AWS CloudTrail and S3
+    /// server access logs, where it appears as `app/<name>` within the user agent.
+    ///
+    /// The placeholders `{hostname}` and `{version}` are replaced with the host name of the system
+    /// and the version of Vector, respectively. Once rendered, the name may only contain
+    /// alphanumeric characters and any of the characters ``!#$%&'*+-.^_`|~``.
+    #[configurable(metadata(docs::examples = "vector-{hostname}"))]
+    #[configurable(metadata(docs::examples = "my-deployment-{version}"))]
+    pub user_agent_app_name: Option<String>,
+
+    /// The minimum number of events an object should contain.
+    ///
+    /// When set, a batch which reaches `batch.timeout_secs` with fewer events is held open until
+    /// it reaches this number of events, rather than being flushed as a small object. Batch size
+    /// limits still apply, so full batches are flushed regardless.
+    ///
+    /// This trades freshness for fewer, larger objects, and requires `max_age_secs` to be set.
+    #[configurable(metadata(docs::type_unit = "events"))]
+    #[configurable(metadata(docs::examples = 1000))]
+    pub min_events_per_object: Option<NonZeroUsize>,
+
+    /// The maximum age of a batch, in seconds, before it is flushed.
+    ///
+    /// This bounds the latency introduced by `min_events_per_object`: once a batch is this old, it
+    /// is flushed even if it contains fewer than the minimum number of events. The age of a batch
+    /// is measured from when its first event is received.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 900))]
+    pub max_age_secs: Option<NonZeroU64>,
+
+    /// The interval, in seconds, of the wall-clock boundaries at which all batches are flushed.
+    ///
+    /// Boundaries fall on multiples of the interval since midnight, in the timezone of `timezone`,
+    /// such that an interval of `3600` flushes all batches at the start of every hour. This lets
+    /// consumers processing the objects of each hour once it has passed find them complete, when
+    /// `key_prefix` partitions events by hour. Batches are flushed regardless of
+    /// `min_events_per_object`.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 3600))]
+    pub flush_at_boundary_secs: Option<NonZeroU64>,
+
+    #[configurable(derived)]
+    pub flush_signal: Option<FlushSignal>,
+
+    /// The minimum interval, in seconds, between writes of objects of the same partition.
+    ///
+    /// A batch which reaches `batch.timeout_secs` within this interval of the previous flush of
+    /// its partition is held open, and keeps accumulating events, until the interval has passed.
+    /// This coalesces frequent writes to the same object key, such as when `key_prefix` renders
+    /// a stable key that is rewritten on each flush, so that it is written at most once per
+    /// interval. Batch size limits, `max_age_secs`, and `flush_at_boundary_secs` still apply.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 60))]
+    pub min_write_interval_secs: Option<NonZeroU64>,
+
+    /// The period, in seconds, after the sink starts during which batches are not flushed for
+    /// reaching `batch.timeout_secs`.
+    ///
+    /// Batches timing out during this period are held open, and keep accumulating events, until
+    /// it ends. This avoids writing a burst of small objects right after Vector starts, while
+    /// few events have arrived yet, so that the first objects are of the same size as the
+    /// following ones. Batch size limits, `max_age_secs`, and `flush_at_boundary_secs` still
+    /// apply.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 300))]
+    pub startup_warmup_secs: Option<NonZeroU64>,
+
+    /// Whether or not to convert the entire object key to lowercase.
+    ///
+    /// This is useful when objects are consumed by tools which treat keys case-insensitively. The
+    /// `key_prefix` is also lowercased when partitioning events, so that events whose keys only
+    /// differ by case are batched into the same object rather than into colliding objects.
+    #[serde(default)]
+    pub lowercase_keys: bool,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub partition_case: PartitionCase,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub on_missing_key: OnMissingKey,
+
+    #[configurable(derived)]
+    pub key_prefix_mapping: Option<KeyPrefixMapping>,
+
+    #[configurable(derived)]
+    pub lag_partitioning: Option<LagPartitioning>,
+
+    #[configurable(derived)]
+    pub range_partitioning: Option<RangePartitioning>,
+
+    #[configurable(derived)]
+    pub hash_partitioning: Option<HashPartitioning>,
+
+    #[configurable(derived)]
+    pub event_time_windowing: Option<EventTimeWindowing>,
+
+    /// Normalizers for the values of fields rendered in `key_prefix`, by the path of the field.
+    ///
+    /// This keeps partitions by enriched fields clean, such as a country code rendered as
+    /// `{{ geo.country }}`, so that `us` and `US` share a prefix and unexpected or missing values
+    /// don't create new ones. Each field must be referenced in `key_prefix`. Events missing a
+    /// normalized field are partitioned under its `invalid` value rather than being handled by
+    /// `on_missing_key`.
+    #[serde(default)]
+    #[configurable(metadata(docs::additional_props_description = "The normalizer of the field."))]
+    pub key_segment_normalizers: HashMap<String, KeySegmentNormalizer>,
+
+    /// The number of partition keys to cache, by the values of the fields they are rendered from.
+    ///
+    /// When set, the partition key of each event, rendered from `key_prefix` along with
+    /// `ssekms_key_id`, `storage_class_template`, `key_prefix_mapping` and `region_routing`, is
+    /// reused for following events sharing the values of the fields they reference, rather than
+    /// rendered again. This saves CPU when many events share a partition, such as logs of the same
+    /// service and hour.
+    /// Keys rendered from a timestamp are cached for each second of it, or each timestamp if they
+    /// render fractions of seconds.
+    ///
+    /// The least recently used keys are evicted once the cache is full. By default, keys are not
+    /// cached.
+    #[configurable(metadata(docs::examples = 1024))]
+    pub partition_key_cache_size: Option<NonZeroUsize>,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub framing_scope: FramingScope,
+
+    /// Whether or not to skip the delimiter after events which already end with it.
+    ///
+    /// Some sources produce events whose encoded form already ends with a newline, which
+    /// newline-delimited framing follows with another one, leaving empty lines that strict NDJSON
+    /// readers reject. When set, events already ending with the delimiter of `framing` are not
+    /// followed by another one. This only applies to `newline_delimited` and `character_delimited`
+    /// framing.
+    #[serde(default)]
+    pub collapse_trailing_delimiter: bool,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub embedded_newlines: EmbeddedNewlines,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub invalid_utf8: InvalidUtf8,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub on_empty_encode: OnEmptyEncode,
+
+    /// Whether or not to collapse batches whose events are all encoded identically.
+    ///
+    /// When set, a batch whose events all encode to the same bytes, such as repeated status
+    /// snapshots, is written as a single copy of its event, and the number of events it stands for
+    /// is set as the `collapsed_count` tag of the object. All of the events are still acknowledged
+    /// once the object is written, while the reported byte sizes are those of the collapsed
+    /// object. Each event is encoded once more to compare it, so this is best left unset unless
+    /// such batches are common.
+    #[serde(default)]
+    pub collapse_identical_events: bool,
+
+    /// Whether or not to check the event count of each object against the records encoded in it.
+    ///
+    /// When set, an error is logged for each object whose number of encoded records differs from
+    /// the number of events it is accounted for, which would otherwise skew the reported event
+    /// counts. This is meant to catch accounting regressions during development and canary
+    /// deployments.
+    #[serde(default)]
+    pub verify_event_counts: bool,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub byte_order_mark: ByteOrderMark,
+
+    /// Whether or not to write a companion metadata object for each object.
+    ///
+    /// Once an object is uploaded, a JSON object is written to the same key with a `.meta.json`
+    /// suffix. It contains the number of events, the uncompressed and compressed sizes of the
+    /// object in bytes, the earliest and latest timestamps of the events, the rendered key
+    /// prefix, the codec, and the ETag and version ID of the object. Unlike object metadata
+    /// headers, this survives tools which strip S3 metadata.
+    #[serde(default)]
+    pub write_object_metadata: bool,
+
+    #[configurable(derived)]
+    pub lineage: Option<LineageConfig>,
+
+    #[configurable(derived)]
+    pub bloom_filter: Option<BloomFilterConfig>,
+
+    /// The compression of the companion objects written by `write_object_metadata` and
+    /// `bloom_filter`.
+    ///
+    /// Companion objects are compressed independently of `compression`, as some catalog tools
+    /// expect them uncompressed, and are given the extension and `Content-Encoding` of their
+    /// compression, such as `.meta.json.gz`. By default, they are uncompressed. Indexes written by
+    /// `write_gzip_index` are always uncompressed, as `bgzip` reads them as they are.
+    #[configurable(derived)]
+    #[serde(default)]
+    pub sidecar_compression: Compression,
+
+    /// The maximum size of events, in bytes.
+    ///
+    /// Events are measured by their size in memory, before they are encoded, and events larger than
+    /// this are rejected and counted as discarded rather than being encoded and batched. This keeps
+    /// a single malformed event, such as one hundreds of megabytes large, from exhausting the
+    /// memory of the sink.
+    ///
+    /// By default, events of any size are accepted.
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    #[configurable(metadata(docs::examples = 10485760))]
+    pub max_event_bytes: Option<NonZeroUsize>,
+
+    /// Whether or not to write a `bgzip` index for each object, for random access into it.
+    ///
+    /// When set, objects are compressed in the blocked gzip format (BGZF) used by `bgzip`, which
+    /// any gzip decoder can read, and once an object is uploaded, the index of its blocks is
+    /// written to the same key with a `.gzi` suffix. Readers such as `bgzip` or `htslib` can then
+    /// decompress an object from a given uncompressed offset, without decompressing it from the
+    /// start. Objects left uncompressed by `compression_min_bytes` have no index.
+    ///
+    /// Requires `compression` to be set to `gzip`.
+    #[serde(default)]
+    pub write_gzip_index: bool,
+
+    /// The value of the OS field of the header of gzip objects.
+    ///
+    /// Some strict decompressors only accept given values, such as `255` (unknown) or `3` (Unix).
+    /// By default, the field is set to `255`. Objects left uncompressed by `compression_min_bytes`,
+    /// or compressed with another of the `compression_candidates`, are unaffected.
+    ///
+    /// Requires `compression` to be set to `gzip`, and can't be combined with `write_gzip_index`.
+    #[configurable(metadata(docs::examples = 3))]
+    pub gzip_header_os: Option<u8>,
+
+    /// Whether or not to compress zstd objects deterministically.
+    ///
+    /// When set, objects compressed with `zstd`, including as one of the `compression_candidates`,
+    /// are compressed in one go, on a single thread, as a single frame with a fixed configuration,
+    /// such that identical objects are compressed to identical bytes. This suits content-addressed
+    /// storage and deduplication, at the cost of holding each object uncompressed in memory while it
+    /// is compressed. Frames record the size of the object, and carry no content checksum.
+    ///
+    /// Gzip objects are always compressed deterministically, as their header carries no
+    /// modification time.
+    #[serde(default)]
+    pub deterministic_compression: bool,
+
+    /// The maximum length of object keys, in bytes.
+    ///
+    /// S3 rejects object keys longer than 1024 bytes. Keys exceeding this length are handled
+    /// according to `on_key_too_long`.
+    #[serde(default = "default_max_key_length")]
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    pub max_key_length: usize,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub on_key_too_long: KeyTooLongStrategy,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub on_key_collision: KeyCollisionStrategy,
+
+    /// A template for the storage class of the created objects.
+    ///
+    /// Takes precedence over `storage_class`, and objects are partitioned by the rendered value.
+    /// It must render to one of the `storage_class` values. Events for which it cannot be
+    /// rendered, or renders to an invalid value, are written with `storage_class`.
+    #[configurable(metadata(docs::examples = "{{ storage_class }}"))]
+    #[configurable(metadata(docs::templateable))]
+    pub storage_class_template: Option<Template>,
+
+    /// Whether or not to profile the encoding and compression of the first events.
+    ///
+    /// Once `profile_startup_events` events have been received, they are encoded and compressed
+    /// as a single object would be, and the achieved throughput and compression ratio are logged.
+    /// The events are sent on as usual without waiting for the profile.
+    #[serde(default)]
+    pub profile_startup: bool,
+
+    /// The number of events to profile when `profile_startup` is enabled.
+    #[serde(default = "default_profile_startup_events")]
+    #[configurable(metadata(docs::type_unit = "events"))]
+    pub profile_startup_events: NonZeroUsize,
+
+    /// A local directory to write objects to before uploading them.
+    ///
+    /// When set, each object is written to this directory before it is uploaded, and removed from
+    /// it once uploaded. Objects left in the directory, such as when Vector crashes or an upload
+    /// fails permanently, are uploaded when the sink next starts, before any new events are
+    /// processed. This makes objects durable once they are built, but events which are still
+    /// being batched are not staged.
+    ///
+    /// The directory must not be shared with other sinks.
+    #[configurable(metadata(docs::examples = "/var/lib/vector/s3_staging"))]
+    pub staging_dir: Option<PathBuf>,
+
+    #[configurable(derived)]
+    pub spill: Option<SpillConfig>,
+
+    #[configurable(derived)]
+    pub retention: Option<RetentionConfig>,
+
+    /// The maximum time for an object to be uploaded after its first event is batched, in seconds.
+    ///
+    /// The time between the first event of each object entering its batch and the object being
+    /// uploaded is always recorded in the `aws_s3_object_upload_latency_seconds` histogram. When
+    /// set, objects taking longer than this are also counted by the `aws_s3_slo_violations_total`
+    /// counter.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 300))]
+    pub upload_slo_secs: Option<NonZeroU64>,
+
+    /// Whether or not to log the version ID of each object once it is uploaded.
+    ///
+    /// On buckets with versioning enabled, each upload creates a new version of its object. When
+    /// set, the bucket, key, and version ID of each object are logged at the `info` level once it
+    /// is uploaded, for audit trails recording exactly which version each write created. Nothing
+    /// is logged for buckets without versioning.
+    #[serde(default)]
+    pub log_object_versions: bool,
+
+    /// Whether or not to record the build of Vector which wrote each object in its metadata.
+    ///
+    /// When set, the `x-amz-meta-vector-version` and `x-amz-meta-vector-build` user-defined metadata
+    /// of each object are set to the version of Vector and the short hash of the Git commit it was
+    /// built from, identifying the exact binary which encoded the object, such as to reproduce its
+    /// encoding when investigating an anomaly. Companion objects are not affected.
+    #[serde(default)]
+    pub write_build_metadata: bool,
+
+    #[configurable(derived)]
+    pub generation_metadata: Option<GenerationMetadataConfig>,
+
+    #[configurable(derived)]
+    pub verify_uploads: Option<VerifyUploadsConfig>,
+
+    /// The size, in bytes, above which objects are uploaded with a multipart upload.
+    ///
+    /// Objects larger than this are uploaded in parts of `multipart_part_size_bytes`, which avoids
+    /// the 5 GiB limit on the size of a single upload, while smaller objects are still uploaded
+    /// with a single request. The size is that of the object once encoded and compressed. Parts
+    /// failing with a retriable error are retried on their own, up to 4 times in all. If a part
+    /// still fails to upload, the multipart upload is aborted and the whole object is uploaded
+    /// again.
+    ///
+    /// Objects larger than 5 GiB are always uploaded with a multipart upload when this is set, and
+    /// batches are only split into several objects above 5 TiB, the largest object S3 allows, rather
+    /// than 5 GiB.
+    ///
+    /// By default, all objects are uploaded with a single request.
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    #[configurable(metadata(docs::examples = 104857600))]
+    pub multipart_threshold_bytes: Option<NonZeroUsize>,
+
+    /// The size, in bytes, of the parts of multipart uploads.
+    ///
+    /// Larger parts take fewer requests to upload an object, at the cost of more data uploaded
+    /// again when a part fails. The size must be between 5 MiB and 5 GiB. Objects too large to be
+    /// uploaded in 10,000 parts of this size, the most S3 allows, are uploaded in larger parts,
+    /// and a warning is logged.
+    ///
+    /// By default, parts are 8 MiB.
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    #[configurable(metadata(docs::examples = 67108864))]
+    pub multipart_part_size_bytes: Option<NonZeroUsize>,
+
+    /// The maximum number of objects uploaded concurrently under each key prefix.
+    ///
+    /// S3 limits the request rate of each prefix, such that uploading many objects to a single
+    /// prefix at once is throttled even when the overall concurrency is within limits. Prefixes
+    /// are those of the keys of the objects, up to their last `/`. Uploads beyond this wait for
+    /// another upload to the same prefix to complete, retries included, while holding on to the
+    /// overall concurrency set by `request.concurrency`.
+    ///
+    /// By default, uploads to the same prefix are only limited by the overall concurrency.
+    #[configurable(metadata(docs::examples = 4))]
+    pub max_concurrent_uploads_per_prefix: Option<NonZeroUsize>,
+
+    /// Whether or not to send requests to the region of the bucket when it is in another region.
+    ///
+    /// When S3 reports that the bucket is in another region than the configured `region`, the
+    /// request is sent again to the region of the bucket, as are all later requests. Otherwise,
+    /// the request fails with an error naming the region of the bucket.
+    ///
+    /// Has no effect when `endpoint` is set.
+    #[serde(default)]
+    pub auto_region: bool,
+
+    #[configurable(derived)]
+    pub region_routing: Option<RegionRouting>,
+
+    /// A prefix to upload objects under until they are committed.
+    ///
+    /// When set, objects are first uploaded under this prefix, such that an object key of
+    /// `date=2022-07-18/1658176486.log.gz` is uploaded as
+    /// `_uncommitted/date=2022-07-18/1658176486.log.gz`. Once uploaded, the object is copied to its
+    /// key and deleted from under the prefix, and only then are its events acknowledged. Consumers
+    /// of the other keys never see objects which failed to upload.
+    ///
+    /// This requires the `s3:GetObject` and `s3:DeleteObject` permissions on the uncommitted
+    /// objects, and objects larger than 5 GB cannot be copied. Objects left under the prefix by
+    /// failed uploads are not cleaned up, which can be done with a lifecycle rule.
+    #[configurable(metadata(docs::examples = "_uncommitted/"))]
+    pub uncommitted_key_prefix: Option<String>,
+
+    /// A suffix to apply to object keys until they are committed.
+    ///
+    /// When set, objects are first uploaded with this suffix, such that an object key of
+    /// `date=2022-07-18/1658176486.log.gz` is uploaded as `date=2022-07-18/1658176486.log.gz.tmp`,
+    /// then committed like with `uncommitted_key_prefix`, with which it can be combined. Consumers
+    /// watching the prefix of the objects can then skip the keys ending with the suffix, and only
+    /// ever read complete objects.
+    ///
+    /// This requires the same permissions as `uncommitted_key_prefix`.
+    #[configurable(metadata(docs::examples = ".tmp"))]
+    pub uncommitted_key_suffix: Option<String>,
+
+    #[configurable(derived)]
+    pub checksum_footer: Option<ChecksumFooter>,
+
+    /// Whether or not to upload the objects of each partition in the order they are built.
+    ///
+    /// When set, an object is only uploaded once the previous object of its partition has been
+    /// uploaded, including any retries, while objects of different partitions are still uploaded
+    /// concurrently. This keeps the order of uploads consistent with the order of object keys,
+    /// such as with `filename_sequence_start`.
+    ///
+    /// Objects waiting for the previous object of their partition count towards
+    /// `request.concurrency`, and towards `request.timeout_secs` once their upload starts, such
+    /// that objects waiting for longer than the timeout are retried.
+    #[serde(default)]
+    pub preserve_partition_order: bool,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub payload_signing: PayloadSigning,
+
+    /// The key of a tag set on each object to the ID of the sink component that wrote it.
+    ///
+    /// This attributes objects to the sink that wrote them, such as for cost allocation or
+    /// debugging in buckets written by several sinks or Vector instances. A tag of the same key
+    /// set in `tags` takes precedence.
+    #[configurable(metadata(docs::examples = "vector_component_id"))]
+    pub component_id_tag: Option<String>,
+
+    /// Tags to apply to the objects of partitions whose key prefix starts with a given prefix.
+    ///
+    /// This keeps tags such as a governance classification in sync with the partition objects are
+    /// written under, such as a segment added by `key_prefix_mapping`. The tags of every entry
+    /// whose prefix matches the rendered key prefix are applied, in order, such that later entries
+    /// take precedence over earlier ones, and all of them over the tags set in `tags`.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "example_partition_tags()"))]
+    pub partition_tags: Vec<PartitionTags>,
+
+    #[configurable(derived)]
+    pub event_time_tags: Option<EventTimeTagsConfig>,
+
+    #[configurable(derived)]
+    pub summary_object: Option<SummaryObjectConfig>,
+
+    #[configurable(derived)]
+    pub heartbeat: Option<HeartbeatConfig>,
+
+    #[configurable(derived)]
+    pub emf_summary: Option<EmfSummaryConfig>,
+
     #[serde(flatten)]
     pub options: S3Options,
 
@@ -103,6 +806,16 @@ pub struct S3SinkConfig {
     #[serde(flatten)]
     pub encoding: EncodingConfigWithFraming,
 
+    /// Whether or not to write objects encoded with the `avro` codec as Avro Object Container
+    /// Files.
+    ///
+    /// When enabled, each object starts with a header embedding the schema, and each event is
+    /// written as a data block of its own, such that each object is a standard `.avro` file
+    /// readable by any Avro tool, uploaded with the `application/avro` content type and the `avro`
+    /// extension. Requires the `avro` codec, with either the `bytes` framing or no framing set.
+    #[serde(default)]
+    pub avro_object_container_file: bool,
+
     /// Compression configuration.
     ///
     /// All compression algorithms use the default compression level unless otherwise specified.
@@ -113,6 +826,29 @@ pub struct S3SinkConfig {
     #[serde(default = "Compression::gzip_default")]
     pub compression: Compression,
 
+    /// The size, in bytes, below which objects are uploaded uncompressed.
+    ///
+    /// Compression formats add headers and footers of their own, so compressing very small objects
+    /// can make them larger than they were. Objects whose encoded size is below this threshold are
+    /// uploaded as is, without the extension and `Content-Encoding` of the compression, unless
+    /// `filename_extension` or `content_encoding` are set.
+    ///
+    /// By default, all objects are compressed.
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    #[configurable(metadata(docs::examples = 256))]
+    pub compression_min_bytes: Option<NonZeroUsize>,
+
+    /// Compression algorithms to also compress each object with, uploading whichever of these and
+    /// `compression` makes the object the smallest.
+    ///
+    /// The extension and `Content-Encoding` of objects are those of the selected compression,
+    /// unless `filename_extension` or `content_encoding` are set. This trades CPU for storage, as
+    /// each object is compressed once per algorithm, and the number of objects each algorithm was
+    /// selected for is counted by the `aws_s3_compression_selected_total` counter. By default,
+    /// objects are only compressed with `compression`.
+    #[serde(default)]
+    pub compression_candidates: Vec<Compression>,
+
     #[configurable(derived)]
     #[serde(default)]
     pub batch: BatchConfig<BulkSizeBasedDefaultBatchSettings>,
@@ -121,6 +857,32 @@ pub struct S3SinkConfig {
     #[serde(default)]
     pub request: TowerRequestConfig,
 
+    /// Error codes to retry requests on, on top of the ones retried by default.
+    ///
+    /// S3-compatible stores, such as MinIO or Wasabi, may respond with non-standard error codes
+    /// when throttling requests. Requests failing with an error response whose error code is
+    /// listed, or whose error message or body contains one of the listed values, are retried.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "SlowDownRead"))]
+    #[configurable(metadata(docs::examples = "XMinioServerNotInitialized"))]
+    pub retry_extra_retryable_codes: Vec<String>,
+
+    /// How long, in seconds, to keep retrying requests which fail to connect to the endpoint.
+    ///
+    /// Failures to resolve the name of the endpoint, refused connections, and connection timeouts
+    /// are transient for custom endpoints, such as during a DNS failover, and are retried with
+    /// backoff. Once requests have been failing to connect for longer than this, they are no longer
+    /// retried, until a request succeeds.
+    ///
+    /// By default, they are retried for as long as any other retriable error.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 300))]
+    pub connection_retry_limit_secs: Option<NonZeroU64>,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub on_persistent_error: OnPersistentError,
+
     #[configurable(derived)]
     pub tls: Option<TlsConfig>,
 
@@ -141,42 +903,1104 @@ pub struct S3SinkConfig {
     pub timezone: Option<TimeZone>,
 }
 
-pub(super) fn default_key_prefix() -> String {
-    "date=%F".to_string()
-}
+/// A table normalizing a value rendered from each event into a segment prefixed to `key_prefix`.
+///
+/// This keeps prefixes stable when the same value is represented inconsistently, such as a
+/// severity of `ERROR`, `err` or `3`. The segment is followed by a `/`, and precedes the rendered
+/// `key_prefix`, including the fallback prefix of `on_missing_key`.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct KeyPrefixMapping {
+    /// The template rendering the value to normalize.
+    #[configurable(metadata(docs::examples = "{{ severity }}"))]
+    #[configurable(metadata(docs::templateable))]
+    pub source: Template,
 
-pub(super) fn default_filename_time_format() -> String {
-    "%s".to_string()
+    /// The segment each rendered value is normalized to.
+    #[configurable(metadata(
+        docs::additional_props_description = "The segment for the rendered value."
+    ))]
+    #[configurable(metadata(docs::examples = "example_key_prefix_mapping_values()"))]
+    pub values: HashMap<String, String>,
+
+    /// The segment of values missing from `values`, or which fail to render.
+    #[configurable(metadata(docs::examples = "other"))]
+    pub default: String,
 }
 
-impl GenerateConfig for S3SinkConfig {
-    fn generate_config() -> toml::Value {
-        toml::Value::try_from(Self {
-            bucket: "".to_owned(),
-            key_prefix: default_key_prefix(),
-            filename_time_format: default_filename_time_format(),
-            filename_append_uuid: true,
-            filename_extension: None,
-            options: S3Options::default(),
-            region: RegionOrEndpoint::default(),
-            encoding: (None::<FramingConfig>, TextSerializerConfig::default()).into(),
-            compression: Compression::gzip_default(),
-            batch: BatchConfig::default(),
-            request: TowerRequestConfig::default(),
-            tls: Some(TlsConfig::default()),
-            auth: AwsAuthentication::default(),
-            acknowledgements: Default::default(),
-            timezone: Default::default(),
-        })
-        .unwrap()
-    }
+fn example_key_prefix_mapping_values() -> HashMap<String, String> {
+    HashMap::from([
+        ("ERROR".to_owned(), "error".to_owned()),
+        ("err".to_owned(), "error".to_owned()),
+        ("WARN".to_owned(), "warn".to_owned()),
+    ])
 }
 
-#[async_trait::async_trait]
-#[typetag::serde(name = "aws_s3")]
-impl SinkConfig for S3SinkConfig {
-    async fn build(&self, cx: SinkContext) -> crate::Result<(VectorSink, Healthcheck)> {
-        let service = self.create_service(&cx.proxy).await?;
+/// Routing of events to buckets in several regions, by a value rendered from each event.
+///
+/// This keeps events in the bucket of their region, such as for data residency. Events are
+/// partitioned by the rendered value, so that objects never mix events bound for different
+/// targets, and each object is uploaded to the region of its target by the same client. Objects
+/// are only written to the buckets of `targets`, never to `bucket`, and `auto_region` has no
+/// effect.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RegionRouting {
+    /// The template rendering the value selecting the target of each event.
+    ///
+    /// Events for which it fails to render are dropped.
+    #[configurable(metadata(docs::examples = "{{ residency }}"))]
+    #[configurable(metadata(docs::templateable))]
+    pub source: Template,
+
+    /// The target of each rendered value.
+    ///
+    /// Batches whose value has no target are rejected rather than written elsewhere, with an error
+    /// which doesn't include the value.
+    #[configurable(metadata(docs::additional_props_description = "The target of the value."))]
+    pub targets: HashMap<String, S3Target>,
+}
+
+/// A bucket, and the region it is in.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct S3Target {
+    /// The S3 bucket name.
+    #[configurable(metadata(docs::examples = "my-eu-bucket"))]
+    pub bucket: String,
+
+    /// The AWS region of the bucket.
+    #[configurable(metadata(docs::examples = "eu-west-1"))]
+    pub region: String,
+}
+
+/// Partitioning of events by their ingest lag, the time between their timestamp and when they are
+/// received by the sink.
+///
+/// This isolates late-arriving events from on-time ones, such as to inspect backpressure. The
+/// segment of the lag of each event is followed by a `/`, and precedes the segment of
+/// `key_prefix_mapping` and the rendered `key_prefix`, such that an object key of
+/// `date=2022-07-18/1658176486` becomes `late/date=2022-07-18/1658176486`. Events without a
+/// timestamp, or with one in the future, are on time.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LagPartitioning {
+    /// The field holding the timestamp to measure the lag of.
+    ///
+    /// By default, the timestamp of the event is used.
+    #[configurable(metadata(docs::examples = "event_time"))]
+    pub timestamp_field: Option<ConfigValuePath>,
+
+    /// The segment of events lagging less than every threshold.
+    #[serde(default = "default_on_time_segment")]
+    pub on_time: String,
+
+    /// The segments of events lagging at least as much as each threshold.
+    ///
+    /// Events are partitioned under the segment of the largest threshold their lag reaches.
+    #[configurable(metadata(docs::examples = "example_lag_thresholds()"))]
+    pub thresholds: Vec<LagThreshold>,
+}
+
+/// Partitioning of events by tumbling windows of their timestamp, with allowed lateness.
+///
+/// The sink tracks a watermark, the latest timestamp seen. Events within the window of the
+/// watermark, or after it, are partitioned by `key_prefix` as usual. Events before it, but within
+/// the allowed lateness of its start, are partitioned as if their timestamp were its start, so that
+/// they are written to the current window rather than reopening a closed one. Events later than
+/// that are handled according to `late_events`.
+///
+/// Windows are aligned to the Unix epoch, and the times of `key_prefix` should be no finer than
+/// the windows. Events without a timestamp are partitioned as usual.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EventTimeWindowing {
+    /// The field holding the timestamp of the events.
+    ///
+    /// By default, the timestamp of the event is used.
+    #[configurable(metadata(docs::examples = "event_time"))]
+    pub timestamp_field: Option<ConfigValuePath>,
+
+    /// The length of the windows, in seconds.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 3600))]
+    pub window_secs: NonZeroU64,
+
+    /// How late events can be, in seconds before the start of the current window, to still be
+    /// written to it.
+    #[serde(default)]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 300))]
+    pub allowed_lateness_secs: u64,
+
+    /// The prefix of the keys of events later than the allowed lateness.
+    #[serde(default = "default_late_prefix")]
+    pub late_prefix: String,
+
+    /// How events later than the allowed lateness are handled.
+    #[serde(default)]
+    pub late_events: LateEvents,
+}
+
+fn default_late_prefix() -> String {
+    "_late/".to_owned()
+}
+
+fn default_on_time_segment() -> String {
+    "ontime".to_owned()
+}
+
+fn example_lag_thresholds() -> Vec<LagThreshold> {
+    vec![
+        LagThreshold {
+            min_lag_secs: 300,
+            segment: "late".to_owned(),
+        },
+        LagThreshold {
+            min_lag_secs: 3600,
+            segment: "very_late".to_owned(),
+        },
+    ]
+}
+
+/// The segment of events lagging at least a given time.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LagThreshold {
+    /// The minimum lag of the events of the segment, in seconds.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    pub min_lag_secs: u64,
+
+    /// The segment of the events.
+    pub segment: String,
+}
+
+/// Partitioning of events into a fixed number of buckets by the hash of a key, for balanced read
+/// parallelism.
+///
+/// Each event is assigned to the bucket `crc32(key) % buckets`, where `crc32` is the CRC-32 (IEEE
+/// 802.3, as used by gzip) of the UTF-8 bytes of the key rendered from the event, so that readers
+/// can reproduce the assignment. Events are partitioned under `bucket=<number>`, the number
+/// zero-padded to the width of the largest one, followed by a `/`, preceding the label of
+/// `range_partitioning`, such that an object key of `date=2022-07-18/1658176486` becomes
+/// `bucket=07/date=2022-07-18/1658176486`.
+///
+/// Events sharing a key are always in the same bucket, so buckets are balanced as long as the key
+/// has many distinct values, each making up a small share of the events. Events whose key fails to
+/// render are in bucket 0.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HashPartitioning {
+    /// The key events are hashed by.
+    #[configurable(metadata(docs::examples = "{{ user_id }}"))]
+    pub key: Template,
+
+    /// The number of buckets.
+    #[configurable(metadata(docs::examples = 16))]
+    pub buckets: NonZeroU32,
+}
+
+/// Partitioning of events by ranges of the value of a numeric field.
+///
+/// Events are partitioned under the label of the first range their value falls in, followed by a
+/// `/`, preceding the segment of `key_prefix_mapping` and the rendered `key_prefix`, such that an
+/// object key of `date=2022-07-18/1658176486` becomes `100-500/date=2022-07-18/1658176486`. Unlike
+/// rendering the value itself, this keeps a small, stable set of prefixes, which downstream
+/// queries can select ranges of values by.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RangePartitioning {
+    /// The field holding the value of log events.
+    #[configurable(metadata(docs::examples = "latency_ms"))]
+    pub field: ConfigValuePath,
+
+    /// The ranges of values, and their labels.
+    #[configurable(metadata(docs::examples = "example_ranges()"))]
+    pub ranges: Vec<RangeBucket>,
+
+    /// The label of events whose value falls in none of the ranges, or which have no integer or
+    /// float value for the field.
+    #[serde(default = "default_range_label")]
+    pub default: String,
+}
+
+fn default_range_label() -> String {
+    "other".to_owned()
+}
+
+fn example_ranges() -> Vec<RangeBucket> {
+    vec![
+        RangeBucket {
+            min: Some(0.0),
+            max: Some(100.0),
+            label: "0-100".to_owned(),
+        },
+        RangeBucket {
+            min: Some(100.0),
+            max: Some(500.0),
+            label: "100-500".to_owned(),
+        },
+        RangeBucket {
+            min: Some(500.0),
+            max: None,
+            label: "500+".to_owned(),
+        },
+    ]
+}
+
+/// A labeled range of values.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RangeBucket {
+    /// The lower bound of the range, inclusive.
+    ///
+    /// By default, the range is unbounded below.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+
+    /// The upper bound of the range, exclusive.
+    ///
+    /// By default, the range is unbounded above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+
+    /// The label of the events in the range.
+    pub label: String,
+}
+
+/// Tags applied to the objects of the partitions under a key prefix.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PartitionTags {
+    /// The prefix of the rendered key prefix of the partitions to tag.
+    pub prefix: String,
+
+    /// The tags to apply to the objects of the partitions.
+    #[configurable(metadata(docs::additional_props_description = "A single tag."))]
+    pub tags: BTreeMap<String, String>,
+}
+
+fn example_partition_tags() -> Vec<PartitionTags> {
+    vec![PartitionTags {
+        prefix: "pii/".to_owned(),
+        tags: BTreeMap::from([("classification".to_owned(), "pii".to_owned())]),
+    }]
+}
+
+/// Normalizes the value of a field rendered in `key_prefix`.
+///
+/// Values are trimmed, and lowercased if configured, and then checked against the allowed values.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct KeySegmentNormalizer {
+    /// Whether or not to lowercase values, and the allowed values, before comparing them.
+    #[serde(default = "crate::serde::default_true")]
+    pub lowercase: bool,
+
+    /// The values allowed in the key prefix, such as a list of ISO country codes.
+    #[configurable(metadata(docs::examples = "example_allowed_segments()"))]
+    pub allowed: Vec<String>,
+
+    /// The value rendered in place of values which are missing or not allowed.
+    #[serde(default = "default_invalid_segment")]
+    pub invalid: String,
+}
+
+fn example_allowed_segments() -> Vec<String> {
+    ["us", "fr", "de"].map(ToOwned::to_owned).to_vec()
+}
+
+fn default_invalid_segment() -> String {
+    "unknown".to_owned()
+}
+
+/// Configuration for writing a companion bloom filter object for each object.
+///
+/// Once an object is uploaded, a JSON object is written to the same key with a `.bloom` suffix,
+/// holding a bloom filter over the value rendered from each of its events. Point lookups can then
+/// read the filters first, and skip the objects whose filter does not contain the value.
+///
+/// The filter object contains the `source` template, the `hash` used, which is always `md5`, the
+/// `num_bits` and `num_hashes` of the filter, the `item_count` of values inserted, and the `bits`
+/// of the filter encoded as base64. To test a value, compute its MD5 digest, and read its first
+/// and last 8 bytes as the little-endian unsigned integers `h1` and `h2`. The value may be in the
+/// object if, for each `i` from `0` to `num_hashes - 1`, the bit `(h1 + i * h2) mod num_bits` is
+/// set, computed with wrapping 64-bit arithmetic, where bit `j` is the bit `j mod 8` of byte
+/// `j / 8` of `bits`, counting from the least significant bit. Otherwise, the value is certainly
+/// not in the object.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BloomFilterConfig {
+    /// The template rendering the value each event is indexed by.
+    ///
+    /// Events whose value fails to render are left out of the filter.
+    #[configurable(metadata(docs::examples = "{{ user_id }}"))]
+    #[configurable(metadata(docs::templateable))]
+    pub source: Template,
+
+    /// The number of distinct values objects are expected to contain.
+    ///
+    /// Along with `false_positive_rate`, this sets the size of the filters. Objects containing
+    /// more values than expected have a higher false positive rate.
+    #[serde(default = "default_bloom_filter_expected_items")]
+    pub expected_items: NonZeroUsize,
+
+    /// The rate at which the filter reports values missing from an object as possibly present,
+    /// when the object contains `expected_items` values.
+    ///
+    /// Must be strictly between `0` and `1`.
+    #[serde(default = "default_bloom_filter_false_positive_rate")]
+    pub false_positive_rate: f64,
+}
+
+fn default_bloom_filter_expected_items() -> NonZeroUsize {
+    NonZeroUsize::new(10_000).unwrap()
+}
+
+const fn default_bloom_filter_false_positive_rate() -> f64 {
+    0.01
+}
+
+/// The behavior when the `key_prefix` of an event fails to render.
+///
+/// This happens when the event is missing a field referenced by the template.
+#[configurable_component]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+#[configurable(metadata(
+    docs::enum_tag_description = "The action to take when the `key_prefix` of an event fails to render."
+))]
+pub enum OnMissingKey {
+    /// Drop the event.
+    #[default]
+    Drop,
+
+    /// Write the event under a fallback key prefix.
+    ///
+    /// The `s3_key_prefix_fallback_events_total` counter tracks the number of events written under
+    /// the fallback prefix, which can be used to detect events missing fields upstream.
+    Fallback {
+        /// The key prefix to write events under when their `key_prefix` fails to render.
+        #[configurable(metadata(docs::examples = "_unpartitioned/"))]
+        prefix: String,
+    },
+}
+
+/// The behavior when requests keep failing once retries are exhausted.
+#[configurable_component]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+#[configurable(metadata(
+    docs::enum_tag_description = "The action to take when requests keep failing once retries are exhausted."
+))]
+pub enum OnPersistentError {
+    /// Keep accepting events, dropping the events of each failed request.
+    ///
+    /// Dropped events are counted by the `component_discarded_events_total` counter.
+    #[default]
+    Degrade,
+
+    /// Stop the sink with an error once consecutive requests have failed.
+    ///
+    /// This stops Vector, such that the failure is noticed by whatever supervises it rather than
+    /// events being dropped until it is. Events still buffered or in flight when the sink stops
+    /// are not acknowledged, so sources supporting end-to-end acknowledgements send them again.
+    FailFast {
+        /// The number of consecutive requests which must fail for the sink to stop.
+        ///
+        /// Any request which succeeds resets the count.
+        #[serde(default = "default_max_consecutive_failures")]
+        #[configurable(metadata(docs::examples = 10))]
+        max_consecutive_failures: NonZeroUsize,
+    },
+}
+
+fn default_max_consecutive_failures() -> NonZeroUsize {
+    NonZeroUsize::new(10).unwrap()
+}
+
+/// Whether the batch prefix and suffix of the encoding apply to each object, or to all the objects
+/// of a partition.
+///
+/// This matters for encodings which enclose batches of events, such as JSON arrays when using the
+/// `json` codec with the default framing, when the objects are concatenated by consumers.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FramingScope {
+    /// Each object is a complete document, enclosed by the batch prefix and suffix.
+    #[default]
+    PerObject,
+
+    /// The objects of each partition form a single document once concatenated in order.
+    ///
+    /// Only the first object of a partition starts with the batch prefix, and following objects
+    /// start with the separator between events instead. Only the last object, written when Vector
+    /// shuts down, ends with the batch suffix. Partitions without events left to write on shutdown
+    /// are closed with an object containing only the batch suffix.
+    ///
+    /// Objects must be concatenated in the order they were written, so this is best combined with
+    /// a `filename_time_format` which sorts in that order. The set of partitions which have
+    /// started a document is kept in memory until shutdown.
+    Stream,
+}
+
+/// A footer line appended to each object, containing a checksum of the rest of the object.
+///
+/// The checksum covers all the bytes of the object preceding the footer, before compression. When
+/// the object does not end with a newline, one is written before the footer and covered by the
+/// checksum. Objects of a partition no longer concatenate into a single document when
+/// `framing_scope` is set to `stream`, unless consumers remove the footers.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ChecksumFooter {
+    #[configurable(derived)]
+    #[serde(default)]
+    pub algorithm: ChecksumAlgorithm,
+
+    /// The format of the footer line, excluding its trailing newline.
+    ///
+    /// The placeholders `{algorithm}` and `{checksum}` are replaced with the name of the algorithm
+    /// and the checksum in lowercase hexadecimal, respectively.
+    #[serde(default = "default_checksum_footer_format")]
+    #[configurable(metadata(docs::examples = "#{algorithm}:{checksum}"))]
+    pub format: String,
+}
+
+/// The algorithm used to compute the checksum of objects.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumAlgorithm {
+    /// CRC-32, as used by gzip.
+    #[default]
+    Crc32,
+
+    /// MD5.
+    Md5,
+}
+
+impl ChecksumAlgorithm {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Crc32 => "crc32",
+            Self::Md5 => "md5",
+        }
+    }
+}
+
+/// Configuration for writing a heartbeat object periodically.
+///
+/// The heartbeat object is overwritten with each heartbeat, so its age shows whether the sink is
+/// running even when no events flow through it.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HeartbeatConfig {
+    /// The key of the heartbeat object.
+    ///
+    /// The object contains, as JSON, the `timestamp` it was written at, the `host` running Vector,
+    /// and the `component_id` of the sink. The key is used as is, and should be outside of
+    /// `key_prefix`, so that the heartbeat is not mistaken for an object of events.
+    #[configurable(metadata(docs::examples = "heartbeats/vector.json"))]
+    pub key: String,
+
+    /// How often to write the heartbeat object, in seconds.
+    #[serde(default = "default_heartbeat_interval_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::human_name = "Heartbeat Interval"))]
+    pub interval_secs: NonZeroU64,
+}
+
+fn default_heartbeat_interval_secs() -> NonZeroU64 {
+    NonZeroU64::new(60).unwrap()
+}
+
+/// Configuration for tagging objects with the range of the timestamps of their events.
+///
+/// Objects are tagged with the earliest and latest timestamps of their events, formatted as RFC
+/// 3339 to the second, under the `min_event_time` and `max_event_time` tags. Along with
+/// tag-based lifecycle rules, this allows objects to be expired according to the time of their
+/// events rather than the time they were uploaded. Events without a timestamp are left out of the
+/// range, and objects without any event with a timestamp are not tagged.
+///
+/// As S3 allows at most 10 tags per object, Vector fails to start if these tags, along with the
+/// other tags set by the sink, could exceed that limit.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EventTimeTagsConfig {
+    /// The field holding the timestamp of log events.
+    ///
+    /// By default, the timestamp of the event is used. Values of the field which are not
+    /// timestamps are ignored.
+    #[configurable(metadata(docs::examples = "event_time"))]
+    pub timestamp_field: Option<ConfigValuePath>,
+}
+
+/// Configuration for writing a summary object aggregating the events of each window.
+///
+/// The events of each object are added to the running aggregate of its window, which is written,
+/// once the object is uploaded, to the summary object of the window, overwriting its previous
+/// version. Windows are identified by the key of their summary object, such that a key rendered
+/// from the date gives an aggregate per day. This lets dashboards read a single object rather than
+/// every object of a window.
+///
+/// Summary objects are JSON objects holding the time they were `updated_at`, the `count` of events
+/// of the window, and, under `fields`, the `count`, `sum`, `min`, and `max` of the numeric values
+/// of each aggregated field. Running aggregates start from zero when Vector starts, and only those
+/// of the 1024 most recently updated windows are kept. As objects are uploaded concurrently, a
+/// summary object may be briefly overwritten by an earlier version.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SummaryObjectConfig {
+    /// The template of the key of the summary object of each object, rendered with its first
+    /// event.
+    ///
+    /// By default, summary objects are written as `_summary.json` under the key prefix of the
+    /// objects, giving an aggregate per partition.
+    #[configurable(metadata(docs::examples = "summaries/date=%F/_summary.json"))]
+    #[configurable(metadata(docs::templateable))]
+    pub key: Option<Template>,
+
+    /// The fields of log events to aggregate.
+    ///
+    /// Values of the fields which are not integers or floats are ignored.
+    #[serde(default)]
+    #[configurable(metadata(docs::examples = "duration_ms"))]
+    pub fields: Vec<ConfigValuePath>,
+}
+
+/// Configuration for recording the source offsets of the events of each object.
+///
+/// The companion metadata object written by `write_object_metadata` is given a `lineage` array
+/// holding, for each source partition of the events of the object, the values of its
+/// `partition_fields`, the `min_offset` and `max_offset` of its events, and their `event_count`.
+/// This lets consumers trace an object back to the exact range of records it was built from, such
+/// as the offsets of each Kafka topic partition, for auditing and replay.
+///
+/// Only log events holding an integer offset are recorded.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LineageConfig {
+    /// The fields of log events identifying their source partition.
+    ///
+    /// Fields the events have no value for are left out of their partition.
+    #[serde(default = "default_lineage_partition_fields")]
+    #[configurable(metadata(docs::examples = "topic"))]
+    pub partition_fields: Vec<ConfigValuePath>,
+
+    /// The field of log events holding their offset within their source partition.
+    #[serde(default = "default_lineage_offset_field")]
+    #[configurable(metadata(docs::examples = "offset"))]
+    pub offset_field: ConfigValuePath,
+}
+
+fn default_lineage_partition_fields() -> Vec<ConfigValuePath> {
+    vec!["topic".into(), "partition".into()]
+}
+
+fn default_lineage_offset_field() -> ConfigValuePath {
+    "offset".into()
+}
+
+/// Configuration for reading back uploaded objects to verify them.
+///
+/// Once uploaded, objects are requested with `HeadObject`, and their size and ETag are compared to
+/// the uploaded body, catching objects which S3 stored differently than they were sent. The ETag
+/// is not compared for objects encrypted with AWS KMS, as it is not their MD5 digest.
+///
+/// This requires the `s3:GetObject` permission on the objects, and costs a request per verified
+/// object, so only a sample of the objects can be verified. Failed verifications are counted by
+/// the `aws_s3_upload_verification_failures_total` counter.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyUploadsConfig {
+    /// Verify one in this many uploaded objects.
+    #[serde(default = "default_verify_uploads_sample_rate")]
+    #[configurable(metadata(docs::examples = 100))]
+    pub sample_rate: NonZeroUsize,
+
+    #[configurable(derived)]
+    #[serde(default)]
+    pub on_mismatch: OnUploadMismatch,
+}
+
+const fn default_verify_uploads_sample_rate() -> NonZeroUsize {
+    NonZeroUsize::MIN
+}
+
+/// The behavior when an uploaded object does not match its upload once read back.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnUploadMismatch {
+    /// Upload the object again, according to the retry settings of the sink.
+    #[default]
+    Retry,
+
+    /// Fail the request without retrying it, rejecting its events.
+    Error,
+}
+
+/// Configuration for writing summaries of the activity of the sink as CloudWatch embedded metric
+/// format (EMF) objects.
+///
+/// Every interval, an object is written containing the number of events and bytes written by the
+/// sink, and the number of requests which failed, during the interval, as an EMF document with
+/// the `ComponentId` and `Host` dimensions. Once ingested by CloudWatch, they are extracted as
+/// metrics, which allows the sink to be monitored where the metrics of Vector can't be scraped.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EmfSummaryConfig {
+    /// The prefix of the keys of the summary objects.
+    ///
+    /// Objects are named after the time they are written, in milliseconds since the Unix epoch,
+    /// such as `emf/1658176486000.json`. The prefix should be outside of `key_prefix`, so that the
+    /// summaries are not mistaken for objects of events.
+    #[configurable(metadata(docs::examples = "emf/"))]
+    pub key_prefix: String,
+
+    /// The CloudWatch namespace of the metrics.
+    #[serde(default = "default_emf_namespace")]
+    pub namespace: String,
+
+    /// How often to write a summary object, in seconds.
+    #[serde(default = "default_emf_interval_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::human_name = "EMF Summary Interval"))]
+    pub interval_secs: NonZeroU64,
+}
+
+fn default_emf_namespace() -> String {
+    "Vector".to_owned()
+}
+
+fn default_emf_interval_secs() -> NonZeroU64 {
+    NonZeroU64::new(60).unwrap()
+}
+
+/// Spills objects which fail to upload, once their retries are exhausted, to a local directory.
+///
+/// Spilled objects are reported as delivered, rather than being dropped or failing the pipeline,
+/// and are uploaded again from the directory every retry interval, starting when the sink starts.
+/// Objects are removed from the directory once uploaded. When the directory is full, its oldest
+/// objects are dropped to make room for new ones; objects larger than the directory are not
+/// spilled. Objects rejected without being uploaded, such as for an invalid key, are not spilled
+/// either.
+///
+/// Spilled objects are uploaded without their companion metadata, bloom filter, or index objects.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpillConfig {
+    /// The local directory to spill objects to.
+    ///
+    /// The directory must not be shared with other sinks, or be the same as `staging_dir`.
+    #[configurable(metadata(docs::examples = "/var/lib/vector/s3_spill"))]
+    pub path: PathBuf,
+
+    /// The maximum total size of the spilled objects, in bytes.
+    #[serde(default = "default_spill_max_bytes")]
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    pub max_bytes: NonZeroU64,
+
+    /// How often to upload the spilled objects again, in seconds.
+    #[serde(default = "default_spill_retry_interval_secs")]
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::human_name = "Spill Retry Interval"))]
+    pub retry_interval_secs: NonZeroU64,
+}
+
+fn default_spill_max_bytes() -> NonZeroU64 {
+    NonZeroU64::new(1024 * 1024 * 1024).unwrap()
+}
+
+fn default_spill_retry_interval_secs() -> NonZeroU64 {
+    NonZeroU64::new(300).unwrap()
+}
+
+/// Limits on the objects kept under each key prefix, enforced by deleting the oldest of them.
+///
+/// **This deletes objects.** Once an object is uploaded, the objects directly under its key prefix,
+/// up to the last `/` of its key, are listed, and the oldest of them, by their last modification
+/// time, are deleted until the rest are within the limits. The uploaded object itself is never
+/// deleted. This keeps a rolling buffer of the latest objects in S3 without lifecycle rules.
+///
+/// Every object under the prefix is counted and may be deleted, including objects written by other
+/// writers, so the key prefix must be dedicated to the sink. As companion objects are written under
+/// the same prefix, this can't be combined with `write_object_metadata`, `bloom_filter`,
+/// `write_gzip_index`, `uncommitted_key_suffix`, or a `summary_object` without a `key`.
+///
+/// This requires the `s3:ListBucket` and `s3:DeleteObject` permissions, and costs a `ListObjectsV2`
+/// request per uploaded object. Objects which fail to be deleted are retried on the next upload to
+/// the same prefix.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// The maximum number of objects to keep under each key prefix.
+    #[configurable(metadata(docs::examples = 100))]
+    pub max_objects: Option<NonZeroUsize>,
+
+    /// The maximum total size of the objects to keep under each key prefix, in bytes.
+    #[configurable(metadata(docs::type_unit = "bytes"))]
+    #[configurable(metadata(docs::examples = 10737418240_u64))]
+    pub max_bytes: Option<NonZeroU64>,
+}
+
+/// Ordering metadata written to each object, ordering objects across restarts of the sink.
+///
+/// The sink keeps a generation counter in a marker object, which is read and incremented on the
+/// first upload after each start. Each object is then written with the generation and its sequence
+/// number within the generation, starting from 0, as the `x-amz-meta-generation` and
+/// `x-amz-meta-seq` user-defined metadata. Objects are ordered by their generation, then by their
+/// sequence number, regardless of clock skew, as long as a single sink writes the marker object.
+///
+/// Sequence numbers are assigned as objects are built, so retries keep them, though concurrent
+/// uploads can complete out of order. Objects uploaded again from `spill`, and companion objects,
+/// have no ordering metadata.
+#[configurable_component]
+#[derive(Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GenerationMetadataConfig {
+    /// The key of the marker object holding the generation, in `bucket`.
+    #[configurable(metadata(docs::examples = "vector/generation"))]
+    pub marker_key: String,
+}
+
+/// A signal which flushes the batches of all partitions when Vector receives it.
+///
+/// Batches are flushed at once, regardless of `batch.timeout_secs`, `min_events_per_object`,
+/// `min_write_interval_secs`, and `startup_warmup_secs`, then batching resumes as usual. This sets
+/// the boundaries of objects on demand, such as before taking a snapshot of the bucket. Objects
+/// already being uploaded, multipart uploads included, are completed as usual.
+///
+/// Vector also reloads its configuration on `SIGHUP`, which leaves the sink running as it is when
+/// its configuration is unchanged. Only supported on Unix.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlushSignal {
+    /// Flush on `SIGHUP`.
+    Sighup,
+
+    /// Flush on `SIGUSR1`.
+    Sigusr1,
+
+    /// Flush on `SIGUSR2`.
+    Sigusr2,
+}
+
+/// Returns the requests to flush all batches made by the signal.
+#[cfg(unix)]
+fn flush_signal_requests(flush_signal: FlushSignal) -> crate::Result<FlushRequests> {
+    use futures::{stream, StreamExt};
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let kind = match flush_signal {
+        FlushSignal::Sighup => SignalKind::hangup(),
+        FlushSignal::Sigusr1 => SignalKind::user_defined1(),
+        FlushSignal::Sigusr2 => SignalKind::user_defined2(),
+    };
+    let signals = signal(kind)?;
+    Ok(stream::unfold(signals, |mut signals| async move {
+        signals.recv().await.map(|()| ((), signals))
+    })
+    .boxed())
+}
+
+#[cfg(not(unix))]
+fn flush_signal_requests(_: FlushSignal) -> crate::Result<FlushRequests> {
+    Err(BuildError::FlushSignalUnsupported.into())
+}
+
+/// The behavior when the serializer encodes an event to zero bytes.
+///
+/// This happens, for example, with metrics which have no value the codec can represent.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnEmptyEncode {
+    /// Keep the event, writing an empty frame for it, such as an empty line with
+    /// newline-delimited framing.
+    #[default]
+    Keep,
+
+    /// Drop the event, counting it as discarded.
+    Drop,
+}
+
+/// Whether to add or strip a UTF-8 byte order mark (BOM) at the start of objects.
+///
+/// Some consumers, such as spreadsheet applications opening CSV files, rely on the BOM to detect
+/// that text is UTF-8, while others fail to parse text starting with one. This only applies to
+/// text-based codecs.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteOrderMark {
+    /// Objects are written as encoded.
+    #[default]
+    None,
+
+    /// A BOM is written at the start of objects, unless they already start with one.
+    ///
+    /// When `framing_scope` is set to `stream`, only the first object of each partition starts
+    /// with a BOM, as the following objects continue it.
+    Add,
+
+    /// The BOM objects start with, if any, is removed.
+    Strip,
+}
+
+/// How the payload of uploads is signed.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadSigning {
+    /// The SHA-256 hash of the payload is included in the signature of the request.
+    #[default]
+    Signed,
+
+    /// The payload is not included in the signature of the request, saving hashing it.
+    ///
+    /// This saves a pass over the payload before each upload, but the signature no longer
+    /// protects the payload from being modified in transit. Its integrity then relies on TLS,
+    /// which must be used, and on the `Content-MD5` header sent with each object.
+    Unsigned,
+}
+
+fn default_checksum_footer_format() -> String {
+    "#{algorithm}:{checksum}".to_string()
+}
+
+/// When to append a UUID v4 token to the end of the object key.
+///
+/// Takes precedence over `filename_append_uuid` when set.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UniqueSuffix {
+    /// Append a UUID to every object key.
+    Always,
+
+    /// Append a UUID only when the object key would otherwise be the same as the key of an
+    /// object already built by this sink with the same timestamp.
+    ///
+    /// This keeps object keys predictable, such that a partition flushing at most one object per
+    /// timestamp overwrites the same object when events are sent again, while objects sharing a
+    /// partition and timestamp still get distinct keys. A UUID is never needed when
+    /// `filename_sequence_start` is set. Objects built before Vector restarted, or by other
+    /// instances of the sink, are not taken into account.
+    WhenNeeded,
+
+    /// Never append a UUID.
+    Never,
+}
+
+/// The time object filenames are named after.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilenameTimeSource {
+    /// The system clock when the object is built.
+    #[default]
+    Clock,
+
+    /// The system clock, never going back before the time of the previous object.
+    ///
+    /// Filenames keep their order when the clock is stepped back, such as by NTP, at the cost of
+    /// reusing the last time until the clock catches up.
+    Monotonic,
+
+    /// The system clock, clamped to the latest event timestamp of the object.
+    ///
+    /// This prevents keys dated in the future on hosts whose clock is ahead of their events.
+    /// Objects without event timestamps use the system clock.
+    LatestEvent,
+}
+
+/// The strategy to use for object keys longer than `max_key_length`.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyTooLongStrategy {
+    /// Reject the object, and log an error naming the oversized key.
+    #[default]
+    Error,
+
+    /// Truncate the key, replacing its end with a hash of the full key.
+    ///
+    /// The extension of the key is preserved, and the hash keeps truncated keys unique.
+    Truncate,
+
+    /// Replace the key prefix with a hash of it, keeping the filename and extension.
+    HashPrefix,
+}
+
+/// The strategy to use when the key of an object is the same as the key of another object built by
+/// this sink with the same timestamp.
+///
+/// Objects of different partitions can end up with the same key when it doesn't include a UUID or
+/// sequence number, such as when their key prefixes only differ by case with `lowercase_keys`, or
+/// once hashed by `on_key_too_long`. Keys are only compared with those of objects built since the
+/// formatted `filename_time_format` last changed, and not with objects built before Vector
+/// restarted or by other instances of the sink.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCollisionStrategy {
+    /// Upload the object, overwriting the object with the same key.
+    ///
+    /// Keys are not tracked.
+    #[default]
+    Overwrite,
+
+    /// Append a counter to the filename, such as `-1`, until the key is unique.
+    AppendCounter,
+
+    /// Reject the object, and log an error naming the colliding key.
+    Error,
+}
+
+pub(super) fn default_profile_startup_events() -> NonZeroUsize {
+    NonZeroUsize::new(10_000).unwrap()
+}
+
+pub(super) const fn default_max_key_length() -> usize {
+    1024
+}
+
+pub(super) fn default_key_prefix() -> String {
+    "date=%F".to_string()
+}
+
+pub(super) fn default_filename_time_format() -> String {
+    "%s".to_string()
+}
+
+impl GenerateConfig for S3SinkConfig {
+    fn generate_config() -> toml::Value {
+        toml::Value::try_from(Self {
+            bucket: "".to_owned(),
+            key_prefix: default_key_prefix(),
+            key_prefix_fallbacks: Vec::new(),
+            filename_time_format: default_filename_time_format(),
+            prefix_time_format: None,
+            filename_time_source: FilenameTimeSource::Clock,
+            clock_skew_threshold_secs: None,
+            filename_append_uuid: true,
+            unique_suffix: None,
+            filename_sequence_start: None,
+            filename_extension: None,
+            split_on_change: None,
+            user_agent_app_name: None,
+            min_events_per_object: None,
+            max_age_secs: None,
+            flush_at_boundary_secs: None,
+            flush_signal: None,
+            min_write_interval_secs: None,
+            startup_warmup_secs: None,
+            lowercase_keys: false,
+            partition_case: PartitionCase::Preserve,
+            on_missing_key: Default::default(),
+            key_prefix_mapping: None,
+            lag_partitioning: None,
+            range_partitioning: None,
+            hash_partitioning: None,
+            event_time_windowing: None,
+            key_segment_normalizers: HashMap::new(),
+            partition_key_cache_size: None,
+            framing_scope: Default::default(),
+            collapse_trailing_delimiter: false,
+            embedded_newlines: EmbeddedNewlines::Keep,
+            invalid_utf8: InvalidUtf8::Keep,
+            on_empty_encode: OnEmptyEncode::default(),
+            collapse_identical_events: false,
+            verify_event_counts: false,
+            byte_order_mark: ByteOrderMark::default(),
+            write_object_metadata: false,
+            lineage: None,
+            bloom_filter: None,
+            sidecar_compression: Compression::None,
+            max_event_bytes: None,
+            write_gzip_index: false,
+            gzip_header_os: None,
+            deterministic_compression: false,
+            max_key_length: 1024,
+            on_key_too_long: Default::default(),
+            on_key_collision: Default::default(),
+            storage_class_template: None,
+            profile_startup: false,
+            profile_startup_events: default_profile_startup_events(),
+            staging_dir: None,
+            spill: None,
+            retention: None,
+            upload_slo_secs: None,
+            log_object_versions: false,
+            write_build_metadata: false,
+            generation_metadata: None,
+            verify_uploads: None,
+            multipart_threshold_bytes: None,
+            multipart_part_size_bytes: None,
+            max_concurrent_uploads_per_prefix: None,
+            auto_region: false,
+            region_routing: None,
+            uncommitted_key_prefix: None,
+            uncommitted_key_suffix: None,
+            checksum_footer: None,
+            preserve_partition_order: false,
+            payload_signing: PayloadSigning::default(),
+            component_id_tag: None,
+            partition_tags: Vec::new(),
+            event_time_tags: None,
+            summary_object: None,
+            heartbeat: None,
+            emf_summary: None,
+            options: S3Options::default(),
+            region: RegionOrEndpoint::default(),
+            encoding: (None::<FramingConfig>, TextSerializerConfig::default()).into(),
+            avro_object_container_file: false,
+            compression: Compression::gzip_default(),
+            compression_min_bytes: None,
+            compression_candidates: Vec::new(),
+            batch: BatchConfig::default(),
+            request: TowerRequestConfig::default(),
+            retry_extra_retryable_codes: Vec::new(),
+            connection_retry_limit_secs: None,
+            on_persistent_error: Default::default(),
+            tls: Some(TlsConfig::default()),
+            auth: AwsAuthentication::default(),
+            acknowledgements: Default::default(),
+            timezone: Default::default(),
+        })
+        .unwrap()
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "aws_s3")]
+impl SinkConfig for S3SinkConfig {
+    async fn build(&self, cx: SinkContext) -> crate::Result<(VectorSink, Healthcheck)> {
+        let service = self.create_service(&cx.proxy).await?;
         let healthcheck = self.build_healthcheck(service.client())?;
         let sink = self.build_processor(service, cx)?;
         Ok((sink, healthcheck))
@@ -202,9 +2026,82 @@ impl S3SinkConfig {
         // order to configure the client/service with retries, concurrency
         // limits, rate limits, and whatever else the client should have.
         let request_limits = self.request.into_settings();
+        let service = service
+            .with_staging_dir(self.staging_dir.clone().map(StagingDir::new))
+            .with_auto_region(
+                self.auto_region
+                    && self.region.endpoint().is_none()
+                    && !self.bucket.starts_with("arn:")
+                    && self.region_routing.is_none(),
+            )
+            .with_uncommitted_prefix(self.uncommitted_key_prefix.clone())
+            .with_uncommitted_suffix(self.uncommitted_key_suffix.clone())
+            .with_unsigned_payload(self.payload_signing == PayloadSigning::Unsigned)
+            .with_upload_slo(
+                self.upload_slo_secs
+                    .map(|upload_slo_secs| Duration::from_secs(upload_slo_secs.get())),
+            )
+            .with_log_object_versions(self.log_object_versions)
+            .with_build_metadata(self.write_build_metadata)
+            .with_generation(self.generation_metadata.as_ref().map(|generation| {
+                Generation::new(self.bucket.clone(), generation.marker_key.clone())
+            }))
+            .with_sidecar_compression(self.sidecar_compression)
+            .with_multipart_threshold(self.multipart_threshold_bytes.map(NonZeroUsize::get))
+            .with_multipart_part_size(self.multipart_part_size_bytes.map(NonZeroUsize::get))
+            .with_prefix_concurrency(
+                self.max_concurrent_uploads_per_prefix
+                    .map(NonZeroUsize::get),
+            )
+            .with_retention(self.retention.as_ref().map(|retention| {
+                Retention::new(
+                    retention.max_objects.map(NonZeroUsize::get),
+                    retention.max_bytes.map(NonZeroU64::get),
+                )
+            }))
+            .with_upload_verification(self.verify_uploads.as_ref().map(|verify_uploads| {
+                UploadVerification::new(
+                    verify_uploads.sample_rate,
+                    verify_uploads.on_mismatch == OnUploadMismatch::Retry,
+                )
+            }));
+        let orphan_uploads = service.clone().upload_orphans();
+        let heartbeat_service = self.heartbeat.as_ref().map(|_| service.clone());
+        let emf_service = self.emf_summary.as_ref().map(|_| service.clone());
+        let spill_dir = self.spill.as_ref().map(|spill| {
+            SpillDir::new(
+                spill.path.clone(),
+                spill.max_bytes.get(),
+                Duration::from_secs(spill.retry_interval_secs.get()),
+            )
+        });
+        let spill_recovery = spill_dir
+            .clone()
+            .map(|spill_dir| spill_dir.recover(service.clone()).boxed());
         let service = ServiceBuilder::new()
-            .settings(request_limits, S3RetryLogic)
+            .settings(
+                request_limits,
+                S3RetryLogic::new(self.retry_extra_retryable_codes.clone())
+                    .with_connection_retry_limit(
+                        self.connection_retry_limit_secs
+                            .map(|secs| Duration::from_secs(secs.get())),
+                    ),
+            )
             .service(service);
+        let service = OrderedPartitions::new(service, self.preserve_partition_order);
+        let service = SpillService::new(service, spill_dir);
+        let fail_fast = match self.on_persistent_error {
+            OnPersistentError::Degrade => None,
+            OnPersistentError::FailFast {
+                max_consecutive_failures,
+            } => Some(FailFast::new(max_consecutive_failures)),
+        };
+        let service = FailFastService::new(service, fail_fast.clone());
+        let emf_stats = self
+            .emf_summary
+            .as_ref()
+            .map(|_| Arc::new(EmfStats::default()));
+        let service = EmfStatsService::new(service, emf_stats.clone());
 
         let offset = self
             .timezone
@@ -215,6 +2112,25 @@ impl S3SinkConfig {
         let batch_settings = self.batch.into_batcher_settings()?;
 
         let key_prefix = Template::try_from(self.key_prefix.clone())?.with_tz_offset(offset);
+        let key_prefix_fallbacks = self
+            .key_prefix_fallbacks
+            .iter()
+            .map(|template| template.clone().with_tz_offset(offset))
+            .collect::<Vec<_>>();
+        let key_prefix_fields = std::iter::once(&key_prefix)
+            .chain(&key_prefix_fallbacks)
+            .flat_map(|template| template.get_fields().unwrap_or_default())
+            .collect::<Vec<_>>();
+        if let Some(field) = self
+            .key_segment_normalizers
+            .keys()
+            .find(|field| !key_prefix_fields.contains(field))
+        {
+            return Err(BuildError::UnreferencedKeySegmentField {
+                field: field.clone(),
+            }
+            .into());
+        }
 
         let ssekms_key_id = self
             .options
@@ -224,43 +2140,966 @@ impl S3SinkConfig {
             .map(|ssekms_key_id| Template::try_from(ssekms_key_id.as_str()))
             .transpose()?;
 
-        let partitioner = S3KeyPartitioner::new(key_prefix, ssekms_key_id);
+        let storage_class_template = self
+            .storage_class_template
+            .clone()
+            .map(|template| template.with_tz_offset(offset));
+
+        let split_on_change = self
+            .split_on_change
+            .clone()
+            .map(|split_on_change| split_on_change.with_tz_offset(offset));
+
+        let target_template = self
+            .region_routing
+            .as_ref()
+            .map(|region_routing| region_routing.source.clone().with_tz_offset(offset));
+
+        if self.min_events_per_object.is_some() && self.max_age_secs.is_none() {
+            return Err(BuildError::MinEventsWithoutMaxAge.into());
+        }
+        validate_bucket(&self.bucket)?;
+        if let Some(region_routing) = &self.region_routing {
+            if region_routing.targets.is_empty() {
+                return Err(BuildError::EmptyRegionRoutingTargets.into());
+            }
+            for target in region_routing.targets.values() {
+                validate_bucket(&target.bucket)?;
+            }
+        }
+        if let Some(cache_control) = &self.options.cache_control {
+            validate_cache_control(cache_control)?;
+        }
+        if let Some(checksum_footer) = &self.checksum_footer {
+            let format = &checksum_footer.format;
+            if !format.contains("{checksum}") || format.contains(['\r', '\n']) {
+                return Err(BuildError::InvalidChecksumFooterFormat {
+                    format: format.clone(),
+                }
+                .into());
+            }
+        }
+        if let Some(bloom_filter) = &self.bloom_filter {
+            let false_positive_rate = bloom_filter.false_positive_rate;
+            if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+                return Err(BuildError::InvalidBloomFilterFalsePositiveRate {
+                    false_positive_rate,
+                }
+                .into());
+            }
+        }
+        if let Some(part_size) = self.multipart_part_size_bytes {
+            validate_multipart_part_size(part_size.get())?;
+        }
+        if self.write_gzip_index && !matches!(self.compression, Compression::Gzip(_)) {
+            return Err(BuildError::GzipIndexWithoutGzip.into());
+        }
+        if self.lineage.is_some() && !self.write_object_metadata {
+            return Err(BuildError::LineageWithoutObjectMetadata.into());
+        }
+        if self.gzip_header_os.is_some() {
+            if !matches!(self.compression, Compression::Gzip(_)) {
+                return Err(BuildError::GzipHeaderOsWithoutGzip.into());
+            }
+            if self.write_gzip_index {
+                return Err(BuildError::GzipHeaderOsWithGzipIndex.into());
+            }
+        }
+        if let (Some(spill), Some(staging_dir)) = (&self.spill, &self.staging_dir) {
+            if spill.path == *staging_dir {
+                return Err(BuildError::SpillDirIsStagingDir.into());
+            }
+        }
+        if let Some(retention) = &self.retention {
+            validate_retention(self, retention)?;
+        }
+        if let Some(expires_secs) = self.options.expires_secs {
+            if expires_at(expires_secs).is_none() {
+                return Err(BuildError::ExpiresTooLate { expires_secs }.into());
+            }
+        }
+
+        // Heartbeats and EMF summaries are not rendered from events, so they are only encrypted
+        // with static keys.
+        let heartbeat_ssekms_key_id = ssekms_key_id
+            .as_ref()
+            .filter(|ssekms_key_id| !ssekms_key_id.is_dynamic())
+            .map(|ssekms_key_id| ssekms_key_id.get_ref().to_owned());
+        let partitioner = S3KeyPartitioner::new(key_prefix, ssekms_key_id)
+            .with_key_prefix_fallbacks(key_prefix_fallbacks)
+            .with_split_on_change(split_on_change)
+            .with_min_events(self.min_events_per_object)
+            .with_lowercase_keys(self.lowercase_keys)
+            .with_partition_case(self.partition_case)
+            .with_key_prefix_fallback(match &self.on_missing_key {
+                OnMissingKey::Drop => None,
+                OnMissingKey::Fallback { prefix } => Some(prefix.clone()),
+            })
+            .with_storage_class_template(storage_class_template)
+            .with_target_template(target_template)
+            .with_segment_normalizers(
+                self.key_segment_normalizers
+                    .iter()
+                    .map(|(field, normalizer)| {
+                        let segment_normalizer = SegmentNormalizer::new(
+                            normalizer.lowercase,
+                            normalizer.allowed.clone(),
+                            normalizer.invalid.clone(),
+                        );
+                        (field.clone(), segment_normalizer)
+                    })
+                    .collect(),
+            )
+            .with_prefix_mapping(self.key_prefix_mapping.as_ref().map(|mapping| {
+                PrefixMapping::new(
+                    mapping.source.clone().with_tz_offset(offset),
+                    mapping.values.clone(),
+                    mapping.default.clone(),
+                )
+            }))
+            .with_event_time_windows(self.event_time_windowing.as_ref().map(|windowing| {
+                EventTimeWindows::new(
+                    windowing
+                        .timestamp_field
+                        .as_ref()
+                        .map(|field| field.0.clone()),
+                    Duration::from_secs(windowing.window_secs.get()),
+                    Duration::from_secs(windowing.allowed_lateness_secs),
+                    windowing.late_prefix.clone(),
+                    windowing.late_events,
+                )
+            }))
+            .with_hash_buckets(self.hash_partitioning.as_ref().map(|hash_partitioning| {
+                HashBuckets::new(hash_partitioning.key.clone(), hash_partitioning.buckets)
+            }))
+            .with_range_buckets(self.range_partitioning.as_ref().map(|range_partitioning| {
+                RangeBuckets::new(
+                    range_partitioning.field.0.clone(),
+                    range_partitioning
+                        .ranges
+                        .iter()
+                        .map(|range| (range.min, range.max, range.label.clone()))
+                        .collect(),
+                    range_partitioning.default.clone(),
+                )
+            }))
+            .with_lag_segments(self.lag_partitioning.as_ref().map(|lag_partitioning| {
+                LagSegments::new(
+                    lag_partitioning
+                        .timestamp_field
+                        .as_ref()
+                        .map(|field| field.0.clone()),
+                    lag_partitioning.on_time.clone(),
+                    lag_partitioning
+                        .thresholds
+                        .iter()
+                        .map(|threshold| {
+                            (
+                                Duration::from_secs(threshold.min_lag_secs),
+                                threshold.segment.clone(),
+                            )
+                        })
+                        .collect(),
+                )
+            }))
+            .with_max_age(
+                self.max_age_secs
+                    .map(|max_age_secs| Duration::from_secs(max_age_secs.get())),
+            )
+            .with_min_write_interval(
+                self.min_write_interval_secs
+                    .map(|interval_secs| Duration::from_secs(interval_secs.get())),
+            )
+            .with_startup_warmup(
+                self.startup_warmup_secs
+                    .map(|warmup_secs| Duration::from_secs(warmup_secs.get())),
+            )
+            .with_flush_boundary(
+                self.flush_at_boundary_secs.map(|flush_at_boundary_secs| {
+                    Duration::from_secs(flush_at_boundary_secs.get())
+                }),
+                offset,
+            )
+            .with_flush_requests(self.flush_signal.map(flush_signal_requests).transpose()?)
+            .with_key_cache(self.partition_key_cache_size)
+            .with_distinct_keys_window(DISTINCT_KEYS_WINDOW);
+
+        // Objects smaller than the minimum object size of some storage classes are billed as if
+        // they were that size, so warn when batches are likely to be flushed below it. Compression
+        // only makes objects smaller.
+        let storage_class = self.options.storage_class;
+        if let Some(minimum) = storage_class.minimum_object_size() {
+            if batch_settings.size_limit < minimum {
+                warn!(
+                    message = "Batch size is below the minimum object size of the storage class, objects may be billed as that size.",
+                    storage_class = ?storage_class,
+                    batch_max_bytes = batch_settings.size_limit,
+                    minimum_object_size = minimum,
+                );
+            }
+        }
+
+        if let Some(FramingConfig::LengthDelimited(config)) = self.encoding.config().0 {
+            validate_length_delimited(config)?;
+        }
+        let avro_object_container = match self.encoding.config() {
+            _ if !self.avro_object_container_file => None,
+            (None | Some(FramingConfig::Bytes), SerializerConfig::Avro { avro }) => Some(avro),
+            (Some(_), SerializerConfig::Avro { .. }) => {
+                return Err(BuildError::AvroObjectContainerWithFraming.into());
+            }
+            _ => return Err(BuildError::AvroObjectContainerWithoutAvro.into()),
+        };
+        if let (Some(framing), serializer) = self.encoding.config() {
+            validate_framing_codec(framing, serializer)?;
+        }
+        if self.byte_order_mark != ByteOrderMark::None {
+            validate_byte_order_mark_codec(self.encoding.config().1)?;
+        }
+        if self.invalid_utf8 != InvalidUtf8::Keep && is_binary_codec(self.encoding.config().1) {
+            return Err(BuildError::InvalidUtf8WithBinaryCodec {
+                codec: codec_name(self.encoding.config().1),
+            }
+            .into());
+        }
 
         let transformer = self.encoding.transformer();
-        let (framer, serializer) = self.encoding.build(SinkType::MessageBased)?;
+        let (framer, serializer) = match avro_object_container {
+            // Events are framed by the data blocks of the file, whose header is the batch prefix.
+            Some(avro) => (
+                BytesEncoder.into(),
+                Serializer::Avro(
+                    AvroSerializerConfig::new(avro.schema.clone())
+                        .with_object_container_file(true)
+                        .build()?,
+                ),
+            ),
+            None => self.encoding.build(SinkType::MessageBased)?,
+        };
         let encoder = Encoder::<Framer>::new(framer, serializer);
+        let has_batch_suffix = !encoder.batch_suffix().is_empty();
+        let codec = codec_name(self.encoding.config().1);
+
+        let startup_profile = self.profile_startup.then(|| {
+            StartupProfile::new(
+                self.profile_startup_events,
+                (transformer.clone(), encoder.clone()),
+                codec.clone(),
+                self.compression,
+            )
+        });
+
+        let mut api_options = self.options.clone();
+        let media_type = if self.avro_object_container_file {
+            Some(("application/avro", "avro"))
+        } else {
+            codec_media_type(self.encoding.config().1)
+        };
+        if let Some((content_type, _)) = media_type {
+            api_options
+                .content_type
+                .get_or_insert_with(|| content_type.to_owned());
+        }
+        if let Some(component_id_tag) = &self.component_id_tag {
+            api_options
+                .tags
+                .get_or_insert_with(Default::default)
+                .entry(component_id_tag.clone())
+                .or_insert_with(|| cx.key.id().to_owned());
+        }
+        let mut generated_tags = Vec::new();
+        if self.collapse_identical_events {
+            generated_tags.push(COLLAPSED_COUNT_TAG);
+        }
+        if self.event_time_tags.is_some() {
+            generated_tags.extend([MIN_EVENT_TIME_TAG, MAX_EVENT_TIME_TAG]);
+        }
+        validate_tag_count(
+            api_options.tags.as_ref(),
+            &self.partition_tags,
+            &generated_tags,
+        )?;
+
+        let heartbeat = match (&self.heartbeat, heartbeat_service) {
+            (Some(heartbeat), Some(service)) => {
+                if heartbeat.key.is_empty() {
+                    return Err(BuildError::EmptyHeartbeatKey.into());
+                }
+                let heartbeat = Heartbeat {
+                    bucket: self.bucket.clone(),
+                    key: heartbeat.key.clone(),
+                    interval: Duration::from_secs(heartbeat.interval_secs.get()),
+                    options: S3Options {
+                        ssekms_key_id: heartbeat_ssekms_key_id.clone(),
+                        content_encoding: None,
+                        content_type: Some("application/json".to_owned()),
+                        expires_secs: None,
+                        ..api_options.clone()
+                    },
+                    host: crate::get_hostname()?,
+                    component_id: cx.key.id().to_owned(),
+                };
+                Some(heartbeat.run(service).boxed())
+            }
+            _ => None,
+        };
+
+        let emf_summary = match (&self.emf_summary, emf_service, emf_stats) {
+            (Some(emf_summary), Some(service), Some(stats)) => {
+                let emf_summary = EmfSummary {
+                    bucket: self.bucket.clone(),
+                    key_prefix: emf_summary.key_prefix.clone(),
+                    namespace: emf_summary.namespace.clone(),
+                    interval: Duration::from_secs(emf_summary.interval_secs.get()),
+                    options: S3Options {
+                        ssekms_key_id: heartbeat_ssekms_key_id,
+                        content_encoding: None,
+                        content_type: Some("application/json".to_owned()),
+                        expires_secs: None,
+                        ..api_options.clone()
+                    },
+                    stats,
+                    host: crate::get_hostname()?,
+                    component_id: cx.key.id().to_owned(),
+                };
+                Some(emf_summary.run(service).boxed())
+            }
+            _ => None,
+        };
 
         let request_options = S3RequestOptions {
             bucket: self.bucket.clone(),
-            api_options: self.options.clone(),
+            targets: self
+                .region_routing
+                .as_ref()
+                .map(|region_routing| region_routing.targets.clone()),
+            api_options,
             filename_extension: self.filename_extension.clone(),
+            codec_extension: media_type.map(|(_, extension)| extension),
             filename_time_format: self.filename_time_format.clone(),
-            filename_append_uuid: self.filename_append_uuid,
-            encoder: (transformer, encoder),
+            prefix_time_format: self.prefix_time_format.clone(),
+            unique_suffix: self.unique_suffix.unwrap_or(if self.filename_append_uuid {
+                UniqueSuffix::Always
+            } else {
+                UniqueSuffix::Never
+            }),
+            used_filenames: Default::default(),
+            encoder: ObjectEncoder {
+                encoder: (transformer, encoder),
+                checksum_footer: self.checksum_footer.clone(),
+                framing: BatchFraming {
+                    collapse_trailing_delimiter: self.collapse_trailing_delimiter,
+                    drop_empty_events: self.on_empty_encode == OnEmptyEncode::Drop,
+                    embedded_newlines: self.embedded_newlines,
+                    invalid_utf8: self.invalid_utf8,
+                },
+                byte_order_mark: self.byte_order_mark,
+            },
             compression: self.compression,
+            compression_min_bytes: self.compression_min_bytes,
+            compression_candidates: self.compression_candidates.clone(),
             filename_tz_offset: offset,
+            lowercase_keys: self.lowercase_keys,
+            write_object_metadata: self.write_object_metadata,
+            lineage: self.lineage.as_ref().map(|lineage| {
+                Lineage::new(
+                    lineage
+                        .partition_fields
+                        .iter()
+                        .map(|field| field.0.clone())
+                        .collect(),
+                    lineage.offset_field.0.clone(),
+                )
+            }),
+            max_key_length: self.max_key_length,
+            on_key_too_long: self.on_key_too_long,
+            on_key_collision: self.on_key_collision,
+            used_keys: Default::default(),
+            filename_time_source: self.filename_time_source,
+            last_filename_time: Default::default(),
+            clock_skew_threshold: self
+                .clock_skew_threshold_secs
+                .map(|threshold| Duration::from_secs(threshold.get())),
+            sequence: self
+                .filename_sequence_start
+                .map(|start| Arc::new(AtomicU64::new(start))),
+            generation_sequence: self
+                .generation_metadata
+                .as_ref()
+                .map(|_| Arc::new(AtomicU64::new(0))),
+            collapse_identical_events: self.collapse_identical_events,
+            verify_event_counts: self.verify_event_counts,
+            bloom_filter: self
+                .bloom_filter
+                .clone()
+                .map(|bloom_filter| BloomFilterConfig {
+                    source: bloom_filter.source.with_tz_offset(offset),
+                    ..bloom_filter
+                }),
+            write_gzip_index: self.write_gzip_index,
+            gzip_header_os: self.gzip_header_os,
+            deterministic_compression: self.deterministic_compression,
+            event_time_tags: self.event_time_tags.clone(),
+            summary_object: self
+                .summary_object
+                .clone()
+                .map(|summary_object| SummaryObjectConfig {
+                    key: summary_object.key.map(|key| key.with_tz_offset(offset)),
+                    ..summary_object
+                }),
+            window_summaries: Default::default(),
+            partition_tags: self.partition_tags.clone(),
+            codec,
         };
 
-        let sink = S3Sink::new(service, request_options, partitioner, batch_settings);
+        let mut sink = S3Sink::new(service, request_options, partitioner, batch_settings)
+            .with_startup_profile(startup_profile)
+            .with_orphan_uploads(Box::pin(orphan_uploads))
+            .with_heartbeat(heartbeat)
+            .with_emf_summary(emf_summary)
+            .with_spill_recovery(spill_recovery)
+            .with_max_event_bytes(self.max_event_bytes)
+            .with_max_request_bytes(if self.multipart_threshold_bytes.is_some() {
+                MAX_MULTIPART_REQUEST_BYTES
+            } else {
+                MAX_REQUEST_BYTES
+            })
+            .with_fail_fast(fail_fast);
+        if self.framing_scope == FramingScope::Stream {
+            sink = sink.with_stream_framing(has_batch_suffix);
+        }
 
         Ok(VectorSink::from_event_streamsink(sink))
     }
 
     pub fn build_healthcheck(&self, client: S3Client) -> crate::Result<Healthcheck> {
-        s3_common::config::build_healthcheck(self.bucket.clone(), client)
+        let Some(region_routing) = &self.region_routing else {
+            return s3_common::config::build_healthcheck(self.bucket.clone(), client);
+        };
+        // Each target bucket is checked in its own region, as objects are never written to
+        // `bucket`.
+        let healthchecks = region_routing
+            .targets
+            .values()
+            .map(|target| {
+                let config = client
+                    .config()
+                    .to_builder()
+                    .region(Region::new(target.region.clone()))
+                    .build();
+                s3_common::config::build_healthcheck(
+                    target.bucket.clone(),
+                    S3Client::from_conf(config),
+                )
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(futures::future::try_join_all(healthchecks)
+            .map_ok(|_| ())
+            .boxed())
     }
 
     pub async fn create_service(&self, proxy: &ProxyConfig) -> crate::Result<S3Service> {
-        s3_common::config::create_service(&self.region, &self.auth, proxy, &self.tls).await
+        let app_name = self
+            .user_agent_app_name
+            .as_deref()
+            .map(render_app_name)
+            .transpose()?;
+
+        s3_common::config::create_service(&self.region, &self.auth, proxy, &self.tls, app_name)
+            .await
+    }
+}
+
+/// Checks that events of the maximum frame length can be prefixed by the configured length field.
+fn validate_length_delimited(config: &LengthDelimitedEncoderConfig) -> Result<(), BuildError> {
+    let length_field_length = config.length_delimited.length_field_length;
+    if !(1..=8).contains(&length_field_length) {
+        return Err(BuildError::InvalidLengthFieldLength {
+            length_field_length,
+        });
+    }
+
+    let max_representable = u64::MAX >> (64 - 8 * length_field_length);
+    let max_frame_length = config.length_delimited.max_frame_length;
+    if max_frame_length as u64 > max_representable {
+        return Err(BuildError::MaxFrameLengthTooLarge {
+            max_frame_length,
+            length_field_length,
+            max_representable,
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks that the bucket, if it is an ARN, is that of an access point, as only buckets can be
+/// written to otherwise.
+fn validate_bucket(bucket: &str) -> Result<(), BuildError> {
+    let Some(arn) = bucket.strip_prefix("arn:") else {
+        return Ok(());
+    };
+    let is_name = |value: &str| {
+        !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+
+    let error = |reason| BuildError::InvalidBucketArn {
+        bucket: bucket.to_owned(),
+        reason,
+    };
+    let parts = arn.splitn(5, ':').collect::<Vec<_>>();
+    let [partition, service, region, account_id, resource] = parts[..] else {
+        return Err(error(
+            "ARNs must be of the form `arn:<partition>:<service>:<region>:<account-id>:<resource>`",
+        ));
+    };
+    if !is_name(partition) || !is_name(region) {
+        return Err(error("the partition and region must not be empty"));
+    }
+    if !is_name(account_id) {
+        return Err(error(
+            "the account ID must only contain a-z, A-Z, 0-9 and `-`",
+        ));
+    }
+    match (service, &resource.split(['/', ':']).collect::<Vec<_>>()[..]) {
+        ("s3", ["accesspoint", name]) if is_name(name) => Ok(()),
+        ("s3", _) => Err(error(
+            "S3 ARNs must be of an access point, named `accesspoint/<name>` with a-z, A-Z, 0-9 and `-`",
+        )),
+        ("s3-outposts", ["outpost", outpost_id, "accesspoint", name])
+            if is_name(outpost_id) && is_name(name) =>
+        {
+            Ok(())
+        }
+        ("s3-outposts", _) => Err(error(
+            "S3 on Outposts ARNs must be of an access point, named `outpost/<outpost-id>/accesspoint/<name>` with a-z, A-Z, 0-9 and `-`",
+        )),
+        _ => Err(error("the service must be `s3` or `s3-outposts`")),
+    }
+}
+
+/// Checks that the value is a list of `Cache-Control` directives, as in RFC 9111.
+fn validate_cache_control(cache_control: &str) -> Result<(), BuildError> {
+    let is_token = |value: &str| {
+        !value.is_empty()
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+    };
+    let is_quoted_string = |value: &str| {
+        value.len() >= 2
+            && value.starts_with('"')
+            && value.ends_with('"')
+            && value[1..value.len() - 1]
+                .chars()
+                .all(|c| c == '\t' || (c.is_ascii() && !c.is_ascii_control()))
+    };
+
+    let error = |reason| BuildError::InvalidCacheControl {
+        cache_control: cache_control.to_owned(),
+        reason,
+    };
+    for directive in cache_control.split(',').map(str::trim) {
+        let (name, value) = match directive.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (directive, None),
+        };
+        if !is_token(name) {
+            return Err(error(
+                "directives must be separated by commas and named with alphanumeric characters or any of \"!#$%&'*+-.^_`|~\"",
+            ));
+        }
+        if value.is_some_and(|value| !is_token(value) && !is_quoted_string(value)) {
+            return Err(error(
+                "directive arguments must be tokens or quoted strings",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether the codec encodes binary data, rather than text.
+const fn is_binary_codec(serializer: &SerializerConfig) -> bool {
+    matches!(
+        serializer,
+        SerializerConfig::Avro { .. }
+            | SerializerConfig::Cbor
+            | SerializerConfig::MessagePack
+            | SerializerConfig::Native
+            | SerializerConfig::Protobuf(_)
+    )
+}
+
+/// Checks that the codec encodes text, as a BOM preceding binary data would corrupt it.
+fn validate_byte_order_mark_codec(serializer: &SerializerConfig) -> Result<(), BuildError> {
+    if is_binary_codec(serializer) {
+        return Err(BuildError::ByteOrderMarkWithBinaryCodec {
+            codec: codec_name(serializer),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that events encoded by binary codecs are not separated by a delimiter, as it may occur
+/// within them, which would corrupt the boundaries of the events.
+fn validate_framing_codec(
+    framing: &FramingConfig,
+    serializer: &SerializerConfig,
+) -> Result<(), BuildError> {
+    let framing = match framing {
+        FramingConfig::NewlineDelimited => "newline_delimited",
+        FramingConfig::CharacterDelimited(_) => "character_delimited",
+        _ => return Ok(()),
+    };
+    if is_binary_codec(serializer) {
+        return Err(BuildError::DelimitedFramingWithBinaryCodec {
+            framing,
+            codec: codec_name(serializer),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that retention has limits, and that no companion objects are written under the key
+/// prefix of objects, where retention would count and delete them.
+fn validate_retention(
+    config: &S3SinkConfig,
+    retention: &RetentionConfig,
+) -> Result<(), BuildError> {
+    if retention.max_objects.is_none() && retention.max_bytes.is_none() {
+        return Err(BuildError::RetentionWithoutLimits);
+    }
+    let companion_objects = [
+        (config.write_object_metadata, "write_object_metadata"),
+        (config.bloom_filter.is_some(), "bloom_filter"),
+        (config.write_gzip_index, "write_gzip_index"),
+        (
+            config.uncommitted_key_suffix.is_some(),
+            "uncommitted_key_suffix",
+        ),
+        (
+            config
+                .summary_object
+                .as_ref()
+                .is_some_and(|summary_object| summary_object.key.is_none()),
+            "summary_object",
+        ),
+    ];
+    match companion_objects.into_iter().find(|(enabled, _)| *enabled) {
+        Some((_, option)) => Err(BuildError::RetentionWithCompanionObjects { option }),
+        None => Ok(()),
     }
 }
 
+/// Returns the content type and filename extension of objects encoded with the codec, when it
+/// has its own rather than those of text logs.
+fn codec_media_type(serializer: &SerializerConfig) -> Option<(&'static str, &'static str)> {
+    match serializer {
+        SerializerConfig::Cbor => Some(("application/cbor", "cbor")),
+        SerializerConfig::MessagePack => Some(("application/msgpack", "msgpack")),
+        _ => None,
+    }
+}
+
+/// The sizes S3 allows for the parts of multipart uploads, except for their last part.
+const MULTIPART_PART_SIZES: std::ops::RangeInclusive<u64> =
+    5 * 1024 * 1024..=5 * 1024 * 1024 * 1024;
+
+/// Checks that the part size is one S3 allows.
+fn validate_multipart_part_size(part_size: usize) -> Result<(), BuildError> {
+    if !MULTIPART_PART_SIZES.contains(&(part_size as u64)) {
+        return Err(BuildError::InvalidMultipartPartSize { part_size });
+    }
+    Ok(())
+}
+
+/// The maximum number of tags S3 allows on an object.
+const MAX_OBJECT_TAGS: usize = 10;
+
+/// Validates that no object can be given more tags than S3 allows.
+///
+/// The tags of a `partition_tags` entry apply along with those of every entry whose prefix is a
+/// prefix of its own, as all of them match the key prefixes it matches.
+fn validate_tag_count(
+    tags: Option<&BTreeMap<String, String>>,
+    partition_tags: &[PartitionTags],
+    generated_tags: &[&str],
+) -> Result<(), BuildError> {
+    let base = tags
+        .into_iter()
+        .flat_map(|tags| tags.keys().map(String::as_str))
+        .chain(generated_tags.iter().copied());
+    let count_under = |prefix: &str| {
+        partition_tags
+            .iter()
+            .filter(|entry| prefix.starts_with(&entry.prefix))
+            .flat_map(|entry| entry.tags.keys().map(String::as_str))
+            .chain(base.clone())
+            .collect::<HashSet<_>>()
+            .len()
+    };
+    let count = partition_tags
+        .iter()
+        .map(|entry| count_under(&entry.prefix))
+        .fold(count_under(""), usize::max);
+
+    if count > MAX_OBJECT_TAGS {
+        return Err(BuildError::TooManyTags { count });
+    }
+    Ok(())
+}
+
+/// Returns the name of the configured codec, as it's written in the configuration.
+fn codec_name(serializer: &SerializerConfig) -> String {
+    serde_json::to_value(serializer)
+        .ok()
+        .and_then(|value| value.get("codec")?.as_str().map(ToOwned::to_owned))
+        .unwrap_or_default()
+}
+
+/// Renders the placeholders of the configured `user_agent_app_name`.
+fn render_app_name(app_name: &str) -> crate::Result<AppName> {
+    let mut rendered = app_name.replace("{version}", &crate::vector_version().to_string());
+    if rendered.contains("{hostname}") {
+        rendered = rendered.replace("{hostname}", &crate::get_hostname()?);
+    }
+
+    AppName::new(rendered.clone()).map_err(|_| {
+        format!(
+            "Invalid `user_agent_app_name` {:?}: only alphanumeric characters and any of \
+             \"!#$%&'*+-.^_`|~\" are allowed.",
+            rendered
+        )
+        .into()
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::S3SinkConfig;
+    use std::collections::BTreeMap;
+
+    use vector_lib::codecs::{
+        encoding::FramingConfig, JsonSerializerConfig, NativeSerializerConfig, TextSerializerConfig,
+    };
+
+    use super::{
+        codec_name, render_app_name, validate_bucket, validate_byte_order_mark_codec,
+        validate_cache_control, validate_framing_codec, validate_length_delimited,
+        validate_multipart_part_size, validate_tag_count, BuildError, PartitionTags, S3SinkConfig,
+    };
+
+    fn length_delimited_framing(options: &str) -> FramingConfig {
+        toml::from_str(&format!(
+            "method = \"length_delimited\"\nlength_delimited = {{ {} }}",
+            options
+        ))
+        .unwrap()
+    }
+
+    fn validate_framing(options: &str) -> Result<(), BuildError> {
+        match length_delimited_framing(options) {
+            FramingConfig::LengthDelimited(config) => validate_length_delimited(&config),
+            _ => unreachable!(),
+        }
+    }
 
     #[test]
     fn generate_config() {
         crate::test_util::test_generate_config::<S3SinkConfig>();
     }
+
+    #[test]
+    fn renders_app_name_placeholders() {
+        let app_name = render_app_name("my-deployment-{version}").unwrap();
+        assert_eq!(
+            app_name.as_ref(),
+            format!("my-deployment-{}", crate::vector_version())
+        );
+
+        let app_name = render_app_name("vector-{hostname}").unwrap();
+        assert_eq!(
+            app_name.as_ref(),
+            format!("vector-{}", crate::get_hostname().unwrap())
+        );
+    }
+
+    #[test]
+    fn validates_length_delimited_framing() {
+        assert!(validate_framing("length_field_length = 4").is_ok());
+        assert!(
+            validate_framing("length_field_length = 8, length_field_is_big_endian = false").is_ok()
+        );
+        assert!(validate_framing("length_field_length = 2, max_frame_length = 65535").is_ok());
+
+        assert!(matches!(
+            validate_framing("length_field_length = 2"),
+            Err(BuildError::MaxFrameLengthTooLarge {
+                max_frame_length: 8388608,
+                length_field_length: 2,
+                max_representable: 65535,
+            })
+        ));
+        assert!(matches!(
+            validate_framing("length_field_length = 16"),
+            Err(BuildError::InvalidLengthFieldLength {
+                length_field_length: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn validates_cache_control() {
+        for cache_control in [
+            "max-age=3600",
+            "no-cache, no-store",
+            "public,max-age=60,stale-while-revalidate=30",
+            "private=\"set-cookie\"",
+        ] {
+            assert!(
+                validate_cache_control(cache_control).is_ok(),
+                "{cache_control}"
+            );
+        }
+        for cache_control in [
+            "",
+            "max-age=",
+            "max age=60",
+            "no-cache;no-store",
+            "max-age=\"60",
+        ] {
+            assert!(
+                matches!(
+                    validate_cache_control(cache_control),
+                    Err(BuildError::InvalidCacheControl { .. })
+                ),
+                "{cache_control}"
+            );
+        }
+    }
+
+    #[test]
+    fn validates_bucket_arns() {
+        for bucket in [
+            "my-bucket",
+            "arn:aws:s3:us-east-1:123456789012:accesspoint/my-access-point",
+            "arn:aws:s3:us-east-1:123456789012:accesspoint:my-access-point",
+            "arn:aws:s3-outposts:us-west-2:123456789012:outpost/op-01ac5d28a6a232904/accesspoint/my-access-point",
+        ] {
+            assert!(validate_bucket(bucket).is_ok(), "{bucket}");
+        }
+        for bucket in [
+            "arn:aws:s3:::my-bucket",
+            "arn:aws:s3:us-east-1:123456789012:accesspoint/",
+            "arn:aws:s3:us-east-1:123456789012:accesspoint/my_access_point",
+            "arn:aws:s3:us-east-1::accesspoint/my-access-point",
+            "arn:aws:s3-outposts:us-west-2:123456789012:outpost/op-01ac5d28a6a232904",
+            "arn:aws:s3-object-lambda:us-east-1:123456789012:accesspoint/my-access-point",
+            "arn:aws:s3:us-east-1",
+        ] {
+            assert!(
+                matches!(
+                    validate_bucket(bucket),
+                    Err(BuildError::InvalidBucketArn { .. })
+                ),
+                "{bucket}"
+            );
+        }
+    }
+
+    #[test]
+    fn codec_names() {
+        assert_eq!(codec_name(&JsonSerializerConfig::default().into()), "json");
+        assert_eq!(codec_name(&TextSerializerConfig::default().into()), "text");
+    }
+
+    #[test]
+    fn byte_order_mark_requires_text_codec() {
+        assert!(validate_byte_order_mark_codec(&TextSerializerConfig::default().into()).is_ok());
+        assert!(matches!(
+            validate_byte_order_mark_codec(&NativeSerializerConfig.into()),
+            Err(BuildError::ByteOrderMarkWithBinaryCodec { codec }) if codec == "native"
+        ));
+    }
+
+    #[test]
+    fn delimited_framing_requires_text_codec() {
+        assert!(validate_framing_codec(
+            &FramingConfig::NewlineDelimited,
+            &JsonSerializerConfig::default().into()
+        )
+        .is_ok());
+        assert!(validate_framing_codec(
+            &length_delimited_framing(""),
+            &NativeSerializerConfig.into()
+        )
+        .is_ok());
+        assert!(matches!(
+            validate_framing_codec(&FramingConfig::NewlineDelimited, &NativeSerializerConfig.into()),
+            Err(BuildError::DelimitedFramingWithBinaryCodec { framing, codec })
+                if framing == "newline_delimited" && codec == "native"
+        ));
+    }
+
+    #[test]
+    fn tag_count_is_limited_per_object() {
+        let tags = |count: usize, name: &str| {
+            (0..count)
+                .map(|i| (format!("{name}_{i}"), "value".to_owned()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let partition_tags = |prefix: &str, tags| PartitionTags {
+            prefix: prefix.to_owned(),
+            tags,
+        };
+        let generated = ["min_event_time", "max_event_time"];
+
+        assert!(validate_tag_count(Some(&tags(8, "static")), &[], &generated).is_ok());
+        assert!(matches!(
+            validate_tag_count(Some(&tags(9, "static")), &[], &generated),
+            Err(BuildError::TooManyTags { count: 11 })
+        ));
+
+        // Entries under disjoint prefixes never apply to the same object.
+        let disjoint = [
+            partition_tags("a/", tags(5, "a")),
+            partition_tags("b/", tags(5, "b")),
+        ];
+        assert!(validate_tag_count(Some(&tags(3, "static")), &disjoint, &generated).is_ok());
+
+        // Entries under nested prefixes do, but tags they share are only counted once.
+        let nested = [
+            partition_tags("a/", tags(5, "a")),
+            partition_tags("a/b/", tags(5, "a")),
+        ];
+        assert!(validate_tag_count(Some(&tags(3, "static")), &nested, &generated).is_ok());
+        let nested = [
+            partition_tags("a/", tags(5, "a")),
+            partition_tags("a/b/", tags(2, "b")),
+        ];
+        assert!(matches!(
+            validate_tag_count(Some(&tags(3, "static")), &nested, &generated),
+            Err(BuildError::TooManyTags { count: 12 })
+        ));
+    }
+
+    #[test]
+    fn multipart_part_size_is_within_s3_limits() {
+        const MIB: usize = 1024 * 1024;
+        assert!(matches!(
+            validate_multipart_part_size(5 * MIB - 1),
+            Err(BuildError::InvalidMultipartPartSize { part_size }) if part_size == 5 * MIB - 1
+        ));
+        assert!(validate_multipart_part_size(5 * MIB).is_ok());
+        assert!(validate_multipart_part_size(64 * MIB).is_ok());
+        assert!(validate_multipart_part_size(5 * 1024 * MIB).is_ok());
+        assert!(matches!(
+            validate_multipart_part_size(5 * 1024 * MIB + 1),
+            Err(BuildError::InvalidMultipartPartSize { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_app_name() {
+        assert!(render_app_name("my deployment").is_err());
+        assert!(render_app_name("").is_err());
+    }
 }
@@ -31,7 +31,10 @@ use crate::{
     common::s3::S3ClientBuilder,
     config::SinkContext,
     sinks::{
-        aws_s3::config::default_filename_time_format,
+        aws_s3::config::{
+            default_filename_time_format, default_profile_startup_events, ByteOrderMark,
+            OnEmptyEncode, PayloadSigning,
+        },
         s3_common::config::{S3Options, S3ServerSideEncryption},
         util::{BatchConfig, Compression, TowerRequestConfig},
     },
@@ -426,14 +429,90 @@ async fn s3_flush_on_exhaustion() {
             bucket: bucket.to_string(),
             key_prefix: random_string(10) + "/date=%F",
             filename_time_format: default_filename_time_format(),
+            prefix_time_format: None,
+            filename_time_source: Default::default(),
+            clock_skew_threshold_secs: None,
             filename_append_uuid: true,
+            unique_suffix: None,
+            filename_sequence_start: None,
             filename_extension: None,
+            split_on_change: None,
+            user_agent_app_name: None,
+            min_events_per_object: None,
+            max_age_secs: None,
+            flush_at_boundary_secs: None,
+            flush_signal: None,
+            min_write_interval_secs: None,
+            startup_warmup_secs: None,
+            lowercase_keys: false,
+            on_missing_key: Default::default(),
+            key_prefix_mapping: None,
+            lag_partitioning: None,
+            hash_partitioning: None,
+            range_partitioning: None,
+            event_time_windowing: None,
+            key_segment_normalizers: Default::default(),
+            partition_key_cache_size: None,
+            framing_scope: Default::default(),
+            collapse_trailing_delimiter: false,
+            embedded_newlines: Default::default(),
+            invalid_utf8: Default::default(),
+            on_empty_encode: OnEmptyEncode::default(),
+            collapse_identical_events: false,
+            verify_event_counts: false,
+            byte_order_mark: ByteOrderMark::default(),
+            write_object_metadata: false,
+            lineage: None,
+            bloom_filter: None,
+            sidecar_compression: Compression::None,
+            max_event_bytes: None,
+            write_gzip_index: false,
+            gzip_header_os: None,
+            deterministic_compression: false,
+            max_key_length: 1024,
+            on_key_too_long: Default::default(),
+            on_key_collision: Default::default(),
+            storage_class_template: None,
+            profile_startup: false,
+            profile_startup_events: default_profile_startup_events(),
+            staging_dir: None,
+            spill: None,
+            retention: None,
+            upload_slo_secs: None,
+            log_object_versions: false,
+            write_build_metadata: false,
+            generation_metadata: None,
+            partition_case: Default::default(),
+            verify_uploads: None,
+            multipart_threshold_bytes: None,
+            multipart_part_size_bytes: None,
+            max_concurrent_uploads_per_prefix: None,
+            auto_region: false,
+            region_routing: None,
+            uncommitted_key_prefix: None,
+            uncommitted_key_suffix: None,
+            key_prefix_fallbacks: Vec::new(),
+            checksum_footer: None,
+            preserve_partition_order: false,
+            payload_signing: PayloadSigning::default(),
+            component_id_tag: None,
+            partition_tags: Vec::new(),
+            event_time_tags: None,
+            summary_object: None,
+            heartbeat: None,
+            emf_summary: None,
             options: S3Options::default(),
             region: RegionOrEndpoint::with_both("us-east-1", s3_address()),
             encoding: (None::<FramingConfig>, TextSerializerConfig::default()).into(),
+            avro_object_container_file: false,
             compression: Compression::None,
+            compression_min_bytes: None,
+            compression_candidates: Vec::new(),
             batch,
             request: TowerRequestConfig::default(),
+            retry_extra_retryable_codes: Vec::new(),
+            connection_retry_limit_secs: None,
+            on_persistent_error: Default::default(),
             tls: Default::default(),
             auth: Default::default(),
             acknowledgements: Default::default(),
@@ -510,14 +589,90 @@ fn config(bucket: &str, batch_size: usize) -> S3SinkConfig {
         bucket: bucket.to_string(),
         key_prefix: random_string(10) + "/date=%F",
         filename_time_format: default_filename_time_format(),
+        prefix_time_format: None,
+        filename_time_source: Default::default(),
+        clock_skew_threshold_secs: None,
         filename_append_uuid: true,
+        unique_suffix: None,
+        filename_sequence_start: None,
         filename_extension: None,
+        split_on_change: None,
+        user_agent_app_name: None,
+        min_events_per_object: None,
+        max_age_secs: None,
+        flush_at_boundary_secs: None,
+        flush_signal: None,
+        min_write_interval_secs: None,
+        startup_warmup_secs: None,
+        lowercase_keys: false,
+        on_missing_key: Default::default(),
+        key_prefix_mapping: None,
+        lag_partitioning: None,
+        hash_partitioning: None,
+        range_partitioning: None,
+        event_time_windowing: None,
+        key_segment_normalizers: Default::default(),
+        partition_key_cache_size: None,
+        framing_scope: Default::default(),
+        collapse_trailing_delimiter: false,
+        embedded_newlines: Default::default(),
+        invalid_utf8: Default::default(),
+        on_empty_encode: OnEmptyEncode::default(),
+        collapse_identical_events: false,
+        verify_event_counts: false,
+        byte_order_mark: ByteOrderMark::default(),
+        write_object_metadata: false,
+        lineage: None,
+        bloom_filter: None,
+        sidecar_compression: Compression::None,
+        max_event_bytes: None,
+        write_gzip_index: false,
+        gzip_header_os: None,
+        deterministic_compression: false,
+        max_key_length: 1024,
+        on_key_too_long: Default::default(),
+        on_key_collision: Default::default(),
+        storage_class_template: None,
+        profile_startup: false,
+        profile_startup_events: default_profile_startup_events(),
+        staging_dir: None,
+        spill: None,
+        retention: None,
+        upload_slo_secs: None,
+        log_object_versions: false,
+        write_build_metadata: false,
+        generation_metadata: None,
+        partition_case: Default::default(),
+        verify_uploads: None,
+        multipart_threshold_bytes: None,
+        multipart_part_size_bytes: None,
+        max_concurrent_uploads_per_prefix: None,
+        auto_region: false,
+        region_routing: None,
+        uncommitted_key_prefix: None,
+        uncommitted_key_suffix: None,
+        key_prefix_fallbacks: Vec::new(),
+        checksum_footer: None,
+        preserve_partition_order: false,
+        payload_signing: PayloadSigning::default(),
+        component_id_tag: None,
+        partition_tags: Vec::new(),
+        event_time_tags: None,
+        summary_object: None,
+        heartbeat: None,
+        emf_summary: None,
         options: S3Options::default(),
         region: RegionOrEndpoint::with_both("us-east-1", s3_address()),
         encoding: (None::<FramingConfig>, TextSerializerConfig::default()).into(),
+        avro_object_container_file: false,
         compression: Compression::None,
+        compression_min_bytes: None,
+        compression_candidates: Vec::new(),
         batch,
         request: TowerRequestConfig::default(),
+        retry_extra_retryable_codes: Vec::new(),
+        connection_retry_limit_secs: None,
+        on_persistent_error: Default::default(),
         tls: Default::default(),
         auth: Default::default(),
         acknowledgements: Default::default(),
@@ -1,24 +1,53 @@
-use std::io;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Write},
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
-use bytes::Bytes;
-use chrono::{FixedOffset, Utc};
+use aws_sdk_s3::config::Region;
+use aws_smithy_types::DateTime as AwsDateTime;
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use lru::LruCache;
+use md5::Digest;
 use uuid::Uuid;
 use vector_lib::codecs::encoding::Framer;
 use vector_lib::event::Finalizable;
-use vector_lib::request_metadata::RequestMetadata;
+use vector_lib::request_metadata::{GroupedCountByteSize, RequestMetadata};
+use vrl::path::{OwnedValuePath, PathPrefix};
 
+use super::config::{
+    BloomFilterConfig, ByteOrderMark, ChecksumAlgorithm, ChecksumFooter, EventTimeTagsConfig,
+    FilenameTimeSource, KeyCollisionStrategy, KeyTooLongStrategy, PartitionTags, S3Target,
+    SummaryObjectConfig, UniqueSuffix,
+};
 use crate::{
     codecs::{Encoder, Transformer},
     event::Event,
+    internal_events::{
+        S3ClockSkewDetected, S3CompressionSelected, S3EventCountMismatch, TemplateRenderingError,
+    },
     sinks::{
         s3_common::{
+            bgzf,
+            bloom::BloomFilter,
             config::S3Options,
+            lineage::Lineage,
             partitioner::S3PartitionKey,
-            service::{S3Metadata, S3Request},
+            service::{S3Metadata, S3ObjectMetadata, S3Request},
+            sink::{encode_object_events, ObjectPosition},
+            summary::{Aggregate, WindowSummaries, WindowSummary},
         },
         util::{
-            metadata::RequestMetadataBuilder, request_builder::EncodeResult, Compression,
-            RequestBuilder,
+            encoding::{self, BatchFraming, Encoder as _},
+            metadata::RequestMetadataBuilder,
+            request_builder::EncodeResult,
+            zstd, Compression, Compressor, RequestBuilder,
         },
     },
 };
@@ -26,20 +55,70 @@ use crate::{
 #[derive(Clone)]
 pub struct S3RequestOptions {
     pub bucket: String,
+    /// The target bucket and region of each routing value, when routing objects to them.
+    pub targets: Option<HashMap<String, S3Target>>,
     pub filename_time_format: String,
-    pub filename_append_uuid: bool,
+    /// The format of the date partition inserted between the key prefix and the filename, if any.
+    pub prefix_time_format: Option<String>,
+    pub unique_suffix: UniqueSuffix,
+    /// The filenames built with the current timestamp, when a UUID is only appended when needed.
+    pub used_filenames: Arc<Mutex<UsedFilenames>>,
     pub filename_extension: Option<String>,
+    /// The extension replacing `log` in the default extension, for codecs with their own.
+    pub codec_extension: Option<&'static str>,
     pub api_options: S3Options,
-    pub encoder: (Transformer, Encoder<Framer>),
+    pub encoder: ObjectEncoder,
     pub compression: Compression,
+    /// The algorithms objects are also compressed with, uploading whichever is the smallest.
+    pub compression_candidates: Vec<Compression>,
+    /// The size below which objects are uploaded uncompressed, if any.
+    pub compression_min_bytes: Option<NonZeroUsize>,
     pub filename_tz_offset: Option<FixedOffset>,
+    pub lowercase_keys: bool,
+    pub write_object_metadata: bool,
+    /// The fields the source offsets recorded in companion metadata objects are read from, if any.
+    pub lineage: Option<Lineage>,
+    /// The name of the codec events are encoded with, recorded in companion metadata objects.
+    pub codec: String,
+    pub max_key_length: usize,
+    pub on_key_too_long: KeyTooLongStrategy,
+    pub on_key_collision: KeyCollisionStrategy,
+    /// The keys built with the current timestamp, when collisions are checked for.
+    pub used_keys: Arc<Mutex<UsedFilenames>>,
+    pub filename_time_source: FilenameTimeSource,
+    /// The time the last object was named after, when filename times are monotonic.
+    pub last_filename_time: Arc<Mutex<Option<DateTime<Utc>>>>,
+    /// The difference between the clock and event timestamps above which clock skew is reported.
+    pub clock_skew_threshold: Option<Duration>,
+    /// The sequence number of the next object, when filenames are prefixed with one.
+    pub sequence: Option<Arc<AtomicU64>>,
+    /// The sequence number of the next object within the generation, when objects are written
+    /// with ordering metadata.
+    pub generation_sequence: Option<Arc<AtomicU64>>,
+    /// Whether to write a single copy of the events of batches whose events are all encoded
+    /// identically.
+    pub collapse_identical_events: bool,
+    /// Whether to check the event count of each request against the records encoded in it.
+    pub verify_event_counts: bool,
+    pub bloom_filter: Option<BloomFilterConfig>,
+    /// Whether to compress objects as BGZF blocks, and write the `bgzip` index of the blocks.
+    pub write_gzip_index: bool,
+    /// The value to set the OS field of the header of gzip objects to.
+    pub gzip_header_os: Option<u8>,
+    /// Whether to compress zstd objects as a single frame with a fixed configuration.
+    pub deterministic_compression: bool,
+    pub event_time_tags: Option<EventTimeTagsConfig>,
+    pub partition_tags: Vec<PartitionTags>,
+    pub summary_object: Option<SummaryObjectConfig>,
+    /// The running aggregates of the windows, when summary objects are written.
+    pub window_summaries: Arc<WindowSummaries>,
 }
 
-impl RequestBuilder<(S3PartitionKey, Vec<Event>)> for S3RequestOptions {
+impl RequestBuilder<(S3PartitionKey, Vec<Event>, ObjectPosition, Instant)> for S3RequestOptions {
     type Metadata = S3Metadata;
-    type Events = Vec<Event>;
-    type Encoder = (Transformer, Encoder<Framer>);
-    type Payload = Bytes;
+    type Events = (Vec<Event>, ObjectPosition);
+    type Encoder = ObjectEncoder;
+    type Payload = S3Payload;
     type Request = S3Request;
     type Error = io::Error; // TODO: this is ugly.
 
@@ -53,21 +132,93 @@ impl RequestBuilder<(S3PartitionKey, Vec<Event>)> for S3RequestOptions {
 
     fn split_input(
         &self,
-        input: (S3PartitionKey, Vec<Event>),
+        input: (S3PartitionKey, Vec<Event>, ObjectPosition, Instant),
     ) -> (Self::Metadata, RequestMetadataBuilder, Self::Events) {
-        let (partition_key, mut events) = input;
+        let (partition_key, mut events, position, batch_started) = input;
         let builder = RequestMetadataBuilder::from_events(&events);
 
         let finalizers = events.take_finalizers();
         let s3_key_prefix = partition_key.key_prefix.clone();
 
+        let object_metadata = self.write_object_metadata.then(|| {
+            let (min_timestamp, max_timestamp) = timestamp_range(&events);
+            S3ObjectMetadata {
+                min_timestamp,
+                max_timestamp,
+                key_prefix: partition_key.key_prefix.clone(),
+                codec: self.codec.clone(),
+                lineage: self
+                    .lineage
+                    .as_ref()
+                    .map(|lineage| lineage.source_ranges(&events)),
+                ..Default::default()
+            }
+        });
+
+        let bloom_filter = self
+            .bloom_filter
+            .as_ref()
+            .map(|config| build_bloom_filter(config, &events));
+
+        let latest_event_timestamp = if self.filename_time_source == FilenameTimeSource::LatestEvent
+            || self.clock_skew_threshold.is_some()
+        {
+            timestamp_range(&events).1
+        } else {
+            None
+        };
+
+        let event_time_range = self.event_time_tags.as_ref().and_then(|event_time_tags| {
+            let timestamp_field = event_time_tags.timestamp_field.as_ref();
+            event_time_range(&events, timestamp_field.map(|field| &field.0))
+        });
+
+        let summary = self
+            .summary_object
+            .as_ref()
+            .and_then(|config| window_summary(config, &partition_key.key_prefix, &events));
+
         let metadata = S3Metadata {
             partition_key,
             s3_key: s3_key_prefix,
             finalizers,
+            object_metadata,
+            bloom_filter,
+            gzip_index: None,
+            key_error: None,
+            batch_started: Some(batch_started),
+            latest_event_timestamp,
+            event_time_range,
+            summary,
+            sequence: None,
         };
 
-        (metadata, builder, events)
+        (metadata, builder, (events, position))
+    }
+
+    fn encode_events(
+        &self,
+        (mut events, position): Self::Events,
+    ) -> Result<EncodeResult<Self::Payload>, Self::Error> {
+        let collapsed_count = if self.collapse_identical_events {
+            collapse_identical_events(&self.encoder.encoder, &mut events)?
+        } else {
+            None
+        };
+        let mut encoded = encode_object(
+            &self.encoder,
+            self.compression,
+            &self.compression_candidates,
+            self.compression_min_bytes,
+            self.write_gzip_index,
+            self.deterministic_compression,
+            (events, position),
+        )?;
+        encoded.payload.collapsed_count = collapsed_count;
+        if let Some(os) = self.gzip_header_os {
+            set_gzip_header_os(&mut encoded.payload, os);
+        }
+        Ok(encoded)
     }
 
     fn build_request(
@@ -76,44 +227,857 @@ impl RequestBuilder<(S3PartitionKey, Vec<Event>)> for S3RequestOptions {
         request_metadata: RequestMetadata,
         payload: EncodeResult<Self::Payload>,
     ) -> Self::Request {
+        // Objects below `compression_min_bytes` are left uncompressed, and objects may be
+        // compressed with one of the candidates instead.
+        let compression = payload.payload.compression;
+        let collapsed_count = payload.payload.collapsed_count;
+        let now = Utc::now();
+        let latest_event_timestamp = s3metadata.latest_event_timestamp;
+        if let (Some(threshold), Some(latest_event_timestamp)) =
+            (self.clock_skew_threshold, latest_event_timestamp)
+        {
+            let skew = (now - latest_event_timestamp)
+                .num_milliseconds()
+                .unsigned_abs();
+            let skew = Duration::from_millis(skew);
+            if skew > threshold {
+                emit!(S3ClockSkewDetected { skew, threshold });
+            }
+        }
+        let time = filename_time(
+            self.filename_time_source,
+            now,
+            latest_event_timestamp,
+            &self.last_filename_time,
+        );
+        let format_time = |format: &str| {
+            match self.filename_tz_offset {
+                Some(offset) => time.with_timezone(&offset).format(format),
+                None => time.format(format),
+            }
+            .to_string()
+        };
+        let formatted_ts = format_time(&self.filename_time_format);
+        // The date partition is formatted from the same time as the filename, so that they agree.
+        if let Some(prefix_time_format) = &self.prefix_time_format {
+            s3metadata.s3_key.push_str(&format_time(prefix_time_format));
+        }
         let filename = {
-            let formatted_ts = match self.filename_tz_offset {
-                Some(offset) => Utc::now()
-                    .with_timezone(&offset)
-                    .format(self.filename_time_format.as_str()),
-                None => Utc::now()
-                    .with_timezone(&chrono::Utc)
-                    .format(self.filename_time_format.as_str()),
+            let append_uuid = match self.unique_suffix {
+                UniqueSuffix::Always => true,
+                UniqueSuffix::WhenNeeded => {
+                    self.sequence.is_none()
+                        && !self
+                            .used_filenames
+                            .lock()
+                            .expect("used filenames mutex poisoned")
+                            .insert(&s3metadata.s3_key, &formatted_ts)
+                }
+                UniqueSuffix::Never => false,
             };
 
-            self.filename_append_uuid
+            append_uuid
                 .then(|| format!("{}-{}", formatted_ts, Uuid::new_v4().hyphenated()))
-                .unwrap_or_else(|| formatted_ts.to_string())
+                .unwrap_or_else(|| formatted_ts.clone())
+        };
+        // The sequence number is assigned once the request is built, so retries of the request
+        // reuse it.
+        let filename = match &self.sequence {
+            Some(sequence) => {
+                sequenced_filename(sequence.fetch_add(1, Ordering::Relaxed), &filename)
+            }
+            None => filename,
         };
+        s3metadata.sequence = self
+            .generation_sequence
+            .as_ref()
+            .map(|sequence| sequence.fetch_add(1, Ordering::Relaxed));
 
         let ssekms_key_id = s3metadata.partition_key.ssekms_key_id.clone();
         let mut s3_options = self.api_options.clone();
         s3_options.ssekms_key_id = ssekms_key_id;
+        if let Some(storage_class) = s3metadata.partition_key.storage_class {
+            s3_options.storage_class = storage_class;
+        }
+        if let Some(collapsed_count) = collapsed_count {
+            s3_options
+                .tags
+                .get_or_insert_with(BTreeMap::new)
+                .insert(COLLAPSED_COUNT_TAG.to_owned(), collapsed_count.to_string());
+        }
+        apply_partition_tags(
+            &mut s3_options.tags,
+            &self.partition_tags,
+            &s3metadata.partition_key.key_prefix,
+        );
+        if let Some((min, max)) = s3metadata.event_time_range {
+            let tags = s3_options.tags.get_or_insert_with(BTreeMap::new);
+            let format = |time: DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Secs, true);
+            tags.insert(MIN_EVENT_TIME_TAG.to_owned(), format(min));
+            tags.insert(MAX_EVENT_TIME_TAG.to_owned(), format(max));
+        }
 
         let extension = self
             .filename_extension
             .as_ref()
             .cloned()
-            .unwrap_or_else(|| self.compression.extension().into());
+            .unwrap_or_else(|| default_extension(self.codec_extension, compression));
+
+        let (key_prefix, filename, extension) = if self.lowercase_keys {
+            (
+                s3metadata.s3_key.to_lowercase(),
+                filename.to_lowercase(),
+                extension.to_lowercase(),
+            )
+        } else {
+            (std::mem::take(&mut s3metadata.s3_key), filename, extension)
+        };
+        let build_key = |filename: &str| {
+            limit_key_length(
+                &key_prefix,
+                filename,
+                &extension,
+                self.max_key_length,
+                self.on_key_too_long,
+            )
+            .map_err(|error| (error, format_s3_key(&key_prefix, filename, &extension)))
+        };
+        let s3_key = match self.on_key_collision {
+            KeyCollisionStrategy::Overwrite => build_key(&filename),
+            strategy => build_key(&filename).and_then(|s3_key| {
+                resolve_key_collision(
+                    s3_key,
+                    &mut self.used_keys.lock().expect("used keys mutex poisoned"),
+                    &formatted_ts,
+                    strategy,
+                    |counter| build_key(&format!("{filename}-{counter}")),
+                )
+            }),
+        };
+        s3metadata.s3_key = match s3_key {
+            Ok(s3_key) => s3_key,
+            Err((error, s3_key)) => {
+                s3metadata.key_error = Some(error);
+                s3_key
+            }
+        };
+
+        if self.verify_event_counts {
+            // Collapsed objects only encode one of the events they are accounted for.
+            let event_count = match collapsed_count {
+                Some(_) => 1,
+                None => request_metadata.event_count(),
+            };
+            let record_count = record_count(&payload.transformed_json_size);
+            // Events dropped for encoding to zero bytes are counted, but not encoded.
+            if record_count > event_count
+                || (record_count < event_count && !self.encoder.framing.drop_empty_events)
+            {
+                emit!(S3EventCountMismatch {
+                    key: &s3metadata.s3_key,
+                    event_count,
+                    record_count,
+                });
+            }
+        }
+
+        // The aggregate of the object is only added to its window once its request is built, as
+        // the events of batches exceeding the maximum object size are split in several requests.
+        if let Some(summary) = &mut s3metadata.summary {
+            summary.body = Some(
+                self.window_summaries
+                    .add(&summary.key, &summary.aggregate, now),
+            );
+        }
+
+        if self.write_gzip_index && matches!(compression, Compression::Gzip(_)) {
+            s3metadata.gzip_index = bgzf::index(payload.payload.as_ref()).map(Into::into);
+        }
+
+        if let Some(object_metadata) = &mut s3metadata.object_metadata {
+            object_metadata.event_count = request_metadata.event_count();
+            object_metadata.uncompressed_byte_size = request_metadata.request_encoded_size();
+            object_metadata.compressed_byte_size = request_metadata.request_wire_size();
+        }
 
-        s3metadata.s3_key = format_s3_key(&s3metadata.s3_key, &filename, &extension);
+        let (bucket, region) = match &self.targets {
+            Some(targets) => {
+                let target = s3metadata
+                    .partition_key
+                    .target
+                    .as_ref()
+                    .and_then(|target| targets.get(target));
+                match target {
+                    Some(target) => (
+                        target.bucket.clone(),
+                        Some(Region::new(target.region.clone())),
+                    ),
+                    // The routing value is left out of the error, as it may be subject to the
+                    // same restrictions as the events themselves.
+                    None => {
+                        s3metadata.key_error.get_or_insert_with(|| {
+                            "No `region_routing` target is configured for the routing value of the batch."
+                                .to_owned()
+                        });
+                        (self.bucket.clone(), None)
+                    }
+                }
+            }
+            None => (self.bucket.clone(), None),
+        };
 
         S3Request {
-            body: payload.into_payload(),
-            bucket: self.bucket.clone(),
+            body: payload.into_payload().body,
+            bucket,
             metadata: s3metadata,
             request_metadata,
-            content_encoding: self.compression.content_encoding(),
+            content_encoding: compression.content_encoding(),
+            expires: self.api_options.expires_secs.and_then(expires_at),
             options: s3_options,
+            region,
+        }
+    }
+}
+
+/// The tag set to the number of events an object stands for, when they were collapsed into one.
+pub(super) const COLLAPSED_COUNT_TAG: &str = "collapsed_count";
+
+/// The tags of the earliest and latest event timestamps of objects.
+pub(super) const MIN_EVENT_TIME_TAG: &str = "min_event_time";
+pub(super) const MAX_EVENT_TIME_TAG: &str = "max_event_time";
+
+/// Collapses the events into the first of them if they are all serialized to the same bytes,
+/// returning the number of events it then stands for.
+fn collapse_identical_events(
+    (transformer, encoder): &(Transformer, Encoder<Framer>),
+    events: &mut Vec<Event>,
+) -> io::Result<Option<usize>> {
+    if events.len() < 2 {
+        return Ok(None);
+    }
+    let mut encoder = encoder.clone();
+    let mut serialize = |event: &Event| {
+        let mut event = event.clone();
+        transformer.transform(&mut event);
+        let mut bytes = BytesMut::new();
+        encoder
+            .serialize(event, &mut bytes)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        io::Result::Ok(bytes)
+    };
+    let first = serialize(&events[0])?;
+    for event in &events[1..] {
+        if serialize(event)? != first {
+            return Ok(None);
+        }
+    }
+    let count = events.len();
+    events.truncate(1);
+    Ok(Some(count))
+}
+
+/// Builds the body of the companion bloom filter object over the values rendered from the events.
+///
+/// Events whose value fails to render are left out of the filter.
+fn build_bloom_filter(config: &BloomFilterConfig, events: &[Event]) -> Bytes {
+    let mut filter = BloomFilter::new(config.expected_items.get(), config.false_positive_rate);
+    let mut item_count = 0;
+    for event in events {
+        match config.source.render_string(event) {
+            Ok(value) => {
+                filter.insert(value.as_bytes());
+                item_count += 1;
+            }
+            Err(error) => emit!(TemplateRenderingError {
+                error,
+                field: Some("bloom_filter.source"),
+                drop_event: false,
+            }),
+        }
+    }
+    filter.to_json(config.source.get_ref(), item_count).into()
+}
+
+/// The name of summary objects written under the key prefix of objects, when their key is not
+/// templated.
+const SUMMARY_OBJECT_NAME: &str = "_summary.json";
+
+/// Aggregates the events of an object for the summary object of its window.
+///
+/// The summary object is skipped if its key fails to render.
+fn window_summary(
+    config: &SummaryObjectConfig,
+    key_prefix: &str,
+    events: &[Event],
+) -> Option<WindowSummary> {
+    let key = match &config.key {
+        Some(key) => match key.render_string(events.first()?) {
+            Ok(key) => key,
+            Err(error) => {
+                emit!(TemplateRenderingError {
+                    error,
+                    field: Some("summary_object.key"),
+                    drop_event: false,
+                });
+                return None;
+            }
+        },
+        None => format!("{}{}", key_prefix, SUMMARY_OBJECT_NAME),
+    };
+    Some(WindowSummary {
+        key,
+        aggregate: Aggregate::of_events(events, config.fields.iter().map(|field| &field.0)),
+        body: None,
+    })
+}
+
+/// The number of records encoded, as counted while encoding them.
+fn record_count(encoded: &GroupedCountByteSize) -> usize {
+    match encoded {
+        GroupedCountByteSize::Tagged { sizes } => sizes.values().map(|size| size.0).sum(),
+        GroupedCountByteSize::Untagged { size } => size.0,
+    }
+}
+
+/// Encodes and compresses an object, leaving it uncompressed if it is smaller than
+/// `compression_min_bytes`, as compression headers would then make up much of the object.
+///
+/// Gzip objects are compressed as BGZF blocks when `block_gzip` is set, and zstd objects are
+/// compressed with a fixed frame configuration when `deterministic` is set. When compression
+/// candidates are given, the object is also compressed with each of them, and the smallest is kept.
+fn encode_object(
+    encoder: &ObjectEncoder,
+    compression: Compression,
+    compression_candidates: &[Compression],
+    compression_min_bytes: Option<NonZeroUsize>,
+    block_gzip: bool,
+    deterministic: bool,
+    events: (Vec<Event>, ObjectPosition),
+) -> io::Result<EncodeResult<S3Payload>> {
+    let compression_min_bytes = compression_min_bytes
+        .filter(|_| compression.is_compressed() || !compression_candidates.is_empty());
+    let block_gzip = block_gzip && matches!(compression, Compression::Gzip(_));
+    let deterministic_zstd = deterministic && matches!(compression, Compression::Zstd(_));
+    if compression_min_bytes.is_none()
+        && !block_gzip
+        && !deterministic_zstd
+        && compression_candidates.is_empty()
+    {
+        let mut compressor = Compressor::from(compression);
+        let (_, json_size) = encoder.encode_input(events, &mut compressor)?;
+        let payload = S3Payload {
+            body: compressor.finish()?.freeze(),
+            compression,
+            collapsed_count: None,
+        };
+        return Ok(if compression.is_compressed() {
+            let compressed_byte_size = payload.body.len();
+            EncodeResult::compressed(payload, compressed_byte_size, json_size)
+        } else {
+            EncodeResult::uncompressed(payload, json_size)
+        });
+    }
+
+    // The size is only known once the object is encoded, blocks are compressed on their own,
+    // deterministic frames are compressed in one go, and candidates all compress the same object,
+    // so it is compressed afterwards.
+    let mut uncompressed = Vec::new();
+    let (uncompressed_byte_size, json_size) = encoder.encode_input(events, &mut uncompressed)?;
+    if compression_min_bytes.is_some_and(|min_bytes| uncompressed.len() < min_bytes.get()) {
+        let payload = S3Payload::from(Bytes::from(uncompressed));
+        return Ok(EncodeResult::uncompressed(payload, json_size));
+    }
+    let mut payload = compress(&uncompressed, compression, block_gzip, deterministic)?;
+    if !compression_candidates.is_empty() {
+        // Ties go to the configured compression, then to the earliest candidate.
+        for &candidate in compression_candidates {
+            let compressed = compress(&uncompressed, candidate, block_gzip, deterministic)?;
+            if compressed.body.len() < payload.body.len() {
+                payload = compressed;
+            }
+        }
+        emit!(S3CompressionSelected {
+            compression: payload.compression,
+        });
+    }
+    Ok(if payload.compression.is_compressed() {
+        EncodeResult::compressed(payload, uncompressed_byte_size, json_size)
+    } else {
+        EncodeResult::uncompressed(payload, json_size)
+    })
+}
+
+fn compress(
+    data: &[u8],
+    compression: Compression,
+    block_gzip: bool,
+    deterministic: bool,
+) -> io::Result<S3Payload> {
+    let body = match compression {
+        Compression::Gzip(level) if block_gzip => {
+            Bytes::from(bgzf::compress(data, level.as_flate2())?)
+        }
+        Compression::Zstd(level) if deterministic => {
+            Bytes::from(zstd::compress_deterministic(data, level.into())?)
+        }
+        _ => {
+            let mut compressor = Compressor::from(compression);
+            compressor.write_all(data)?;
+            compressor.finish()?.freeze()
+        }
+    };
+    Ok(S3Payload {
+        body,
+        compression,
+        collapsed_count: None,
+    })
+}
+
+/// The offset of the OS field in the header of gzip members.
+const GZIP_OS_OFFSET: usize = 9;
+
+/// Sets the OS field of the header of gzip objects, leaving other objects as they are.
+///
+/// Only the header of the first member is set, as objects are compressed as a single member
+/// unless they are compressed as BGZF blocks.
+fn set_gzip_header_os(payload: &mut S3Payload, os: u8) {
+    if !matches!(payload.compression, Compression::Gzip(_)) || payload.body.len() <= GZIP_OS_OFFSET
+    {
+        return;
+    }
+    let mut body = BytesMut::from(payload.body.as_ref());
+    body[GZIP_OS_OFFSET] = os;
+    payload.body = body.freeze();
+}
+
+/// The body of an object, along with the compression it was compressed with.
+#[derive(Clone, Debug)]
+pub struct S3Payload {
+    pub body: Bytes,
+    pub compression: Compression,
+    /// The number of events the body stands for, when identical events were collapsed into one.
+    pub collapsed_count: Option<usize>,
+}
+
+impl From<Bytes> for S3Payload {
+    fn from(body: Bytes) -> Self {
+        Self {
+            body,
+            compression: Compression::None,
+            collapsed_count: None,
+        }
+    }
+}
+
+impl AsRef<[u8]> for S3Payload {
+    fn as_ref(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+/// Encodes objects, followed by a checksum footer if configured.
+#[derive(Clone)]
+pub struct ObjectEncoder {
+    pub encoder: (Transformer, Encoder<Framer>),
+    pub checksum_footer: Option<ChecksumFooter>,
+    pub framing: BatchFraming,
+    pub byte_order_mark: ByteOrderMark,
+}
+
+impl ObjectEncoder {
+    fn encode_events(
+        &self,
+        (events, position): (Vec<Event>, ObjectPosition),
+        writer: &mut dyn io::Write,
+    ) -> io::Result<(usize, GroupedCountByteSize)> {
+        if self.byte_order_mark == ByteOrderMark::None {
+            return encode_object_events(&self.encoder, (events, position), writer, self.framing);
+        }
+
+        // Objects following the first one of a stream continue its text, so they never get a BOM.
+        let add = self.byte_order_mark == ByteOrderMark::Add && position.first;
+        let mut writer = ByteOrderMarkWriter::new(writer, add);
+        let (byte_size, json_size) =
+            encode_object_events(&self.encoder, (events, position), &mut writer, self.framing)?;
+        Ok((writer.finish(byte_size)?, json_size))
+    }
+}
+
+impl encoding::Encoder<(Vec<Event>, ObjectPosition)> for ObjectEncoder {
+    fn encode_input(
+        &self,
+        input: (Vec<Event>, ObjectPosition),
+        writer: &mut dyn io::Write,
+    ) -> io::Result<(usize, GroupedCountByteSize)> {
+        let Some(checksum_footer) = &self.checksum_footer else {
+            return self.encode_events(input, writer);
+        };
+
+        // The footer covers everything before it, so it is written once the events are.
+        let mut writer = ChecksumWriter::new(writer, checksum_footer.algorithm);
+        let (byte_size, json_size) = self.encode_events(input, &mut writer)?;
+        let footer_byte_size = writer.write_footer(&checksum_footer.format)?;
+        Ok((byte_size + footer_byte_size, json_size))
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Strips the UTF-8 BOM the bytes written through it start with, if any, and optionally writes
+/// one in its place.
+struct ByteOrderMarkWriter<'a> {
+    inner: &'a mut dyn io::Write,
+    add: bool,
+    /// The first bytes written, held back until it is known whether they are a BOM.
+    start: Option<Vec<u8>>,
+    stripped: bool,
+}
+
+impl<'a> ByteOrderMarkWriter<'a> {
+    fn new(inner: &'a mut dyn io::Write, add: bool) -> Self {
+        Self {
+            inner,
+            add,
+            start: Some(Vec::with_capacity(UTF8_BOM.len())),
+            stripped: false,
+        }
+    }
+
+    fn write_start(&mut self) -> io::Result<()> {
+        let Some(start) = self.start.take() else {
+            return Ok(());
+        };
+        if self.add {
+            self.inner.write_all(UTF8_BOM)?;
+        }
+        match start.strip_prefix(UTF8_BOM) {
+            Some(rest) => {
+                self.stripped = true;
+                self.inner.write_all(rest)
+            }
+            None => self.inner.write_all(&start),
+        }
+    }
+
+    /// Writes out any bytes still held back, returning the given number of bytes encoded adjusted
+    /// for the BOM added or stripped.
+    fn finish(mut self, byte_size: usize) -> io::Result<usize> {
+        self.write_start()?;
+        let added = if self.add { UTF8_BOM.len() } else { 0 };
+        let stripped = if self.stripped { UTF8_BOM.len() } else { 0 };
+        Ok(byte_size + added - stripped)
+    }
+}
+
+impl io::Write for ByteOrderMarkWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(start) = &mut self.start else {
+            #[allow(clippy::disallowed_methods)]
+            // We pass on the result of `write` to the caller.
+            return self.inner.write(buf);
+        };
+        let n = buf.len().min(UTF8_BOM.len() - start.len());
+        start.extend_from_slice(&buf[..n]);
+        if start.len() == UTF8_BOM.len() {
+            self.write_start()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+enum Checksum {
+    Crc32(crc32fast::Hasher),
+    Md5(md5::Md5),
+}
+
+/// Computes the checksum of the bytes written through it.
+struct ChecksumWriter<'a> {
+    inner: &'a mut dyn io::Write,
+    checksum: Checksum,
+    algorithm: ChecksumAlgorithm,
+    last_byte: Option<u8>,
+}
+
+impl<'a> ChecksumWriter<'a> {
+    fn new(inner: &'a mut dyn io::Write, algorithm: ChecksumAlgorithm) -> Self {
+        let checksum = match algorithm {
+            ChecksumAlgorithm::Crc32 => Checksum::Crc32(crc32fast::Hasher::new()),
+            ChecksumAlgorithm::Md5 => Checksum::Md5(md5::Md5::new()),
+        };
+        Self {
+            inner,
+            checksum,
+            algorithm,
+            last_byte: None,
+        }
+    }
+
+    /// Writes the footer line, returning the number of bytes written.
+    ///
+    /// The newline ending the last line before the footer is written first if missing, and is
+    /// covered by the checksum.
+    fn write_footer(mut self, format: &str) -> io::Result<usize> {
+        let mut byte_size = 0;
+        if self.last_byte.is_some_and(|last_byte| last_byte != b'\n') {
+            io::Write::write_all(&mut self, b"\n")?;
+            byte_size += 1;
+        }
+
+        let checksum = match self.checksum {
+            Checksum::Crc32(hasher) => format!("{:08x}", hasher.finalize()),
+            Checksum::Md5(hasher) => format!("{:x}", hasher.finalize()),
+        };
+        let footer = format!(
+            "{}\n",
+            format
+                .replace("{algorithm}", self.algorithm.name())
+                .replace("{checksum}", &checksum)
+        );
+        self.inner.write_all(footer.as_bytes())?;
+        Ok(byte_size + footer.len())
+    }
+}
+
+impl io::Write for ChecksumWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        #[allow(clippy::disallowed_methods)] // We pass on the result of `write` to the caller.
+        let n = self.inner.write(buf)?;
+        match &mut self.checksum {
+            Checksum::Crc32(hasher) => hasher.update(&buf[..n]),
+            Checksum::Md5(hasher) => hasher.update(&buf[..n]),
+        }
+        if n > 0 {
+            self.last_byte = Some(buf[n - 1]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The number of key prefixes, or keys, and timestamps objects were built under to remember.
+const USED_FILENAMES_CAPACITY: usize = 16_384;
+
+/// The key prefixes, or the keys, objects have been built under, along with their timestamp.
+///
+/// Timestamps may go back, such as when objects are named after the time of their events, or when
+/// requests are built out of order, so the most recently used pairs are tracked, whatever their
+/// timestamp, up to `USED_FILENAMES_CAPACITY` of them.
+#[derive(Debug)]
+pub struct UsedFilenames {
+    keys: LruCache<(String, String), ()>,
+}
+
+impl Default for UsedFilenames {
+    fn default() -> Self {
+        Self {
+            keys: LruCache::new(NonZeroUsize::new(USED_FILENAMES_CAPACITY).unwrap()),
         }
     }
 }
 
+impl UsedFilenames {
+    /// Records an object built under the key, or key prefix, with the timestamp, returning
+    /// whether no other object was built under it with the same timestamp.
+    fn insert(&mut self, key: &str, timestamp: &str) -> bool {
+        self.keys
+            .put((key.to_owned(), timestamp.to_owned()), ())
+            .is_none()
+    }
+}
+
+/// Records the key of an object built with the timestamp, applying the strategy if another object
+/// was already built under it.
+///
+/// Errors hold the reason the key is rejected, along with the key.
+fn resolve_key_collision(
+    s3_key: String,
+    used_keys: &mut UsedFilenames,
+    timestamp: &str,
+    strategy: KeyCollisionStrategy,
+    build_key: impl Fn(u64) -> Result<String, (String, String)>,
+) -> Result<String, (String, String)> {
+    if used_keys.insert(&s3_key, timestamp) {
+        return Ok(s3_key);
+    }
+    match strategy {
+        KeyCollisionStrategy::Overwrite => Ok(s3_key),
+        KeyCollisionStrategy::AppendCounter => (1..)
+            .map(build_key)
+            .find(|s3_key| match s3_key {
+                Ok(s3_key) => used_keys.insert(s3_key, timestamp),
+                Err(_) => true,
+            })
+            .expect("counters are unbounded"),
+        KeyCollisionStrategy::Error => Err((
+            format!(
+                "Object key {s3_key:?} is already used by another object with the same timestamp."
+            ),
+            s3_key,
+        )),
+    }
+}
+
+/// Returns the time objects built at `now` are named after.
+///
+/// Monotonic times never go back before the last time returned, and times are only clamped to
+/// the latest event timestamp when the batch has one.
+fn filename_time(
+    source: FilenameTimeSource,
+    now: DateTime<Utc>,
+    latest_event_timestamp: Option<DateTime<Utc>>,
+    last_filename_time: &Mutex<Option<DateTime<Utc>>>,
+) -> DateTime<Utc> {
+    match source {
+        FilenameTimeSource::Clock => now,
+        FilenameTimeSource::Monotonic => {
+            let mut last = last_filename_time
+                .lock()
+                .expect("last filename time mutex poisoned");
+            let time = last.map_or(now, |last| last.max(now));
+            *last = Some(time);
+            time
+        }
+        FilenameTimeSource::LatestEvent => {
+            latest_event_timestamp.map_or(now, |latest| latest.min(now))
+        }
+    }
+}
+
+/// Returns the earliest and latest timestamps of the given events.
+fn timestamp_range(events: &[Event]) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    let timestamps = events.iter().filter_map(|event| match event {
+        Event::Log(log) => log.get_timestamp().and_then(|v| v.as_timestamp()).copied(),
+        Event::Metric(metric) => metric.timestamp(),
+        _ => None,
+    });
+    timestamps.fold((None, None), |(min, max), timestamp| {
+        (
+            min.min(Some(timestamp)).or(Some(timestamp)),
+            max.max(Some(timestamp)),
+        )
+    })
+}
+
+/// Applies the tags of the partitions whose prefix the given key prefix starts with, in order.
+fn apply_partition_tags(
+    tags: &mut Option<BTreeMap<String, String>>,
+    partition_tags: &[PartitionTags],
+    key_prefix: &str,
+) {
+    for partition_tags in partition_tags {
+        if key_prefix.starts_with(&partition_tags.prefix) {
+            tags.get_or_insert_with(BTreeMap::new)
+                .extend(partition_tags.tags.clone());
+        }
+    }
+}
+
+/// Returns the earliest and latest timestamps of the given events, read from the given field of
+/// log events rather than their own timestamp.
+///
+/// Values of the field which are not timestamps are ignored.
+fn event_time_range(
+    events: &[Event],
+    timestamp_field: Option<&OwnedValuePath>,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let Some(field) = timestamp_field else {
+        let (min, max) = timestamp_range(events);
+        return min.zip(max);
+    };
+    events
+        .iter()
+        .filter_map(|event| {
+            event
+                .maybe_as_log()?
+                .get((PathPrefix::Event, field))?
+                .as_timestamp()
+                .copied()
+        })
+        .fold(None, |range, timestamp| {
+            Some(range.map_or((timestamp, timestamp), |(min, max)| {
+                (min.min(timestamp), max.max(timestamp))
+            }))
+        })
+}
+
+/// Formats the object key, applying the given strategy if it's longer than `max_key_length`.
+fn limit_key_length(
+    key_prefix: &str,
+    filename: &str,
+    extension: &str,
+    max_key_length: usize,
+    strategy: KeyTooLongStrategy,
+) -> Result<String, String> {
+    let s3_key = format_s3_key(key_prefix, filename, extension);
+    if s3_key.len() <= max_key_length {
+        return Ok(s3_key);
+    }
+
+    let limited = match strategy {
+        KeyTooLongStrategy::Error => None,
+        KeyTooLongStrategy::Truncate => {
+            let hash = format!("-{:x}", md5::Md5::digest(&s3_key));
+            let suffix = format_s3_key("", &hash, extension);
+            max_key_length.checked_sub(suffix.len()).map(|mut end| {
+                while !s3_key.is_char_boundary(end) {
+                    end -= 1;
+                }
+                format!("{}{}", &s3_key[..end], suffix)
+            })
+        }
+        KeyTooLongStrategy::HashPrefix => {
+            let key_prefix = format!("{:x}/", md5::Md5::digest(key_prefix));
+            Some(format_s3_key(&key_prefix, filename, extension))
+                .filter(|s3_key| s3_key.len() <= max_key_length)
+        }
+    };
+
+    limited.ok_or_else(|| {
+        format!(
+            "Object key of {} bytes exceeds the `max_key_length` of {} bytes: {:?}.",
+            s3_key.len(),
+            max_key_length,
+            s3_key
+        )
+    })
+}
+
+/// The start of the year 10000, after which dates cannot be represented as HTTP dates.
+const HTTP_DATE_END_SECS: i64 = 253_402_300_800;
+
+/// Returns the `Expires` date of an object built now, if it can be represented as an HTTP date.
+pub(super) fn expires_at(expires_secs: u64) -> Option<AwsDateTime> {
+    let expires = i64::try_from(expires_secs)
+        .ok()?
+        .checked_add(Utc::now().timestamp())?;
+    (expires < HTTP_DATE_END_SECS).then(|| AwsDateTime::from_secs(expires))
+}
+
+/// Prefixes the filename with the sequence number, zero-padded so that keys sort in sequence order.
+fn sequenced_filename(sequence: u64, filename: &str) -> String {
+    format!("{:020}-{}", sequence, filename)
+}
+
+/// Returns the extension of objects with the given compression, naming the codec instead of `log`
+/// when it has an extension of its own, such as `cbor.gz`.
+fn default_extension(codec_extension: Option<&str>, compression: Compression) -> String {
+    let extension = compression.extension();
+    match codec_extension {
+        Some(codec_extension) => {
+            let compression_suffix = extension.strip_prefix("log").unwrap_or_default();
+            format!("{codec_extension}{compression_suffix}")
+        }
+        None => extension.to_owned(),
+    }
+}
+
 fn format_s3_key(s3_key: &str, filename: &str, extension: &str) -> String {
     if extension.is_empty() {
         format!("{}{}", s3_key, filename)
@@ -124,7 +1088,45 @@ fn format_s3_key(s3_key: &str, filename: &str, extension: &str) -> String {
 
 #[cfg(test)]
 mod tests {
+    use std::io::Read;
+
+    use vector_lib::codecs::{
+        CharacterDelimitedEncoder, JsonSerializerConfig, NewlineDelimitedEncoder,
+        TextSerializerConfig,
+    };
+    use vector_lib::lookup::owned_value_path;
+
     use super::*;
+    use crate::event::LogEvent;
+
+    fn text_encoder() -> (Transformer, Encoder<Framer>) {
+        (
+            Transformer::default(),
+            Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::default().into(),
+                TextSerializerConfig::default().build().into(),
+            ),
+        )
+    }
+
+    fn json_array_encoder() -> (Transformer, Encoder<Framer>) {
+        (
+            Transformer::default(),
+            Encoder::<Framer>::new(
+                CharacterDelimitedEncoder::new(b',').into(),
+                JsonSerializerConfig::default().build().into(),
+            ),
+        )
+    }
+
+    fn object_encoder(encoder: (Transformer, Encoder<Framer>)) -> ObjectEncoder {
+        ObjectEncoder {
+            encoder,
+            checksum_footer: None,
+            framing: BatchFraming::default(),
+            byte_order_mark: ByteOrderMark::None,
+        }
+    }
 
     #[test]
     fn test_format_s3_key() {
@@ -134,4 +1136,482 @@ mod tests {
         );
         assert_eq!("s3_key_filename", format_s3_key("s3_key_", "filename", ""));
     }
+
+    #[test]
+    fn expires_at_valid_http_dates() {
+        let expires = expires_at(3600).unwrap();
+        assert!((expires.secs() - Utc::now().timestamp() - 3600).abs() <= 1);
+        assert!(expires_at(u64::MAX).is_none());
+        assert!(expires_at(400_000_000_000).is_none());
+    }
+
+    #[test]
+    fn checksum_footer_covers_the_object() {
+        let encoder = ObjectEncoder {
+            checksum_footer: Some(ChecksumFooter {
+                algorithm: ChecksumAlgorithm::Crc32,
+                format: "#{algorithm}:{checksum}".to_owned(),
+            }),
+            ..object_encoder(json_array_encoder())
+        };
+        let events = vec![Event::Log(LogEvent::from("a"))];
+
+        let mut writer = Vec::new();
+        let (byte_size, _) = encoder
+            .encode_input((events, ObjectPosition::STANDALONE), &mut writer)
+            .unwrap();
+
+        let object = "[{\"message\":\"a\"}]\n";
+        let expected = format!(
+            "{}#crc32:{:08x}\n",
+            object,
+            crc32fast::hash(object.as_bytes())
+        );
+        assert_eq!(String::from_utf8(writer).unwrap(), expected);
+        assert_eq!(byte_size, expected.len());
+    }
+
+    #[test]
+    fn byte_order_marks_are_added_or_stripped() {
+        let encode = |byte_order_mark, messages: &[&str], position| {
+            let encoder = ObjectEncoder {
+                byte_order_mark,
+                ..object_encoder(text_encoder())
+            };
+            let events = messages
+                .iter()
+                .map(|message| Event::Log(LogEvent::from(*message)))
+                .collect();
+            let mut writer = Vec::new();
+            let (byte_size, _) = encoder
+                .encode_input((events, position), &mut writer)
+                .unwrap();
+            assert_eq!(byte_size, writer.len());
+            String::from_utf8(writer).unwrap()
+        };
+        let continued = ObjectPosition {
+            first: false,
+            last: true,
+        };
+
+        for messages in [&["a", "b"][..], &["\u{feff}a", "b"]] {
+            assert_eq!(
+                encode(ByteOrderMark::Add, messages, ObjectPosition::STANDALONE),
+                "\u{feff}a\nb"
+            );
+            assert_eq!(
+                encode(ByteOrderMark::Strip, messages, ObjectPosition::STANDALONE),
+                "a\nb"
+            );
+        }
+        assert_eq!(encode(ByteOrderMark::Add, &["b"], continued), "\nb");
+        assert_eq!(
+            encode(ByteOrderMark::Add, &[], ObjectPosition::STANDALONE),
+            "\u{feff}"
+        );
+        assert_eq!(
+            encode(
+                ByteOrderMark::Strip,
+                &["\u{feff}"],
+                ObjectPosition::STANDALONE
+            ),
+            ""
+        );
+    }
+
+    #[test]
+    fn small_objects_are_not_compressed() {
+        let encoder = object_encoder(json_array_encoder());
+        let encode = |message: &str| {
+            let events = vec![Event::Log(LogEvent::from(message))];
+            encode_object(
+                &encoder,
+                Compression::gzip_default(),
+                &[],
+                NonZeroUsize::new(64),
+                false,
+                false,
+                (events, ObjectPosition::STANDALONE),
+            )
+            .unwrap()
+        };
+
+        let small = encode("a");
+        assert_eq!(small.compressed_byte_size, None);
+        assert_eq!(small.payload.body, Bytes::from("[{\"message\":\"a\"}]"));
+
+        let large = encode(&"a".repeat(64));
+        assert!(large.compressed_byte_size.is_some());
+        assert_eq!(large.uncompressed_byte_size, 80);
+        assert_eq!(&large.payload.body[..2], [0x1f, 0x8b]);
+    }
+
+    #[test]
+    fn smallest_compression_candidate_is_kept() {
+        let encoder = object_encoder(text_encoder());
+        let encode = |compression, candidates: &[Compression]| {
+            let events = (0..1_000)
+                .map(|i| Event::Log(LogEvent::from(format!("line {i}"))))
+                .collect();
+            encode_object(
+                &encoder,
+                compression,
+                candidates,
+                None,
+                false,
+                false,
+                (events, ObjectPosition::STANDALONE),
+            )
+            .unwrap()
+        };
+
+        let snappy = encode(Compression::Snappy, &[]);
+        let zstd = encode(Compression::zstd_default(), &[]);
+        assert!(zstd.payload.body.len() < snappy.payload.body.len());
+
+        let selected = encode(
+            Compression::Snappy,
+            &[Compression::None, Compression::zstd_default()],
+        );
+        assert_eq!(selected.payload.compression, Compression::zstd_default());
+        assert_eq!(selected.payload.body, zstd.payload.body);
+        assert!(selected.compressed_byte_size.is_some());
+
+        let compressed = encode(Compression::Snappy, &[Compression::None]);
+        assert_eq!(compressed.payload.compression, Compression::Snappy);
+    }
+
+    #[test]
+    fn block_gzip_objects_are_indexed() {
+        let encoder = object_encoder(text_encoder());
+        let events = (0..20_000)
+            .map(|i| Event::Log(LogEvent::from(format!("line {i}"))))
+            .collect();
+
+        let encoded = encode_object(
+            &encoder,
+            Compression::gzip_default(),
+            &[],
+            None,
+            true,
+            false,
+            (events, ObjectPosition::STANDALONE),
+        )
+        .unwrap();
+        assert!(encoded.compressed_byte_size.is_some());
+        let index = bgzf::index(&encoded.payload.body).unwrap();
+        let entries = u64::from_le_bytes(index[..8].try_into().unwrap());
+        assert_eq!(entries, 3);
+    }
+
+    #[test]
+    fn gzip_header_os_is_set() {
+        let mut compressor = Compressor::from(Compression::gzip_default());
+        compressor.write_all(b"message\n").unwrap();
+        let mut payload = S3Payload {
+            body: compressor.finish().unwrap().freeze(),
+            compression: Compression::gzip_default(),
+            collapsed_count: None,
+        };
+        set_gzip_header_os(&mut payload, 3);
+        assert_eq!(payload.body[GZIP_OS_OFFSET], 3);
+
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(payload.body.as_ref())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, "message\n");
+
+        let mut payload = S3Payload::from(Bytes::from("message\n"));
+        set_gzip_header_os(&mut payload, 3);
+        assert_eq!(payload.body, "message\n");
+    }
+
+    #[test]
+    fn deterministic_zstd_objects_are_reproducible() {
+        let encoder = object_encoder(text_encoder());
+        let encode = || {
+            let events = (0..1_000)
+                .map(|i| Event::Log(LogEvent::from(format!("line {i}"))))
+                .collect();
+            encode_object(
+                &encoder,
+                Compression::zstd_default(),
+                &[],
+                None,
+                false,
+                true,
+                (events, ObjectPosition::STANDALONE),
+            )
+            .unwrap()
+        };
+
+        let encoded = encode();
+        assert!(encoded.compressed_byte_size.is_some());
+        assert_eq!(encoded.payload.body, encode().payload.body);
+        let decoded = ::zstd::decode_all(&encoded.payload.body[..]).unwrap();
+        assert!(decoded.starts_with(b"line 0\nline 1\n"));
+    }
+
+    #[test]
+    fn records_are_counted_while_encoding() {
+        let encoder = ObjectEncoder {
+            framing: BatchFraming {
+                drop_empty_events: true,
+                ..Default::default()
+            },
+            ..object_encoder(text_encoder())
+        };
+        let events = ["a", "", "b"]
+            .map(|message| Event::Log(LogEvent::from(message)))
+            .to_vec();
+
+        let encoded = encode_object(
+            &encoder,
+            Compression::None,
+            &[],
+            None,
+            false,
+            false,
+            (events, ObjectPosition::STANDALONE),
+        )
+        .unwrap();
+        assert_eq!(record_count(&encoded.transformed_json_size), 2);
+    }
+
+    #[test]
+    fn identical_events_are_collapsed() {
+        let encoder = text_encoder();
+        let log_events = |messages: &[&str]| {
+            messages
+                .iter()
+                .map(|message| Event::Log(LogEvent::from(*message)))
+                .collect::<Vec<_>>()
+        };
+
+        let mut events = log_events(&["up", "up", "up"]);
+        assert_eq!(
+            collapse_identical_events(&encoder, &mut events).unwrap(),
+            Some(3)
+        );
+        assert_eq!(events, log_events(&["up"]));
+
+        let mut events = log_events(&["up", "up", "down"]);
+        assert_eq!(
+            collapse_identical_events(&encoder, &mut events).unwrap(),
+            None
+        );
+        assert_eq!(events.len(), 3);
+
+        let mut events = log_events(&["up"]);
+        assert_eq!(
+            collapse_identical_events(&encoder, &mut events).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn used_filenames_are_unique_per_timestamp() {
+        let mut used = UsedFilenames::default();
+        assert!(used.insert("a/", "1658176486"));
+        assert!(used.insert("b/", "1658176486"));
+        assert!(!used.insert("a/", "1658176486"));
+
+        assert!(used.insert("a/", "1658176487"));
+        assert!(!used.insert("a/", "1658176487"));
+        assert!(used.insert("b/", "1658176487"));
+
+        // Timestamps going back still collide with the objects built under them.
+        assert!(!used.insert("a/", "1658176486"));
+        assert!(used.insert("c/", "1658176486"));
+    }
+
+    #[test]
+    fn key_collisions_are_resolved() {
+        let build_key = |counter| Ok(format!("a/1658176486-{counter}.log"));
+        let resolve = |used: &mut UsedFilenames, strategy| {
+            resolve_key_collision(
+                "a/1658176486.log".to_owned(),
+                used,
+                "1658176486",
+                strategy,
+                build_key,
+            )
+        };
+
+        let mut used = UsedFilenames::default();
+        let keys = [(); 3].map(|_| resolve(&mut used, KeyCollisionStrategy::AppendCounter));
+        assert_eq!(
+            keys,
+            [
+                Ok("a/1658176486.log".to_owned()),
+                Ok("a/1658176486-1.log".to_owned()),
+                Ok("a/1658176486-2.log".to_owned()),
+            ]
+        );
+
+        let mut used = UsedFilenames::default();
+        assert!(resolve(&mut used, KeyCollisionStrategy::Error).is_ok());
+        let (_, s3_key) = resolve(&mut used, KeyCollisionStrategy::Error).unwrap_err();
+        assert_eq!(s3_key, "a/1658176486.log");
+    }
+
+    #[test]
+    fn filename_times_follow_their_source() {
+        let at = |secs| DateTime::from_timestamp(secs, 0).unwrap();
+        let last = Mutex::new(None);
+        let time = |source, now, latest| filename_time(source, at(now), latest, &last);
+
+        assert_eq!(time(FilenameTimeSource::Clock, 100, Some(at(50))), at(100));
+
+        assert_eq!(time(FilenameTimeSource::Monotonic, 100, None), at(100));
+        assert_eq!(time(FilenameTimeSource::Monotonic, 90, None), at(100));
+        assert_eq!(time(FilenameTimeSource::Monotonic, 110, None), at(110));
+
+        assert_eq!(
+            time(FilenameTimeSource::LatestEvent, 100, Some(at(50))),
+            at(50)
+        );
+        assert_eq!(
+            time(FilenameTimeSource::LatestEvent, 100, Some(at(150))),
+            at(100)
+        );
+        assert_eq!(time(FilenameTimeSource::LatestEvent, 100, None), at(100));
+    }
+
+    #[test]
+    fn default_extensions_name_the_codec() {
+        assert_eq!(default_extension(None, Compression::None), "log");
+        assert_eq!(default_extension(Some("cbor"), Compression::None), "cbor");
+        assert_eq!(
+            default_extension(Some("msgpack"), Compression::gzip_default()),
+            "msgpack.gz"
+        );
+    }
+
+    #[test]
+    fn sequenced_filenames_sort_in_order() {
+        assert_eq!(
+            sequenced_filename(42, "1658176486"),
+            "00000000000000000042-1658176486"
+        );
+
+        let filenames = [9, 10, u64::MAX].map(|sequence| sequenced_filename(sequence, "a"));
+        let mut sorted = filenames.clone();
+        sorted.sort();
+        assert_eq!(sorted, filenames);
+    }
+
+    #[test]
+    fn test_limit_key_length() {
+        let key_prefix = format!("{}/", "p".repeat(100));
+        let limit = |strategy, max_key_length| {
+            limit_key_length(&key_prefix, "filename", "json", max_key_length, strategy)
+        };
+
+        for strategy in [
+            KeyTooLongStrategy::Error,
+            KeyTooLongStrategy::Truncate,
+            KeyTooLongStrategy::HashPrefix,
+        ] {
+            assert_eq!(
+                limit(strategy, 114),
+                Ok(format!("{}filename.json", key_prefix))
+            );
+        }
+
+        let error = limit(KeyTooLongStrategy::Error, 113).unwrap_err();
+        assert!(error.contains(&format!("\"{}filename.json\"", key_prefix)));
+
+        let truncated = limit(KeyTooLongStrategy::Truncate, 60).unwrap();
+        assert_eq!(truncated.len(), 60);
+        assert!(truncated.starts_with("ppp"));
+        assert!(truncated.ends_with(".json"));
+        assert!(limit(KeyTooLongStrategy::Truncate, 30).is_err());
+
+        let hashed = limit(KeyTooLongStrategy::HashPrefix, 60).unwrap();
+        assert_eq!(hashed.len(), 46);
+        assert!(hashed.ends_with("/filename.json"));
+        assert!(limit(KeyTooLongStrategy::HashPrefix, 45).is_err());
+    }
+
+    #[test]
+    fn test_timestamp_range() {
+        let event = |timestamp: Option<&str>| {
+            let mut log = LogEvent::default();
+            if let Some(timestamp) = timestamp {
+                log.insert("timestamp", timestamp.parse::<DateTime<Utc>>().unwrap());
+            }
+            Event::Log(log)
+        };
+
+        assert_eq!(timestamp_range(&[event(None)]), (None, None));
+
+        let events = [
+            event(Some("2024-01-02T00:00:00Z")),
+            event(None),
+            event(Some("2024-01-01T00:00:00Z")),
+            event(Some("2024-01-03T00:00:00Z")),
+        ];
+        assert_eq!(
+            timestamp_range(&events),
+            (
+                Some("2024-01-01T00:00:00Z".parse().unwrap()),
+                Some("2024-01-03T00:00:00Z".parse().unwrap()),
+            )
+        );
+    }
+
+    #[test]
+    fn test_apply_partition_tags() {
+        let partition_tags = |prefix: &str, value: &str| PartitionTags {
+            prefix: prefix.to_owned(),
+            tags: BTreeMap::from([("classification".to_owned(), value.to_owned())]),
+        };
+        let partition_tags = [
+            partition_tags("", "internal"),
+            partition_tags("pii/", "pii"),
+        ];
+        let apply = |key_prefix| {
+            let mut tags = Some(BTreeMap::from([
+                ("classification".to_owned(), "public".to_owned()),
+                ("team".to_owned(), "data".to_owned()),
+            ]));
+            apply_partition_tags(&mut tags, &partition_tags, key_prefix);
+            tags.unwrap()
+        };
+
+        assert_eq!(apply("pii/date=2024-01-01/")["classification"], "pii");
+        assert_eq!(apply("date=2024-01-01/")["classification"], "internal");
+        assert_eq!(apply("pii/")["team"], "data");
+    }
+
+    #[test]
+    fn test_event_time_range() {
+        let event = |event_time: &str| {
+            let mut log = LogEvent::default();
+            match event_time.parse::<DateTime<Utc>>() {
+                Ok(timestamp) => log.insert("event_time", timestamp),
+                Err(_) => log.insert("event_time", event_time),
+            };
+            Event::Log(log)
+        };
+        let field = owned_value_path!("event_time");
+
+        assert_eq!(event_time_range(&[event("invalid")], Some(&field)), None);
+
+        let events = [
+            event("2024-01-02T00:00:00Z"),
+            event("invalid"),
+            event("2024-01-01T00:00:00Z"),
+        ];
+        assert_eq!(
+            event_time_range(&events, Some(&field)),
+            Some((
+                "2024-01-01T00:00:00Z".parse().unwrap(),
+                "2024-01-02T00:00:00Z".parse().unwrap(),
+            ))
+        );
+        assert_eq!(event_time_range(&events, None), None);
+    }
 }
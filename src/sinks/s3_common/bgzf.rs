@@ -0,0 +1,159 @@
+use std::io::{self, Write};
+
+use flate2::{write::DeflateEncoder, Compression};
+
+/// The number of bytes compressed into each block, as used by `bgzip`, such that compressed blocks
+/// always fit the 16-bit block size field.
+const BLOCK_INPUT_BYTES: usize = 0xff00;
+
+/// The largest block, header and trailer included.
+const MAX_BLOCK_BYTES: usize = 0x10000;
+
+const HEADER_BYTES: usize = 18;
+const TRAILER_BYTES: usize = 8;
+
+/// The header of each block, up to the block size held by its `BC` extra field.
+const HEADER_PREFIX: &[u8] = &[
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+];
+
+/// The empty block marking the end of a BGZF file.
+const EOF_BLOCK: &[u8] = &[
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Compresses the data in the blocked gzip format (BGZF) used by `bgzip`.
+///
+/// The output is a concatenation of gzip members, each compressing up to 65280 bytes, followed by
+/// an empty end-of-file member, and can be read by any gzip decoder. Each member records its
+/// compressed size in a `BC` extra field, so that readers can find the block containing an
+/// uncompressed offset with an index, and decompress from there.
+pub fn compress(data: &[u8], level: Compression) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len() / 2 + EOF_BLOCK.len());
+    for chunk in data.chunks(BLOCK_INPUT_BYTES) {
+        let mut deflated = deflate(chunk, level)?;
+        // Incompressible chunks can grow past the block size limit, in which case they are stored.
+        if deflated.len() + HEADER_BYTES + TRAILER_BYTES > MAX_BLOCK_BYTES {
+            deflated = deflate(chunk, Compression::none())?;
+        }
+
+        let block_size = HEADER_BYTES + deflated.len() + TRAILER_BYTES;
+        output.extend_from_slice(HEADER_PREFIX);
+        output.extend_from_slice(&((block_size - 1) as u16).to_le_bytes());
+        output.extend_from_slice(&deflated);
+        output.extend_from_slice(&crc32fast::hash(chunk).to_le_bytes());
+        output.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+    }
+    output.extend_from_slice(EOF_BLOCK);
+    Ok(output)
+}
+
+fn deflate(data: &[u8], level: Compression) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), level);
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Builds the `bgzip` index (`.gzi`) of BGZF data, as written by `compress`.
+///
+/// The index starts with the number of entries, followed by the compressed and uncompressed
+/// offsets of the start of each block but the first, all as little-endian 64-bit integers. Returns
+/// `None` if the data is not made of BGZF blocks.
+pub fn index(data: &[u8]) -> Option<Vec<u8>> {
+    let mut offsets = Vec::new();
+    let (mut compressed_offset, mut uncompressed_offset) = (0usize, 0u64);
+    while compressed_offset < data.len() {
+        let block = &data[compressed_offset..];
+        if block.len() < HEADER_BYTES || !block.starts_with(HEADER_PREFIX) {
+            return None;
+        }
+        let block_size = usize::from(u16::from_le_bytes([block[16], block[17]])) + 1;
+        if block_size < HEADER_BYTES + TRAILER_BYTES {
+            return None;
+        }
+        let trailer = block.get(block_size - TRAILER_BYTES..block_size)?;
+        let input_size = u32::from_le_bytes(trailer[4..].try_into().ok()?);
+
+        if compressed_offset > 0 && input_size > 0 {
+            offsets.push((compressed_offset as u64, uncompressed_offset));
+        }
+        compressed_offset += block_size;
+        uncompressed_offset += u64::from(input_size);
+    }
+
+    let mut index = Vec::with_capacity(8 + offsets.len() * 16);
+    index.extend_from_slice(&(offsets.len() as u64).to_le_bytes());
+    for (compressed_offset, uncompressed_offset) in offsets {
+        index.extend_from_slice(&compressed_offset.to_le_bytes());
+        index.extend_from_slice(&uncompressed_offset.to_le_bytes());
+    }
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::MultiGzDecoder;
+
+    use super::*;
+
+    fn read_u64(bytes: &[u8], entry: usize) -> u64 {
+        u64::from_le_bytes(bytes[8 * entry..8 * entry + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn blocks_decompress_from_the_indexed_offsets() {
+        let data = (0..40_000)
+            .map(|i| format!("{{\"message\":\"line {i}\"}}\n"))
+            .collect::<String>();
+        let compressed = compress(data.as_bytes(), Compression::default()).unwrap();
+        assert!(compressed.ends_with(EOF_BLOCK));
+
+        let mut decompressed = String::new();
+        MultiGzDecoder::new(&compressed[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+
+        let index = index(&compressed).unwrap();
+        let entries = read_u64(&index, 0) as usize;
+        assert_eq!(entries, data.len().div_ceil(BLOCK_INPUT_BYTES) - 1);
+        assert_eq!(index.len(), 8 + entries * 16);
+
+        for entry in 0..entries {
+            let compressed_offset = read_u64(&index, 1 + 2 * entry) as usize;
+            let uncompressed_offset = read_u64(&index, 2 + 2 * entry) as usize;
+            assert_eq!(uncompressed_offset, (entry + 1) * BLOCK_INPUT_BYTES);
+
+            let mut tail = String::new();
+            MultiGzDecoder::new(&compressed[compressed_offset..])
+                .read_to_string(&mut tail)
+                .unwrap();
+            assert_eq!(tail, data[uncompressed_offset..]);
+        }
+    }
+
+    #[test]
+    fn incompressible_blocks_fit() {
+        let data = (0..200_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<_>>();
+        let compressed = compress(&data, Compression::best()).unwrap();
+
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+        assert!(index(&compressed).is_some());
+    }
+
+    #[test]
+    fn plain_gzip_is_not_indexed() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"a").unwrap();
+        assert!(index(&encoder.finish().unwrap()).is_none());
+    }
+}
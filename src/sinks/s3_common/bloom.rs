@@ -0,0 +1,113 @@
+use std::f64::consts::LN_2;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use md5::Digest;
+use serde::Serialize;
+
+/// A bloom filter over values rendered from the events of an object, written as a companion
+/// `<key>.bloom` object so that readers can skip objects which do not contain a value.
+///
+/// Each value is hashed with MD5, and the first and last 8 bytes of the digest are read as
+/// little-endian integers `h1` and `h2`. The bits set for the value are then
+/// `(h1 + i * h2) mod num_bits` for each `i` in `0..num_hashes`, computed with wrapping 64-bit
+/// arithmetic, where bit `j` is the bit `j % 8` of byte `j / 8`, counting from the least
+/// significant bit.
+#[derive(Clone, Debug)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+/// The JSON document a bloom filter is written as.
+#[derive(Serialize)]
+struct BloomFilterObject<'a> {
+    source: &'a str,
+    hash: &'static str,
+    num_bits: u64,
+    num_hashes: u32,
+    item_count: usize,
+    bits: String,
+}
+
+impl BloomFilter {
+    /// Sizes the filter such that `expected_items` values are tested with the given false positive
+    /// rate.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let items = expected_items.max(1) as f64;
+        let num_bits = (-items * false_positive_rate.ln() / (LN_2 * LN_2))
+            .ceil()
+            .max(8.0) as u64;
+        let num_hashes = ((num_bits as f64 / items) * LN_2).round().max(1.0) as u32;
+        Self {
+            bits: vec![0; num_bits.div_ceil(8) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    fn bit_indices(&self, value: &[u8]) -> impl Iterator<Item = u64> {
+        let digest = md5::Md5::digest(value);
+        let (h1, h2) = digest.split_at(8);
+        let h1 = u64::from_le_bytes(h1.try_into().expect("MD5 digests are 16 bytes"));
+        let h2 = u64::from_le_bytes(h2.try_into().expect("MD5 digests are 16 bytes"));
+        let num_bits = self.num_bits;
+        (0..u64::from(self.num_hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+
+    pub fn insert(&mut self, value: &[u8]) {
+        for index in self.bit_indices(value) {
+            self.bits[(index / 8) as usize] |= 1 << (index % 8);
+        }
+    }
+
+    /// Returns whether the value may have been inserted, it was not if this returns `false`.
+    pub fn contains(&self, value: &[u8]) -> bool {
+        self.bit_indices(value)
+            .all(|index| self.bits[(index / 8) as usize] & (1 << (index % 8)) != 0)
+    }
+
+    /// Serializes the filter, along with the template its values were rendered from and the number
+    /// of values inserted.
+    pub fn to_json(&self, source: &str, item_count: usize) -> Vec<u8> {
+        serde_json::to_vec(&BloomFilterObject {
+            source,
+            hash: "md5",
+            num_bits: self.num_bits,
+            num_hashes: self.num_hashes,
+            item_count,
+            bits: BASE64_STANDARD.encode(&self.bits),
+        })
+        .expect("bloom filters should always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_filters_contain_inserted_values() {
+        let mut filter = BloomFilter::new(1000, 0.01);
+        assert_eq!(filter.num_bits, 9586);
+        assert_eq!(filter.num_hashes, 7);
+
+        for i in 0..1000 {
+            filter.insert(format!("user-{i}").as_bytes());
+        }
+        assert!((0..1000).all(|i| filter.contains(format!("user-{i}").as_bytes())));
+        let false_positives = (1000..11000)
+            .filter(|i| filter.contains(format!("user-{i}").as_bytes()))
+            .count();
+        assert!(false_positives < 200, "{false_positives}");
+
+        let object =
+            serde_json::from_slice::<serde_json::Value>(&filter.to_json("{{ id }}", 1000)).unwrap();
+        assert_eq!(object["num_bits"], 9586);
+        assert_eq!(object["hash"], "md5");
+        let bits = BASE64_STANDARD
+            .decode(object["bits"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(bits, filter.bits);
+    }
+}
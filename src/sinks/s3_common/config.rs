@@ -1,6 +1,13 @@
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use aws_sdk_s3::{
+    config::AppName,
+    error::ProvideErrorMetadata,
     operation::put_object::PutObjectError,
     types::{ObjectCannedAcl, ServerSideEncryption, StorageClass},
     Client as S3Client,
@@ -10,16 +17,21 @@ use aws_smithy_runtime_api::{
     http::StatusCode,
 };
 use futures::FutureExt;
+use serde::{de::IntoDeserializer, Deserialize};
 use snafu::Snafu;
 use vector_lib::configurable::configurable_component;
 
 use super::service::{S3Response, S3Service};
+use super::verification::is_retriable_mismatch;
 use crate::{
     aws::{create_client, is_retriable_error, AwsAuthentication, RegionOrEndpoint},
     common::s3::S3ClientBuilder,
     config::ProxyConfig,
     http::status,
-    sinks::{util::retries::RetryLogic, Healthcheck},
+    sinks::{
+        util::retries::{RetryAction, RetryLogic},
+        Healthcheck,
+    },
     tls::TlsConfig,
 };
 
@@ -122,9 +134,26 @@ pub struct S3Options {
     /// Directly comparable to the `Content-Type` HTTP header.
     ///
     /// If not specified, the compression scheme used dictates this value.
-    /// When `compression` is set to `none`, the value `text/x-log` is used.
+    /// When `compression` is set to `none`, the value `text/x-log` is used. Objects encoded with
+    /// the `cbor` or `message_pack` codecs use `application/cbor` or `application/msgpack`.
     #[configurable(metadata(docs::examples = "application/gzip"))]
     pub content_type: Option<String>,
+
+    /// Specifies caching behavior for the object.
+    ///
+    /// Directly comparable to the `Cache-Control` HTTP header, which S3 returns when the object is
+    /// retrieved, such as through CloudFront.
+    #[configurable(metadata(docs::examples = "max-age=3600"))]
+    #[configurable(metadata(docs::examples = "no-cache, no-store"))]
+    pub cache_control: Option<String>,
+
+    /// The time after an object is built at which it can no longer be cached, in seconds.
+    ///
+    /// Sets the `Expires` HTTP header of the object, which S3 returns when the object is
+    /// retrieved, to the date at which this time elapses.
+    #[configurable(metadata(docs::type_unit = "seconds"))]
+    #[configurable(metadata(docs::examples = 86400))]
+    pub expires_secs: Option<u64>,
 }
 
 fn example_tags() -> HashMap<String, String> {
@@ -141,7 +170,7 @@ fn example_tags() -> HashMap<String, String> {
 ///
 /// [aws_docs]: https://docs.aws.amazon.com/AmazonS3/latest/userguide/storage-class-intro.html
 #[configurable_component]
-#[derive(Clone, Copy, Debug, Derivative, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Derivative, PartialEq, Eq, Hash)]
 #[derivative(Default)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum S3StorageClass {
@@ -171,6 +200,25 @@ pub enum S3StorageClass {
     DeepArchive,
 }
 
+impl S3StorageClass {
+    /// The size below which objects are billed as if they were this size, or are not eligible
+    /// for automatic tiering.
+    pub const fn minimum_object_size(self) -> Option<usize> {
+        match self {
+            Self::StandardIa | Self::OnezoneIa | Self::IntelligentTiering => Some(128 * 1024),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for S3StorageClass {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
 impl From<S3StorageClass> for StorageClass {
     fn from(x: S3StorageClass) -> Self {
         match x {
@@ -186,6 +234,51 @@ impl From<S3StorageClass> for StorageClass {
     }
 }
 
+/// The case the values of fields rendered in the key prefix are converted to.
+///
+/// Only the values of the fields are converted: the literal parts of the key prefix, such as the
+/// names of Hive-style partitions, and the filename keep their case.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionCase {
+    /// Keep the case of the values.
+    #[default]
+    Preserve,
+
+    /// Convert the values to lowercase.
+    Lower,
+
+    /// Convert the values to uppercase.
+    Upper,
+}
+
+impl PartitionCase {
+    pub fn apply(self, value: String) -> String {
+        match self {
+            Self::Preserve => value,
+            Self::Lower => value.to_lowercase(),
+            Self::Upper => value.to_uppercase(),
+        }
+    }
+}
+
+/// What to do with events later than the allowed lateness of their window, once it is closed.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LateEvents {
+    /// Write late events under `late_prefix`, keeping closed windows complete.
+    #[default]
+    Redirect,
+
+    /// Write late events to their own window, as usual, reopening it with a new object.
+    Reopen,
+
+    /// Drop late events, keeping closed windows complete at the cost of losing them.
+    Drop,
+}
+
 /// AWS S3 Server-Side Encryption algorithms.
 ///
 /// More information on each algorithm can be found in the [AWS documentation][aws_docs].
@@ -308,15 +401,132 @@ impl From<S3CannedAcl> for ObjectCannedAcl {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct S3RetryLogic;
+#[derive(Debug, Clone, Default)]
+pub struct S3RetryLogic {
+    /// Error codes, or substrings of error responses, to retry on top of the built-in ones.
+    extra_retryable_codes: Arc<[String]>,
+    /// How long requests failing to connect are retried for, if bounded.
+    connection_retry_limit: Option<Duration>,
+    /// When requests started failing to connect, shared by the clones of the retry logic and
+    /// cleared once a request succeeds.
+    connection_failing_since: Arc<Mutex<Option<Instant>>>,
+}
+
+impl S3RetryLogic {
+    pub fn new(extra_retryable_codes: Vec<String>) -> Self {
+        Self {
+            extra_retryable_codes: extra_retryable_codes.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Stops retrying requests which fail to connect, such as when the endpoint fails to resolve,
+    /// once requests have been failing to connect for longer than the limit, until one succeeds.
+    pub fn with_connection_retry_limit(mut self, connection_retry_limit: Option<Duration>) -> Self {
+        self.connection_retry_limit = connection_retry_limit;
+        self
+    }
+
+    /// Checks if a request which failed to connect should be retried, given how long requests
+    /// have been failing to connect.
+    fn is_retriable_connection_error(&self) -> bool {
+        let mut failing_since = self
+            .connection_failing_since
+            .lock()
+            .expect("connection failure mutex poisoned");
+        let failing_since = *failing_since.get_or_insert_with(Instant::now);
+        self.connection_retry_limit
+            .map_or(true, |limit| failing_since.elapsed() <= limit)
+    }
+
+    /// Checks if the error response matches any of the extra retryable codes.
+    ///
+    /// S3-compatible stores don't all respond with the standard error codes, or even with an S3
+    /// error document, so the codes are matched against the error code, and as substrings of the
+    /// error message and the body of the response.
+    fn is_extra_retryable(&self, error: &SdkError<PutObjectError, HttpResponse>) -> bool {
+        let SdkError::ServiceError(inner) = error else {
+            return false;
+        };
+        let code = inner.err().code();
+        let message = inner.err().message().unwrap_or_default();
+        let body = inner
+            .raw()
+            .body()
+            .bytes()
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default();
+        self.extra_retryable_codes.iter().any(|extra| {
+            code == Some(extra.as_str()) || message.contains(extra) || body.contains(extra)
+        })
+    }
+}
 
 impl RetryLogic for S3RetryLogic {
     type Error = SdkError<PutObjectError, HttpResponse>;
     type Response = S3Response;
 
     fn is_retriable_error(&self, error: &Self::Error) -> bool {
+        if is_connection_error(error) {
+            return self.is_retriable_connection_error();
+        }
         is_retriable_error(error)
+            || is_operation_aborted(error)
+            || is_retriable_mismatch(error)
+            || self.is_extra_retryable(error)
+    }
+
+    fn should_retry_response(&self, _response: &Self::Response) -> RetryAction {
+        *self
+            .connection_failing_since
+            .lock()
+            .expect("connection failure mutex poisoned") = None;
+        RetryAction::Successful
+    }
+}
+
+/// Checks if the request failed to reach the endpoint, such as when its name fails to resolve or
+/// the connection is refused or times out, rather than being rejected by it.
+const fn is_connection_error(error: &SdkError<PutObjectError, HttpResponse>) -> bool {
+    matches!(
+        error,
+        SdkError::DispatchFailure(_) | SdkError::TimeoutError(_)
+    )
+}
+
+/// Checks if S3 aborted the request because of a conflicting operation.
+///
+/// S3 responds with `409 OperationAborted` when another operation on the same object or bucket is
+/// in progress, such as two writers racing to create the same object. The conflict is transient,
+/// so the request should be retried with backoff rather than being rejected.
+fn is_operation_aborted(error: &SdkError<PutObjectError, HttpResponse>) -> bool {
+    match error {
+        SdkError::ServiceError(inner) => {
+            inner.raw().status().as_u16() == status::CONFLICT
+                && inner.err().code() == Some("OperationAborted")
+        }
+        _ => false,
+    }
+}
+
+/// Returns the region of the bucket, if the request failed because it was sent to another region.
+///
+/// S3 responds with `301 PermanentRedirect`, or `400 AuthorizationHeaderMalformed` when the
+/// request was signed for the wrong region, and names the region of the bucket in the
+/// `x-amz-bucket-region` header.
+pub fn bucket_region(error: &SdkError<PutObjectError, HttpResponse>) -> Option<&str> {
+    match error {
+        SdkError::ServiceError(inner) => {
+            let wrong_region = match inner.raw().status().as_u16() {
+                status::MOVED_PERMANENTLY => true,
+                status::BAD_REQUEST => inner.err().code() == Some("AuthorizationHeaderMalformed"),
+                _ => false,
+            };
+            wrong_region
+                .then(|| inner.raw().headers().get("x-amz-bucket-region"))
+                .flatten()
+        }
+        _ => None,
     }
 }
 
@@ -363,19 +573,138 @@ pub async fn create_service(
     auth: &AwsAuthentication,
     proxy: &ProxyConfig,
     tls_options: &Option<TlsConfig>,
+    app_name: Option<AppName>,
 ) -> crate::Result<S3Service> {
     let endpoint = region.endpoint();
     let region = region.region();
     let client =
         create_client::<S3ClientBuilder>(auth, region.clone(), endpoint, proxy, tls_options, &None)
             .await?;
+
+    // The app name is appended to the user agent of every request made by the client.
+    let client = match app_name {
+        Some(app_name) => {
+            S3Client::from_conf(client.config().to_builder().app_name(app_name).build())
+        }
+        None => client,
+    };
+
     Ok(S3Service::new(client))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::S3StorageClass;
-    use crate::serde::json::to_string;
+    use std::{io, time::Duration};
+
+    use aws_sdk_s3::operation::put_object::PutObjectError;
+    use aws_smithy_runtime_api::{
+        client::{
+            orchestrator::HttpResponse,
+            result::{ConnectorError, SdkError},
+        },
+        http::StatusCode,
+    };
+    use aws_smithy_types::{body::SdkBody, error::ErrorMetadata};
+    use vector_lib::request_metadata::GroupedCountByteSize;
+
+    use super::{bucket_region, S3RetryLogic, S3StorageClass};
+    use crate::{
+        serde::json::to_string,
+        sinks::{s3_common::service::S3Response, util::retries::RetryLogic},
+    };
+
+    fn service_error(status: u16, code: &str) -> SdkError<PutObjectError, HttpResponse> {
+        service_error_in_region(status, code, None)
+    }
+
+    fn service_error_in_region(
+        status: u16,
+        code: &str,
+        region: Option<&str>,
+    ) -> SdkError<PutObjectError, HttpResponse> {
+        let meta = ErrorMetadata::builder().code(code).build();
+        let body = SdkBody::from(format!("<Error><Code>{}</Code></Error>", code));
+        let mut raw = HttpResponse::new(StatusCode::try_from(status).unwrap(), body);
+        if let Some(region) = region {
+            raw.headers_mut()
+                .insert("x-amz-bucket-region", region.to_owned());
+        }
+
+        SdkError::service_error(PutObjectError::generic(meta), raw)
+    }
+
+    #[test]
+    fn retries_operation_aborted() {
+        let error = service_error(409, "OperationAborted");
+        assert!(S3RetryLogic::default().is_retriable_error(&error));
+    }
+
+    #[test]
+    fn does_not_retry_other_conflicts() {
+        let error = service_error(409, "InvalidBucketState");
+        assert!(!S3RetryLogic::default().is_retriable_error(&error));
+    }
+
+    #[test]
+    fn retries_extra_retryable_codes() {
+        let retry_logic = S3RetryLogic::new(vec!["SlowDownPlease".to_owned()]);
+        assert!(retry_logic.is_retriable_error(&service_error(400, "SlowDownPlease")));
+        assert!(!retry_logic.is_retriable_error(&service_error(400, "InvalidArgument")));
+
+        // Stores responding with a non-standard body are matched on the body.
+        let raw = HttpResponse::new(
+            StatusCode::try_from(400).unwrap(),
+            SdkBody::from("request rate exceeded: SlowDownPlease"),
+        );
+        let meta = ErrorMetadata::builder().build();
+        let error = SdkError::service_error(PutObjectError::generic(meta), raw);
+        assert!(retry_logic.is_retriable_error(&error));
+    }
+
+    #[test]
+    fn retries_connection_errors_within_limit() {
+        let retry_logic =
+            S3RetryLogic::default().with_connection_retry_limit(Some(Duration::from_millis(50)));
+        let error = || {
+            SdkError::dispatch_failure(ConnectorError::io(
+                io::Error::new(io::ErrorKind::Other, "failed to lookup address information").into(),
+            ))
+        };
+        assert!(retry_logic.is_retriable_error(&error()));
+
+        // The clones of the retry logic used by each request share when requests started failing.
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!retry_logic.clone().is_retriable_error(&error()));
+
+        // A successful request resets the limit.
+        retry_logic.should_retry_response(&S3Response::new(GroupedCountByteSize::new_untagged()));
+        assert!(retry_logic.is_retriable_error(&error()));
+    }
+
+    #[test]
+    fn detects_bucket_region() {
+        let region = Some("eu-west-1");
+        assert_eq!(
+            bucket_region(&service_error_in_region(301, "PermanentRedirect", region)),
+            region
+        );
+        assert_eq!(
+            bucket_region(&service_error_in_region(
+                400,
+                "AuthorizationHeaderMalformed",
+                region
+            )),
+            region
+        );
+        assert_eq!(
+            bucket_region(&service_error_in_region(400, "InvalidArgument", region)),
+            None
+        );
+        assert_eq!(
+            bucket_region(&service_error(301, "PermanentRedirect")),
+            None
+        );
+    }
 
     #[test]
     fn storage_class_names() {
@@ -395,6 +724,8 @@ mod tests {
                     panic!("Unparsable storage class name {:?}: {}", name, error)
                 });
             assert_eq!(result, storage_class);
+            assert_eq!(name.parse::<S3StorageClass>().unwrap(), storage_class);
         }
+        assert!("standard".parse::<S3StorageClass>().is_err());
     }
 }
@@ -0,0 +1,226 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use tower::Service;
+use vector_lib::{
+    finalization::EventFinalizers,
+    request_metadata::{MetaDescriptive, RequestMetadata},
+};
+
+use super::{
+    config::S3Options,
+    partitioner::S3PartitionKey,
+    service::{S3Metadata, S3Request, S3Service},
+};
+use crate::internal_events::{S3EmfSummaryError, S3EmfSummaryWritten};
+
+/// The activity of the sink since the last summary was written.
+#[derive(Default)]
+pub struct EmfStats {
+    events: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl EmfStats {
+    fn record(&self, events: usize, bytes: usize, failed: bool) {
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.events.fetch_add(events as u64, Ordering::Relaxed);
+            self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Takes the number of events and bytes written, and of failed requests, resetting them.
+    fn take(&self) -> (u64, u64, u64) {
+        (
+            self.events.swap(0, Ordering::Relaxed),
+            self.bytes.swap(0, Ordering::Relaxed),
+            self.errors.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+/// Records the outcome of each request of the inner service with `EmfStats`, if any.
+///
+/// Requests are counted as failed when the inner service, retries included, returns an error.
+pub struct EmfStatsService<S> {
+    inner: S,
+    stats: Option<Arc<EmfStats>>,
+}
+
+impl<S> EmfStatsService<S> {
+    pub const fn new(inner: S, stats: Option<Arc<EmfStats>>) -> Self {
+        Self { inner, stats }
+    }
+}
+
+impl<S, Request> Service<Request> for EmfStatsService<S>
+where
+    S: Service<Request>,
+    S::Future: Send + 'static,
+    Request: MetaDescriptive,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let metadata = request.get_metadata();
+        let (events, bytes) = (metadata.event_count(), metadata.request_encoded_size());
+        let response = self.inner.call(request);
+        let Some(stats) = self.stats.clone() else {
+            return Box::pin(response);
+        };
+        Box::pin(async move {
+            let result = response.await;
+            stats.record(events, bytes, result.is_err());
+            result
+        })
+    }
+}
+
+/// Periodically writes the activity of the sink as a CloudWatch embedded metric format (EMF)
+/// document, such that it is extracted as metrics once the objects are ingested by CloudWatch.
+///
+/// Each summary is written to its own object, named after the time it is written under the key
+/// prefix, outside of the partitioning and naming of the objects holding events.
+#[derive(Clone)]
+pub struct EmfSummary {
+    pub bucket: String,
+    pub key_prefix: String,
+    pub namespace: String,
+    pub interval: Duration,
+    pub options: S3Options,
+    pub stats: Arc<EmfStats>,
+    /// Identifies the instance of Vector writing the summaries.
+    pub host: String,
+    pub component_id: String,
+}
+
+impl EmfSummary {
+    /// Writes a summary every interval, starting one interval from now, until the future is
+    /// dropped.
+    ///
+    /// Summaries are neither staged nor retried: the activity of a failed summary is lost.
+    pub async fn run(self, service: S3Service) {
+        let mut service = service
+            .with_staging_dir(None)
+            .with_upload_slo(None)
+            .with_retention(None);
+        let start = tokio::time::Instant::now() + self.interval;
+        let mut interval = tokio::time::interval_at(start, self.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let request = self.request(Utc::now());
+            let key = request.metadata.s3_key.clone();
+            match service.call(request).await {
+                Ok(_) => emit!(S3EmfSummaryWritten { key: &key }),
+                Err(error) => emit!(S3EmfSummaryError { key: &key, error }),
+            }
+        }
+    }
+
+    fn request(&self, now: DateTime<Utc>) -> S3Request {
+        let (events, bytes, errors) = self.stats.take();
+        let body = serde_json::json!({
+            "_aws": {
+                "Timestamp": now.timestamp_millis(),
+                "CloudWatchMetrics": [{
+                    "Namespace": self.namespace,
+                    "Dimensions": [["ComponentId", "Host"]],
+                    "Metrics": [
+                        { "Name": "EventsWritten", "Unit": "Count" },
+                        { "Name": "BytesWritten", "Unit": "Bytes" },
+                        { "Name": "RequestErrors", "Unit": "Count" },
+                    ],
+                }],
+            },
+            "ComponentId": self.component_id,
+            "Host": self.host,
+            "EventsWritten": events,
+            "BytesWritten": bytes,
+            "RequestErrors": errors,
+        });
+        S3Request {
+            body: Bytes::from(body.to_string()),
+            bucket: self.bucket.clone(),
+            metadata: S3Metadata {
+                partition_key: S3PartitionKey {
+                    key_prefix: self.key_prefix.clone(),
+                    ssekms_key_id: None,
+                    storage_class: None,
+                    target: None,
+                    split_run: None,
+                },
+                s3_key: format!("{}{}.json", self.key_prefix, now.timestamp_millis()),
+                finalizers: EventFinalizers::default(),
+                object_metadata: None,
+                bloom_filter: None,
+                gzip_index: None,
+                key_error: None,
+                batch_started: None,
+                latest_event_timestamp: None,
+                event_time_range: None,
+                summary: None,
+                sequence: None,
+            },
+            request_metadata: RequestMetadata::default(),
+            content_encoding: None,
+            expires: None,
+            options: self.options.clone(),
+            region: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries_are_emf_documents() {
+        let stats = Arc::new(EmfStats::default());
+        stats.record(10, 1000, false);
+        stats.record(5, 500, true);
+        let summary = EmfSummary {
+            bucket: "bucket".to_owned(),
+            key_prefix: "metrics/".to_owned(),
+            namespace: "Vector".to_owned(),
+            interval: Duration::from_secs(60),
+            options: S3Options::default(),
+            stats: Arc::clone(&stats),
+            host: "host-1".to_owned(),
+            component_id: "out".to_owned(),
+        };
+
+        let now = DateTime::from_timestamp_millis(1_658_176_486_000).unwrap();
+        let request = summary.request(now);
+        assert_eq!(request.metadata.s3_key, "metrics/1658176486000.json");
+        let body = serde_json::from_slice::<serde_json::Value>(&request.body).unwrap();
+        assert_eq!(body["_aws"]["Timestamp"], 1_658_176_486_000_i64);
+        assert_eq!(body["_aws"]["CloudWatchMetrics"][0]["Namespace"], "Vector");
+        assert_eq!(body["ComponentId"], "out");
+        assert_eq!(body["EventsWritten"], 10);
+        assert_eq!(body["BytesWritten"], 1000);
+        assert_eq!(body["RequestErrors"], 1);
+
+        // The activity is reset once summarized.
+        assert_eq!(stats.take(), (0, 0, 0));
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use tokio::sync::Notify;
+use tower::Service;
+
+/// Counts consecutive requests which failed once retries were exhausted, and trips once there
+/// are `max_consecutive_failures` of them.
+pub struct FailFast {
+    max_consecutive_failures: usize,
+    consecutive_failures: AtomicUsize,
+    tripped: Notify,
+}
+
+impl FailFast {
+    pub fn new(max_consecutive_failures: NonZeroUsize) -> Arc<Self> {
+        Arc::new(Self {
+            max_consecutive_failures: max_consecutive_failures.get(),
+            consecutive_failures: AtomicUsize::new(0),
+            tripped: Notify::new(),
+        })
+    }
+
+    /// Completes once the number of consecutive failures reaches the maximum, returning it.
+    pub async fn tripped(&self) -> usize {
+        self.tripped.notified().await;
+        self.max_consecutive_failures
+    }
+
+    fn record(&self, failed: bool) {
+        if !failed {
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+        } else if self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1
+            == self.max_consecutive_failures
+        {
+            // The permit is stored until the sink waits on it.
+            self.tripped.notify_one();
+        }
+    }
+}
+
+/// Records the outcome of each request of the inner service with a `FailFast`, if any.
+///
+/// Requests are counted as failed when the inner service, retries included, returns an error.
+pub struct FailFastService<S> {
+    inner: S,
+    fail_fast: Option<Arc<FailFast>>,
+}
+
+impl<S> FailFastService<S> {
+    pub const fn new(inner: S, fail_fast: Option<Arc<FailFast>>) -> Self {
+        Self { inner, fail_fast }
+    }
+}
+
+impl<S, Request> Service<Request> for FailFastService<S>
+where
+    S: Service<Request>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let response = self.inner.call(request);
+        let Some(fail_fast) = self.fail_fast.clone() else {
+            return Box::pin(response);
+        };
+        Box::pin(async move {
+            let result = response.await;
+            fail_fast.record(result.is_err());
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn trips_after_consecutive_failures() {
+        let fail_fast = FailFast::new(NonZeroUsize::new(2).unwrap());
+        let mut service = FailFastService::new(
+            tower::service_fn(|fail: bool| async move {
+                if fail {
+                    Err(())
+                } else {
+                    Ok(())
+                }
+            }),
+            Some(Arc::clone(&fail_fast)),
+        );
+
+        for fail in [true, false, true] {
+            let _ = service.call(fail).await;
+        }
+        assert!(fail_fast.tripped().now_or_never().is_none());
+
+        let _ = service.call(true).await;
+        assert_eq!(fail_fast.tripped().now_or_never(), Some(2));
+    }
+}
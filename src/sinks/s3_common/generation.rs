@@ -0,0 +1,128 @@
+use aws_sdk_s3::{
+    config::Region,
+    operation::{get_object::GetObjectError, put_object::PutObjectError},
+    Client as S3Client,
+};
+use aws_smithy_runtime_api::client::{orchestrator::HttpResponse, result::SdkError};
+use aws_smithy_types::byte_stream::ByteStream;
+use tokio::sync::OnceCell;
+
+/// The generation of the objects of a run of the sink, kept in a marker object and incremented
+/// once per run, on the first upload.
+///
+/// Objects are ordered by their generation, then by their sequence number within it, regardless
+/// of clocks, as long as a single sink writes the marker object.
+#[derive(Debug)]
+pub struct Generation {
+    bucket: String,
+    marker_key: String,
+    generation: OnceCell<u64>,
+}
+
+impl Generation {
+    pub fn new(bucket: String, marker_key: String) -> Self {
+        Self {
+            bucket,
+            marker_key,
+            generation: OnceCell::new(),
+        }
+    }
+
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// Returns the generation of this run, incrementing the generation of the marker object the
+    /// first time. A missing marker object is taken to hold generation 0.
+    pub async fn get(
+        &self,
+        client: &S3Client,
+        region: Option<Region>,
+    ) -> Result<u64, SdkError<PutObjectError, HttpResponse>> {
+        self.generation
+            .get_or_try_init(|| self.increment(client, region))
+            .await
+            .copied()
+    }
+
+    async fn increment(
+        &self,
+        client: &S3Client,
+        region: Option<Region>,
+    ) -> Result<u64, SdkError<PutObjectError, HttpResponse>> {
+        let config_override = || {
+            region
+                .clone()
+                .map(|region| aws_sdk_s3::config::Builder::default().region(region))
+        };
+
+        let mut request = client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&self.marker_key)
+            .customize();
+        if let Some(config_override) = config_override() {
+            request = request.config_override(config_override);
+        }
+        let previous = match request.send().await {
+            Ok(output) => {
+                let body = output
+                    .body
+                    .collect()
+                    .await
+                    .map_err(SdkError::construction_failure)?
+                    .into_bytes();
+                parse_generation(&body).ok_or_else(|| {
+                    SdkError::construction_failure(format!(
+                        "Invalid generation in marker object {:?}.",
+                        self.marker_key
+                    ))
+                })?
+            }
+            Err(error)
+                if error
+                    .as_service_error()
+                    .is_some_and(GetObjectError::is_no_such_key) =>
+            {
+                0
+            }
+            Err(error) => {
+                return Err(
+                    error.map_service_error(|error| PutObjectError::generic(error.meta().clone()))
+                )
+            }
+        };
+
+        let generation = previous + 1;
+        let mut request = client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&self.marker_key)
+            .content_type("text/plain")
+            .body(ByteStream::from(generation.to_string().into_bytes()))
+            .customize();
+        if let Some(config_override) = config_override() {
+            request = request.config_override(config_override);
+        }
+        request.send().await?;
+        Ok(generation)
+    }
+}
+
+/// Parses the generation held by a marker object, as a decimal number.
+fn parse_generation(body: &[u8]) -> Option<u64> {
+    std::str::from_utf8(body).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker_objects_hold_a_decimal_generation() {
+        assert_eq!(parse_generation(b"41"), Some(41));
+        assert_eq!(parse_generation(b"41\n"), Some(41));
+        assert_eq!(parse_generation(b"-1"), None);
+        assert_eq!(parse_generation(b"forty-one"), None);
+    }
+}
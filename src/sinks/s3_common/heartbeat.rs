@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use chrono::{SecondsFormat, Utc};
+use tower::Service;
+use vector_lib::{finalization::EventFinalizers, request_metadata::RequestMetadata};
+
+use super::{
+    config::S3Options,
+    partitioner::S3PartitionKey,
+    service::{S3Metadata, S3Request, S3Service},
+};
+use crate::internal_events::{S3HeartbeatError, S3HeartbeatWritten};
+
+/// A small object rewritten periodically, such that its age shows whether the sink is running even
+/// when no events flow through it.
+///
+/// Heartbeats are written to their key as is, outside of the partitioning and naming of the
+/// objects holding events.
+#[derive(Clone, Debug)]
+pub struct Heartbeat {
+    pub bucket: String,
+    pub key: String,
+    pub interval: Duration,
+    pub options: S3Options,
+    /// Identifies the instance of Vector writing the heartbeats.
+    pub host: String,
+    pub component_id: String,
+}
+
+impl Heartbeat {
+    /// Writes a heartbeat every interval, starting right away, until the future is dropped.
+    ///
+    /// Heartbeats are neither staged nor retried: a failed heartbeat is superseded by the next one.
+    pub async fn run(self, service: S3Service) {
+        let mut service = service
+            .with_staging_dir(None)
+            .with_upload_slo(None)
+            .with_retention(None);
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match service.call(self.request()).await {
+                Ok(_) => emit!(S3HeartbeatWritten { key: &self.key }),
+                Err(error) => emit!(S3HeartbeatError {
+                    key: &self.key,
+                    error
+                }),
+            }
+        }
+    }
+
+    fn request(&self) -> S3Request {
+        let body = serde_json::json!({
+            "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            "host": self.host,
+            "component_id": self.component_id,
+        });
+        S3Request {
+            body: Bytes::from(body.to_string()),
+            bucket: self.bucket.clone(),
+            metadata: S3Metadata {
+                partition_key: S3PartitionKey {
+                    key_prefix: String::new(),
+                    ssekms_key_id: None,
+                    storage_class: None,
+                    target: None,
+                    split_run: None,
+                },
+                s3_key: self.key.clone(),
+                finalizers: EventFinalizers::default(),
+                object_metadata: None,
+                bloom_filter: None,
+                gzip_index: None,
+                key_error: None,
+                batch_started: None,
+                latest_event_timestamp: None,
+                event_time_range: None,
+                summary: None,
+                sequence: None,
+            },
+            request_metadata: RequestMetadata::default(),
+            content_encoding: None,
+            expires: None,
+            options: self.options.clone(),
+            region: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heartbeats_identify_the_instance() {
+        let heartbeat = Heartbeat {
+            bucket: "bucket".to_owned(),
+            key: "heartbeats/vector".to_owned(),
+            interval: Duration::from_secs(60),
+            options: S3Options::default(),
+            host: "host-1".to_owned(),
+            component_id: "out".to_owned(),
+        };
+
+        let request = heartbeat.request();
+        assert_eq!(request.metadata.s3_key, "heartbeats/vector");
+        let body = serde_json::from_slice::<serde_json::Value>(&request.body).unwrap();
+        assert_eq!(body["host"], "host-1");
+        assert_eq!(body["component_id"], "out");
+        assert!(chrono::DateTime::parse_from_rfc3339(body["timestamp"].as_str().unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::stream::{Fuse, FusedStream, Stream, StreamExt};
+use pin_project::pin_project;
+
+/// Yields the items of each partition in turn, rather than in the order they arrive.
+///
+/// Once items back up, such as when uploads are slower than batches are flushed, up to
+/// `max_buffered` items are buffered and yielded round-robin across the partitions they belong to.
+/// This keeps a partition flushing many batches from delaying the batches of other partitions.
+/// Items of the same partition are still yielded in the order they arrived.
+#[pin_project]
+pub struct Interleave<St, K, F>
+where
+    St: Stream,
+{
+    #[pin]
+    stream: Fuse<St>,
+    partition: F,
+    queues: HashMap<K, VecDeque<St::Item>>,
+    /// The partitions with buffered items, in the order they take their turn.
+    turns: VecDeque<K>,
+    buffered: usize,
+    max_buffered: usize,
+}
+
+impl<St, K, F> Interleave<St, K, F>
+where
+    St: Stream,
+    F: Fn(&St::Item) -> K,
+{
+    pub fn new(stream: St, max_buffered: usize, partition: F) -> Self {
+        Self {
+            stream: stream.fuse(),
+            partition,
+            queues: HashMap::new(),
+            turns: VecDeque::new(),
+            buffered: 0,
+            max_buffered: max_buffered.max(1),
+        }
+    }
+}
+
+impl<St, K, F> Stream for Interleave<St, K, F>
+where
+    St: Stream,
+    K: Clone + Eq + Hash,
+    F: Fn(&St::Item) -> K,
+{
+    type Item = St::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        while *this.buffered < *this.max_buffered {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    let key = (this.partition)(&item);
+                    let queue = this.queues.entry(key.clone()).or_default();
+                    if queue.is_empty() {
+                        this.turns.push_back(key);
+                    }
+                    queue.push_back(item);
+                    *this.buffered += 1;
+                }
+                Poll::Ready(None) | Poll::Pending => break,
+            }
+        }
+
+        let Some(key) = this.turns.pop_front() else {
+            return if this.stream.is_terminated() {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
+        };
+        let queue = this
+            .queues
+            .get_mut(&key)
+            .expect("partitions taking turns should have buffered items");
+        let item = queue.pop_front();
+        if queue.is_empty() {
+            this.queues.remove(&key);
+        } else {
+            this.turns.push_back(key);
+        }
+        *this.buffered -= 1;
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn interleaves_backed_up_partitions() {
+        let items = stream::iter([("a", 1), ("a", 2), ("a", 3), ("b", 1), ("a", 4), ("c", 1)]);
+        let interleaved = Interleave::new(items, 8, |item: &(&'static str, u8)| item.0)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            interleaved,
+            [("a", 1), ("b", 1), ("c", 1), ("a", 2), ("a", 3), ("a", 4)]
+        );
+    }
+
+    #[tokio::test]
+    async fn buffers_at_most_max_buffered_items() {
+        let items = stream::iter([("a", 1), ("a", 2), ("b", 1), ("b", 2)]);
+        let interleaved = Interleave::new(items, 2, |item: &(&'static str, u8)| item.0)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(interleaved, [("a", 1), ("a", 2), ("b", 1), ("b", 2)]);
+    }
+}
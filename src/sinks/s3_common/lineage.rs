@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use vrl::path::{OwnedValuePath, PathPrefix};
+
+use crate::event::{Event, Value};
+
+/// The range of source offsets of the events of an object read from the same source partition.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SourceRange {
+    /// The values of the partition fields of the events, keyed by their path. Fields the events
+    /// have no value for are left out.
+    pub partition: BTreeMap<String, serde_json::Value>,
+    pub min_offset: i64,
+    pub max_offset: i64,
+    pub event_count: u64,
+}
+
+/// The fields identifying the source partition and offset of events, such as the topic, partition,
+/// and offset of events read from Kafka.
+#[derive(Clone, Debug)]
+pub struct Lineage {
+    partition_fields: Vec<OwnedValuePath>,
+    offset_field: OwnedValuePath,
+}
+
+impl Lineage {
+    pub const fn new(partition_fields: Vec<OwnedValuePath>, offset_field: OwnedValuePath) -> Self {
+        Self {
+            partition_fields,
+            offset_field,
+        }
+    }
+
+    /// Returns the range of offsets of each source partition of the events, ordered by partition.
+    ///
+    /// Only log events holding an integer offset are counted.
+    pub fn source_ranges(&self, events: &[Event]) -> Vec<SourceRange> {
+        let mut ranges = BTreeMap::<String, SourceRange>::new();
+        for log in events.iter().filter_map(Event::maybe_as_log) {
+            let Some(Value::Integer(offset)) = log.get((PathPrefix::Event, &self.offset_field))
+            else {
+                continue;
+            };
+            let partition = self
+                .partition_fields
+                .iter()
+                .filter_map(|field| {
+                    let value = serde_json::to_value(log.get((PathPrefix::Event, field))?).ok()?;
+                    Some((String::from(field.clone()), value))
+                })
+                .collect::<BTreeMap<_, _>>();
+            // Partitions are keyed by their serialized form, as JSON values can't be ordered.
+            let key = serde_json::to_string(&partition).expect("partition should always serialize");
+            ranges
+                .entry(key)
+                .and_modify(|range| {
+                    range.min_offset = range.min_offset.min(*offset);
+                    range.max_offset = range.max_offset.max(*offset);
+                    range.event_count += 1;
+                })
+                .or_insert_with(|| SourceRange {
+                    partition,
+                    min_offset: *offset,
+                    max_offset: *offset,
+                    event_count: 1,
+                });
+        }
+        ranges.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::LogEvent;
+
+    fn event(topic: &str, partition: i64, offset: Option<i64>) -> Event {
+        let mut log = LogEvent::from("message");
+        log.insert("topic", topic);
+        log.insert("partition", partition);
+        if let Some(offset) = offset {
+            log.insert("offset", offset);
+        }
+        log.into()
+    }
+
+    #[test]
+    fn offsets_are_ranged_per_partition() {
+        let lineage = Lineage::new(
+            vec![
+                OwnedValuePath::try_from("topic".to_owned()).unwrap(),
+                OwnedValuePath::try_from("partition".to_owned()).unwrap(),
+            ],
+            OwnedValuePath::try_from("offset".to_owned()).unwrap(),
+        );
+        let ranges = lineage.source_ranges(&[
+            event("logs", 1, Some(42)),
+            event("logs", 0, Some(7)),
+            event("logs", 1, Some(40)),
+            event("logs", 1, None),
+            event("logs", 0, Some(9)),
+        ]);
+
+        let ranges = serde_json::to_value(ranges).unwrap();
+        assert_eq!(
+            ranges,
+            serde_json::json!([
+                {
+                    "partition": {"partition": 0, "topic": "logs"},
+                    "min_offset": 7,
+                    "max_offset": 9,
+                    "event_count": 2,
+                },
+                {
+                    "partition": {"partition": 1, "topic": "logs"},
+                    "min_offset": 40,
+                    "max_offset": 42,
+                    "event_count": 2,
+                },
+            ])
+        );
+    }
+}
@@ -1,4 +1,20 @@
+pub mod bgzf;
+pub mod bloom;
 pub mod config;
+pub mod emf;
+pub mod fail_fast;
+pub mod generation;
+pub mod heartbeat;
+pub mod interleave;
+pub mod lineage;
+pub mod ordering;
 pub mod partitioner;
+pub mod prefix_concurrency;
+pub mod profile;
+pub mod retention;
 pub mod service;
 pub mod sink;
+pub mod spill;
+pub mod staging;
+pub mod summary;
+pub mod verification;
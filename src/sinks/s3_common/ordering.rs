@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::oneshot,
+    future::{BoxFuture, FutureExt, Shared},
+};
+use tower::Service;
+
+use super::{partitioner::S3PartitionKey, service::S3Request};
+
+/// Uploads the objects of each partition one at a time, in the order they are sent.
+///
+/// Objects of different partitions are still uploaded concurrently. The inner service is called
+/// as soon as a request is sent, so that it holds on to its concurrency and rate limits, but the
+/// response is not polled until the previous object of the partition has been uploaded, retries
+/// included.
+pub struct OrderedPartitions<S> {
+    inner: S,
+    /// Completes once the last object sent for each partition is uploaded, when preserving order.
+    previous: Option<HashMap<S3PartitionKey, Shared<oneshot::Receiver<()>>>>,
+}
+
+impl<S> OrderedPartitions<S> {
+    pub fn new(inner: S, preserve_order: bool) -> Self {
+        Self {
+            inner,
+            previous: preserve_order.then(HashMap::new),
+        }
+    }
+}
+
+impl<S> Service<S3Request> for OrderedPartitions<S>
+where
+    S: Service<S3Request>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: S3Request) -> Self::Future {
+        let Some(previous) = self.previous.as_mut() else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        // Partitions whose objects have all been uploaded have nothing left to wait for.
+        previous.retain(|_, uploaded| uploaded.clone().now_or_never().is_none());
+        let (uploaded, receiver) = oneshot::channel();
+        let wait = previous.insert(request.metadata.partition_key.clone(), receiver.shared());
+
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            if let Some(wait) = wait {
+                // The previous object may have failed, in which case its sender is dropped.
+                let _ = wait.await;
+            }
+            let result = response.await;
+            drop(uploaded);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use bytes::Bytes;
+    use futures::future;
+    use vector_lib::{finalization::EventFinalizers, request_metadata::RequestMetadata};
+
+    use super::*;
+    use crate::sinks::s3_common::{config::S3Options, service::S3Metadata};
+
+    fn request(key_prefix: &str, s3_key: &str) -> S3Request {
+        S3Request {
+            body: Bytes::new(),
+            bucket: "bucket".to_owned(),
+            metadata: S3Metadata {
+                partition_key: S3PartitionKey {
+                    key_prefix: key_prefix.to_owned(),
+                    ssekms_key_id: None,
+                    storage_class: None,
+                    target: None,
+                    split_run: None,
+                },
+                s3_key: s3_key.to_owned(),
+                finalizers: EventFinalizers::default(),
+                object_metadata: None,
+                bloom_filter: None,
+                gzip_index: None,
+                key_error: None,
+                batch_started: None,
+                latest_event_timestamp: None,
+                event_time_range: None,
+                summary: None,
+                sequence: None,
+            },
+            request_metadata: RequestMetadata::default(),
+            content_encoding: None,
+            expires: None,
+            options: S3Options::default(),
+            region: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn objects_of_a_partition_are_uploaded_in_order() {
+        let uploaded = Arc::new(Mutex::new(Vec::new()));
+        let inner = tower::service_fn({
+            let uploaded = Arc::clone(&uploaded);
+            move |request: S3Request| {
+                let uploaded = Arc::clone(&uploaded);
+                async move {
+                    // The first object of each partition takes the longest to upload.
+                    if request.metadata.s3_key.ends_with('1') {
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                    uploaded.lock().unwrap().push(request.metadata.s3_key);
+                    Ok::<_, ()>(())
+                }
+            }
+        });
+        let mut service = OrderedPartitions::new(inner, true);
+
+        let responses =
+            ["a1", "b1", "a2", "c2"].map(|s3_key| service.call(request(&s3_key[..1], s3_key)));
+        future::join_all(responses).await;
+        assert_eq!(*uploaded.lock().unwrap(), ["c2", "a1", "b1", "a2"]);
+    }
+}
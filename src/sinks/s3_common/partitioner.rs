@@ -1,46 +1,904 @@
-use vector_lib::{event::Event, partition::Partitioner};
+use std::{
+    cell::Cell,
+    collections::{hash_map::RandomState, HashMap, HashSet, VecDeque},
+    future::Future,
+    hash::BuildHasher,
+    num::{NonZeroU32, NonZeroUsize},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
 
-use crate::{internal_events::TemplateRenderingError, template::Template};
+use chrono::{DateTime, FixedOffset, SubsecRound, Utc};
+use futures::{stream::BoxStream, StreamExt};
+use lru::LruCache;
+use tokio::time::Sleep;
+use vector_lib::{
+    event::{Event, Value},
+    partition::Partitioner,
+    stream::ExpirationQueue,
+    time::KeyedTimer,
+};
+use vrl::path::{OwnedValuePath, PathPrefix};
+
+use super::config::{LateEvents, PartitionCase, S3StorageClass};
+use crate::{
+    internal_events::{
+        S3DistinctPartitionKeys, S3FlushRequested, S3InvalidStorageClass, S3KeyPrefixFallback,
+        S3LateEventDropped, TemplateRenderingError,
+    },
+    template::{event_timestamp, render_field, Template},
+};
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct S3PartitionKey {
     pub key_prefix: String,
     pub ssekms_key_id: Option<String>,
+    /// The storage class of the objects of this partition, overriding the configured one.
+    pub storage_class: Option<S3StorageClass>,
+    /// The routing value selecting the bucket and region of the objects of this partition.
+    pub target: Option<String>,
+    /// The run of contiguous events within this partition, when splitting on value changes.
+    pub split_run: Option<u64>,
+}
+
+/// Normalizes a value rendered from events through a table, for use as a key prefix segment.
+pub struct PrefixMapping {
+    template: Template,
+    values: HashMap<String, String>,
+    default: String,
+}
+
+impl PrefixMapping {
+    pub const fn new(template: Template, values: HashMap<String, String>, default: String) -> Self {
+        Self {
+            template,
+            values,
+            default,
+        }
+    }
+
+    /// Maps the value rendered from the event, falling back to the default for values missing
+    /// from the table or failing to render. `clean` is cleared if the value fails to render.
+    fn segment(&self, item: &Event, clean: &Cell<bool>) -> &str {
+        let value = self
+            .template
+            .render_string(item)
+            .map_err(|error| {
+                clean.set(false);
+                emit!(TemplateRenderingError {
+                    error,
+                    field: Some("key_prefix_mapping.source"),
+                    drop_event: false,
+                });
+            })
+            .ok();
+        value
+            .and_then(|value| self.values.get(&value))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Segments events by their ingest lag, the time between their timestamp and when they are
+/// partitioned, for use as a key prefix segment.
+pub struct LagSegments {
+    /// The field of the timestamp of log events, rather than their own timestamp.
+    timestamp_field: Option<OwnedValuePath>,
+    on_time: String,
+    /// The minimum lag of each segment, in ascending order.
+    thresholds: Vec<(Duration, String)>,
+}
+
+impl LagSegments {
+    pub fn new(
+        timestamp_field: Option<OwnedValuePath>,
+        on_time: String,
+        mut thresholds: Vec<(Duration, String)>,
+    ) -> Self {
+        thresholds.sort_by_key(|(min_lag, _)| *min_lag);
+        Self {
+            timestamp_field,
+            on_time,
+            thresholds,
+        }
+    }
+
+    /// Returns the segment of the lag of the event at `now`.
+    ///
+    /// Events without a timestamp, or with one in the future, are on time.
+    fn segment(&self, item: &Event, now: DateTime<Utc>) -> &str {
+        let timestamp = match (&self.timestamp_field, item) {
+            (Some(field), Event::Log(log)) => log
+                .get((PathPrefix::Event, field))
+                .and_then(Value::as_timestamp)
+                .copied(),
+            _ => Some(event_timestamp(item.into())),
+        };
+        let lag = timestamp
+            .and_then(|timestamp| (now - timestamp).to_std().ok())
+            .unwrap_or_default();
+        self.thresholds
+            .iter()
+            .rev()
+            .find(|(min_lag, _)| lag >= *min_lag)
+            .map_or(&self.on_time, |(_, segment)| segment)
+    }
+}
+
+/// Buckets events by ranges of the value of a numeric field, for use as a key prefix segment.
+pub struct RangeBuckets {
+    field: OwnedValuePath,
+    /// The lower bound, inclusive, and upper bound, exclusive, of each bucket, and its label.
+    buckets: Vec<(Option<f64>, Option<f64>, String)>,
+    default: String,
+}
+
+impl RangeBuckets {
+    pub const fn new(
+        field: OwnedValuePath,
+        buckets: Vec<(Option<f64>, Option<f64>, String)>,
+        default: String,
+    ) -> Self {
+        Self {
+            field,
+            buckets,
+            default,
+        }
+    }
+
+    /// Returns the label of the first bucket the value of the field of the event falls in.
+    ///
+    /// Events whose value falls in no bucket, or which have no integer or float value for the
+    /// field, are in the default bucket.
+    fn segment(&self, item: &Event) -> &str {
+        let value = item
+            .maybe_as_log()
+            .and_then(|log| log.get((PathPrefix::Event, &self.field)))
+            .and_then(|value| match value {
+                Value::Integer(value) => Some(*value as f64),
+                Value::Float(value) => Some(value.into_inner()),
+                _ => None,
+            });
+        value
+            .and_then(|value| {
+                self.buckets.iter().find(|(min, max, _)| {
+                    min.map_or(true, |min| value >= min) && max.map_or(true, |max| value < max)
+                })
+            })
+            .map_or(&self.default, |(_, _, label)| label)
+    }
+}
+
+/// Assigns events to a fixed number of buckets by the hash of a key rendered from them, for use as
+/// a key prefix segment.
+///
+/// Events are assigned to the bucket `crc32(key) % buckets`, where `crc32` is the CRC-32 (IEEE) of
+/// the rendered key, so that readers can reproduce the assignment.
+pub struct HashBuckets {
+    key: Template,
+    buckets: u32,
+    /// The number of digits bucket numbers are zero-padded to, that of the largest bucket number.
+    width: usize,
+}
+
+impl HashBuckets {
+    pub fn new(key: Template, buckets: NonZeroU32) -> Self {
+        Self {
+            key,
+            buckets: buckets.get(),
+            width: (buckets.get() - 1).to_string().len(),
+        }
+    }
+
+    /// Returns the `bucket=<number>` segment of the bucket of the event.
+    ///
+    /// Events whose key fails to render are in bucket 0.
+    fn segment(&self, item: &Event) -> String {
+        let bucket = match self.key.render(item) {
+            Ok(key) => crc32fast::hash(&key) % self.buckets,
+            Err(error) => {
+                emit!(TemplateRenderingError {
+                    error,
+                    field: Some("hash_partitioning.key"),
+                    drop_event: false,
+                });
+                0
+            }
+        };
+        format!("bucket={:0width$}", bucket, width = self.width)
+    }
+}
+
+/// Assigns events to tumbling windows of their timestamp, tracking a watermark of the latest
+/// timestamp seen, so that late events are written to the current window rather than reopening a
+/// closed one.
+pub struct EventTimeWindows {
+    /// The field of the timestamp of log events, rather than their own timestamp.
+    timestamp_field: Option<OwnedValuePath>,
+    /// The length of the windows, and the allowed lateness, in milliseconds.
+    window: i64,
+    allowed_lateness: i64,
+    late_prefix: String,
+    late_events: LateEvents,
+    /// The latest timestamp seen, in milliseconds since the Unix epoch.
+    watermark: AtomicI64,
+}
+
+/// The window an event is assigned to by `EventTimeWindows`.
+enum EventTimeWindow {
+    /// The event is rendered at the given time, either its own or the start of the current window.
+    Open(DateTime<Utc>),
+    /// The event is later than the allowed lateness, and is either rendered at its own time under
+    /// the late prefix, or dropped.
+    Late(DateTime<Utc>),
+}
+
+impl EventTimeWindows {
+    pub fn new(
+        timestamp_field: Option<OwnedValuePath>,
+        window: Duration,
+        allowed_lateness: Duration,
+        late_prefix: String,
+        late_events: LateEvents,
+    ) -> Self {
+        Self {
+            timestamp_field,
+            window: i64::try_from(window.as_millis()).unwrap_or(i64::MAX).max(1),
+            allowed_lateness: i64::try_from(allowed_lateness.as_millis()).unwrap_or(i64::MAX),
+            late_prefix,
+            late_events,
+            watermark: AtomicI64::new(i64::MIN),
+        }
+    }
+
+    /// Advances the watermark to the timestamp of the event, and assigns the event to a window.
+    ///
+    /// The current window is the one of the watermark, with windows aligned to the Unix epoch.
+    /// Events in the current window are rendered at their own time, as are events after it, which
+    /// advance the watermark. Events before it, but within the allowed lateness of its start, are
+    /// rendered at its start. Events later than that are late, unless they reopen their window, in
+    /// which case they are rendered at their own time. Events without a timestamp are not assigned
+    /// to a window.
+    fn assign(&self, item: &Event) -> Option<EventTimeWindow> {
+        let timestamp = match (&self.timestamp_field, item) {
+            (Some(field), Event::Log(log)) => log
+                .get((PathPrefix::Event, field))
+                .and_then(Value::as_timestamp)
+                .copied()?,
+            _ => event_timestamp(item.into()),
+        };
+        let millis = timestamp.timestamp_millis();
+        let watermark = self
+            .watermark
+            .fetch_max(millis, Ordering::Relaxed)
+            .max(millis);
+        let window_start = watermark - watermark.rem_euclid(self.window);
+        Some(if millis >= window_start {
+            EventTimeWindow::Open(timestamp)
+        } else if millis >= window_start.saturating_sub(self.allowed_lateness) {
+            EventTimeWindow::Open(
+                DateTime::from_timestamp_millis(window_start).unwrap_or(timestamp),
+            )
+        } else if self.late_events == LateEvents::Reopen {
+            EventTimeWindow::Open(timestamp)
+        } else {
+            EventTimeWindow::Late(timestamp)
+        })
+    }
+}
+
+/// Normalizes the value of a field rendered in the key prefix, so that equivalent values share a
+/// partition and unexpected values don't create new ones.
+pub struct SegmentNormalizer {
+    lowercase: bool,
+    allowed: HashSet<String>,
+    invalid: String,
+}
+
+impl SegmentNormalizer {
+    pub fn new(
+        lowercase: bool,
+        allowed: impl IntoIterator<Item = String>,
+        invalid: String,
+    ) -> Self {
+        let allowed = allowed
+            .into_iter()
+            .map(|value| {
+                if lowercase {
+                    value.to_lowercase()
+                } else {
+                    value
+                }
+            })
+            .collect();
+        Self {
+            lowercase,
+            allowed,
+            invalid,
+        }
+    }
+
+    /// Trims and optionally lowercases the value, falling back to the invalid value for values
+    /// which are missing or not allowed.
+    fn normalize(&self, value: Option<&str>) -> String {
+        let value = value.map(str::trim).map(|value| {
+            if self.lowercase {
+                value.to_lowercase()
+            } else {
+                value.to_owned()
+            }
+        });
+        match value {
+            Some(value) if self.allowed.contains(&value) => value,
+            _ => self.invalid.clone(),
+        }
+    }
+}
+
+/// The inputs partition keys are rendered from: the values of the fields referenced by the
+/// templates, and the timestamp of the event if they render it.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct KeyInputs {
+    values: Vec<Option<String>>,
+    timestamp: Option<DateTime<Utc>>,
+}
+
+/// The fields and timestamp resolution partition keys depend on.
+struct KeyDependencies {
+    fields: Vec<String>,
+    /// Whether keys depend on the timestamp, and whether on fractions of seconds.
+    timestamp: Option<bool>,
+}
+
+/// A cache of the partition keys rendered for the most recently seen inputs.
+///
+/// Keys whose rendering logged an error or fell back are not cached, so that each event they
+/// apply to is still reported.
+struct KeyCache {
+    dependencies: OnceLock<KeyDependencies>,
+    entries: Mutex<LruCache<KeyInputs, S3PartitionKey>>,
+}
+
+impl KeyCache {
+    fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            dependencies: OnceLock::new(),
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Returns the inputs of the partition key of the event, with the timestamp its key prefix is
+    /// rendered at, when it is not that of the event.
+    fn inputs(
+        &self,
+        dependencies: &KeyDependencies,
+        item: &Event,
+        timestamp: Option<DateTime<Utc>>,
+    ) -> KeyInputs {
+        let values = dependencies
+            .fields
+            .iter()
+            .map(|field| render_field(field, item.into()).map(Into::into))
+            .collect();
+        let timestamp = dependencies.timestamp.map(|subseconds| {
+            let timestamp = timestamp.unwrap_or_else(|| event_timestamp(item.into()));
+            if subseconds {
+                timestamp
+            } else {
+                timestamp.trunc_subsecs(0)
+            }
+        });
+        KeyInputs { values, timestamp }
+    }
+}
+
+/// The window the distinct partition keys are counted over.
+pub const DISTINCT_KEYS_WINDOW: Duration = Duration::from_secs(60);
+
+/// Counts the distinct partition keys seen within consecutive windows.
+///
+/// Keys are tracked by their hash, and without their split run, so that the count reflects the
+/// cardinality of the rendered templates rather than the number of runs.
+struct DistinctKeys {
+    window: Duration,
+    hasher: RandomState,
+    state: Mutex<DistinctKeysState>,
+}
+
+struct DistinctKeysState {
+    window_start: Instant,
+    current: HashSet<u64>,
+    /// The number of distinct keys seen in the previous window.
+    previous: usize,
+}
+
+impl DistinctKeys {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            hasher: RandomState::new(),
+            state: Mutex::new(DistinctKeysState {
+                window_start: Instant::now(),
+                current: HashSet::new(),
+                previous: 0,
+            }),
+        }
+    }
+
+    /// Records the key, emitting the count whenever it changes.
+    ///
+    /// The count is that of the previous window until the current window exceeds it, so that it
+    /// doesn't drop at the start of each window, while a jump is still reported as it happens.
+    fn observe(&self, key: &S3PartitionKey) {
+        let hash = self.hasher.hash_one((
+            &key.key_prefix,
+            &key.ssekms_key_id,
+            &key.storage_class,
+            &key.target,
+        ));
+        let mut state = self.state.lock().expect("distinct keys mutex poisoned");
+        let before = state.previous.max(state.current.len());
+        if state.window_start.elapsed() >= self.window {
+            state.previous = state.current.len();
+            state.current.clear();
+            state.window_start = Instant::now();
+        }
+        state.current.insert(hash);
+        let count = state.previous.max(state.current.len());
+        if count != before {
+            emit!(S3DistinctPartitionKeys { count });
+        }
+    }
 }
 
 /// Partitions items based on the generated key for the given event.
-pub struct S3KeyPartitioner(Template, Option<Template>);
+pub struct S3KeyPartitioner {
+    key_prefix_template: Template,
+    /// The templates tried in order when `key_prefix_template` fails to render, or renders empty.
+    key_prefix_fallbacks: Vec<Template>,
+    ssekms_key_id_template: Option<Template>,
+    split_on_change: Option<SplitOnChange>,
+    min_events: Option<MinEvents>,
+    max_age: Option<Duration>,
+    min_write_interval: Option<Duration>,
+    startup_warmup: Option<Duration>,
+    lowercase_keys: bool,
+    key_prefix_fallback: Option<String>,
+    storage_class_template: Option<Template>,
+    target_template: Option<Template>,
+    prefix_mapping: Option<PrefixMapping>,
+    lag_segments: Option<LagSegments>,
+    range_buckets: Option<RangeBuckets>,
+    hash_buckets: Option<HashBuckets>,
+    event_time_windows: Option<EventTimeWindows>,
+    partition_case: PartitionCase,
+    /// The normalizers of fields rendered in the key prefix, by field path.
+    segment_normalizers: HashMap<String, SegmentNormalizer>,
+    /// The interval of the wall-clock boundaries all batches are flushed at, and the offset of
+    /// the timezone they are aligned in.
+    flush_boundary: Option<(Duration, Option<FixedOffset>)>,
+    key_cache: Option<KeyCache>,
+    distinct_keys: Option<DistinctKeys>,
+    /// The requests to flush all batches, taken by the first timer built.
+    flush_requests: Mutex<Option<FlushRequests>>,
+}
 
 impl S3KeyPartitioner {
     pub const fn new(
         key_prefix_template: Template,
         ssekms_key_id_template: Option<Template>,
     ) -> Self {
-        Self(key_prefix_template, ssekms_key_id_template)
+        Self {
+            key_prefix_template,
+            key_prefix_fallbacks: Vec::new(),
+            ssekms_key_id_template,
+            split_on_change: None,
+            min_events: None,
+            max_age: None,
+            min_write_interval: None,
+            startup_warmup: None,
+            lowercase_keys: false,
+            key_prefix_fallback: None,
+            storage_class_template: None,
+            target_template: None,
+            prefix_mapping: None,
+            lag_segments: None,
+            range_buckets: None,
+            hash_buckets: None,
+            event_time_windows: None,
+            partition_case: PartitionCase::Preserve,
+            segment_normalizers: HashMap::new(),
+            flush_boundary: None,
+            key_cache: None,
+            distinct_keys: None,
+            flush_requests: Mutex::new(None),
+        }
     }
-}
 
-impl Partitioner for S3KeyPartitioner {
-    type Item = Event;
-    type Key = Option<S3PartitionKey>;
+    /// Splits each partition into contiguous runs of events sharing the same rendered value.
+    pub fn with_split_on_change(mut self, template: Option<Template>) -> Self {
+        self.split_on_change = template.map(SplitOnChange::new);
+        self
+    }
 
-    fn partition(&self, item: &Self::Item) -> Self::Key {
-        let key_prefix = self
-            .0
-            .render_string(item)
-            .map_err(|error| {
+    /// Holds batches past their timeout until they contain at least `min_events` events.
+    pub fn with_min_events(mut self, min_events: Option<NonZeroUsize>) -> Self {
+        self.min_events = min_events.map(MinEvents::new);
+        self
+    }
+
+    /// Expires batches once they are `max_age` old, even if they are being held for not
+    /// containing enough events yet.
+    pub const fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Holds batches past their timeout until `min_write_interval` has passed since the previous
+    /// batch of the same partition was flushed, so that a hot key is written at most once per
+    /// interval.
+    pub const fn with_min_write_interval(mut self, min_write_interval: Option<Duration>) -> Self {
+        self.min_write_interval = min_write_interval;
+        self
+    }
+
+    /// Holds batches past their timeout until `startup_warmup` has passed since the timer was
+    /// built, so that the first batches fill up rather than being flushed small.
+    pub const fn with_startup_warmup(mut self, startup_warmup: Option<Duration>) -> Self {
+        self.startup_warmup = startup_warmup;
+        self
+    }
+
+    /// Lowercases the rendered key prefix, so that prefixes only differing by case share a batch.
+    pub const fn with_lowercase_keys(mut self, lowercase_keys: bool) -> Self {
+        self.lowercase_keys = lowercase_keys;
+        self
+    }
+
+    /// Renders the key prefix of events with the first of the given templates which renders a
+    /// non-empty prefix, when the key prefix template fails to render or renders empty.
+    ///
+    /// Events for which none of them render are handled like events whose key prefix fails to
+    /// render.
+    pub fn with_key_prefix_fallbacks(mut self, key_prefix_fallbacks: Vec<Template>) -> Self {
+        self.key_prefix_fallbacks = key_prefix_fallbacks;
+        self
+    }
+
+    /// Partitions events whose key prefix fails to render under the given prefix, rather than
+    /// dropping them.
+    pub fn with_key_prefix_fallback(mut self, key_prefix_fallback: Option<String>) -> Self {
+        self.key_prefix_fallback = key_prefix_fallback;
+        self
+    }
+
+    /// Partitions events by the storage class rendered from the given template.
+    pub fn with_storage_class_template(mut self, template: Option<Template>) -> Self {
+        self.storage_class_template = template;
+        self
+    }
+
+    /// Partitions events by the routing value rendered from the given template, so that batches
+    /// never mix events bound for different buckets.
+    ///
+    /// Events whose routing value fails to render are dropped.
+    pub fn with_target_template(mut self, template: Option<Template>) -> Self {
+        self.target_template = template;
+        self
+    }
+
+    /// Prefixes the key prefix with the segment the mapping normalizes each event to, followed by
+    /// a `/`.
+    pub fn with_prefix_mapping(mut self, prefix_mapping: Option<PrefixMapping>) -> Self {
+        self.prefix_mapping = prefix_mapping;
+        self
+    }
+
+    /// Prefixes the key prefix with the segment of the ingest lag of each event, followed by a
+    /// `/`, ahead of the segment of the prefix mapping.
+    ///
+    /// The lag changes with every event, so the segment is added after partition keys are looked
+    /// up in the key cache.
+    pub fn with_lag_segments(mut self, lag_segments: Option<LagSegments>) -> Self {
+        self.lag_segments = lag_segments;
+        self
+    }
+
+    /// Prefixes the key prefix with the label of the range bucket of each event, followed by a `/`,
+    /// ahead of the segment of the prefix mapping.
+    ///
+    /// The segment is added after partition keys are looked up in the key cache, as the field it
+    /// is read from may not be rendered by any template.
+    pub fn with_range_buckets(mut self, range_buckets: Option<RangeBuckets>) -> Self {
+        self.range_buckets = range_buckets;
+        self
+    }
+
+    /// Prefixes the key prefix with the `bucket=<number>` segment of the hash bucket of each event,
+    /// followed by a `/`, ahead of the label of its range bucket.
+    ///
+    /// The segment is added after partition keys are looked up in the key cache, as the key it is
+    /// rendered from may not be rendered by any other template.
+    pub fn with_hash_buckets(mut self, hash_buckets: Option<HashBuckets>) -> Self {
+        self.hash_buckets = hash_buckets;
+        self
+    }
+
+    /// Renders the time formats of the key prefix of each event at the time of the window it is
+    /// assigned to, and prefixes the key prefix of events later than the allowed lateness with the
+    /// late prefix.
+    pub fn with_event_time_windows(mut self, event_time_windows: Option<EventTimeWindows>) -> Self {
+        self.event_time_windows = event_time_windows;
+        self
+    }
+
+    /// Converts the values of the fields rendered in the key prefix to the given case, after any
+    /// segment normalizer, leaving its literal parts untouched.
+    pub const fn with_partition_case(mut self, partition_case: PartitionCase) -> Self {
+        self.partition_case = partition_case;
+        self
+    }
+
+    /// Normalizes the values of the given fields when rendering the key prefix.
+    pub fn with_segment_normalizers(
+        mut self,
+        segment_normalizers: HashMap<String, SegmentNormalizer>,
+    ) -> Self {
+        self.segment_normalizers = segment_normalizers;
+        self
+    }
+
+    /// Flushes all batches at each multiple of `interval` since midnight, in the timezone of the
+    /// given offset, such as at the start of every hour.
+    pub const fn with_flush_boundary(
+        mut self,
+        interval: Option<Duration>,
+        tz_offset: Option<FixedOffset>,
+    ) -> Self {
+        self.flush_boundary = match interval {
+            Some(interval) => Some((interval, tz_offset)),
+            None => None,
+        };
+        self
+    }
+
+    /// Flushes all batches whenever a flush is requested, such as on a signal.
+    pub fn with_flush_requests(mut self, flush_requests: Option<FlushRequests>) -> Self {
+        self.flush_requests = Mutex::new(flush_requests);
+        self
+    }
+
+    /// Caches up to `capacity` partition keys by the values of the fields they are rendered from,
+    /// reusing them for events sharing those values rather than rendering the templates again.
+    pub fn with_key_cache(mut self, capacity: Option<NonZeroUsize>) -> Self {
+        self.key_cache = capacity.map(KeyCache::new);
+        self
+    }
+
+    /// Reports the number of distinct partition keys seen within each `window`, to surface
+    /// templates rendering far more keys than expected.
+    pub fn with_distinct_keys_window(mut self, window: Duration) -> Self {
+        self.distinct_keys = Some(DistinctKeys::new(window));
+        self
+    }
+
+    /// Returns the fields and timestamp resolution the templates rendering partition keys depend
+    /// on.
+    fn key_dependencies(&self) -> KeyDependencies {
+        let templates = [
+            Some(&self.key_prefix_template),
+            self.ssekms_key_id_template.as_ref(),
+            self.storage_class_template.as_ref(),
+            self.target_template.as_ref(),
+            self.prefix_mapping
+                .as_ref()
+                .map(|mapping| &mapping.template),
+        ];
+        let templates = templates
+            .into_iter()
+            .flatten()
+            .chain(&self.key_prefix_fallbacks)
+            .collect::<Vec<_>>();
+
+        let mut fields = templates
+            .iter()
+            .flat_map(|template| template.get_fields().unwrap_or_default())
+            .collect::<Vec<_>>();
+        fields.sort();
+        fields.dedup();
+        let timestamp = templates
+            .iter()
+            .any(|template| template.renders_timestamp())
+            .then(|| {
+                templates
+                    .iter()
+                    .any(|template| template.renders_subseconds())
+            });
+        KeyDependencies { fields, timestamp }
+    }
+
+    /// Renders the key prefix with the first template rendering a non-empty prefix.
+    ///
+    /// If none of them do, the result of the key prefix template is returned.
+    fn render_key_prefix(
+        &self,
+        item: &Event,
+        timestamp: Option<DateTime<Utc>>,
+    ) -> Result<String, crate::template::TemplateRenderingError> {
+        let key_prefix =
+            self.render_key_prefix_template(&self.key_prefix_template, item, timestamp);
+        if matches!(&key_prefix, Ok(key_prefix) if !key_prefix.is_empty()) {
+            return key_prefix;
+        }
+        self.key_prefix_fallbacks
+            .iter()
+            .find_map(|template| {
+                self.render_key_prefix_template(template, item, timestamp)
+                    .ok()
+                    .filter(|key_prefix| !key_prefix.is_empty())
+            })
+            .map_or(key_prefix, Ok)
+    }
+
+    fn render_key_prefix_template(
+        &self,
+        template: &Template,
+        item: &Event,
+        timestamp: Option<DateTime<Utc>>,
+    ) -> Result<String, crate::template::TemplateRenderingError> {
+        let normalize = |field: &str, value: Option<&str>| {
+            let value = match self.segment_normalizers.get(field) {
+                Some(normalizer) => normalizer.normalize(value),
+                None => value?.to_owned(),
+            };
+            Some(self.partition_case.apply(value))
+        };
+        match timestamp {
+            Some(timestamp) => template.render_string_at(item, timestamp, &normalize),
+            None if self.segment_normalizers.is_empty()
+                && self.partition_case == PartitionCase::Preserve =>
+            {
+                template.render_string(item)
+            }
+            None => template.render_string_normalized(item, &normalize),
+        }
+    }
+
+    /// Builds the timer used to expire the batches of this partitioner.
+    ///
+    /// Besides expiring batches once `timeout` elapses, the timer immediately expires the batch
+    /// of a run that has ended because the value of `split_on_change` changed, and all batches at
+    /// each flush boundary, or when a flush is requested.
+    pub fn timer(&self, timeout: Duration) -> S3PartitionTimer {
+        S3PartitionTimer {
+            expirations: ExpirationQueue::new(timeout),
+            deadlines: self.max_age.map(ExpirationQueue::new),
+            active: HashSet::new(),
+            split_state: self
+                .split_on_change
+                .as_ref()
+                .map(|split| Arc::clone(&split.state)),
+            min_events: self
+                .min_events
+                .as_ref()
+                .map(|min_events| (min_events.min_events.get(), Arc::clone(&min_events.state))),
+            batch_starts: BatchStarts::default(),
+            cooldowns: self.min_write_interval.map(WriteCooldowns::new),
+            warmup: self.startup_warmup.map(StartupWarmup::new),
+            boundary: self
+                .flush_boundary
+                .map(|(interval, tz_offset)| FlushBoundary::new(interval, tz_offset)),
+            flush_requests: self
+                .flush_requests
+                .lock()
+                .expect("flush requests mutex poisoned")
+                .take(),
+            requested: VecDeque::new(),
+        }
+    }
+
+    fn partition_key(&self, item: &Event) -> Option<S3PartitionKey> {
+        let window = self
+            .event_time_windows
+            .as_ref()
+            .and_then(|windows| windows.assign(item));
+        let timestamp = match window {
+            Some(EventTimeWindow::Open(timestamp) | EventTimeWindow::Late(timestamp)) => {
+                Some(timestamp)
+            }
+            None => None,
+        };
+        if let (Some(EventTimeWindow::Late(_)), Some(windows)) = (&window, &self.event_time_windows)
+        {
+            if windows.late_events == LateEvents::Drop {
+                emit!(S3LateEventDropped);
+                return None;
+            }
+        }
+        let mut key = match &self.key_cache {
+            Some(cache) => {
+                let dependencies = cache.dependencies.get_or_init(|| self.key_dependencies());
+                let inputs = cache.inputs(dependencies, item, timestamp);
+                let mut entries = cache.entries.lock().expect("key cache mutex poisoned");
+                match entries.get(&inputs) {
+                    Some(key) => key.clone(),
+                    None => {
+                        let clean = Cell::new(true);
+                        let key = self.render_partition_key(item, timestamp, &clean)?;
+                        if clean.get() {
+                            entries.put(inputs, key.clone());
+                        }
+                        key
+                    }
+                }
+            }
+            None => self.render_partition_key(item, timestamp, &Cell::new(true))?,
+        };
+        if let Some(range_buckets) = &self.range_buckets {
+            let segment = range_buckets.segment(item);
+            key.key_prefix = format!("{}/{}", segment, key.key_prefix);
+        }
+        if let Some(hash_buckets) = &self.hash_buckets {
+            let segment = hash_buckets.segment(item);
+            key.key_prefix = format!("{}/{}", segment, key.key_prefix);
+        }
+        if let (Some(EventTimeWindow::Late(_)), Some(windows)) = (&window, &self.event_time_windows)
+        {
+            key.key_prefix = format!("{}{}", windows.late_prefix, key.key_prefix);
+        }
+        if let Some(lag_segments) = &self.lag_segments {
+            let segment = lag_segments.segment(item, Utc::now());
+            key.key_prefix = format!("{}/{}", segment, key.key_prefix);
+        }
+        if let Some(split) = &self.split_on_change {
+            key.split_run = Some(split.run(&key, item));
+        }
+        Some(key)
+    }
+
+    /// Renders the partition key of the event, without its split run, formatting the times of
+    /// the key prefix from `timestamp` when set.
+    ///
+    /// `clean` is cleared if rendering reported an error, or fell back to a default value.
+    fn render_partition_key(
+        &self,
+        item: &Event,
+        timestamp: Option<DateTime<Utc>>,
+        clean: &Cell<bool>,
+    ) -> Option<S3PartitionKey> {
+        let key_prefix = match self.render_key_prefix(item, timestamp) {
+            Ok(key_prefix) => key_prefix,
+            Err(error) => {
+                clean.set(false);
                 emit!(TemplateRenderingError {
                     error,
                     field: Some("key_prefix"),
-                    drop_event: true,
+                    drop_event: self.key_prefix_fallback.is_none(),
                 });
-            })
-            .ok()?;
+                let fallback = self.key_prefix_fallback.clone()?;
+                emit!(S3KeyPrefixFallback);
+                fallback
+            }
+        };
+        let key_prefix = match &self.prefix_mapping {
+            Some(mapping) => format!("{}/{}", mapping.segment(item, clean), key_prefix),
+            None => key_prefix,
+        };
+        let key_prefix = if self.lowercase_keys {
+            key_prefix.to_lowercase()
+        } else {
+            key_prefix
+        };
         let ssekms_key_id = self
-            .1
+            .ssekms_key_id_template
             .as_ref()
             .map(|ssekms_key_id| {
                 ssekms_key_id.render_string(item).map_err(|error| {
+                    clean.set(false);
                     emit!(TemplateRenderingError {
                         error,
                         field: Some("ssekms_key_id"),
@@ -50,9 +908,1256 @@ impl Partitioner for S3KeyPartitioner {
             })
             .transpose()
             .ok()?;
+        // Events with an invalid storage class are written with the configured storage class
+        // rather than dropped.
+        let storage_class = self.storage_class_template.as_ref().and_then(|template| {
+            let storage_class = template
+                .render_string(item)
+                .map_err(|error| {
+                    clean.set(false);
+                    emit!(TemplateRenderingError {
+                        error,
+                        field: Some("storage_class_template"),
+                        drop_event: false,
+                    });
+                })
+                .ok()?;
+            storage_class
+                .parse()
+                .map_err(|_| {
+                    clean.set(false);
+                    emit!(S3InvalidStorageClass {
+                        storage_class: &storage_class
+                    });
+                })
+                .ok()
+        });
+        let target = self
+            .target_template
+            .as_ref()
+            .map(|target| {
+                target.render_string(item).map_err(|error| {
+                    clean.set(false);
+                    emit!(TemplateRenderingError {
+                        error,
+                        field: Some("region_routing.source"),
+                        drop_event: true,
+                    });
+                })
+            })
+            .transpose()
+            .ok()?;
         Some(S3PartitionKey {
             key_prefix,
             ssekms_key_id,
+            storage_class,
+            target,
+            split_run: None,
         })
     }
 }
+
+impl Partitioner for S3KeyPartitioner {
+    type Item = Event;
+    type Key = Option<S3PartitionKey>;
+
+    fn partition(&self, item: &Self::Item) -> Self::Key {
+        let key = self.partition_key(item);
+        if let (Some(distinct_keys), Some(key)) = (&self.distinct_keys, &key) {
+            distinct_keys.observe(key);
+        }
+        if let Some(min_events) = &self.min_events {
+            min_events.count(&key);
+        }
+        key
+    }
+}
+
+#[derive(Default)]
+struct SplitState {
+    /// The last rendered value and the current run of each partition.
+    runs: HashMap<S3PartitionKey, (String, u64)>,
+    /// Partition keys whose run has ended and whose batch should be flushed.
+    retired: VecDeque<S3PartitionKey>,
+}
+
+struct SplitOnChange {
+    template: Template,
+    state: Arc<Mutex<SplitState>>,
+}
+
+impl SplitOnChange {
+    fn new(template: Template) -> Self {
+        Self {
+            template,
+            state: Arc::default(),
+        }
+    }
+
+    /// Returns the run the event belongs to within the given partition, starting a new run if the
+    /// rendered value differs from that of the previous event.
+    fn run(&self, key: &S3PartitionKey, item: &Event) -> u64 {
+        // Events which fail to render are treated as having an empty value, so that they are
+        // grouped together rather than dropped.
+        let value = self.template.render_string(item).unwrap_or_else(|error| {
+            emit!(TemplateRenderingError {
+                error,
+                field: Some("split_on_change"),
+                drop_event: false,
+            });
+            String::new()
+        });
+
+        let mut state = self.state.lock().expect("split state mutex poisoned");
+        let SplitState { runs, retired } = &mut *state;
+        match runs.get_mut(key) {
+            Some((previous, run)) if *previous != value => {
+                retired.push_back(S3PartitionKey {
+                    split_run: Some(*run),
+                    ..key.clone()
+                });
+                *previous = value;
+                *run += 1;
+                *run
+            }
+            Some((_, run)) => *run,
+            None => {
+                runs.insert(key.clone(), (value, 0));
+                0
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct EventCounts {
+    /// The number of events in the current batch of each partition.
+    counts: HashMap<Option<S3PartitionKey>, usize>,
+    /// Partition keys whose batch has timed out before reaching the minimum number of events.
+    held: HashSet<Option<S3PartitionKey>>,
+    /// Held partition keys whose batch has since reached the minimum number of events.
+    ready: VecDeque<Option<S3PartitionKey>>,
+}
+
+impl EventCounts {
+    fn forget(&mut self, key: &Option<S3PartitionKey>) {
+        self.counts.remove(key);
+        if self.held.remove(key) {
+            return;
+        }
+        // The batch may have been released by the event that has just been partitioned, but been
+        // closed in the meantime because that event did not fit in it.
+        self.ready.retain(|ready| ready != key);
+    }
+}
+
+struct MinEvents {
+    min_events: NonZeroUsize,
+    state: Arc<Mutex<EventCounts>>,
+}
+
+impl MinEvents {
+    fn new(min_events: NonZeroUsize) -> Self {
+        Self {
+            min_events,
+            state: Arc::default(),
+        }
+    }
+
+    /// Counts an event added to the batch of the given partition, releasing the batch if it was
+    /// held and has now reached the minimum number of events.
+    fn count(&self, key: &Option<S3PartitionKey>) {
+        let mut state = self.state.lock().expect("event counts mutex poisoned");
+        let count = state.counts.entry(key.clone()).or_default();
+        *count += 1;
+        if *count >= self.min_events.get() && state.held.remove(key) {
+            state.ready.push_back(key.clone());
+        }
+    }
+}
+
+/// The times at which the batches of each partition were started, oldest first.
+///
+/// A partition can have more than one started batch, as a batch overflowing into a new one is
+/// only flushed after the new one has been started.
+#[derive(Clone, Default)]
+pub struct BatchStarts(Arc<Mutex<HashMap<S3PartitionKey, VecDeque<Instant>>>>);
+
+impl BatchStarts {
+    fn record(&self, key: &S3PartitionKey) {
+        self.0
+            .lock()
+            .expect("batch starts mutex poisoned")
+            .entry(key.clone())
+            .or_default()
+            .push_back(Instant::now());
+    }
+
+    /// Takes the start time of the oldest batch of the given partition, once it has been flushed.
+    pub fn take(&self, key: &S3PartitionKey) -> Option<Instant> {
+        let mut starts = self.0.lock().expect("batch starts mutex poisoned");
+        let partition = starts.get_mut(key)?;
+        let started = partition.pop_front();
+        if partition.is_empty() {
+            starts.remove(key);
+        }
+        started
+    }
+}
+
+/// The partitions whose batch was flushed within the minimum write interval.
+struct WriteCooldowns {
+    expirations: ExpirationQueue<Option<S3PartitionKey>>,
+    cooling: HashSet<Option<S3PartitionKey>>,
+    /// Cooling partitions whose batch has timed out, and is flushed once the interval has passed.
+    waiting: HashSet<Option<S3PartitionKey>>,
+}
+
+impl WriteCooldowns {
+    fn new(interval: Duration) -> Self {
+        Self {
+            expirations: ExpirationQueue::new(interval),
+            cooling: HashSet::new(),
+            waiting: HashSet::new(),
+        }
+    }
+
+    /// Starts the interval of the given partition, whose batch has just been flushed.
+    fn start(&mut self, key: &Option<S3PartitionKey>) {
+        self.waiting.remove(key);
+        self.cooling.insert(key.clone());
+        self.expirations.insert(key.clone());
+    }
+
+    fn clear(&mut self) {
+        self.expirations.clear();
+        self.cooling.clear();
+        self.waiting.clear();
+    }
+}
+
+/// The warm-up period after startup, during which batches are not flushed for timing out.
+struct StartupWarmup {
+    sleep: Pin<Box<Sleep>>,
+    ended: bool,
+    /// The batches which timed out during the warm-up, and are flushed once it ends.
+    held: VecDeque<Option<S3PartitionKey>>,
+}
+
+impl StartupWarmup {
+    fn new(period: Duration) -> Self {
+        Self {
+            sleep: Box::pin(tokio::time::sleep(period)),
+            ended: false,
+            held: VecDeque::new(),
+        }
+    }
+}
+
+/// A `KeyedTimer` which expires batches after a timeout, or as soon as their run has ended.
+///
+/// When a minimum number of events is configured, batches which time out before reaching it are
+/// held until they do, or until they reach their maximum age. When a minimum write interval is
+/// configured, batches which time out within it of the previous flush of their partition are held
+/// until it has passed. When a startup warm-up is configured, batches which time out during it
+/// are held until it ends.
+pub struct S3PartitionTimer {
+    expirations: ExpirationQueue<Option<S3PartitionKey>>,
+    /// The expirations of batches reaching their maximum age.
+    deadlines: Option<ExpirationQueue<Option<S3PartitionKey>>>,
+    /// The keys of the batches currently tracked by the timer.
+    active: HashSet<Option<S3PartitionKey>>,
+    split_state: Option<Arc<Mutex<SplitState>>>,
+    min_events: Option<(usize, Arc<Mutex<EventCounts>>)>,
+    batch_starts: BatchStarts,
+    cooldowns: Option<WriteCooldowns>,
+    warmup: Option<StartupWarmup>,
+    boundary: Option<FlushBoundary>,
+    flush_requests: Option<FlushRequests>,
+    /// The batches active when the last flush was requested, which are left to be flushed.
+    requested: VecDeque<Option<S3PartitionKey>>,
+}
+
+/// Requests to flush all batches, regardless of their timeout.
+pub type FlushRequests = BoxStream<'static, ()>;
+
+/// Wall-clock boundaries at which all batches are flushed.
+struct FlushBoundary {
+    interval: Duration,
+    tz_offset: Option<FixedOffset>,
+    sleep: Pin<Box<Sleep>>,
+    /// The batches active when the last boundary passed, which are left to be flushed.
+    flushing: VecDeque<Option<S3PartitionKey>>,
+}
+
+impl FlushBoundary {
+    fn new(interval: Duration, tz_offset: Option<FixedOffset>) -> Self {
+        Self {
+            interval,
+            tz_offset,
+            sleep: Box::pin(tokio::time::sleep(until_boundary(interval, tz_offset))),
+            flushing: VecDeque::new(),
+        }
+    }
+}
+
+/// Returns the time until the next multiple of `interval` since midnight in the timezone of the
+/// given offset, or since the Unix epoch for intervals which don't divide a day.
+fn until_boundary(interval: Duration, tz_offset: Option<FixedOffset>) -> Duration {
+    let interval = interval.as_nanos().max(1);
+    let offset = tz_offset.map_or(0, |offset| {
+        i128::from(offset.local_minus_utc()) * 1_000_000_000
+    });
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as i128
+        + offset;
+    let elapsed = now.rem_euclid(interval as i128) as u128;
+    Duration::from_nanos((interval - elapsed) as u64)
+}
+
+impl S3PartitionTimer {
+    /// Returns the times at which batches were started, to be taken as they are flushed.
+    pub fn batch_starts(&self) -> BatchStarts {
+        self.batch_starts.clone()
+    }
+
+    fn poll_retired(&mut self) -> Option<Option<S3PartitionKey>> {
+        let key = {
+            let mut state = self
+                .split_state
+                .as_ref()?
+                .lock()
+                .expect("split state mutex poisoned");
+            // The batch of a retired run may already have been flushed for being full.
+            std::iter::from_fn(|| state.retired.pop_front())
+                .map(Some)
+                .find(|key| self.active.contains(key))?
+        };
+        self.untrack(&key);
+        Some(key)
+    }
+
+    fn poll_ready(&mut self) -> Option<Option<S3PartitionKey>> {
+        let key = {
+            let mut state = self
+                .min_events
+                .as_ref()?
+                .1
+                .lock()
+                .expect("event counts mutex poisoned");
+            std::iter::from_fn(|| state.ready.pop_front()).find(|key| self.active.contains(key))?
+        };
+        self.expire(&key);
+        Some(key)
+    }
+
+    /// Returns the next held batch whose partition has reached the end of its write interval.
+    fn poll_cooled(&mut self, cx: &mut Context) -> Option<Option<S3PartitionKey>> {
+        let cooldowns = self.cooldowns.as_mut()?;
+        let key = loop {
+            let Poll::Ready(Some(key)) = cooldowns.expirations.poll_expired(cx) else {
+                return None;
+            };
+            cooldowns.cooling.remove(&key);
+            if cooldowns.waiting.remove(&key) && self.active.contains(&key) {
+                break key;
+            }
+        };
+        self.expire(&key);
+        Some(key)
+    }
+
+    /// Returns the next held batch to flush once the startup warm-up has ended.
+    ///
+    /// Held batches are still subject to the minimum number of events and write interval.
+    fn poll_warmed(&mut self, cx: &mut Context) -> Option<Option<S3PartitionKey>> {
+        loop {
+            let warmup = self.warmup.as_mut()?;
+            if !warmup.ended {
+                if warmup.sleep.as_mut().poll(cx).is_pending() {
+                    return None;
+                }
+                warmup.ended = true;
+            }
+            let Some(key) = warmup.held.pop_front() else {
+                self.warmup = None;
+                return None;
+            };
+            if !self.active.contains(&key) {
+                continue;
+            }
+            if self.below_minimum(&key) {
+                self.hold(key);
+            } else if !self.hold_cooling(&key) {
+                self.expire(&key);
+                return Some(key);
+            }
+        }
+    }
+
+    /// Returns the next batch to flush for a flush boundary having passed.
+    fn poll_boundary(&mut self, cx: &mut Context) -> Option<Option<S3PartitionKey>> {
+        let boundary = self.boundary.as_mut()?;
+        if boundary.flushing.is_empty() && boundary.sleep.as_mut().poll(cx).is_ready() {
+            boundary.flushing.extend(self.active.iter().cloned());
+            let next = until_boundary(boundary.interval, boundary.tz_offset);
+            boundary
+                .sleep
+                .as_mut()
+                .reset(tokio::time::Instant::now() + next);
+            // Registers the task to be woken at the next boundary.
+            let _ = boundary.sleep.as_mut().poll(cx);
+        }
+        // Batches may have been flushed for being full since the boundary passed.
+        let key = std::iter::from_fn(|| boundary.flushing.pop_front())
+            .find(|key| self.active.contains(key))?;
+        self.expire(&key);
+        Some(key)
+    }
+
+    /// Returns the next batch to flush for a flush having been requested.
+    fn poll_requested(&mut self, cx: &mut Context) -> Option<Option<S3PartitionKey>> {
+        loop {
+            // Batches may have been flushed for being full since the flush was requested.
+            let key = std::iter::from_fn(|| self.requested.pop_front())
+                .find(|key| self.active.contains(key));
+            if let Some(key) = key {
+                self.expire(&key);
+                return Some(key);
+            }
+            match self.flush_requests.as_mut()?.poll_next_unpin(cx) {
+                Poll::Ready(Some(())) => {
+                    emit!(S3FlushRequested {
+                        batch_count: self.active.len(),
+                    });
+                    self.requested.extend(self.active.iter().cloned());
+                }
+                Poll::Ready(None) => {
+                    self.flush_requests = None;
+                    return None;
+                }
+                Poll::Pending => return None,
+            }
+        }
+    }
+
+    /// Returns whether the batch of the given key contains fewer than the minimum number of
+    /// events.
+    fn below_minimum(&self, key: &Option<S3PartitionKey>) -> bool {
+        self.min_events.as_ref().is_some_and(|(min_events, state)| {
+            state
+                .lock()
+                .expect("event counts mutex poisoned")
+                .counts
+                .get(key)
+                .is_some_and(|count| count < min_events)
+        })
+    }
+
+    /// Holds the batch of the given key if the startup warm-up has not ended, returning whether it
+    /// was held.
+    fn hold_warming(&mut self, key: &Option<S3PartitionKey>) -> bool {
+        match &mut self.warmup {
+            Some(warmup) if !warmup.ended => {
+                warmup.held.push_back(key.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Holds the batch of the given key if its partition is within its write interval, returning
+    /// whether it was held.
+    fn hold_cooling(&mut self, key: &Option<S3PartitionKey>) -> bool {
+        let Some(cooldowns) = &mut self.cooldowns else {
+            return false;
+        };
+        if !cooldowns.cooling.contains(key) {
+            return false;
+        }
+        cooldowns.waiting.insert(key.clone());
+        true
+    }
+
+    fn hold(&self, key: Option<S3PartitionKey>) {
+        if let Some((_, state)) = &self.min_events {
+            state
+                .lock()
+                .expect("event counts mutex poisoned")
+                .held
+                .insert(key);
+        }
+    }
+
+    /// Stops tracking the batch of the given key, once it has been flushed.
+    fn untrack(&mut self, key: &Option<S3PartitionKey>) {
+        if let Some(cooldowns) = &mut self.cooldowns {
+            cooldowns.start(key);
+        }
+        self.active.remove(key);
+        self.expirations.remove(key);
+        if let Some(deadlines) = &mut self.deadlines {
+            deadlines.remove(key);
+        }
+        if let Some((_, state)) = &self.min_events {
+            state
+                .lock()
+                .expect("event counts mutex poisoned")
+                .forget(key);
+        }
+    }
+
+    /// Stops tracking the batch of the given key once it has expired, along with its run.
+    fn expire(&mut self, key: &Option<S3PartitionKey>) {
+        self.untrack(key);
+        if let Some(key) = key {
+            self.forget_run(key);
+        }
+    }
+
+    fn forget_run(&self, key: &S3PartitionKey) {
+        if let (Some(state), Some(split_run)) = (&self.split_state, key.split_run) {
+            let mut state = state.lock().expect("split state mutex poisoned");
+            let base_key = S3PartitionKey {
+                split_run: None,
+                ..key.clone()
+            };
+            // Only forget the partition if no new run has been started in the meantime.
+            if state
+                .runs
+                .get(&base_key)
+                .is_some_and(|(_, run)| *run == split_run)
+            {
+                state.runs.remove(&base_key);
+            }
+        }
+    }
+}
+
+impl KeyedTimer<Option<S3PartitionKey>> for S3PartitionTimer {
+    fn clear(&mut self) {
+        self.expirations.clear();
+        if let Some(deadlines) = &mut self.deadlines {
+            deadlines.clear();
+        }
+        self.active.clear();
+        if let Some(cooldowns) = &mut self.cooldowns {
+            cooldowns.clear();
+        }
+        if let Some(warmup) = &mut self.warmup {
+            warmup.held.clear();
+        }
+        if let Some((_, state)) = &self.min_events {
+            *state.lock().expect("event counts mutex poisoned") = EventCounts::default();
+        }
+    }
+
+    fn insert(&mut self, item_key: Option<S3PartitionKey>) {
+        // A key is inserted whenever a new batch is started, which then only contains the event
+        // that was just partitioned.
+        if let Some((_, state)) = &self.min_events {
+            let mut state = state.lock().expect("event counts mutex poisoned");
+            state.forget(&item_key);
+            state.counts.insert(item_key.clone(), 1);
+        }
+        if let Some(deadlines) = &mut self.deadlines {
+            deadlines.insert(item_key.clone());
+        }
+        if let Some(key) = &item_key {
+            self.batch_starts.record(key);
+        }
+        self.active.insert(item_key.clone());
+        self.expirations.insert(item_key);
+    }
+
+    fn remove(&mut self, item_key: &Option<S3PartitionKey>) {
+        self.untrack(item_key);
+    }
+
+    // The batch of a run is flushed as soon as the run ends, rather than once the input is idle,
+    // as no more events are added to it.
+    fn take_immediate(&mut self) -> Option<Option<S3PartitionKey>> {
+        self.poll_retired()
+    }
+
+    fn poll_expired(&mut self, cx: &mut Context) -> Poll<Option<Option<S3PartitionKey>>> {
+        if let Some(key) = self
+            .poll_ready()
+            .or_else(|| self.poll_boundary(cx))
+            .or_else(|| self.poll_requested(cx))
+            .or_else(|| self.poll_cooled(cx))
+            .or_else(|| self.poll_warmed(cx))
+        {
+            return Poll::Ready(Some(key));
+        }
+
+        // The maximum age always takes precedence over the minimum number of events.
+        if let Some(deadlines) = &mut self.deadlines {
+            if let Poll::Ready(Some(key)) = deadlines.poll_expired(cx) {
+                if self.below_minimum(&key) {
+                    debug!(
+                        message = "Flushing batch below the minimum number of events as it reached its maximum age.",
+                        key_prefix = key.as_ref().map(|key| key.key_prefix.as_str()),
+                    );
+                }
+                self.expire(&key);
+                return Poll::Ready(Some(key));
+            }
+        }
+
+        loop {
+            match self.expirations.poll_expired(cx) {
+                // The batch remains active, and is expired once it reaches the minimum number of
+                // events or its maximum age.
+                Poll::Ready(Some(key)) if self.below_minimum(&key) => self.hold(key),
+                // The batch remains active, and is expired once the write interval of its
+                // partition has passed.
+                Poll::Ready(Some(key)) if self.hold_cooling(&key) => {}
+                // The batch remains active, and is expired once the startup warm-up has ended.
+                Poll::Ready(Some(key)) if self.hold_warming(&key) => {}
+                Poll::Ready(Some(key)) => {
+                    self.expire(&key);
+                    return Poll::Ready(Some(key));
+                }
+                expired => return expired,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vector_lib::stream::{BatcherSettings, PartitionedBatcher};
+
+    use super::*;
+    use crate::event::LogEvent;
+
+    fn session_event(session: &str) -> Event {
+        let mut log = LogEvent::from("message");
+        log.insert("session", session);
+        log.into()
+    }
+
+    #[test]
+    fn distinct_keys_ignore_split_runs() {
+        let partitioner =
+            S3KeyPartitioner::new(Template::try_from("{{ session }}/").unwrap(), None)
+                .with_split_on_change(Some(Template::try_from("{{ session }}").unwrap()))
+                .with_distinct_keys_window(DISTINCT_KEYS_WINDOW);
+        for session in ["a", "a", "b", "a", "b"] {
+            partitioner.partition(&session_event(session));
+        }
+
+        let distinct_keys = partitioner.distinct_keys.unwrap();
+        let state = distinct_keys.state.lock().unwrap();
+        assert_eq!(state.current.len(), 2);
+        assert_eq!(state.previous, 0);
+    }
+
+    #[test]
+    fn distinct_keys_roll_over_windows() {
+        let partitioner =
+            S3KeyPartitioner::new(Template::try_from("{{ session }}/").unwrap(), None)
+                .with_distinct_keys_window(Duration::ZERO);
+        for session in ["a", "b", "c"] {
+            partitioner.partition(&session_event(session));
+        }
+
+        let distinct_keys = partitioner.distinct_keys.unwrap();
+        let state = distinct_keys.state.lock().unwrap();
+        assert_eq!(state.current.len(), 1);
+        assert_eq!(state.previous, 1);
+    }
+
+    #[test]
+    fn key_prefix_fallbacks_are_tried_in_order() {
+        let partitioner =
+            S3KeyPartitioner::new(Template::try_from("service={{ service }}/").unwrap(), None)
+                .with_key_prefix_fallbacks(vec![
+                    Template::try_from("app={{ app }}/").unwrap(),
+                    Template::try_from("{{ host }}").unwrap(),
+                ])
+                .with_key_prefix_fallback(Some("default/".to_owned()));
+        let key_prefix = |fields: &[(&str, &str)]| {
+            let mut log = LogEvent::from("message");
+            for (field, value) in fields {
+                log.insert(*field, *value);
+            }
+            partitioner.partition(&log.into()).map(|key| key.key_prefix)
+        };
+
+        assert_eq!(
+            key_prefix(&[("service", "api"), ("app", "web")]),
+            Some("service=api/".to_owned())
+        );
+        assert_eq!(
+            key_prefix(&[("app", "web"), ("host", "a")]),
+            Some("app=web/".to_owned())
+        );
+        assert_eq!(key_prefix(&[("host", "a")]), Some("a".to_owned()));
+        assert_eq!(key_prefix(&[("host", "")]), Some("default/".to_owned()));
+    }
+
+    #[test]
+    fn split_on_change_starts_new_runs() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("prefix/").unwrap(), None)
+            .with_split_on_change(Some(Template::try_from("{{ session }}").unwrap()));
+
+        let runs = ["a", "a", "b", "b", "a"]
+            .into_iter()
+            .map(|session| {
+                partitioner
+                    .partition(&session_event(session))
+                    .unwrap()
+                    .split_run
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(runs, [Some(0), Some(0), Some(1), Some(1), Some(2)]);
+
+        let state = partitioner.split_on_change.unwrap().state;
+        let retired = state
+            .lock()
+            .unwrap()
+            .retired
+            .iter()
+            .map(|key| key.split_run)
+            .collect::<Vec<_>>();
+        assert_eq!(retired, [Some(0), Some(1)]);
+    }
+
+    #[tokio::test]
+    async fn split_on_change_flushes_ended_runs_under_sustained_input() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("prefix/").unwrap(), None)
+            .with_split_on_change(Some(Template::try_from("{{ session }}").unwrap()));
+        let timer = partitioner.timer(Duration::from_secs(300));
+        let settings = BatcherSettings::new(
+            Duration::from_secs(300),
+            NonZeroUsize::new(usize::MAX).unwrap(),
+            NonZeroUsize::new(100).unwrap(),
+        );
+        // The input never goes idle, so the timer is never polled for expired batches.
+        let input = futures::stream::iter([session_event("a"), session_event("a")])
+            .chain(futures::stream::repeat_with(|| session_event("b")));
+        let mut batcher = PartitionedBatcher::with_timer(input, partitioner, timer, || {
+            settings.as_byte_size_config()
+        });
+
+        let (key, batch) = batcher.next().await.unwrap();
+        assert_eq!(key.unwrap().split_run, Some(0));
+        assert_eq!(batch.len(), 2);
+        let (key, batch) = batcher.next().await.unwrap();
+        assert_eq!(key.unwrap().split_run, Some(1));
+        assert_eq!(batch.len(), 100);
+    }
+
+    fn poll_expired(timer: &mut S3PartitionTimer) -> Option<Option<S3PartitionKey>> {
+        let noop_waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&noop_waker);
+        match timer.poll_expired(&mut cx) {
+            Poll::Ready(Some(key)) => Some(key),
+            Poll::Ready(None) | Poll::Pending => None,
+        }
+    }
+
+    fn min_events_partitioner() -> S3KeyPartitioner {
+        S3KeyPartitioner::new(Template::try_from("prefix/").unwrap(), None)
+            .with_min_events(NonZeroUsize::new(3))
+            .with_max_age(Some(Duration::from_secs(10)))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn min_events_holds_batch_past_timeout() {
+        let partitioner = min_events_partitioner();
+        let mut timer = partitioner.timer(Duration::from_secs(1));
+
+        let key = partitioner.partition(&session_event("a"));
+        timer.insert(key.clone());
+        partitioner.partition(&session_event("a"));
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(poll_expired(&mut timer), None);
+
+        partitioner.partition(&session_event("a"));
+        assert_eq!(poll_expired(&mut timer), Some(key));
+        assert_eq!(poll_expired(&mut timer), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn max_age_flushes_batch_below_min_events() {
+        let partitioner = min_events_partitioner();
+        let mut timer = partitioner.timer(Duration::from_secs(1));
+
+        let key = partitioner.partition(&session_event("a"));
+        timer.insert(key.clone());
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(poll_expired(&mut timer), None);
+
+        tokio::time::advance(Duration::from_secs(9)).await;
+        assert_eq!(poll_expired(&mut timer), Some(key));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn batch_with_min_events_flushes_on_timeout() {
+        let partitioner = min_events_partitioner();
+        let mut timer = partitioner.timer(Duration::from_secs(1));
+
+        let key = partitioner.partition(&session_event("a"));
+        timer.insert(key.clone());
+        partitioner.partition(&session_event("a"));
+        partitioner.partition(&session_event("a"));
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(poll_expired(&mut timer), Some(key));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn min_write_interval_holds_hot_partition() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("prefix/").unwrap(), None)
+            .with_min_write_interval(Some(Duration::from_secs(10)));
+        let mut timer = partitioner.timer(Duration::from_secs(1));
+
+        let key = partitioner.partition(&session_event("a"));
+        timer.insert(key.clone());
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(poll_expired(&mut timer), Some(key.clone()));
+
+        timer.insert(key.clone());
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(poll_expired(&mut timer), None);
+
+        tokio::time::advance(Duration::from_secs(8)).await;
+        assert_eq!(poll_expired(&mut timer), Some(key));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn startup_warmup_holds_first_batches() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("prefix/").unwrap(), None)
+            .with_startup_warmup(Some(Duration::from_secs(10)));
+        let mut timer = partitioner.timer(Duration::from_secs(1));
+
+        let key = partitioner.partition(&session_event("a"));
+        timer.insert(key.clone());
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(poll_expired(&mut timer), None);
+
+        tokio::time::advance(Duration::from_secs(8)).await;
+        assert_eq!(poll_expired(&mut timer), Some(key.clone()));
+
+        timer.insert(key.clone());
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(poll_expired(&mut timer), Some(key));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flush_boundary_flushes_all_batches() {
+        let partitioner =
+            S3KeyPartitioner::new(Template::try_from("{{ session }}/").unwrap(), None)
+                .with_flush_boundary(Some(Duration::from_secs(3600)), None);
+        let mut timer = partitioner.timer(Duration::from_secs(7200));
+
+        let a = partitioner.partition(&session_event("a"));
+        let b = partitioner.partition(&session_event("b"));
+        timer.insert(a.clone());
+        timer.insert(b.clone());
+        assert_eq!(poll_expired(&mut timer), None);
+
+        tokio::time::advance(Duration::from_secs(3600)).await;
+        let mut flushed = [poll_expired(&mut timer), poll_expired(&mut timer)];
+        flushed.sort_by_key(|key| key.clone().flatten().map(|key| key.key_prefix));
+        assert_eq!(flushed, [Some(a), Some(b)]);
+        assert_eq!(poll_expired(&mut timer), None);
+    }
+
+    #[tokio::test]
+    async fn flush_requests_flush_all_batches() {
+        let (requests, receiver) = futures::channel::mpsc::unbounded();
+        let partitioner =
+            S3KeyPartitioner::new(Template::try_from("{{ session }}/").unwrap(), None)
+                .with_flush_requests(Some(receiver.boxed()));
+        let mut timer = partitioner.timer(Duration::from_secs(7200));
+
+        let a = partitioner.partition(&session_event("a"));
+        let b = partitioner.partition(&session_event("b"));
+        timer.insert(a.clone());
+        timer.insert(b.clone());
+        assert_eq!(poll_expired(&mut timer), None);
+
+        requests.unbounded_send(()).unwrap();
+        let mut flushed = [poll_expired(&mut timer), poll_expired(&mut timer)];
+        flushed.sort_by_key(|key| key.clone().flatten().map(|key| key.key_prefix));
+        assert_eq!(flushed, [Some(a.clone()), Some(b)]);
+        assert_eq!(poll_expired(&mut timer), None);
+
+        // Batching resumes as usual once flushed.
+        timer.insert(a.clone());
+        assert_eq!(poll_expired(&mut timer), None);
+        requests.unbounded_send(()).unwrap();
+        assert_eq!(poll_expired(&mut timer), Some(a));
+    }
+
+    #[test]
+    fn flush_boundaries_are_aligned_to_the_timezone() {
+        let hour = Duration::from_secs(3600);
+        let day = Duration::from_secs(86400);
+        assert!(until_boundary(hour, None) <= hour);
+
+        let offset = FixedOffset::east_opt(3600).unwrap();
+        let utc = until_boundary(day, None).as_secs_f64();
+        let local = until_boundary(day, Some(offset)).as_secs_f64();
+        // Local midnight comes an hour before midnight UTC.
+        assert!(((utc - local).rem_euclid(86400.0) - 3600.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn batch_starts_are_taken_oldest_first() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("prefix/").unwrap(), None);
+        let mut timer = partitioner.timer(Duration::from_secs(1));
+        let batch_starts = timer.batch_starts();
+        let key = partitioner.partition(&session_event("a"));
+
+        timer.insert(key.clone());
+        timer.insert(key.clone());
+        timer.insert(None);
+
+        let key = key.unwrap();
+        let first = batch_starts.take(&key).unwrap();
+        let second = batch_starts.take(&key).unwrap();
+        assert!(first <= second);
+        assert_eq!(batch_starts.take(&key), None);
+    }
+
+    #[test]
+    fn lowercase_keys_share_partition() {
+        let partitioner =
+            S3KeyPartitioner::new(Template::try_from("{{ session }}/").unwrap(), None)
+                .with_lowercase_keys(true);
+
+        let upper = partitioner.partition(&session_event("Logs")).unwrap();
+        let lower = partitioner.partition(&session_event("logs")).unwrap();
+        assert_eq!(upper.key_prefix, "logs/");
+        assert_eq!(upper, lower);
+    }
+
+    #[test]
+    fn missing_key_prefix_uses_fallback() {
+        let template = Template::try_from("{{ session }}/").unwrap();
+        let event = Event::from(LogEvent::from("message"));
+
+        let partitioner = S3KeyPartitioner::new(template.clone(), None);
+        assert_eq!(partitioner.partition(&event), None);
+
+        let partitioner = S3KeyPartitioner::new(template, None)
+            .with_key_prefix_fallback(Some("_unpartitioned/".to_owned()));
+        let key = partitioner.partition(&event).unwrap();
+        assert_eq!(key.key_prefix, "_unpartitioned/");
+        assert_eq!(
+            partitioner
+                .partition(&session_event("a"))
+                .unwrap()
+                .key_prefix,
+            "a/"
+        );
+    }
+
+    #[test]
+    fn prefix_mapping_normalizes_values() {
+        let mapping = PrefixMapping::new(
+            Template::try_from("{{ session }}").unwrap(),
+            HashMap::from([
+                ("ERROR".to_owned(), "error".to_owned()),
+                ("err".to_owned(), "error".to_owned()),
+                ("3".to_owned(), "error".to_owned()),
+            ]),
+            "other".to_owned(),
+        );
+        let partitioner = S3KeyPartitioner::new(Template::try_from("logs/").unwrap(), None)
+            .with_prefix_mapping(Some(mapping));
+        let key_prefix = |event| partitioner.partition(&event).unwrap().key_prefix;
+
+        assert_eq!(key_prefix(session_event("ERROR")), "error/logs/");
+        assert_eq!(key_prefix(session_event("err")), "error/logs/");
+        assert_eq!(key_prefix(session_event("3")), "error/logs/");
+        assert_eq!(key_prefix(session_event("info")), "other/logs/");
+        assert_eq!(
+            key_prefix(Event::from(LogEvent::from("message"))),
+            "other/logs/"
+        );
+    }
+
+    #[test]
+    fn lag_segments_follow_thresholds() {
+        let lag_segments = LagSegments::new(
+            Some(vrl::owned_value_path!("event_time")),
+            "ontime".to_owned(),
+            vec![
+                (Duration::from_secs(3600), "very_late".to_owned()),
+                (Duration::from_secs(300), "late".to_owned()),
+            ],
+        );
+        let now = Utc::now();
+        let segment = |lag_secs| {
+            let mut log = LogEvent::from("message");
+            log.insert("event_time", now - chrono::Duration::seconds(lag_secs));
+            lag_segments.segment(&Event::from(log), now).to_owned()
+        };
+
+        assert_eq!(segment(0), "ontime");
+        assert_eq!(segment(-60), "ontime");
+        assert_eq!(segment(300), "late");
+        assert_eq!(segment(3599), "late");
+        assert_eq!(segment(7200), "very_late");
+        assert_eq!(
+            lag_segments.segment(&Event::from(LogEvent::from("message")), now),
+            "ontime"
+        );
+    }
+
+    #[test]
+    fn range_buckets_label_numeric_values() {
+        let range_buckets = RangeBuckets::new(
+            vrl::owned_value_path!("latency_ms"),
+            vec![
+                (Some(0.0), Some(100.0), "0-100".to_owned()),
+                (Some(100.0), Some(500.0), "100-500".to_owned()),
+                (Some(500.0), None, "500+".to_owned()),
+            ],
+            "other".to_owned(),
+        );
+        let partitioner = S3KeyPartitioner::new(Template::try_from("logs/").unwrap(), None)
+            .with_range_buckets(Some(range_buckets));
+        let key_prefix = |latency: Value| {
+            let mut log = LogEvent::from("message");
+            log.insert("latency_ms", latency);
+            partitioner.partition(&log.into()).unwrap().key_prefix
+        };
+
+        assert_eq!(key_prefix(Value::from(0)), "0-100/logs/");
+        assert_eq!(key_prefix(Value::from(99.5)), "0-100/logs/");
+        assert_eq!(key_prefix(Value::from(100)), "100-500/logs/");
+        assert_eq!(key_prefix(Value::from(12_000)), "500+/logs/");
+        assert_eq!(key_prefix(Value::from(-1)), "other/logs/");
+        assert_eq!(key_prefix(Value::from("fast")), "other/logs/");
+    }
+
+    #[test]
+    fn hash_buckets_are_the_crc32_of_the_key() {
+        let hash_buckets = HashBuckets::new(
+            Template::try_from("{{ user }}").unwrap(),
+            NonZeroU32::new(16).unwrap(),
+        );
+        let partitioner = S3KeyPartitioner::new(Template::try_from("logs/").unwrap(), None)
+            .with_hash_buckets(Some(hash_buckets));
+        let key_prefix = |user: Option<&str>| {
+            let mut log = LogEvent::from("message");
+            if let Some(user) = user {
+                log.insert("user", user);
+            }
+            partitioner.partition(&log.into()).unwrap().key_prefix
+        };
+
+        // The CRC-32 of `123456789` is 0xCBF43926, which is 6 modulo 16.
+        assert_eq!(key_prefix(Some("123456789")), "bucket=06/logs/");
+        assert_eq!(key_prefix(Some("123456789")), key_prefix(Some("123456789")));
+        assert_eq!(key_prefix(None), "bucket=00/logs/");
+    }
+
+    #[test]
+    fn late_events_are_written_to_the_current_window() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("%H:%M/").unwrap(), None)
+            .with_event_time_windows(Some(EventTimeWindows::new(
+                None,
+                Duration::from_secs(600),
+                Duration::from_secs(300),
+                "_late/".to_owned(),
+                LateEvents::Redirect,
+            )));
+        let key_prefix = |time: &str| {
+            let mut log = LogEvent::from("message");
+            log.insert(
+                "timestamp",
+                DateTime::parse_from_rfc3339(time)
+                    .unwrap()
+                    .with_timezone(&Utc),
+            );
+            partitioner.partition(&Event::from(log)).unwrap().key_prefix
+        };
+
+        assert_eq!(key_prefix("2022-07-18T20:34:00Z"), "20:34/");
+        assert_eq!(key_prefix("2022-07-18T20:41:00Z"), "20:41/");
+        // The current window starts at 20:40, and the allowed lateness reaches back to 20:35.
+        assert_eq!(key_prefix("2022-07-18T20:36:00Z"), "20:40/");
+        assert_eq!(key_prefix("2022-07-18T20:34:00Z"), "_late/20:34/");
+    }
+
+    #[test]
+    fn late_events_follow_their_policy() {
+        let event = |time: &str| {
+            let mut log = LogEvent::from("message");
+            log.insert(
+                "timestamp",
+                DateTime::parse_from_rfc3339(time)
+                    .unwrap()
+                    .with_timezone(&Utc),
+            );
+            Event::from(log)
+        };
+        let key_prefix = |late_events| {
+            let partitioner = S3KeyPartitioner::new(Template::try_from("%H:%M/").unwrap(), None)
+                .with_event_time_windows(Some(EventTimeWindows::new(
+                    None,
+                    Duration::from_secs(600),
+                    Duration::ZERO,
+                    "_late/".to_owned(),
+                    late_events,
+                )));
+            partitioner.partition(&event("2022-07-18T20:41:00Z"));
+            partitioner
+                .partition(&event("2022-07-18T20:34:00Z"))
+                .map(|key| key.key_prefix)
+        };
+
+        assert_eq!(
+            key_prefix(LateEvents::Redirect),
+            Some("_late/20:34/".to_owned())
+        );
+        assert_eq!(key_prefix(LateEvents::Reopen), Some("20:34/".to_owned()));
+        assert_eq!(key_prefix(LateEvents::Drop), None);
+    }
+
+    #[test]
+    fn partition_case_applies_to_field_values_only() {
+        let partitioner =
+            S3KeyPartitioner::new(Template::try_from("Service={{ service }}/").unwrap(), None)
+                .with_partition_case(PartitionCase::Lower);
+        let mut log = LogEvent::from("message");
+        log.insert("service", "Auth");
+
+        assert_eq!(
+            partitioner.partition(&Event::from(log)).unwrap().key_prefix,
+            "Service=auth/"
+        );
+    }
+
+    #[test]
+    fn segment_normalizers_normalize_field_values() {
+        let partitioner = S3KeyPartitioner::new(
+            Template::try_from("country={{ geo.country }}/{{ session }}/").unwrap(),
+            None,
+        )
+        .with_segment_normalizers(HashMap::from([(
+            "geo.country".to_owned(),
+            SegmentNormalizer::new(
+                true,
+                ["US".to_owned(), "fr".to_owned()],
+                "unknown".to_owned(),
+            ),
+        )]));
+        let key_prefix = |country: Option<&str>| {
+            let mut log = LogEvent::from("message");
+            log.insert("session", "a");
+            if let Some(country) = country {
+                log.insert("geo.country", country);
+            }
+            partitioner.partition(&log.into()).unwrap().key_prefix
+        };
+
+        assert_eq!(key_prefix(Some("us")), "country=us/a/");
+        assert_eq!(key_prefix(Some(" US")), "country=us/a/");
+        assert_eq!(key_prefix(Some("FR")), "country=fr/a/");
+        assert_eq!(key_prefix(Some("usa")), "country=unknown/a/");
+        assert_eq!(key_prefix(None), "country=unknown/a/");
+        // Fields without a normalizer are still required.
+        let mut log = LogEvent::from("message");
+        log.insert("geo.country", "us");
+        assert_eq!(partitioner.partition(&log.into()), None);
+    }
+
+    #[test]
+    fn storage_class_template_partitions() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("prefix/").unwrap(), None)
+            .with_storage_class_template(Some(Template::try_from("{{ session }}").unwrap()));
+
+        let storage_class = |session| {
+            partitioner
+                .partition(&session_event(session))
+                .unwrap()
+                .storage_class
+        };
+        assert_eq!(
+            storage_class("INTELLIGENT_TIERING"),
+            Some(S3StorageClass::IntelligentTiering)
+        );
+        assert_eq!(
+            storage_class("DEEP_ARCHIVE"),
+            Some(S3StorageClass::DeepArchive)
+        );
+        assert_eq!(storage_class("ARCHIVE"), None);
+    }
+
+    #[test]
+    fn target_template_partitions() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("prefix/").unwrap(), None)
+            .with_target_template(Some(Template::try_from("{{ session }}").unwrap()));
+
+        let eu = partitioner.partition(&session_event("eu")).unwrap();
+        let us = partitioner.partition(&session_event("us")).unwrap();
+        assert_eq!(eu.key_prefix, us.key_prefix);
+        assert_eq!(eu.target.as_deref(), Some("eu"));
+        assert_ne!(eu, us);
+
+        let missing = Event::Log(LogEvent::from("message"));
+        assert_eq!(partitioner.partition(&missing), None);
+    }
+
+    #[test]
+    fn key_cache_reuses_rendered_keys() {
+        let template = Template::try_from("{{ session }}/").unwrap();
+        let uncached = S3KeyPartitioner::new(template.clone(), None)
+            .with_key_prefix_fallback(Some("_unpartitioned/".to_owned()));
+        let cached = S3KeyPartitioner::new(template, None)
+            .with_key_prefix_fallback(Some("_unpartitioned/".to_owned()))
+            .with_key_cache(NonZeroUsize::new(2));
+
+        let mut events = ["a", "a", "b", "a", "c", "a"].map(session_event).to_vec();
+        events.push(Event::from(LogEvent::from("message")));
+        for event in &events {
+            assert_eq!(cached.partition(event), uncached.partition(event));
+        }
+
+        // Keys which fell back are not cached, and the least recently used key was evicted.
+        let cache = cached.key_cache.unwrap();
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.len(), 2);
+        let sessions = entries
+            .iter()
+            .map(|(_, key)| key.key_prefix.split('/').next().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(sessions, ["a", "c"]);
+    }
+
+    #[test]
+    fn key_cache_depends_on_referenced_fields() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("%H/").unwrap(), None)
+            .with_storage_class_template(Some(Template::try_from("{{ class }}").unwrap()))
+            .with_prefix_mapping(Some(PrefixMapping::new(
+                Template::try_from("{{ level }}").unwrap(),
+                HashMap::new(),
+                "other".to_owned(),
+            )));
+
+        let dependencies = partitioner.key_dependencies();
+        assert_eq!(dependencies.fields, ["class", "level"]);
+        assert_eq!(dependencies.timestamp, Some(false));
+    }
+
+    #[test]
+    fn no_split_run_without_split_on_change() {
+        let partitioner = S3KeyPartitioner::new(Template::try_from("prefix/").unwrap(), None);
+
+        let key = partitioner.partition(&session_event("a")).unwrap();
+        assert_eq!(key.split_run, None);
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Limits the number of concurrent uploads under each key prefix, up to the last `/` of the key of
+/// the uploaded object, as S3 limits the request rate of each prefix.
+#[derive(Debug)]
+pub struct PrefixConcurrency {
+    limit: usize,
+    /// The semaphore of each prefix with uploads in flight or waiting.
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl PrefixConcurrency {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            semaphores: Mutex::default(),
+        }
+    }
+
+    /// Waits until an upload can start under the key prefix of the key, returning a permit which
+    /// holds on to it until dropped.
+    pub async fn acquire(&self, key: &str) -> OwnedSemaphorePermit {
+        let prefix = &key[..key.rfind('/').map_or(0, |index| index + 1)];
+        let semaphore = {
+            let mut semaphores = self
+                .semaphores
+                .lock()
+                .expect("prefix semaphores mutex poisoned");
+            // Permits and waiters hold on to the semaphore of their prefix, so the others are idle.
+            semaphores.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            Arc::clone(
+                semaphores
+                    .entry(prefix.to_owned())
+                    .or_insert_with(|| Arc::new(Semaphore::new(self.limit))),
+            )
+        };
+        semaphore
+            .acquire_owned()
+            .await
+            .expect("prefix semaphores are never closed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn uploads_are_limited_per_prefix() {
+        let concurrency = PrefixConcurrency::new(2);
+        let first = concurrency.acquire("logs/a/1.log").await;
+        let _second = concurrency.acquire("logs/a/2.log").await;
+        assert!(concurrency.acquire("logs/a/3.log").now_or_never().is_none());
+        assert!(concurrency.acquire("logs/b/1.log").now_or_never().is_some());
+
+        drop(first);
+        assert!(concurrency.acquire("logs/a/3.log").now_or_never().is_some());
+        // The idle prefix is forgotten.
+        assert_eq!(concurrency.semaphores.lock().unwrap().len(), 1);
+    }
+}
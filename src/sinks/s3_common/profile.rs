@@ -0,0 +1,118 @@
+use std::{num::NonZeroUsize, time::Instant};
+
+use vector_lib::codecs::encoding::Framer;
+
+use super::sink::ObjectPosition;
+use crate::{
+    internal_events::{S3StartupProfileCompleted, S3StartupProfileError},
+    sinks::{prelude::*, util::Compressor},
+};
+
+/// Profiles the encoding and compression of the first events received by the sink.
+///
+/// The sampled events are copies, so the events themselves are sent on without waiting for the
+/// sample to be complete.
+pub struct StartupProfile {
+    sample_size: NonZeroUsize,
+    encoder: (Transformer, Encoder<Framer>),
+    codec: String,
+    compression: Compression,
+}
+
+impl StartupProfile {
+    pub const fn new(
+        sample_size: NonZeroUsize,
+        encoder: (Transformer, Encoder<Framer>),
+        codec: String,
+        compression: Compression,
+    ) -> Self {
+        Self {
+            sample_size,
+            encoder,
+            codec,
+            compression,
+        }
+    }
+
+    /// Samples the first events of the input, profiling them on a blocking thread once the sample
+    /// is complete.
+    ///
+    /// No profile is reported if the input ends before the sample is complete.
+    pub fn sample(self, input: BoxStream<'_, Event>) -> BoxStream<'_, Event> {
+        let sample_size = self.sample_size.get();
+        let mut state = Some((self, Vec::with_capacity(sample_size)));
+        input
+            .map(move |event| {
+                if let Some((_, sample)) = state.as_mut() {
+                    let mut sampled = event.clone();
+                    // The sample must not hold up the acknowledgement of the events.
+                    drop(sampled.take_finalizers());
+                    sample.push(sampled);
+
+                    if sample.len() >= sample_size {
+                        let (profile, sample) = state.take().expect("state checked above");
+                        tokio::task::spawn_blocking(move || profile.run(sample));
+                    }
+                }
+                event
+            })
+            .boxed()
+    }
+
+    /// Encodes and compresses the events as a single object.
+    fn run(&self, events: Vec<Event>) {
+        let event_count = events.len();
+        let started = Instant::now();
+        let mut compressor = Compressor::from(self.compression);
+        let result = self
+            .encoder
+            .encode_input((events, ObjectPosition::STANDALONE), &mut compressor)
+            .and_then(|(uncompressed_byte_size, _)| {
+                Ok((uncompressed_byte_size, compressor.finish()?.len()))
+            });
+
+        match result {
+            Ok((uncompressed_byte_size, compressed_byte_size)) => {
+                emit!(S3StartupProfileCompleted {
+                    event_count,
+                    elapsed: started.elapsed(),
+                    uncompressed_byte_size,
+                    compressed_byte_size,
+                    codec: &self.codec,
+                    compression: self.compression,
+                })
+            }
+            Err(error) => emit!(S3StartupProfileError { error }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vector_lib::codecs::{JsonSerializerConfig, NewlineDelimitedEncoder};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn sample_passes_events_through() {
+        let profile = StartupProfile::new(
+            NonZeroUsize::new(2).unwrap(),
+            (
+                Transformer::default(),
+                Encoder::<Framer>::new(
+                    NewlineDelimitedEncoder::default().into(),
+                    JsonSerializerConfig::default().build().into(),
+                ),
+            ),
+            "json".to_owned(),
+            Compression::gzip_default(),
+        );
+        let events = ["a", "b", "c"]
+            .map(|message| Event::Log(LogEvent::from(message)))
+            .to_vec();
+
+        let input = futures::stream::iter(events.clone()).boxed();
+        let output = profile.sample(input).collect::<Vec<_>>().await;
+        assert_eq!(output, events);
+    }
+}
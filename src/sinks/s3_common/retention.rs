@@ -0,0 +1,189 @@
+use aws_sdk_s3::{
+    config::Region,
+    types::{Delete, ObjectIdentifier},
+    Client as S3Client,
+};
+
+/// The maximum number of objects deleted by a single `DeleteObjects` request.
+const MAX_DELETE_BATCH: usize = 1000;
+
+/// An object listed under the key prefix of an uploaded object.
+#[derive(Clone, Debug)]
+struct ListedObject {
+    key: String,
+    /// The seconds and nanoseconds of the time the object was last modified.
+    last_modified: (i64, u32),
+    byte_size: u64,
+}
+
+/// Bounds the number and total size of the objects kept under the key prefix of each uploaded
+/// object, deleting the oldest of them once a new object is uploaded.
+///
+/// Only the objects directly under the prefix, up to the last `/` of the key of the uploaded
+/// object, are counted, and the uploaded object itself is never deleted.
+#[derive(Clone, Debug)]
+pub struct Retention {
+    max_objects: Option<usize>,
+    max_bytes: Option<u64>,
+}
+
+impl Retention {
+    pub const fn new(max_objects: Option<usize>, max_bytes: Option<u64>) -> Self {
+        Self {
+            max_objects,
+            max_bytes,
+        }
+    }
+
+    /// Deletes the oldest objects under the key prefix of the uploaded object until they are
+    /// within the limits, returning the number of objects deleted.
+    pub async fn enforce(
+        &self,
+        client: &S3Client,
+        bucket: &str,
+        key: &str,
+        region: Option<Region>,
+    ) -> crate::Result<usize> {
+        let prefix = &key[..key.rfind('/').map_or(0, |index| index + 1)];
+        let config_override = || {
+            region
+                .clone()
+                .map(|region| aws_sdk_s3::config::Builder::default().region(region))
+        };
+
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let mut request = client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(prefix)
+                .delimiter("/")
+                .set_continuation_token(continuation_token.take())
+                .customize();
+            if let Some(config_override) = config_override() {
+                request = request.config_override(config_override);
+            }
+            let output = request.send().await?;
+            objects.extend(
+                output
+                    .contents
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|object| {
+                        Some(ListedObject {
+                            key: object.key?,
+                            last_modified: object
+                                .last_modified
+                                .map(|time| (time.secs(), time.subsec_nanos()))
+                                .unwrap_or_default(),
+                            byte_size: object.size.unwrap_or_default().max(0) as u64,
+                        })
+                    }),
+            );
+            match output.next_continuation_token {
+                Some(token) if output.is_truncated == Some(true) => {
+                    continuation_token = Some(token)
+                }
+                _ => break,
+            }
+        }
+
+        let expired = self.expired(objects, key);
+        for keys in expired.chunks(MAX_DELETE_BATCH) {
+            let objects = keys
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<Vec<_>, _>>()?;
+            let delete = Delete::builder()
+                .set_objects(Some(objects))
+                .quiet(true)
+                .build()?;
+            let mut request = client
+                .delete_objects()
+                .bucket(bucket)
+                .delete(delete)
+                .customize();
+            if let Some(config_override) = config_override() {
+                request = request.config_override(config_override);
+            }
+            let output = request.send().await?;
+            if let Some(error) = output.errors.unwrap_or_default().into_iter().next() {
+                return Err(format!(
+                    "Failed to delete object {:?}: {}",
+                    error.key.unwrap_or_default(),
+                    error.message.unwrap_or_default()
+                )
+                .into());
+            }
+        }
+        Ok(expired.len())
+    }
+
+    /// Returns the keys of the oldest objects to delete for the newest ones to be within the
+    /// limits, keeping the uploaded object regardless.
+    fn expired(&self, mut objects: Vec<ListedObject>, key: &str) -> Vec<String> {
+        objects.sort_by(|a, b| {
+            b.last_modified
+                .cmp(&a.last_modified)
+                .then_with(|| b.key.cmp(&a.key))
+        });
+        let (mut count, mut byte_size) = (0, 0);
+        objects
+            .into_iter()
+            .filter(|object| {
+                count += 1;
+                byte_size += object.byte_size;
+                object.key != key
+                    && (self
+                        .max_objects
+                        .is_some_and(|max_objects| count > max_objects)
+                        || self
+                            .max_bytes
+                            .is_some_and(|max_bytes| byte_size > max_bytes))
+            })
+            .map(|object| object.key)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(key: &str, secs: i64, byte_size: u64) -> ListedObject {
+        ListedObject {
+            key: key.to_owned(),
+            last_modified: (secs, 0),
+            byte_size,
+        }
+    }
+
+    #[test]
+    fn oldest_objects_beyond_limits_expire() {
+        let objects = vec![
+            object("logs/a.log", 1, 10),
+            object("logs/c.log", 3, 10),
+            object("logs/b.log", 2, 10),
+            object("logs/d.log", 4, 10),
+        ];
+
+        let retention = Retention::new(Some(2), None);
+        let mut expired = retention.expired(objects.clone(), "logs/d.log");
+        expired.sort();
+        assert_eq!(expired, ["logs/a.log", "logs/b.log"]);
+
+        let retention = Retention::new(Some(3), Some(25));
+        assert_eq!(
+            retention.expired(objects.clone(), "logs/d.log"),
+            ["logs/b.log", "logs/a.log"]
+        );
+
+        // The uploaded object is kept even if it alone exceeds the limits.
+        let retention = Retention::new(None, Some(5));
+        assert_eq!(
+            retention.expired(objects, "logs/c.log"),
+            ["logs/d.log", "logs/b.log", "logs/a.log"]
+        );
+    }
+}
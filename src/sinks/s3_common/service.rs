@@ -1,22 +1,112 @@
-use std::task::{Context, Poll};
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::Write,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
 
-use aws_sdk_s3::operation::put_object::PutObjectError;
+use aws_runtime::auth::SigV4OperationSigningConfig;
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::operation::{
+    create_multipart_upload::builders::CreateMultipartUploadFluentBuilder,
+    put_object::PutObjectError,
+};
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
 use aws_sdk_s3::Client as S3Client;
+use aws_sigv4::http_request::SignableBody;
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::interceptors::{
+    context::BeforeTransmitInterceptorContextMut, Intercept,
+};
 use aws_smithy_runtime_api::client::orchestrator::HttpResponse;
 use aws_smithy_runtime_api::client::result::SdkError;
-use aws_smithy_types::byte_stream::ByteStream;
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use aws_smithy_types::{byte_stream::ByteStream, config_bag::ConfigBag, DateTime as AwsDateTime};
 use base64::prelude::{Engine as _, BASE64_STANDARD};
 use bytes::Bytes;
-use futures::future::BoxFuture;
+use chrono::{DateTime, Utc};
+use futures::future::{self, BoxFuture};
 use md5::Digest;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Serialize;
 use tower::Service;
 use tracing::Instrument;
 use vector_lib::event::{EventFinalizers, EventStatus, Finalizable};
 use vector_lib::request_metadata::{GroupedCountByteSize, MetaDescriptive, RequestMetadata};
 use vector_lib::stream::DriverResponse;
 
-use super::config::S3Options;
+use super::config::{bucket_region, S3Options, S3ServerSideEncryption};
+use super::generation::Generation;
+use super::lineage::SourceRange;
 use super::partitioner::S3PartitionKey;
+use super::prefix_concurrency::PrefixConcurrency;
+use super::retention::Retention;
+use super::sink::MAX_REQUEST_BYTES;
+use super::staging::{StagedObject, StagingDir};
+use super::summary::WindowSummary;
+use super::verification::UploadVerification;
+use crate::{
+    aws::is_retriable_error,
+    internal_events::{
+        S3BucketRegionDetected, S3CompanionObjectError, S3MultipartPartRetried,
+        S3MultipartPartSizeIncreased, S3ObjectUploaded, S3ObjectVersionCreated,
+        S3OrphanUploadError, S3OrphanUploaded, S3RetentionError, S3RetentionObjectsDeleted,
+        S3StagingError, S3UploadVerificationFailed, S3WrongRegionError,
+    },
+    sinks::util::{retries::ExponentialBackoff, Compression, Compressor},
+};
+
+/// The characters of object keys to percent-encode in the source of copy requests.
+const COPY_SOURCE_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Sends the request, to the given region rather than the region of the client if set, and
+/// without signing its payload if set.
+macro_rules! send {
+    ($request:expr, $region:expr) => {
+        send!($request, $region, false)
+    };
+    ($request:expr, $region:expr, $unsigned_payload:expr) => {{
+        let mut request = $request.customize();
+        if let Some(region) = $region {
+            request =
+                request.config_override(aws_sdk_s3::config::Builder::default().region(region));
+        }
+        if $unsigned_payload {
+            request = request.interceptor(UnsignedPayloadInterceptor);
+        }
+        request.send().in_current_span().await
+    }};
+}
+
+/// Signs requests with `UNSIGNED-PAYLOAD` in place of the hash of their payload.
+#[derive(Debug)]
+struct UnsignedPayloadInterceptor;
+
+impl Intercept for UnsignedPayloadInterceptor {
+    fn name(&self) -> &'static str {
+        "UnsignedPayloadInterceptor"
+    }
+
+    fn modify_before_signing(
+        &self,
+        _context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(mut config) = cfg.load::<SigV4OperationSigningConfig>().cloned() {
+            config.signing_options.payload_override = Some(SignableBody::UnsignedPayload);
+            cfg.interceptor_state().store_put(config);
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct S3Request {
@@ -25,7 +115,67 @@ pub struct S3Request {
     pub metadata: S3Metadata,
     pub request_metadata: RequestMetadata,
     pub content_encoding: Option<&'static str>,
+    /// The date at which the object can no longer be cached, computed when the request is built.
+    pub expires: Option<AwsDateTime>,
     pub options: S3Options,
+    /// The region of the bucket, when it differs from the region of the client.
+    pub region: Option<Region>,
+}
+
+impl S3Request {
+    /// Rebuilds the request of an object written to a staging or spill directory.
+    pub fn from_staged(object: StagedObject, body: Bytes) -> Self {
+        Self {
+            body,
+            bucket: object.bucket,
+            metadata: S3Metadata {
+                partition_key: S3PartitionKey {
+                    key_prefix: String::new(),
+                    ssekms_key_id: None,
+                    storage_class: None,
+                    target: None,
+                    split_run: None,
+                },
+                s3_key: object.key,
+                finalizers: EventFinalizers::default(),
+                object_metadata: None,
+                bloom_filter: None,
+                gzip_index: None,
+                key_error: None,
+                batch_started: None,
+                latest_event_timestamp: None,
+                event_time_range: None,
+                summary: None,
+                sequence: None,
+            },
+            request_metadata: RequestMetadata::default(),
+            content_encoding: None,
+            expires: object.expires.map(AwsDateTime::from_secs),
+            options: object.options,
+            region: object.region.map(Region::new),
+        }
+    }
+
+    /// Describes the object for a staging or spill directory.
+    ///
+    /// Staged objects record the resolved content encoding, as the request is rebuilt from the
+    /// options alone when they are uploaded again.
+    pub fn staged_object(&self) -> StagedObject {
+        StagedObject {
+            bucket: self.bucket.clone(),
+            key: self.metadata.s3_key.clone(),
+            expires: self.expires.map(|expires| expires.secs()),
+            region: self.region.as_ref().map(ToString::to_string),
+            options: S3Options {
+                content_encoding: self
+                    .options
+                    .content_encoding
+                    .clone()
+                    .or_else(|| self.content_encoding.map(ToString::to_string)),
+                ..self.options.clone()
+            },
+        }
+    }
 }
 
 impl Finalizable for S3Request {
@@ -49,6 +199,52 @@ pub struct S3Metadata {
     pub partition_key: S3PartitionKey,
     pub s3_key: String,
     pub finalizers: EventFinalizers,
+    pub object_metadata: Option<S3ObjectMetadata>,
+    /// The body of the companion `<key>.bloom` object, written once the object is uploaded.
+    pub bloom_filter: Option<Bytes>,
+    /// The body of the companion `<key>.gzi` index of the BGZF blocks of the object, written once
+    /// the object is uploaded.
+    pub gzip_index: Option<Bytes>,
+    /// The reason the object key is invalid, in which case the object is rejected without being
+    /// uploaded.
+    pub key_error: Option<String>,
+    /// When the first event of the object entered the batcher.
+    pub batch_started: Option<Instant>,
+    /// The latest timestamp of the events of the object, when its filename or the detection of
+    /// clock skew depend on it.
+    pub latest_event_timestamp: Option<DateTime<Utc>>,
+    /// The earliest and latest timestamps of the events of the object, when it is tagged with
+    /// them.
+    pub event_time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// The summary object of the window of the object, when summary objects are written.
+    pub summary: Option<WindowSummary>,
+    /// The sequence number of the object within the generation, when objects are written with
+    /// ordering metadata.
+    pub sequence: Option<u64>,
+}
+
+impl Finalizable for S3Metadata {
+    fn take_finalizers(&mut self) -> EventFinalizers {
+        std::mem::take(&mut self.finalizers)
+    }
+}
+
+/// Statistics about an object, written as a companion `<key>.meta.json` object once the object
+/// itself has been uploaded.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct S3ObjectMetadata {
+    pub event_count: usize,
+    pub uncompressed_byte_size: usize,
+    pub compressed_byte_size: usize,
+    pub min_timestamp: Option<DateTime<Utc>>,
+    pub max_timestamp: Option<DateTime<Utc>>,
+    pub key_prefix: String,
+    pub codec: String,
+    pub etag: Option<String>,
+    pub version_id: Option<String>,
+    /// The range of source offsets of the events of each source partition, when written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<Vec<SourceRange>>,
 }
 
 #[derive(Debug)]
@@ -56,6 +252,12 @@ pub struct S3Response {
     events_byte_size: GroupedCountByteSize,
 }
 
+impl S3Response {
+    pub const fn new(events_byte_size: GroupedCountByteSize) -> Self {
+        Self { events_byte_size }
+    }
+}
+
 impl DriverResponse for S3Response {
     fn event_status(&self) -> EventStatus {
         EventStatus::Delivered
@@ -75,16 +277,190 @@ impl DriverResponse for S3Response {
 #[derive(Clone)]
 pub struct S3Service {
     client: S3Client,
+    staging_dir: Option<StagingDir>,
+    upload_slo: Option<Duration>,
+    /// The region of the bucket once detected, when requests are redirected to it automatically.
+    bucket_region: Option<Arc<Mutex<Option<Region>>>>,
+    /// The prefix objects are uploaded under until they are committed to their key.
+    uncommitted_prefix: Option<String>,
+    /// The suffix objects are uploaded with until they are committed to their key.
+    uncommitted_suffix: Option<String>,
+    unsigned_payload: bool,
+    log_object_versions: bool,
+    /// The compression of the companion metadata and bloom filter objects.
+    sidecar_compression: Compression,
+    upload_verification: Option<Arc<UploadVerification>>,
+    /// The size above which objects are uploaded with a multipart upload.
+    multipart_threshold: Option<usize>,
+    /// The size of the parts of multipart uploads.
+    multipart_part_size: usize,
+    /// The user-defined metadata identifying the build of Vector, set on each object.
+    build_metadata: Option<HashMap<String, String>>,
+    retention: Option<Retention>,
+    prefix_concurrency: Option<Arc<PrefixConcurrency>>,
+    generation: Option<Arc<Generation>>,
 }
 
 impl S3Service {
     pub const fn new(client: S3Client) -> S3Service {
-        S3Service { client }
+        S3Service {
+            client,
+            staging_dir: None,
+            upload_slo: None,
+            bucket_region: None,
+            uncommitted_prefix: None,
+            uncommitted_suffix: None,
+            unsigned_payload: false,
+            log_object_versions: false,
+            sidecar_compression: Compression::None,
+            upload_verification: None,
+            multipart_threshold: None,
+            multipart_part_size: MULTIPART_PART_SIZE,
+            build_metadata: None,
+            retention: None,
+            prefix_concurrency: None,
+            generation: None,
+        }
+    }
+
+    /// Sends requests to the region of the bucket once S3 reports that it is in another region
+    /// than the client.
+    pub fn with_auto_region(mut self, auto_region: bool) -> Self {
+        self.bucket_region = auto_region.then(Arc::default);
+        self
+    }
+
+    /// Writes objects to the given directory before uploading them.
+    pub fn with_staging_dir(mut self, staging_dir: Option<StagingDir>) -> Self {
+        self.staging_dir = staging_dir;
+        self
+    }
+
+    /// Uploads objects under the given prefix, then copies them to their key once uploaded.
+    pub fn with_uncommitted_prefix(mut self, uncommitted_prefix: Option<String>) -> Self {
+        self.uncommitted_prefix = uncommitted_prefix;
+        self
+    }
+
+    /// Uploads objects with the given suffix, then copies them to their key once uploaded.
+    pub fn with_uncommitted_suffix(mut self, uncommitted_suffix: Option<String>) -> Self {
+        self.uncommitted_suffix = uncommitted_suffix;
+        self
+    }
+
+    /// Signs uploads without hashing their payload, such that it is not covered by the signature.
+    pub const fn with_unsigned_payload(mut self, unsigned_payload: bool) -> Self {
+        self.unsigned_payload = unsigned_payload;
+        self
+    }
+
+    /// Counts the objects uploaded later than the given time after their first event.
+    pub const fn with_upload_slo(mut self, upload_slo: Option<Duration>) -> Self {
+        self.upload_slo = upload_slo;
+        self
+    }
+
+    /// Logs the version ID of each object created on a versioned bucket once it is uploaded.
+    pub const fn with_log_object_versions(mut self, log_object_versions: bool) -> Self {
+        self.log_object_versions = log_object_versions;
+        self
+    }
+
+    /// Sets the `vector-version` and `vector-build` user-defined metadata of each object to the
+    /// version and Git commit of the build of Vector uploading it.
+    pub fn with_build_metadata(mut self, build_metadata: bool) -> Self {
+        self.build_metadata = build_metadata.then(|| {
+            HashMap::from([
+                (
+                    "vector-version".to_owned(),
+                    crate::vector_version().to_string(),
+                ),
+                (
+                    "vector-build".to_owned(),
+                    crate::built_info::GIT_SHORT_HASH.to_owned(),
+                ),
+            ])
+        });
+        self
+    }
+
+    /// Deletes the oldest objects under the key prefix of each uploaded object, once it is
+    /// uploaded, to keep them within the given limits.
+    pub fn with_retention(mut self, retention: Option<Retention>) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Uploads at most the given number of objects concurrently under each key prefix.
+    pub fn with_prefix_concurrency(mut self, limit: Option<usize>) -> Self {
+        self.prefix_concurrency = limit.map(|limit| Arc::new(PrefixConcurrency::new(limit)));
+        self
+    }
+
+    /// Writes the generation and sequence number of each object with a sequence number in its
+    /// metadata.
+    pub fn with_generation(mut self, generation: Option<Generation>) -> Self {
+        self.generation = generation.map(Arc::new);
+        self
+    }
+
+    /// Compresses the companion metadata and bloom filter objects of each object.
+    pub const fn with_sidecar_compression(mut self, sidecar_compression: Compression) -> Self {
+        self.sidecar_compression = sidecar_compression;
+        self
+    }
+
+    /// Uploads objects larger than the given number of bytes with a multipart upload, rather than
+    /// a single request.
+    pub const fn with_multipart_threshold(mut self, multipart_threshold: Option<usize>) -> Self {
+        self.multipart_threshold = multipart_threshold;
+        self
+    }
+
+    /// Uploads objects with a multipart upload in parts of the given number of bytes, rather than
+    /// 8 MiB.
+    pub fn with_multipart_part_size(mut self, multipart_part_size: Option<usize>) -> Self {
+        self.multipart_part_size = multipart_part_size.unwrap_or(MULTIPART_PART_SIZE);
+        self
+    }
+
+    /// Reads back a sample of the uploaded objects to verify that they match their upload.
+    pub fn with_upload_verification(
+        mut self,
+        upload_verification: Option<Arc<UploadVerification>>,
+    ) -> Self {
+        self.upload_verification = upload_verification;
+        self
     }
 
     pub fn client(&self) -> S3Client {
         self.client.clone()
     }
+
+    /// Uploads the objects left in the staging directory by a previous run.
+    ///
+    /// Objects which fail to upload are kept in the staging directory until the next run.
+    pub async fn upload_orphans(mut self) {
+        let Some(staging_dir) = self.staging_dir.clone() else {
+            return;
+        };
+        let orphans = match staging_dir.orphans().await {
+            Ok(orphans) => orphans,
+            Err(error) => {
+                emit!(S3StagingError { error });
+                return;
+            }
+        };
+
+        for (object, body) in orphans {
+            let request = S3Request::from_staged(object, body);
+            let key = request.metadata.s3_key.clone();
+            match self.call(request).await {
+                Ok(_) => emit!(S3OrphanUploaded { key: &key }),
+                Err(error) => emit!(S3OrphanUploadError { key: &key, error }),
+            }
+        }
+    }
 }
 
 impl Service<S3Request> for S3Service {
@@ -99,12 +475,64 @@ impl Service<S3Request> for S3Service {
 
     // Emission of internal events for errors and dropped events is handled upstream by the caller.
     fn call(&mut self, request: S3Request) -> Self::Future {
-        let options = request.options;
+        if let Some(error) = request.metadata.key_error {
+            return Box::pin(future::ready(Err(SdkError::construction_failure(error))));
+        }
+
+        let staging = self
+            .staging_dir
+            .clone()
+            .map(|staging_dir| (staging_dir, request.staged_object()));
+
+        let sidecar_compression = self.sidecar_compression;
+        let sidecar_suffix = sidecar_compression
+            .extension()
+            .strip_prefix("log")
+            .unwrap_or_default();
+        let object_metadata = request.metadata.object_metadata.map(|object_metadata| {
+            (
+                object_metadata,
+                request.bucket.clone(),
+                format!("{}.meta.json{}", request.metadata.s3_key, sidecar_suffix),
+                request.options.clone(),
+            )
+        });
+
+        let bloom_filter = request.metadata.bloom_filter.map(|bloom_filter| {
+            (
+                compress_sidecar(bloom_filter, sidecar_compression),
+                request.bucket.clone(),
+                format!("{}.bloom{}", request.metadata.s3_key, sidecar_suffix),
+                request.options.clone(),
+            )
+        });
+
+        let gzip_index = request.metadata.gzip_index.map(|gzip_index| {
+            (
+                gzip_index,
+                request.bucket.clone(),
+                format!("{}.gzi", request.metadata.s3_key),
+                request.options.clone(),
+            )
+        });
+
+        let summary = request.metadata.summary.and_then(|summary| {
+            Some((
+                summary.body?,
+                request.bucket.clone(),
+                summary.key,
+                request.options.clone(),
+            ))
+        });
+
+        let mut options = request.options;
 
         let content_encoding = request.content_encoding;
         let content_encoding = options
             .content_encoding
+            .take()
             .or_else(|| content_encoding.map(|ce| ce.to_string()));
+
         let content_type = options
             .content_type
             .or_else(|| Some("text/x-log".to_owned()));
@@ -123,34 +551,631 @@ impl Service<S3Request> for S3Service {
             .request_metadata
             .into_events_estimated_json_encoded_byte_size();
 
+        let expires = request.expires;
+        let mut user_metadata = self.build_metadata.clone();
+        let generation = self.generation.clone().zip(request.metadata.sequence);
+        let retention = self.retention.clone();
+        let prefix_concurrency = self.prefix_concurrency.clone();
         let client = self.client.clone();
+        let auto_region = self.bucket_region.clone();
+        let detected_region = auto_region
+            .as_ref()
+            .and_then(|region| region.lock().expect("bucket region mutex poisoned").clone());
+        let mut region = request.region.or_else(|| detected_region.clone());
+        let batch_started = request.metadata.batch_started;
+        let upload_slo = self.upload_slo;
+        let unsigned_payload = self.unsigned_payload;
+        let part_size = self.multipart_part_size;
+        let log_object_versions = self.log_object_versions;
+        // ETags of objects encrypted with AWS KMS are not their MD5 digest.
+        let kms_encrypted = matches!(
+            options.server_side_encryption,
+            Some(S3ServerSideEncryption::AwsKms)
+        );
+        let upload_verification = self
+            .upload_verification
+            .clone()
+            .filter(|upload_verification| upload_verification.sample());
+        let multipart = uses_multipart(request.body.len(), self.multipart_threshold);
+        let key = request.metadata.s3_key;
+        let upload_key = format!(
+            "{}{}{}",
+            self.uncommitted_prefix.as_deref().unwrap_or_default(),
+            key,
+            self.uncommitted_suffix.as_deref().unwrap_or_default()
+        );
 
         Box::pin(async move {
-            let request = client
-                .put_object()
-                .body(bytes_to_bytestream(request.body))
-                .bucket(request.bucket)
-                .key(request.metadata.s3_key)
-                .set_content_encoding(content_encoding)
-                .set_content_type(content_type)
-                .set_acl(options.acl.map(Into::into))
-                .set_grant_full_control(options.grant_full_control)
-                .set_grant_read(options.grant_read)
-                .set_grant_read_acp(options.grant_read_acp)
-                .set_grant_write_acp(options.grant_write_acp)
-                .set_server_side_encryption(options.server_side_encryption.map(Into::into))
-                .set_ssekms_key_id(options.ssekms_key_id)
-                .set_storage_class(Some(options.storage_class.into()))
-                .set_tagging(tagging)
-                .content_md5(content_md5);
-
-            let result = request.send().in_current_span().await;
-
-            result.map(|_| S3Response { events_byte_size })
+            let _permit = match &prefix_concurrency {
+                Some(prefix_concurrency) => Some(prefix_concurrency.acquire(&key).await),
+                None => None,
+            };
+
+            // The marker object is in the configured bucket, rather than in the bucket of the
+            // object, which may be routed to another region.
+            if let Some((generation, sequence)) = generation {
+                let generation = match generation.get(&client, detected_region).await {
+                    Ok(generation) => generation,
+                    Err(error) => match (bucket_region(&error), &auto_region) {
+                        (Some(detected), Some(auto_region)) => {
+                            emit!(S3BucketRegionDetected {
+                                bucket: generation.bucket(),
+                                region: detected,
+                            });
+                            let detected = Region::new(detected.to_owned());
+                            *auto_region.lock().expect("bucket region mutex poisoned") =
+                                Some(detected.clone());
+                            generation.get(&client, Some(detected)).await?
+                        }
+                        _ => return Err(error),
+                    },
+                };
+                user_metadata.get_or_insert_with(HashMap::new).extend([
+                    ("generation".to_owned(), generation.to_string()),
+                    ("seq".to_owned(), sequence.to_string()),
+                ]);
+            }
+
+            // Staging is best effort: objects are still uploaded if they cannot be staged.
+            let staged = match staging {
+                Some((staging_dir, object)) => staging_dir
+                    .stage(&object, &request.body)
+                    .await
+                    .map_err(|error| emit!(S3StagingError { error }))
+                    .ok(),
+                None => None,
+            };
+
+            let bucket = request.bucket;
+            let put_object = || {
+                client
+                    .put_object()
+                    .body(bytes_to_bytestream(request.body.clone()))
+                    .bucket(&bucket)
+                    .key(&upload_key)
+                    .set_content_encoding(content_encoding.clone())
+                    .set_content_type(content_type.clone())
+                    .set_cache_control(options.cache_control.clone())
+                    .set_expires(expires)
+                    .set_acl(options.acl.map(Into::into))
+                    .set_grant_full_control(options.grant_full_control.clone())
+                    .set_grant_read(options.grant_read.clone())
+                    .set_grant_read_acp(options.grant_read_acp.clone())
+                    .set_grant_write_acp(options.grant_write_acp.clone())
+                    .set_server_side_encryption(options.server_side_encryption.map(Into::into))
+                    .set_ssekms_key_id(options.ssekms_key_id.clone())
+                    .set_storage_class(Some(options.storage_class.into()))
+                    .set_tagging(tagging.clone())
+                    .set_metadata(user_metadata.clone())
+                    .content_md5(&content_md5)
+            };
+            let create_multipart_upload = || {
+                client
+                    .create_multipart_upload()
+                    .bucket(&bucket)
+                    .key(&upload_key)
+                    .set_content_encoding(content_encoding.clone())
+                    .set_content_type(content_type.clone())
+                    .set_cache_control(options.cache_control.clone())
+                    .set_expires(expires)
+                    .set_acl(options.acl.map(Into::into))
+                    .set_grant_full_control(options.grant_full_control.clone())
+                    .set_grant_read(options.grant_read.clone())
+                    .set_grant_read_acp(options.grant_read_acp.clone())
+                    .set_grant_write_acp(options.grant_write_acp.clone())
+                    .set_server_side_encryption(options.server_side_encryption.map(Into::into))
+                    .set_ssekms_key_id(options.ssekms_key_id.clone())
+                    .set_storage_class(Some(options.storage_class.into()))
+                    .set_tagging(tagging.clone())
+                    .set_metadata(user_metadata.clone())
+            };
+            let upload = |region: Option<Region>| -> BoxFuture<'_, Result<UploadOutput, _>> {
+                if multipart {
+                    Box::pin(upload_multipart(
+                        &client,
+                        create_multipart_upload(),
+                        &request.body,
+                        part_size,
+                        region,
+                        unsigned_payload,
+                    ))
+                } else {
+                    Box::pin(async move {
+                        send!(put_object(), region, unsigned_payload)
+                            .map(|output| (output.e_tag, output.version_id))
+                    })
+                }
+            };
+
+            let (e_tag, uploaded_version_id) = match upload(region.clone()).await {
+                Ok(output) => output,
+                Err(error) => match (bucket_region(&error), auto_region) {
+                    // Requests which raced with the region being detected use the new one when
+                    // they are retried.
+                    (Some(detected), Some(auto_region)) => {
+                        emit!(S3BucketRegionDetected {
+                            bucket: &bucket,
+                            region: detected,
+                        });
+                        let detected = Region::new(detected.to_owned());
+                        *auto_region.lock().expect("bucket region mutex poisoned") =
+                            Some(detected.clone());
+                        region = Some(detected);
+                        upload(region.clone()).await?
+                    }
+                    (Some(detected), None) => {
+                        emit!(S3WrongRegionError {
+                            bucket: &bucket,
+                            region: detected,
+                        });
+                        return Err(error);
+                    }
+                    (None, _) => return Err(error),
+                },
+            };
+
+            // Objects copied to their key are a new version of it, rather than the uploaded one.
+            let version_id = if upload_key != key {
+                commit(
+                    &client,
+                    &bucket,
+                    &upload_key,
+                    &key,
+                    &options,
+                    region.clone(),
+                )
+                .await?
+            } else {
+                uploaded_version_id
+            };
+
+            if let Some(upload_verification) = upload_verification {
+                let head_object = client
+                    .head_object()
+                    .bucket(&bucket)
+                    .key(&key)
+                    .set_version_id(version_id.clone());
+                let head = send!(head_object, region.clone()).map_err(|error| {
+                    error.map_service_error(|error| PutObjectError::generic(error.meta().clone()))
+                })?;
+                // ETags of multipart uploads are not the MD5 digest of the object either.
+                let e_tag = head
+                    .e_tag
+                    .as_deref()
+                    .filter(|_| !kms_encrypted && !multipart);
+                if let Some(mismatch) =
+                    upload_verification.verify(&request.body, head.content_length, e_tag)
+                {
+                    emit!(S3UploadVerificationFailed {
+                        bucket: &bucket,
+                        key: &key,
+                        reason: &mismatch.reason,
+                    });
+                    return Err(SdkError::construction_failure(mismatch));
+                }
+            }
+            if let Some(version_id) = version_id.as_deref().filter(|_| log_object_versions) {
+                emit!(S3ObjectVersionCreated {
+                    bucket: &bucket,
+                    key: &key,
+                    version_id,
+                });
+            }
+
+            if let Some(staged) = staged {
+                if let Err(error) = staged.remove().await {
+                    emit!(S3StagingError { error });
+                }
+            }
+
+            // The object is uploaded by now, so failing to write its companion objects doesn't fail
+            // it, as retrying it would upload it again.
+            if let Some((mut object_metadata, bucket, key, options)) = object_metadata {
+                object_metadata.etag = e_tag;
+                object_metadata.version_id = version_id;
+                let body = serde_json::to_vec(&object_metadata)
+                    .expect("object metadata should always serialize");
+                let body = compress_sidecar(body.into(), sidecar_compression);
+
+                let request = client
+                    .put_object()
+                    .body(bytes_to_bytestream(body))
+                    .bucket(bucket)
+                    .key(&key)
+                    .content_type("application/json")
+                    .set_content_encoding(sidecar_compression.content_encoding().map(Into::into))
+                    .set_acl(options.acl.map(Into::into))
+                    .set_server_side_encryption(options.server_side_encryption.map(Into::into))
+                    .set_ssekms_key_id(options.ssekms_key_id)
+                    .set_storage_class(Some(options.storage_class.into()));
+                if let Err(error) = send!(request, region.clone(), unsigned_payload) {
+                    emit!(S3CompanionObjectError { key: &key, error });
+                }
+            }
+
+            if let Some((body, bucket, key, options)) = bloom_filter {
+                let request = client
+                    .put_object()
+                    .body(bytes_to_bytestream(body))
+                    .bucket(bucket)
+                    .key(&key)
+                    .content_type("application/json")
+                    .set_content_encoding(sidecar_compression.content_encoding().map(Into::into))
+                    .set_acl(options.acl.map(Into::into))
+                    .set_server_side_encryption(options.server_side_encryption.map(Into::into))
+                    .set_ssekms_key_id(options.ssekms_key_id)
+                    .set_storage_class(Some(options.storage_class.into()));
+                if let Err(error) = send!(request, region.clone(), unsigned_payload) {
+                    emit!(S3CompanionObjectError { key: &key, error });
+                }
+            }
+
+            if let Some((body, bucket, key, options)) = gzip_index {
+                let request = client
+                    .put_object()
+                    .body(bytes_to_bytestream(body))
+                    .bucket(bucket)
+                    .key(&key)
+                    .content_type("application/octet-stream")
+                    .set_acl(options.acl.map(Into::into))
+                    .set_server_side_encryption(options.server_side_encryption.map(Into::into))
+                    .set_ssekms_key_id(options.ssekms_key_id)
+                    .set_storage_class(Some(options.storage_class.into()));
+                if let Err(error) = send!(request, region.clone(), unsigned_payload) {
+                    emit!(S3CompanionObjectError { key: &key, error });
+                }
+            }
+
+            if let Some((body, bucket, key, options)) = summary {
+                let request = client
+                    .put_object()
+                    .body(bytes_to_bytestream(body))
+                    .bucket(bucket)
+                    .key(&key)
+                    .content_type("application/json")
+                    .set_acl(options.acl.map(Into::into))
+                    .set_server_side_encryption(options.server_side_encryption.map(Into::into))
+                    .set_ssekms_key_id(options.ssekms_key_id)
+                    .set_storage_class(Some(options.storage_class.into()));
+                if let Err(error) = send!(request, region.clone(), unsigned_payload) {
+                    emit!(S3CompanionObjectError { key: &key, error });
+                }
+            }
+
+            // The object is uploaded by now, so failing to delete older objects doesn't fail it.
+            if let Some(retention) = retention {
+                match retention.enforce(&client, &bucket, &key, region).await {
+                    Ok(0) => (),
+                    Ok(count) => emit!(S3RetentionObjectsDeleted { key: &key, count }),
+                    Err(error) => emit!(S3RetentionError { key: &key, error }),
+                }
+            }
+
+            if let Some(batch_started) = batch_started {
+                emit!(S3ObjectUploaded {
+                    latency: batch_started.elapsed(),
+                    upload_slo,
+                });
+            }
+
+            Ok(S3Response { events_byte_size })
         })
     }
 }
 
+/// The ETag and version ID of an uploaded object.
+type UploadOutput = (Option<String>, Option<String>);
+
+/// Returns whether a body of the given length is uploaded with a multipart upload, given the
+/// multipart threshold, if multipart uploads are enabled.
+///
+/// Bodies too large for a single request are always uploaded with a multipart upload when they are
+/// enabled, whatever the threshold.
+fn uses_multipart(body_len: usize, multipart_threshold: Option<usize>) -> bool {
+    multipart_threshold.is_some_and(|multipart_threshold| {
+        body_len > multipart_threshold || body_len as u64 > MAX_REQUEST_BYTES
+    })
+}
+
+/// The default size of the parts of multipart uploads, except for their last part.
+const MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// The number of times each part of a multipart upload is attempted before the upload fails.
+const MULTIPART_PART_ATTEMPTS: usize = 4;
+
+/// The maximum number of parts of a multipart upload.
+const MAX_MULTIPART_PARTS: usize = 10_000;
+
+/// Returns the size of the parts a body of the given length is uploaded in: the given part size,
+/// increased if needed for the body to fit in `MAX_MULTIPART_PARTS` parts.
+const fn multipart_part_size(body_len: usize, part_size: usize) -> usize {
+    let min_part_size = body_len.div_ceil(MAX_MULTIPART_PARTS);
+    if part_size < min_part_size {
+        min_part_size
+    } else {
+        part_size
+    }
+}
+
+/// Uploads the body with the given multipart upload, in parts of the given size or, if the body
+/// would take more than `MAX_MULTIPART_PARTS` parts, larger, returning the ETag and version ID of
+/// the object.
+///
+/// Parts failing with a retriable error are retried on their own, with backoff, up to
+/// `MULTIPART_PART_ATTEMPTS` times. The upload is aborted once any of its parts fails to upload,
+/// so that the uploaded parts are not left behind.
+async fn upload_multipart(
+    client: &S3Client,
+    create_multipart_upload: CreateMultipartUploadFluentBuilder,
+    body: &Bytes,
+    configured_part_size: usize,
+    region: Option<Region>,
+    unsigned_payload: bool,
+) -> Result<UploadOutput, SdkError<PutObjectError, HttpResponse>> {
+    let bucket = create_multipart_upload.get_bucket().clone();
+    let key = create_multipart_upload.get_key().clone();
+    let part_size = multipart_part_size(body.len(), configured_part_size);
+    if part_size != configured_part_size {
+        emit!(S3MultipartPartSizeIncreased {
+            key: key.as_deref().unwrap_or_default(),
+            part_size,
+            configured_part_size,
+        });
+    }
+    let output = send!(create_multipart_upload, region.clone()).map_err(|error| {
+        error.map_service_error(|error| PutObjectError::generic(error.meta().clone()))
+    })?;
+    let upload_id = output.upload_id;
+
+    let mut parts = Vec::new();
+    for (index, chunk) in body.chunks(part_size).enumerate() {
+        // Part numbers start at 1 and are bounded by `MAX_MULTIPART_PARTS`, so they fit.
+        let part_number = i32::try_from(index + 1).expect("part number out of range");
+        let chunk = body.slice_ref(chunk);
+        let content_md5 = BASE64_STANDARD.encode(md5::Md5::digest(&chunk));
+        let result = retry_part(
+            || {
+                let upload_part = client
+                    .upload_part()
+                    .set_bucket(bucket.clone())
+                    .set_key(key.clone())
+                    .set_upload_id(upload_id.clone())
+                    .part_number(part_number)
+                    .content_md5(&content_md5)
+                    .body(bytes_to_bytestream(chunk.clone()));
+                let region = region.clone();
+                async move { send!(upload_part, region, unsigned_payload) }
+            },
+            is_retriable_error,
+            |error| {
+                emit!(S3MultipartPartRetried {
+                    key: key.as_deref().unwrap_or_default(),
+                    part_number,
+                    error,
+                })
+            },
+        )
+        .await;
+        match result {
+            Ok(output) => parts.push(
+                CompletedPart::builder()
+                    .part_number(part_number)
+                    .set_e_tag(output.e_tag)
+                    .build(),
+            ),
+            Err(error) => {
+                let abort_multipart_upload = client
+                    .abort_multipart_upload()
+                    .set_bucket(bucket)
+                    .set_key(key)
+                    .set_upload_id(upload_id);
+                // Parts left behind by a failed abort can be cleaned up with a lifecycle rule.
+                let _ = send!(abort_multipart_upload, region);
+                return Err(
+                    error.map_service_error(|error| PutObjectError::generic(error.meta().clone()))
+                );
+            }
+        }
+    }
+
+    let complete_multipart_upload = client
+        .complete_multipart_upload()
+        .set_bucket(bucket)
+        .set_key(key)
+        .set_upload_id(upload_id)
+        .multipart_upload(
+            CompletedMultipartUpload::builder()
+                .set_parts(Some(parts))
+                .build(),
+        );
+    let output = send!(complete_multipart_upload, region).map_err(|error| {
+        error.map_service_error(|error| PutObjectError::generic(error.meta().clone()))
+    })?;
+    Ok((output.e_tag, output.version_id))
+}
+
+/// Attempts to upload a part of a multipart upload until it succeeds, fails with an error which
+/// is not retriable, or has been attempted `MULTIPART_PART_ATTEMPTS` times, with exponential
+/// backoff between attempts.
+async fn retry_part<T, E, F>(
+    mut upload_part: impl FnMut() -> F,
+    is_retriable: impl Fn(&E) -> bool,
+    mut on_retry: impl FnMut(&E),
+) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let mut backoff = ExponentialBackoff::from_millis(2)
+        .factor(250)
+        .max_delay(Duration::from_secs(10));
+    let mut attempt = 1;
+    loop {
+        match upload_part().await {
+            Err(error) if attempt < MULTIPART_PART_ATTEMPTS && is_retriable(&error) => {
+                on_retry(&error);
+                attempt += 1;
+                tokio::time::sleep(backoff.next().unwrap()).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Copies the uploaded object to its key, then deletes it from its uncommitted key, returning the
+/// version ID of the copy on versioned buckets.
+///
+/// Object metadata and tags are copied along with the object, but its ACL, encryption and storage
+/// class are set again.
+async fn commit(
+    client: &S3Client,
+    bucket: &str,
+    uncommitted_key: &str,
+    key: &str,
+    options: &S3Options,
+    region: Option<Region>,
+) -> Result<Option<String>, SdkError<PutObjectError, HttpResponse>> {
+    let copy_object = client
+        .copy_object()
+        .copy_source(copy_source(bucket, uncommitted_key))
+        .bucket(bucket)
+        .key(key)
+        .set_acl(options.acl.map(Into::into))
+        .set_grant_full_control(options.grant_full_control.clone())
+        .set_grant_read(options.grant_read.clone())
+        .set_grant_read_acp(options.grant_read_acp.clone())
+        .set_grant_write_acp(options.grant_write_acp.clone())
+        .set_server_side_encryption(options.server_side_encryption.map(Into::into))
+        .set_ssekms_key_id(options.ssekms_key_id.clone())
+        .set_storage_class(Some(options.storage_class.into()));
+    let output = send!(copy_object, region.clone()).map_err(|error| {
+        error.map_service_error(|error| PutObjectError::generic(error.meta().clone()))
+    })?;
+
+    let delete_object = client.delete_object().bucket(bucket).key(uncommitted_key);
+    send!(delete_object, region).map_err(|error| {
+        error.map_service_error(|error| PutObjectError::generic(error.meta().clone()))
+    })?;
+    Ok(output.version_id)
+}
+
+/// The source of a request copying the object from the bucket or access point ARN.
+///
+/// Objects of S3 on Outposts access points are copied from the outpost, rather than the access
+/// point itself.
+fn copy_source(bucket: &str, key: &str) -> String {
+    let key = utf8_percent_encode(key, COPY_SOURCE_ENCODE_SET);
+    if !bucket.starts_with("arn:") {
+        return format!("{}/{}", bucket, key);
+    }
+    let resource = match bucket.split_once(":outpost/") {
+        Some((prefix, outpost)) => {
+            let outpost_id = outpost.split('/').next().unwrap_or_default();
+            format!("{}:outpost/{}", prefix, outpost_id)
+        }
+        None => bucket.to_owned(),
+    };
+    format!("{}/object/{}", resource, key)
+}
+
+/// Compresses the body of a companion object.
+fn compress_sidecar(body: Bytes, compression: Compression) -> Bytes {
+    if !compression.is_compressed() {
+        return body;
+    }
+    let mut compressor = Compressor::from(compression);
+    compressor
+        .write_all(&body)
+        .expect("compressing to memory should not fail");
+    compressor
+        .finish()
+        .expect("compressing to memory should not fail")
+        .freeze()
+}
+
 fn bytes_to_bytestream(buf: Bytes) -> ByteStream {
     ByteStream::from(buf)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, time::Duration};
+
+    use tokio::time::Instant;
+
+    use super::{
+        multipart_part_size, retry_part, uses_multipart, MAX_MULTIPART_PARTS, MAX_REQUEST_BYTES,
+        MULTIPART_PART_ATTEMPTS, MULTIPART_PART_SIZE,
+    };
+
+    /// Retries a part whose attempts fail with the given errors, `true` being retriable, returning
+    /// the result, the number of attempts and retries, and the time spent backing off.
+    async fn retry_failing_part(errors: &[bool]) -> (Result<(), bool>, usize, usize, Duration) {
+        let attempts = Cell::new(0);
+        let retries = Cell::new(0);
+        let started = Instant::now();
+        let result = retry_part(
+            || {
+                let attempt = attempts.get();
+                attempts.set(attempt + 1);
+                async move { errors.get(attempt).map_or(Ok(()), |error| Err(*error)) }
+            },
+            |retriable| *retriable,
+            |_| retries.set(retries.get() + 1),
+        )
+        .await;
+        (result, attempts.get(), retries.get(), started.elapsed())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn parts_are_retried_with_backoff() {
+        let (result, attempts, retries, elapsed) = retry_failing_part(&[true, true]).await;
+        assert_eq!(result, Ok(()));
+        assert_eq!((attempts, retries), (3, 2));
+        assert_eq!(elapsed, Duration::from_millis(500 + 1000));
+
+        let (result, attempts, retries, _) = retry_failing_part(&[false]).await;
+        assert_eq!(result, Err(false));
+        assert_eq!((attempts, retries), (1, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn parts_fail_once_attempts_are_exhausted() {
+        let errors = [true; MULTIPART_PART_ATTEMPTS + 1];
+        let (result, attempts, retries, elapsed) = retry_failing_part(&errors).await;
+        assert_eq!(result, Err(true));
+        assert_eq!(attempts, MULTIPART_PART_ATTEMPTS);
+        assert_eq!(retries, MULTIPART_PART_ATTEMPTS - 1);
+        assert_eq!(elapsed, Duration::from_millis(500 + 1000 + 2000));
+    }
+
+    #[test]
+    fn large_objects_use_multipart_when_enabled() {
+        let max_request_bytes = MAX_REQUEST_BYTES as usize;
+        assert!(!uses_multipart(100, None));
+        assert!(!uses_multipart(max_request_bytes + 1, None));
+        assert!(!uses_multipart(100, Some(100)));
+        assert!(uses_multipart(101, Some(100)));
+        assert!(!uses_multipart(max_request_bytes, Some(usize::MAX)));
+        assert!(uses_multipart(max_request_bytes + 1, Some(usize::MAX)));
+    }
+
+    #[test]
+    fn multipart_parts_are_bounded() {
+        assert_eq!(
+            multipart_part_size(0, MULTIPART_PART_SIZE),
+            MULTIPART_PART_SIZE
+        );
+        assert_eq!(
+            multipart_part_size(
+                MULTIPART_PART_SIZE * MAX_MULTIPART_PARTS,
+                MULTIPART_PART_SIZE
+            ),
+            MULTIPART_PART_SIZE
+        );
+
+        let body_len = MULTIPART_PART_SIZE * MAX_MULTIPART_PARTS + 1;
+        let part_size = multipart_part_size(body_len, MULTIPART_PART_SIZE);
+        assert_eq!(part_size, MULTIPART_PART_SIZE + 1);
+        assert!(body_len.div_ceil(part_size) <= MAX_MULTIPART_PARTS);
+    }
+}
@@ -1,14 +1,153 @@
-use std::fmt;
+use std::{
+    collections::HashSet,
+    fmt, io,
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::Poll,
+    time::Instant,
+};
 
-use crate::sinks::prelude::*;
+use futures::stream;
+use tracing::{Instrument, Span};
+use vector_lib::codecs::encoding::Framer;
+use vector_lib::stream::PartitionedBatcher;
 
-use super::partitioner::{S3KeyPartitioner, S3PartitionKey};
+use crate::{
+    internal_events::{S3EventTooLarge, S3PersistentErrorsExceeded},
+    sinks::prelude::*,
+};
+
+use super::{
+    fail_fast::FailFast,
+    interleave::Interleave,
+    partitioner::{S3KeyPartitioner, S3PartitionKey},
+    profile::StartupProfile,
+};
+
+/// The largest object which can be uploaded with a single `PutObject` request.
+pub const MAX_REQUEST_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+
+/// The largest object which can be uploaded with a multipart upload.
+pub const MAX_MULTIPART_REQUEST_BYTES: u64 = 5 * 1024 * 1024 * 1024 * 1024;
+
+/// How many times their in-memory size batches are assumed to take at most once encoded.
+///
+/// Only batches which could exceed the maximum object size under this assumption are copied
+/// before being encoded, so that they can be split if they do.
+const MAX_ENCODED_SIZE_RATIO: u64 = 4;
+
+/// The position of an object within the objects written for a partition.
+///
+/// Unless objects are framed as a single stream, every object is both the first and the last.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ObjectPosition {
+    /// Whether this is the first object of the partition, and should contain the batch prefix.
+    pub first: bool,
+    /// Whether this is the last object of the partition, and should contain the batch suffix.
+    pub last: bool,
+}
+
+impl ObjectPosition {
+    pub const STANDALONE: Self = Self {
+        first: true,
+        last: true,
+    };
+}
+
+impl encoding::Encoder<(Vec<Event>, ObjectPosition)> for (Transformer, Encoder<Framer>) {
+    fn encode_input(
+        &self,
+        input: (Vec<Event>, ObjectPosition),
+        writer: &mut dyn io::Write,
+    ) -> io::Result<(usize, GroupedCountByteSize)> {
+        encode_object_events(self, input, writer, encoding::BatchFraming::default())
+    }
+}
+
+/// Encodes the events of an object, enclosed according to its position within its partition.
+pub fn encode_object_events(
+    encoder: &(Transformer, Encoder<Framer>),
+    (events, position): (Vec<Event>, ObjectPosition),
+    writer: &mut dyn io::Write,
+    framing: encoding::BatchFraming,
+) -> io::Result<(usize, GroupedCountByteSize)> {
+    // Objects following the first one continue the events of the previous object, so they
+    // start with the separator that would have followed its last event.
+    let batch_prefix = if position.first {
+        encoder.1.batch_prefix()
+    } else if events.is_empty() {
+        &[]
+    } else {
+        encoder.1.batch_separator()
+    };
+    let batch_suffix = if position.last {
+        encoder.1.batch_suffix()
+    } else {
+        &[]
+    };
+    encoding::encode_batch(encoder, events, writer, batch_prefix, batch_suffix, framing)
+}
+
+/// Tracks the position of objects when the objects of each partition are framed as a single
+/// stream.
+#[derive(Clone)]
+struct StreamFraming {
+    /// The partitions which have written their first object, but not their last one.
+    open: Arc<Mutex<HashSet<S3PartitionKey>>>,
+    input_ended: Arc<AtomicBool>,
+    write_trailers: bool,
+}
+
+impl StreamFraming {
+    fn position(&self, key: &S3PartitionKey) -> ObjectPosition {
+        let mut open = self.open.lock().expect("stream framing mutex poisoned");
+        let first = open.insert(key.clone());
+        // Once the input has ended, the batcher only flushes the remaining batch of each partition.
+        let last = self.input_ended.load(Ordering::Relaxed);
+        if last {
+            open.remove(key);
+        }
+        ObjectPosition { first, last }
+    }
+
+    /// Returns the empty objects closing the partitions which had no batch left to flush when the
+    /// input ended.
+    fn trailers(&self) -> Vec<(S3PartitionKey, Vec<Event>, ObjectPosition, Instant)> {
+        let mut open = self.open.lock().expect("stream framing mutex poisoned");
+        if !self.write_trailers {
+            open.clear();
+            return Vec::new();
+        }
+        open.drain()
+            .map(|key| {
+                let position = ObjectPosition {
+                    first: false,
+                    last: true,
+                };
+                (key, Vec::new(), position, Instant::now())
+            })
+            .collect()
+    }
+}
 
 pub struct S3Sink<Svc, RB> {
     service: Svc,
     request_builder: RB,
     partitioner: S3KeyPartitioner,
     batcher_settings: BatcherSettings,
+    stream_framing: Option<bool>,
+    startup_profile: Option<StartupProfile>,
+    orphan_uploads: Option<BoxFuture<'static, ()>>,
+    /// The tasks run alongside the sink, such as the heartbeat.
+    background_tasks: Vec<BoxFuture<'static, ()>>,
+    max_event_bytes: Option<NonZeroUsize>,
+    /// The size objects are split below, once encoded.
+    max_request_bytes: u64,
+    fail_fast: Option<Arc<FailFast>>,
 }
 
 impl<Svc, RB> S3Sink<Svc, RB> {
@@ -23,8 +162,79 @@ impl<Svc, RB> S3Sink<Svc, RB> {
             service,
             request_builder,
             batcher_settings,
+            stream_framing: None,
+            startup_profile: None,
+            orphan_uploads: None,
+            background_tasks: Vec::new(),
+            max_event_bytes: None,
+            max_request_bytes: MAX_REQUEST_BYTES,
+            fail_fast: None,
         }
     }
+
+    /// Frames the objects of each partition as a single stream, rather than each on their own.
+    ///
+    /// Only the first object of a partition contains the batch prefix, and only its last object,
+    /// flushed when the sink shuts down, contains the batch suffix. When `write_trailers` is set,
+    /// partitions with no batch left to flush on shutdown are closed with an empty object
+    /// containing only the batch suffix.
+    pub const fn with_stream_framing(mut self, write_trailers: bool) -> Self {
+        self.stream_framing = Some(write_trailers);
+        self
+    }
+
+    /// Profiles the encoding and compression of the first events, before sending them on as usual.
+    pub fn with_startup_profile(mut self, startup_profile: Option<StartupProfile>) -> Self {
+        self.startup_profile = startup_profile;
+        self
+    }
+
+    /// Uploads the objects left staged by a previous run before processing any events, such that
+    /// they are not mistaken for objects staged by this run.
+    pub fn with_orphan_uploads(mut self, orphan_uploads: BoxFuture<'static, ()>) -> Self {
+        self.orphan_uploads = Some(orphan_uploads);
+        self
+    }
+
+    /// Runs the heartbeat alongside the sink, from when it starts until it shuts down.
+    pub fn with_heartbeat(mut self, heartbeat: Option<BoxFuture<'static, ()>>) -> Self {
+        self.background_tasks.extend(heartbeat);
+        self
+    }
+
+    /// Writes EMF summaries of the activity of the sink alongside it, from when it starts until it
+    /// shuts down.
+    pub fn with_emf_summary(mut self, emf_summary: Option<BoxFuture<'static, ()>>) -> Self {
+        self.background_tasks.extend(emf_summary);
+        self
+    }
+
+    /// Uploads spilled objects again alongside the sink, from when it starts until it shuts down.
+    pub fn with_spill_recovery(mut self, spill_recovery: Option<BoxFuture<'static, ()>>) -> Self {
+        self.background_tasks.extend(spill_recovery);
+        self
+    }
+
+    /// Rejects events whose in-memory size exceeds the given number of bytes before they are
+    /// batched, rather than encoding them.
+    pub const fn with_max_event_bytes(mut self, max_event_bytes: Option<NonZeroUsize>) -> Self {
+        self.max_event_bytes = max_event_bytes;
+        self
+    }
+
+    /// Splits batches which encode to objects larger than the given number of bytes, rather than
+    /// `MAX_REQUEST_BYTES`, such as when large objects are uploaded with a multipart upload.
+    pub const fn with_max_request_bytes(mut self, max_request_bytes: u64) -> Self {
+        self.max_request_bytes = max_request_bytes;
+        self
+    }
+
+    /// Stops the sink with an error once the given `FailFast`, recording the outcome of requests
+    /// sent to the service, trips.
+    pub fn with_fail_fast(mut self, fail_fast: Option<Arc<FailFast>>) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
 }
 
 impl<Svc, RB> S3Sink<Svc, RB>
@@ -33,31 +243,126 @@ where
     Svc::Future: Send + 'static,
     Svc::Response: DriverResponse + Send + 'static,
     Svc::Error: fmt::Debug + Into<crate::Error> + Send,
-    RB: RequestBuilder<(S3PartitionKey, Vec<Event>)> + Send + Sync + 'static,
+    RB: RequestBuilder<(S3PartitionKey, Vec<Event>, ObjectPosition, Instant)>
+        + Send
+        + Sync
+        + 'static,
     RB::Error: fmt::Display + Send,
+    RB::Metadata: Finalizable,
     RB::Request: Finalizable + MetaDescriptive + Send,
 {
     async fn run_inner(self: Box<Self>, input: BoxStream<'_, Event>) -> Result<(), ()> {
+        let background_tasks = self
+            .background_tasks
+            .into_iter()
+            .map(|task| tokio::spawn(task.in_current_span()))
+            .collect::<Vec<_>>();
+        if let Some(orphan_uploads) = self.orphan_uploads {
+            orphan_uploads.await;
+        }
+
+        // An input ending without any events leaves nothing to flush, so the sink stops right away
+        // rather than setting up batching.
+        let Some(input) = wait_for_events(input).await else {
+            debug!(message = "Input ended without any events, stopping.");
+            for task in background_tasks {
+                task.abort();
+            }
+            return Ok(());
+        };
+
         let partitioner = self.partitioner;
         let settings = self.batcher_settings;
-        let request_builder = self.request_builder;
+        let request_builder = Arc::new(self.request_builder);
+        let max_request_bytes = self.max_request_bytes;
+        let input = match self.max_event_bytes {
+            Some(max_event_bytes) => input
+                .filter_map(move |event| future::ready(reject_oversized(event, max_event_bytes)))
+                .boxed(),
+            None => input,
+        };
+        let input = match self.startup_profile {
+            Some(startup_profile) => startup_profile.sample(input),
+            None => input,
+        };
+
+        let input_ended = Arc::new(AtomicBool::new(false));
+        let stream_framing = self.stream_framing.map(|write_trailers| StreamFraming {
+            open: Arc::default(),
+            input_ended: Arc::clone(&input_ended),
+            write_trailers,
+        });
+        let input = input.chain(stream::poll_fn(move |_| {
+            input_ended.store(true, Ordering::Relaxed);
+            Poll::Ready(None)
+        }));
+
+        let timer = partitioner.timer(settings.timeout);
+        let batch_starts = timer.batch_starts();
+
+        let batcher = PartitionedBatcher::with_timer(input, partitioner, timer, || {
+            settings.as_byte_size_config()
+        });
 
-        input
-            .batched_partitioned(partitioner, || settings.as_byte_size_config())
+        let trailers = stream_framing.clone();
+        let batches = batcher
             .filter_map(|(key, batch)| async move { key.map(move |k| (k, batch)) })
-            .request_builder(default_request_builder_concurrency_limit(), request_builder)
-            .filter_map(|request| async move {
-                match request {
-                    Err(error) => {
-                        emit!(SinkRequestBuildError { error });
-                        None
-                    }
-                    Ok(req) => Some(req),
-                }
+            .map(move |(key, batch)| {
+                let position = stream_framing
+                    .as_ref()
+                    .map_or(ObjectPosition::STANDALONE, |framing| framing.position(&key));
+                let started = batch_starts.take(&key).unwrap_or_else(Instant::now);
+                (key, batch, position, started)
+            });
+        // Batches back up while uploads are in flight, so give each partition its turn once they do.
+        let request_builder_limit = default_request_builder_concurrency_limit();
+        let run = Interleave::new(batches, request_builder_limit.get(), |batch| {
+            batch.0.clone()
+        })
+        .chain(
+            stream::once(async move {
+                trailers
+                    .map(|framing| framing.trailers())
+                    .unwrap_or_default()
             })
-            .into_driver(self.service)
-            .run()
-            .await
+            .flat_map(stream::iter),
+        )
+        .concurrent_map(request_builder_limit, move |batch| {
+            let request_builder = Arc::clone(&request_builder);
+            // The future is spawned, so the span must be entered again to keep the sink's tags.
+            let span = Span::current();
+            Box::pin(async move {
+                let _entered = span.enter();
+                build_requests(&*request_builder, batch, max_request_bytes)
+            })
+        })
+        .flat_map(stream::iter)
+        .filter_map(|request| async move {
+            match request {
+                Err(error) => {
+                    emit!(SinkRequestBuildError { error });
+                    None
+                }
+                Ok(req) => Some(req),
+            }
+        })
+        .into_driver(self.service)
+        .run();
+        let result = match self.fail_fast {
+            Some(fail_fast) => tokio::select! {
+                result = run => result,
+                consecutive_failures = fail_fast.tripped() => {
+                    emit!(S3PersistentErrorsExceeded { consecutive_failures });
+                    Err(())
+                }
+            },
+            None => run.await,
+        };
+
+        for task in background_tasks {
+            task.abort();
+        }
+        result
     }
 }
 
@@ -68,11 +373,303 @@ where
     Svc::Future: Send + 'static,
     Svc::Response: DriverResponse + Send + 'static,
     Svc::Error: fmt::Debug + Into<crate::Error> + Send,
-    RB: RequestBuilder<(S3PartitionKey, Vec<Event>)> + Send + Sync + 'static,
+    RB: RequestBuilder<(S3PartitionKey, Vec<Event>, ObjectPosition, Instant)>
+        + Send
+        + Sync
+        + 'static,
     RB::Error: fmt::Display + Send,
+    RB::Metadata: Finalizable,
     RB::Request: Finalizable + MetaDescriptive + Send,
 {
     async fn run(mut self: Box<Self>, input: BoxStream<'_, Event>) -> Result<(), ()> {
         self.run_inner(input).await
     }
 }
+
+/// Waits for the first event of the input, returning the input with it, or `None` if the input
+/// ends without any events.
+async fn wait_for_events(input: BoxStream<'_, Event>) -> Option<BoxStream<'_, Event>> {
+    let mut input = input.peekable();
+    Pin::new(&mut input).peek().await?;
+    Some(input.boxed())
+}
+
+/// Returns the event unless its in-memory size exceeds `max_event_bytes`, in which case it is
+/// rejected.
+fn reject_oversized(mut event: Event, max_event_bytes: NonZeroUsize) -> Option<Event> {
+    let byte_size = event.size_of();
+    if byte_size <= max_event_bytes.get() {
+        return Some(event);
+    }
+    event.take_finalizers().update_status(EventStatus::Rejected);
+    emit!(S3EventTooLarge {
+        byte_size,
+        max_event_bytes: max_event_bytes.get(),
+    });
+    None
+}
+
+/// Builds the requests for a batch, splitting it in halves until each object fits in
+/// `max_request_bytes` once encoded.
+///
+/// The halves share the finalizers of the batch, so its events are only acknowledged once all of
+/// them are uploaded. Objects of a single event which do not fit are rejected, as are those of
+/// batches which were not expected to exceed `max_request_bytes`, going by `MAX_ENCODED_SIZE_RATIO`.
+fn build_requests<RB>(
+    request_builder: &RB,
+    batch: (S3PartitionKey, Vec<Event>, ObjectPosition, Instant),
+    max_request_bytes: u64,
+) -> Vec<Result<RB::Request, RB::Error>>
+where
+    RB: RequestBuilder<(S3PartitionKey, Vec<Event>, ObjectPosition, Instant)>,
+    RB::Metadata: Finalizable,
+{
+    let mut requests = Vec::new();
+    let mut pending = vec![batch];
+    while let Some(batch) = pending.pop() {
+        let (key, events, position, started) = batch;
+        // The finalizers stay with the events given to the request builder, so the copies used to
+        // split the batch don't hold up their acknowledgement.
+        let may_exceed = events.size_of() as u64 * MAX_ENCODED_SIZE_RATIO > max_request_bytes;
+        let copies = (events.len() > 1 && may_exceed).then(|| {
+            events
+                .iter()
+                .cloned()
+                .map(|mut event| {
+                    drop(event.take_finalizers());
+                    event
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let (mut metadata, request_metadata_builder, events) =
+            request_builder.split_input((key.clone(), events, position, started));
+        let payload = match request_builder.encode_events(events) {
+            Ok(payload) => payload,
+            Err(error) => {
+                requests.push(Err(error));
+                continue;
+            }
+        };
+        let byte_size = payload.payload.as_ref().len() as u64;
+        if byte_size <= max_request_bytes {
+            let request_metadata = request_metadata_builder.build(&payload);
+            requests.push(Ok(request_builder.build_request(
+                metadata,
+                request_metadata,
+                payload,
+            )));
+            continue;
+        }
+
+        let finalizers = metadata.take_finalizers();
+        let Some(mut first) = copies else {
+            finalizers.update_status(EventStatus::Rejected);
+            requests.push(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Object of {} bytes exceeds the maximum object size of {} bytes.",
+                    byte_size, max_request_bytes
+                ),
+            )
+            .into()));
+            continue;
+        };
+        debug!(
+            message = "Splitting batch exceeding the maximum object size.",
+            byte_size,
+            max_request_bytes,
+            events = first.len(),
+        );
+        let mut second = first.split_off(first.len() / 2);
+        first[0].metadata_mut().merge_finalizers(finalizers.clone());
+        second[0].metadata_mut().merge_finalizers(finalizers);
+
+        // The halves are taken from the end, so the first half is built first.
+        let second_position = ObjectPosition {
+            first: false,
+            last: position.last,
+        };
+        let first_position = ObjectPosition {
+            first: position.first,
+            last: false,
+        };
+        pending.push((key.clone(), second, second_position, started));
+        pending.push((key, first, first_position, started));
+    }
+    requests
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use vector_lib::codecs::{CharacterDelimitedEncoder, JsonSerializerConfig};
+    use vector_lib::finalization::{BatchNotifier, BatchStatus};
+
+    use super::*;
+    use crate::sinks::util::encoding::Encoder as _;
+
+    /// Builds requests made of their encoded payload.
+    struct PayloadRequestBuilder((Transformer, Encoder<Framer>));
+
+    impl RequestBuilder<(S3PartitionKey, Vec<Event>, ObjectPosition, Instant)>
+        for PayloadRequestBuilder
+    {
+        type Metadata = EventFinalizers;
+        type Events = (Vec<Event>, ObjectPosition);
+        type Encoder = (Transformer, Encoder<Framer>);
+        type Payload = Bytes;
+        type Request = String;
+        type Error = io::Error;
+
+        fn compression(&self) -> Compression {
+            Compression::None
+        }
+
+        fn encoder(&self) -> &Self::Encoder {
+            &self.0
+        }
+
+        fn split_input(
+            &self,
+            (_, mut events, position, _): (S3PartitionKey, Vec<Event>, ObjectPosition, Instant),
+        ) -> (Self::Metadata, RequestMetadataBuilder, Self::Events) {
+            let builder = RequestMetadataBuilder::from_events(&events);
+            (events.take_finalizers(), builder, (events, position))
+        }
+
+        fn build_request(
+            &self,
+            _: Self::Metadata,
+            _: RequestMetadata,
+            payload: EncodeResult<Self::Payload>,
+        ) -> Self::Request {
+            String::from_utf8(payload.into_payload().to_vec()).unwrap()
+        }
+    }
+
+    fn encoding() -> (Transformer, Encoder<Framer>) {
+        (
+            Transformer::default(),
+            Encoder::<Framer>::new(
+                CharacterDelimitedEncoder::new(b',').into(),
+                JsonSerializerConfig::default().build().into(),
+            ),
+        )
+    }
+
+    fn batch(
+        events: &[&str],
+        notifier: &BatchNotifier,
+    ) -> (S3PartitionKey, Vec<Event>, ObjectPosition, Instant) {
+        let key = S3PartitionKey {
+            key_prefix: String::new(),
+            ssekms_key_id: None,
+            storage_class: None,
+            target: None,
+            split_run: None,
+        };
+        let events = events
+            .iter()
+            .map(|message| Event::Log(LogEvent::from(*message).with_batch_notifier(notifier)))
+            .collect();
+        (key, events, ObjectPosition::STANDALONE, Instant::now())
+    }
+
+    fn encode(events: &[&str], first: bool, last: bool) -> String {
+        let encoding = encoding();
+        let events = events
+            .iter()
+            .map(|message| Event::Log(LogEvent::from(*message)))
+            .collect();
+
+        let mut writer = Vec::new();
+        encoding
+            .encode_input((events, ObjectPosition { first, last }), &mut writer)
+            .unwrap();
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn standalone_objects_are_enclosed() {
+        assert_eq!(
+            encode(&["a", "b"], true, true),
+            r#"[{"message":"a"},{"message":"b"}]"#
+        );
+    }
+
+    #[test]
+    fn stream_objects_concatenate_into_one_document() {
+        let document = [
+            encode(&["a"], true, false),
+            encode(&["b", "c"], false, false),
+            encode(&[], false, true),
+        ]
+        .concat();
+        assert_eq!(
+            document,
+            r#"[{"message":"a"},{"message":"b"},{"message":"c"}]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_batches_are_split() {
+        let (notifier, receiver) = BatchNotifier::new_with_receiver();
+        let requests = build_requests(
+            &PayloadRequestBuilder(encoding()),
+            batch(&["a", "b", "c", "d"], &notifier),
+            40,
+        );
+        drop(notifier);
+
+        let requests = requests.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            requests,
+            [
+                encode(&["a", "b"], true, false),
+                encode(&["c", "d"], false, true)
+            ]
+        );
+        assert_eq!(requests.concat(), encode(&["a", "b", "c", "d"], true, true));
+        assert_eq!(receiver.await, BatchStatus::Delivered);
+    }
+
+    #[tokio::test]
+    async fn empty_inputs_end_without_waiting() {
+        assert!(wait_for_events(stream::empty().boxed()).await.is_none());
+
+        let events = ["a", "b"].map(|message| Event::Log(LogEvent::from(message)));
+        let input = wait_for_events(stream::iter(events.clone()).boxed())
+            .await
+            .unwrap();
+        assert_eq!(input.collect::<Vec<_>>().await, events);
+    }
+
+    #[tokio::test]
+    async fn events_above_max_event_bytes_are_rejected() {
+        let (notifier, receiver) = BatchNotifier::new_with_receiver();
+        let small = Event::Log(LogEvent::from("a").with_batch_notifier(&notifier));
+        let large = Event::Log(LogEvent::from("a".repeat(4096)).with_batch_notifier(&notifier));
+        drop(notifier);
+
+        let max_event_bytes = NonZeroUsize::new(small.size_of()).unwrap();
+        assert!(reject_oversized(small, max_event_bytes).is_some());
+        assert!(reject_oversized(large, max_event_bytes).is_none());
+        assert_eq!(receiver.await, BatchStatus::Rejected);
+    }
+
+    #[tokio::test]
+    async fn oversized_events_are_rejected() {
+        let (notifier, receiver) = BatchNotifier::new_with_receiver();
+        let requests = build_requests(
+            &PayloadRequestBuilder(encoding()),
+            batch(&["a"], &notifier),
+            10,
+        );
+        drop(notifier);
+
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].is_err());
+        assert_eq!(receiver.await, BatchStatus::Rejected);
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    io,
+    path::PathBuf,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use tokio::sync::Mutex;
+use tower::Service;
+
+use super::{
+    service::{S3Request, S3Response, S3Service},
+    staging::{StagedObject, StagingDir},
+};
+use crate::internal_events::{
+    S3ObjectSpilled, S3SpillDropped, S3SpillError, S3SpillRecovered, S3SpillRecoveryError,
+};
+
+/// A local directory objects which failed to upload, retries included, are spilled to, and
+/// uploaded again from.
+///
+/// Spilled objects are laid out as in a staging directory. The directory is bounded in size: the
+/// oldest objects are dropped to make room for new ones.
+#[derive(Clone, Debug)]
+pub struct SpillDir {
+    objects: StagingDir,
+    max_bytes: u64,
+    retry_interval: Duration,
+    /// Held while objects are spilled or listed for recovery, as listing them removes the files of
+    /// incomplete objects, which includes those being spilled.
+    lock: Arc<Mutex<()>>,
+}
+
+impl SpillDir {
+    pub fn new(path: PathBuf, max_bytes: u64, retry_interval: Duration) -> Self {
+        Self {
+            objects: StagingDir::new(path),
+            max_bytes,
+            retry_interval,
+            lock: Arc::default(),
+        }
+    }
+
+    /// Writes the object to the spill directory, dropping its oldest objects as needed to stay
+    /// within its maximum size, and returns whether it was spilled.
+    ///
+    /// Objects larger than the maximum size are not spilled.
+    async fn spill(&self, object: &StagedObject, body: &Bytes) -> io::Result<bool> {
+        let byte_size = body.len() as u64;
+        if byte_size > self.max_bytes {
+            return Ok(false);
+        }
+
+        let _lock = self.lock.lock().await;
+        let mut spilled = self.objects.staged().await?;
+        spilled.sort_by_key(|(_, metadata)| metadata.modified().ok());
+        let mut spilled_bytes = spilled
+            .iter()
+            .map(|(_, metadata)| metadata.len())
+            .sum::<u64>();
+        let mut oldest = spilled.into_iter();
+        while spilled_bytes + byte_size > self.max_bytes {
+            let Some((files, metadata)) = oldest.next() else {
+                break;
+            };
+            files.remove().await?;
+            spilled_bytes -= metadata.len();
+            emit!(S3SpillDropped {
+                byte_size: metadata.len()
+            });
+        }
+
+        self.objects.stage(object, body).await?;
+        Ok(true)
+    }
+
+    /// Reads the objects in the spill directory, removing any incomplete ones left by a crash.
+    async fn spilled(&self) -> io::Result<Vec<(StagedObject, Bytes)>> {
+        let _lock = self.lock.lock().await;
+        self.objects.orphans().await
+    }
+
+    /// Uploads the objects in the spill directory every retry interval, starting right away,
+    /// until the future is dropped.
+    ///
+    /// Objects are removed from the spill directory once uploaded, and kept for the next attempt
+    /// otherwise.
+    pub async fn recover(self, service: S3Service) {
+        let mut service = service
+            .with_staging_dir(Some(self.objects.clone()))
+            .with_upload_slo(None);
+        let mut interval = tokio::time::interval(self.retry_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let spilled = match self.spilled().await {
+                Ok(spilled) => spilled,
+                Err(error) => {
+                    emit!(S3SpillError { error });
+                    continue;
+                }
+            };
+            for (object, body) in spilled {
+                let key = object.key.clone();
+                let byte_size = body.len();
+                match service.call(S3Request::from_staged(object, body)).await {
+                    Ok(_) => emit!(S3SpillRecovered {
+                        key: &key,
+                        byte_size
+                    }),
+                    Err(error) => emit!(S3SpillRecoveryError { key: &key, error }),
+                }
+            }
+        }
+    }
+}
+
+/// Spills the objects the inner service fails to upload to a `SpillDir`, if any, reporting them
+/// as delivered once spilled.
+///
+/// Objects which are rejected without being uploaded, such as for an invalid key, are not spilled.
+pub struct SpillService<S> {
+    inner: S,
+    spill_dir: Option<SpillDir>,
+}
+
+impl<S> SpillService<S> {
+    pub const fn new(inner: S, spill_dir: Option<SpillDir>) -> Self {
+        Self { inner, spill_dir }
+    }
+}
+
+impl<S> Service<S3Request> for SpillService<S>
+where
+    S: Service<S3Request, Response = S3Response>,
+    S::Future: Send + 'static,
+    S::Error: std::fmt::Display + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: S3Request) -> Self::Future {
+        let spill_dir = self
+            .spill_dir
+            .clone()
+            .filter(|_| request.metadata.key_error.is_none());
+        let Some(spill_dir) = spill_dir else {
+            return Box::pin(self.inner.call(request));
+        };
+        let object = request.staged_object();
+        let body = request.body.clone();
+        let events_byte_size = request
+            .request_metadata
+            .clone()
+            .into_events_estimated_json_encoded_byte_size();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let error = match response.await {
+                Err(error) => error,
+                result => return result,
+            };
+            match spill_dir.spill(&object, &body).await {
+                Ok(true) => {
+                    emit!(S3ObjectSpilled {
+                        key: &object.key,
+                        byte_size: body.len(),
+                        error,
+                    });
+                    Ok(S3Response::new(events_byte_size))
+                }
+                Ok(false) => Err(error),
+                Err(spill_error) => {
+                    emit!(S3SpillError { error: spill_error });
+                    Err(error)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::s3_common::config::S3Options;
+
+    fn object(key: &str) -> StagedObject {
+        StagedObject {
+            bucket: "bucket".to_owned(),
+            key: key.to_owned(),
+            expires: None,
+            region: None,
+            options: S3Options::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn oldest_objects_are_dropped_when_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill_dir = SpillDir::new(dir.path().join("spill"), 10, Duration::from_secs(60));
+
+        for key in ["a.log", "b.log"] {
+            assert!(spill_dir
+                .spill(&object(key), &Bytes::from("abcd"))
+                .await
+                .unwrap());
+            // Modification times are not always precise enough to order back to back writes.
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(spill_dir
+            .spill(&object("c.log"), &Bytes::from("efgh"))
+            .await
+            .unwrap());
+        assert!(!spill_dir
+            .spill(&object("d.log"), &Bytes::from("too large to spill"))
+            .await
+            .unwrap());
+
+        let mut keys = spill_dir
+            .spilled()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(object, _)| object.key)
+            .collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, ["b.log", "c.log"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn objects_being_spilled_are_not_removed_by_recovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill_dir = SpillDir::new(dir.path().join("spill"), 1 << 20, Duration::from_secs(60));
+
+        let keys = (0..50).map(|i| format!("{i}.log")).collect::<Vec<_>>();
+        let spills = futures::future::join_all(keys.iter().map(|key| {
+            let spill_dir = spill_dir.clone();
+            async move { spill_dir.spill(&object(key), &Bytes::from("abcd")).await }
+        }));
+        let recovery = {
+            let spill_dir = spill_dir.clone();
+            tokio::spawn(async move {
+                loop {
+                    spill_dir.spilled().await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        for spilled in spills.await {
+            assert!(spilled.unwrap());
+        }
+        recovery.abort();
+
+        let mut spilled = spill_dir
+            .spilled()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(object, body)| {
+                assert_eq!(body, Bytes::from("abcd"));
+                object.key
+            })
+            .collect::<Vec<_>>();
+        spilled.sort();
+        let mut keys = keys;
+        keys.sort();
+        assert_eq!(spilled, keys);
+    }
+}
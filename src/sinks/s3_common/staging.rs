@@ -0,0 +1,209 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use md5::Digest;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use super::config::S3Options;
+use crate::internal_events::S3StagingError;
+
+const BODY_EXTENSION: &str = "object";
+const DESCRIPTOR_EXTENSION: &str = "json";
+
+/// A local directory objects are written to before being uploaded, and removed from once uploaded.
+///
+/// Objects left in the directory, such as by a crash, are uploaded when the sink next starts.
+#[derive(Clone, Debug)]
+pub struct StagingDir {
+    path: PathBuf,
+}
+
+/// Everything needed to upload a staged object, other than its body.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StagedObject {
+    pub bucket: String,
+    pub key: String,
+    /// The `Expires` date of the object, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub expires: Option<i64>,
+    /// The region of the bucket, when it differs from the region of the client.
+    #[serde(default)]
+    pub region: Option<String>,
+    pub options: S3Options,
+}
+
+/// The files of an object written to the staging directory.
+#[derive(Debug)]
+pub struct StagedFiles {
+    body: PathBuf,
+    descriptor: PathBuf,
+}
+
+impl StagingDir {
+    pub const fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Writes the object to the staging directory.
+    ///
+    /// Objects are named after their bucket and key, so retries of an upload overwrite the files
+    /// they staged previously. The descriptor is written last, and atomically, so that only
+    /// complete objects are uploaded on startup.
+    pub async fn stage(&self, object: &StagedObject, body: &Bytes) -> io::Result<StagedFiles> {
+        let name = format!(
+            "{:x}",
+            md5::Md5::digest(format!("{}/{}", object.bucket, object.key))
+        );
+        let files = StagedFiles {
+            body: self.path.join(&name).with_extension(BODY_EXTENSION),
+            descriptor: self.path.join(&name).with_extension(DESCRIPTOR_EXTENSION),
+        };
+        let descriptor = serde_json::to_vec(object)?;
+        let partial = self.path.join(name).with_extension("partial");
+
+        fs::write(&files.body, body).await?;
+        fs::write(&partial, descriptor).await?;
+        fs::rename(&partial, &files.descriptor).await?;
+        Ok(files)
+    }
+
+    /// Reads the objects left in the staging directory, removing any incomplete ones.
+    ///
+    /// Creates the staging directory if it does not exist yet.
+    pub async fn orphans(&self) -> io::Result<Vec<(StagedObject, Bytes)>> {
+        fs::create_dir_all(&self.path).await?;
+
+        let mut orphans = Vec::new();
+        let mut entries = fs::read_dir(&self.path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            match path.extension().and_then(|extension| extension.to_str()) {
+                Some(DESCRIPTOR_EXTENSION) => match read_orphan(&path).await {
+                    Ok(orphan) => orphans.push(orphan),
+                    Err(error) => {
+                        emit!(S3StagingError { error });
+                        StagedFiles::for_descriptor(path).remove().await?;
+                    }
+                },
+                Some(BODY_EXTENSION) => {
+                    if fs::metadata(path.with_extension(DESCRIPTOR_EXTENSION))
+                        .await
+                        .is_err()
+                    {
+                        fs::remove_file(&path).await?;
+                    }
+                }
+                Some("partial") => fs::remove_file(&path).await?,
+                _ => {}
+            }
+        }
+        Ok(orphans)
+    }
+
+    /// Lists the complete objects in the staging directory, along with the metadata of their
+    /// body, without reading them.
+    ///
+    /// Creates the staging directory if it does not exist yet.
+    pub async fn staged(&self) -> io::Result<Vec<(StagedFiles, std::fs::Metadata)>> {
+        fs::create_dir_all(&self.path).await?;
+
+        let mut staged = Vec::new();
+        let mut entries = fs::read_dir(&self.path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|extension| extension.to_str())
+                == Some(DESCRIPTOR_EXTENSION)
+            {
+                let files = StagedFiles::for_descriptor(path);
+                if let Ok(metadata) = fs::metadata(&files.body).await {
+                    staged.push((files, metadata));
+                }
+            }
+        }
+        Ok(staged)
+    }
+}
+
+impl StagedFiles {
+    fn for_descriptor(descriptor: PathBuf) -> Self {
+        Self {
+            body: descriptor.with_extension(BODY_EXTENSION),
+            descriptor,
+        }
+    }
+
+    /// Removes the files of the staged object, once it has been uploaded.
+    pub async fn remove(self) -> io::Result<()> {
+        // The descriptor is removed first, so that the body is never uploaded on its own.
+        remove_if_exists(&self.descriptor).await?;
+        remove_if_exists(&self.body).await
+    }
+}
+
+async fn read_orphan(descriptor: &Path) -> io::Result<(StagedObject, Bytes)> {
+    let object = serde_json::from_slice(&fs::read(descriptor).await?)?;
+    let body = fs::read(descriptor.with_extension(BODY_EXTENSION)).await?;
+    Ok((object, body.into()))
+}
+
+async fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path).await {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(key: &str) -> StagedObject {
+        StagedObject {
+            bucket: "bucket".to_owned(),
+            key: key.to_owned(),
+            expires: None,
+            region: None,
+            options: S3Options::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn staged_objects_are_orphaned_until_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = StagingDir::new(dir.path().join("staging"));
+        assert!(staging.orphans().await.unwrap().is_empty());
+
+        let first = staging
+            .stage(&object("a.log"), &Bytes::from("first"))
+            .await
+            .unwrap();
+        staging
+            .stage(&object("b.log"), &Bytes::from("second"))
+            .await
+            .unwrap();
+        first.remove().await.unwrap();
+
+        let orphans = staging.orphans().await.unwrap();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].0.key, "b.log");
+        assert_eq!(orphans[0].1, Bytes::from("second"));
+    }
+
+    #[tokio::test]
+    async fn incomplete_objects_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = StagingDir::new(dir.path().to_owned());
+        let files = staging
+            .stage(&object("a.log"), &Bytes::from("body"))
+            .await
+            .unwrap();
+        std::fs::remove_file(&files.descriptor).unwrap();
+
+        assert!(staging.orphans().await.unwrap().is_empty());
+        assert!(!files.body.exists());
+    }
+}
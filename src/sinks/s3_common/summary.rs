@@ -0,0 +1,193 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use vrl::path::{OwnedValuePath, PathPrefix};
+
+use crate::event::{Event, Value};
+
+/// The number of windows whose running aggregate is kept, beyond which the aggregate of the least
+/// recently updated window is dropped.
+const MAX_WINDOWS: usize = 1024;
+
+/// The count, sum, minimum, and maximum of the numeric values of a field.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct FieldAggregate {
+    pub count: u64,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl FieldAggregate {
+    const fn new(value: f64) -> Self {
+        Self {
+            count: 1,
+            sum: value,
+            min: value,
+            max: value,
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// The aggregate of a set of events: their count, and the aggregate of each of the configured
+/// fields, keyed by their path.
+///
+/// Fields are only aggregated over the log events holding an integer or float value for them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Aggregate {
+    pub count: u64,
+    pub fields: BTreeMap<String, FieldAggregate>,
+}
+
+impl Aggregate {
+    pub fn of_events<'a>(
+        events: &[Event],
+        fields: impl IntoIterator<Item = &'a OwnedValuePath>,
+    ) -> Self {
+        let mut aggregate = Self {
+            count: events.len() as u64,
+            fields: BTreeMap::new(),
+        };
+        for field in fields {
+            let values = events.iter().filter_map(|event| {
+                match event.maybe_as_log()?.get((PathPrefix::Event, field))? {
+                    Value::Integer(value) => Some(*value as f64),
+                    Value::Float(value) => Some(value.into_inner()),
+                    _ => None,
+                }
+            });
+            let field_aggregate = values.fold(None, |aggregate: Option<FieldAggregate>, value| {
+                let value = FieldAggregate::new(value);
+                Some(aggregate.map_or(value, |mut aggregate| {
+                    aggregate.merge(&value);
+                    aggregate
+                }))
+            });
+            if let Some(field_aggregate) = field_aggregate {
+                aggregate
+                    .fields
+                    .insert(String::from(field.clone()), field_aggregate);
+            }
+        }
+        aggregate
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        for (field, other) in &other.fields {
+            self.fields
+                .entry(field.clone())
+                .and_modify(|aggregate| aggregate.merge(other))
+                .or_insert(*other);
+        }
+    }
+}
+
+/// The summary object of the window of an object, written once the object is uploaded.
+#[derive(Clone, Debug)]
+pub struct WindowSummary {
+    pub key: String,
+    /// The aggregate of the events of the object.
+    pub aggregate: Aggregate,
+    /// The body of the summary object, once the aggregate of the object is added to the running
+    /// aggregate of the window.
+    pub body: Option<Bytes>,
+}
+
+/// The JSON document a summary object is written as.
+#[derive(Serialize)]
+struct SummaryObject<'a> {
+    updated_at: DateTime<Utc>,
+    #[serde(flatten)]
+    aggregate: &'a Aggregate,
+}
+
+/// The running aggregates of the windows summary objects are written for, keyed by the key of
+/// their summary object.
+#[derive(Debug, Default)]
+pub struct WindowSummaries {
+    /// The running aggregate of each window, and the update it was last updated by.
+    windows: Mutex<(HashMap<String, (Aggregate, u64)>, u64)>,
+}
+
+impl WindowSummaries {
+    /// Adds the aggregate of an object to the running aggregate of its window, returning the body
+    /// of the summary object of the window.
+    pub fn add(&self, key: &str, aggregate: &Aggregate, now: DateTime<Utc>) -> Bytes {
+        let mut windows = self
+            .windows
+            .lock()
+            .expect("window summaries mutex poisoned");
+        let (windows, updates) = &mut *windows;
+        *updates += 1;
+        if !windows.contains_key(key) && windows.len() >= MAX_WINDOWS {
+            let oldest = windows
+                .iter()
+                .min_by_key(|(_, (_, updated))| *updated)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                windows.remove(&oldest);
+            }
+        }
+        let (running, updated) = windows.entry(key.to_owned()).or_default();
+        running.merge(aggregate);
+        *updated = *updates;
+        let body = serde_json::to_vec(&SummaryObject {
+            updated_at: now,
+            aggregate: running,
+        })
+        .expect("summary object should always serialize");
+        body.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::LogEvent;
+
+    fn event(duration: Value) -> Event {
+        let mut log = LogEvent::from("message");
+        log.insert("duration", duration);
+        log.into()
+    }
+
+    #[test]
+    fn aggregates_accumulate_per_window() {
+        let fields = [OwnedValuePath::try_from("duration".to_owned()).unwrap()];
+        let first = Aggregate::of_events(
+            &[event(Value::from(3)), event(Value::from(1.5))],
+            &fields[..],
+        );
+        let second = Aggregate::of_events(
+            &[event(Value::from(10)), event(Value::from("not a number"))],
+            &fields[..],
+        );
+        let now = DateTime::from_timestamp_millis(1_658_176_486_000).unwrap();
+
+        let summaries = WindowSummaries::default();
+        summaries.add("date=2022-07-18/_summary.json", &first, now);
+        summaries.add("date=2022-07-19/_summary.json", &first, now);
+        let body = summaries.add("date=2022-07-18/_summary.json", &second, now);
+
+        let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+        assert_eq!(body["updated_at"], "2022-07-18T20:34:46Z");
+        assert_eq!(body["count"], 4);
+        assert_eq!(body["fields"]["duration"]["count"], 3);
+        assert_eq!(body["fields"]["duration"]["sum"], 14.5);
+        assert_eq!(body["fields"]["duration"]["min"], 1.5);
+        assert_eq!(body["fields"]["duration"]["max"], 10.0);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    error::Error,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use aws_smithy_runtime_api::client::{orchestrator::HttpResponse, result::SdkError};
+use bytes::Bytes;
+use md5::Digest;
+use snafu::Snafu;
+
+/// Samples the uploaded objects which are read back to verify that they match their upload.
+pub struct UploadVerification {
+    sample_rate: usize,
+    retry: bool,
+    uploads: AtomicUsize,
+}
+
+impl UploadVerification {
+    pub fn new(sample_rate: NonZeroUsize, retry: bool) -> Arc<Self> {
+        Arc::new(Self {
+            sample_rate: sample_rate.get(),
+            retry,
+            uploads: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns whether the next uploaded object is verified.
+    pub fn sample(&self) -> bool {
+        self.uploads.fetch_add(1, Ordering::Relaxed) % self.sample_rate == 0
+    }
+
+    /// Compares the size and ETag read back from an uploaded object to its body, returning the
+    /// mismatch, if any.
+    ///
+    /// ETags are only compared when they are the MD5 digest of the object, which is not the case
+    /// for objects encrypted with AWS KMS.
+    pub fn verify(
+        &self,
+        body: &Bytes,
+        content_length: Option<i64>,
+        e_tag: Option<&str>,
+    ) -> Option<UploadMismatch> {
+        let reason = if content_length != Some(body.len() as i64) {
+            format!(
+                "size of {:?} bytes does not match the {} bytes uploaded",
+                content_length,
+                body.len()
+            )
+        } else {
+            let digest = format!("\"{:x}\"", md5::Md5::digest(body));
+            match e_tag {
+                Some(e_tag) if e_tag != digest => {
+                    format!("ETag {} does not match the digest {}", e_tag, digest)
+                }
+                _ => return None,
+            }
+        };
+        Some(UploadMismatch {
+            reason,
+            retry: self.retry,
+        })
+    }
+}
+
+/// An uploaded object which does not match its upload once read back.
+#[derive(Debug, Snafu)]
+#[snafu(display("Uploaded object does not match its upload: {}", reason))]
+pub struct UploadMismatch {
+    pub reason: String,
+    retry: bool,
+}
+
+/// Checks if the request failed because the uploaded object does not match its upload, and
+/// should be uploaded again.
+pub fn is_retriable_mismatch<E>(error: &SdkError<E, HttpResponse>) -> bool
+where
+    E: Error + 'static,
+{
+    match error {
+        SdkError::ConstructionFailure(_) => error
+            .source()
+            .and_then(|source| source.downcast_ref::<UploadMismatch>())
+            .is_some_and(|mismatch| mismatch.retry),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_one_in_rate_uploads() {
+        let verification = UploadVerification::new(NonZeroUsize::new(3).unwrap(), true);
+        let sampled = (0..6).map(|_| verification.sample()).collect::<Vec<_>>();
+        assert_eq!(sampled, [true, false, false, true, false, false]);
+    }
+
+    #[test]
+    fn detects_mismatched_uploads() {
+        let verification = UploadVerification::new(NonZeroUsize::new(1).unwrap(), false);
+        let body = Bytes::from_static(b"hello");
+        let digest = "\"5d41402abc4b2a76b9719d911017c592\"";
+
+        assert!(verification.verify(&body, Some(5), Some(digest)).is_none());
+        assert!(verification.verify(&body, Some(5), None).is_none());
+        assert!(verification.verify(&body, Some(4), Some(digest)).is_some());
+        assert!(verification
+            .verify(&body, Some(5), Some("\"00000000000000000000000000000000\""))
+            .is_some());
+    }
+}
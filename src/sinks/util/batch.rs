@@ -102,7 +102,14 @@ where
     ///
     /// This is based on the uncompressed size of the batched events, before they are
     /// serialized/compressed.
-    #[serde(default = "default_max_bytes::<D>")]
+    ///
+    /// This can be set either to an integer number of bytes or to a string with a unit, such as
+    /// `128MiB` or `10MB`. Units ending in `iB` are powers of 1024, and the others are powers of
+    /// 1000, such that `1MiB` is 1048576 bytes while `1MB` is 1000000 bytes.
+    #[serde(
+        default = "default_max_bytes::<D>",
+        deserialize_with = "crate::serde::optional_byte_size"
+    )]
     #[configurable(metadata(docs::type_unit = "bytes"))]
     pub max_bytes: Option<usize>,
 
@@ -1,13 +1,17 @@
 use std::io;
 
-use bytes::BytesMut;
-use itertools::{Itertools, Position};
-use tokio_util::codec::Encoder as _;
+use bytes::{BufMut, BytesMut};
 use vector_lib::codecs::encoding::Framer;
+use vector_lib::configurable::configurable_component;
+use vector_lib::internal_event::{ComponentEventsDropped, INTENTIONAL};
 use vector_lib::request_metadata::GroupedCountByteSize;
 use vector_lib::{config::telemetry, EstimatedJsonEncodedSizeOf};
 
-use crate::{codecs::Transformer, event::Event, internal_events::EncoderWriteError};
+use crate::{
+    codecs::Transformer,
+    event::{Event, Value},
+    internal_events::{EncoderInvalidUtf8Error, EncoderWriteError},
+};
 
 pub trait Encoder<T> {
     /// Encodes the input into the provided writer.
@@ -28,47 +32,222 @@ impl Encoder<Vec<Event>> for (Transformer, crate::codecs::Encoder<Framer>) {
         events: Vec<Event>,
         writer: &mut dyn io::Write,
     ) -> io::Result<(usize, GroupedCountByteSize)> {
-        let mut encoder = self.1.clone();
-        let mut bytes_written = 0;
-        let mut n_events_pending = events.len();
-        let batch_prefix = encoder.batch_prefix();
-        write_all(writer, n_events_pending, batch_prefix)?;
-        bytes_written += batch_prefix.len();
+        encode_batch(
+            self,
+            events,
+            writer,
+            self.1.batch_prefix(),
+            self.1.batch_suffix(),
+            BatchFraming::default(),
+        )
+    }
+}
 
-        let mut byte_size = telemetry().create_request_count_byte_size();
+/// Adjustments to how the events of a batch are framed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BatchFraming {
+    /// Skips the delimiter after events whose encoded form already ends with it, so they don't
+    /// leave empty frames.
+    pub collapse_trailing_delimiter: bool,
+    /// Drops events which the serializer encodes to no bytes at all, rather than writing an empty
+    /// frame for them.
+    pub drop_empty_events: bool,
+    /// How line breaks within the string values of events are written, with newline-delimited
+    /// framing.
+    pub embedded_newlines: EmbeddedNewlines,
+    /// How invalid UTF-8 in the encoded form of events is handled.
+    pub invalid_utf8: InvalidUtf8,
+}
 
-        for (position, mut event) in events.into_iter().with_position() {
-            self.0.transform(&mut event);
+/// How line breaks within the string values of events are written.
+///
+/// With newline-delimited framing, an event whose encoded form contains a line break spans
+/// several lines, such as with the `text` codec, breaking readers expecting one event per line.
+/// This only applies to log and trace events, and to `newline_delimited` framing, or
+/// `character_delimited` framing with a `\n` delimiter.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddedNewlines {
+    /// Line breaks are written as is.
+    #[default]
+    Keep,
+
+    /// Line breaks are escaped as in JSON strings, by a backslash followed by `n` or `r`.
+    Escape,
+
+    /// Line breaks are removed.
+    Strip,
+}
 
-            // Ensure the json size is calculated after any fields have been removed
-            // by the transformer.
-            byte_size.add_event(&event, event.estimated_json_encoded_size_of());
+impl EmbeddedNewlines {
+    /// Replaces the line breaks within the string values of the event.
+    fn apply(self, event: &mut Event) {
+        if self == Self::Keep {
+            return;
+        }
+        let value = match event {
+            Event::Log(log) => log.value_mut(),
+            Event::Trace(trace) => trace.value_mut(),
+            Event::Metric(_) => return,
+        };
+        self.replace(value);
+    }
 
-            let mut bytes = BytesMut::new();
-            match position {
-                Position::Last | Position::Only => {
-                    encoder
-                        .serialize(event, &mut bytes)
-                        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
-                }
-                _ => {
-                    encoder
-                        .encode(event, &mut bytes)
-                        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    fn replace(self, value: &mut Value) {
+        match value {
+            Value::Bytes(bytes) if bytes.iter().any(|byte| matches!(byte, b'\n' | b'\r')) => {
+                let mut replaced = BytesMut::with_capacity(bytes.len());
+                for &byte in bytes.iter() {
+                    match (byte, self) {
+                        (b'\n', Self::Escape) => replaced.put_slice(b"\\n"),
+                        (b'\r', Self::Escape) => replaced.put_slice(b"\\r"),
+                        (b'\n' | b'\r', _) => {}
+                        _ => replaced.put_u8(byte),
+                    }
                 }
+                *bytes = replaced.freeze();
+            }
+            Value::Object(map) => map.values_mut().for_each(|value| self.replace(value)),
+            Value::Array(array) => array.iter_mut().for_each(|value| self.replace(value)),
+            _ => {}
+        }
+    }
+}
+
+/// How invalid UTF-8 in the encoded form of events is handled.
+///
+/// Text codecs, such as `text`, write string values as they are, so that an event holding invalid
+/// UTF-8 makes the whole object invalid for strict readers. This only applies to text codecs.
+#[configurable_component]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidUtf8 {
+    /// Invalid UTF-8 is written as is.
+    #[default]
+    Keep,
+
+    /// Invalid sequences are replaced by the replacement character, `U+FFFD`.
+    Replace,
+
+    /// Invalid sequences are removed.
+    Strip,
+
+    /// Events encoded to invalid UTF-8 are rejected with an error, and dropped.
+    Error,
+}
+
+impl InvalidUtf8 {
+    /// Sanitizes the encoded form of an event, returning whether the event is kept.
+    fn sanitize(self, bytes: &mut BytesMut) -> bool {
+        if self == Self::Keep || std::str::from_utf8(bytes).is_ok() {
+            return true;
+        }
+        if self == Self::Error {
+            return false;
+        }
+        let mut sanitized = BytesMut::with_capacity(bytes.len());
+        for chunk in bytes.utf8_chunks() {
+            sanitized.put_slice(chunk.valid().as_bytes());
+            if self == Self::Replace && !chunk.invalid().is_empty() {
+                sanitized.put_slice("\u{FFFD}".as_bytes());
+            }
+        }
+        *bytes = sanitized;
+        true
+    }
+}
+
+/// Encodes a batch of events, enclosed by the given prefix and suffix instead of those of the
+/// encoder.
+pub fn encode_batch(
+    (transformer, encoder): &(Transformer, crate::codecs::Encoder<Framer>),
+    events: Vec<Event>,
+    writer: &mut dyn io::Write,
+    batch_prefix: &[u8],
+    batch_suffix: &[u8],
+    framing: BatchFraming,
+) -> io::Result<(usize, GroupedCountByteSize)> {
+    let mut encoder = encoder.clone();
+    // The separator of batches is the delimiter of the framers which have one.
+    let delimiter = encoder.batch_separator().to_vec();
+    let embedded_newlines = if delimiter == b"\n" {
+        framing.embedded_newlines
+    } else {
+        EmbeddedNewlines::Keep
+    };
+    let mut bytes_written = 0;
+    let mut n_events_pending = events.len();
+    write_all(writer, n_events_pending, batch_prefix)?;
+    bytes_written += batch_prefix.len();
+
+    let mut byte_size = telemetry().create_request_count_byte_size();
+
+    // The last event is not framed, so each event is only framed and written once the next event
+    // to be written is known.
+    let mut previous: Option<BytesMut> = None;
+    for mut event in events {
+        transformer.transform(&mut event);
+        embedded_newlines.apply(&mut event);
+
+        // Ensure the json size is calculated after any fields have been removed
+        // by the transformer.
+        let json_size = event.estimated_json_encoded_size_of();
+        let mut event_byte_size = None;
+        if framing.drop_empty_events || framing.invalid_utf8 == InvalidUtf8::Error {
+            let mut size = telemetry().create_request_count_byte_size();
+            size.add_event(&event, json_size);
+            event_byte_size = Some(size);
+        } else {
+            byte_size.add_event(&event, json_size);
+        }
+
+        let mut bytes = BytesMut::new();
+        encoder
+            .serialize(event, &mut bytes)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        if !framing.invalid_utf8.sanitize(&mut bytes) {
+            emit!(EncoderInvalidUtf8Error);
+            n_events_pending -= 1;
+            continue;
+        }
+        if let Some(event_byte_size) = event_byte_size {
+            if framing.drop_empty_events && bytes.is_empty() {
+                emit!(ComponentEventsDropped::<INTENTIONAL> {
+                    count: 1,
+                    reason: "Event was encoded to zero bytes."
+                });
+                n_events_pending -= 1;
+                continue;
+            }
+            byte_size += event_byte_size;
+        }
+
+        if let Some(mut bytes) = previous.replace(bytes) {
+            if !(framing.collapse_trailing_delimiter
+                && !delimiter.is_empty()
+                && bytes.ends_with(&delimiter))
+            {
+                encoder
+                    .frame(&mut bytes)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
             }
             write_all(writer, n_events_pending, &bytes)?;
             bytes_written += bytes.len();
             n_events_pending -= 1;
         }
+    }
+    if let Some(bytes) = previous {
+        write_all(writer, n_events_pending, &bytes)?;
+        bytes_written += bytes.len();
+        n_events_pending -= 1;
+    }
 
-        let batch_suffix = encoder.batch_suffix();
-        assert!(n_events_pending == 0);
-        write_all(writer, 0, batch_suffix)?;
-        bytes_written += batch_suffix.len();
+    assert!(n_events_pending == 0);
+    write_all(writer, 0, batch_suffix)?;
+    bytes_written += batch_suffix.len();
 
-        Ok((bytes_written, byte_size))
-    }
+    Ok((bytes_written, byte_size))
 }
 
 impl Encoder<Event> for (Transformer, crate::codecs::Encoder<()>) {
@@ -146,6 +325,8 @@ where
 mod tests {
     use std::collections::BTreeMap;
 
+    use bytes::Bytes;
+
     use vector_lib::codecs::{
         CharacterDelimitedEncoder, JsonSerializerConfig, NewlineDelimitedEncoder,
         TextSerializerConfig,
@@ -335,6 +516,138 @@ mod tests {
         assert_eq!(CountByteSize(3, input_json_size), json_size.size().unwrap());
     }
 
+    #[test]
+    fn test_encode_batch_collapses_trailing_delimiters() {
+        let encoding = (
+            Transformer::default(),
+            crate::codecs::Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::default().into(),
+                TextSerializerConfig::default().build().into(),
+            ),
+        );
+        let input = || {
+            ["a\n", "b", "", "c\n"]
+                .map(|message| Event::Log(LogEvent::from(message)))
+                .to_vec()
+        };
+
+        let mut writer = Vec::new();
+        encode_batch(
+            &encoding,
+            input(),
+            &mut writer,
+            &[],
+            &[],
+            BatchFraming::default(),
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(writer).unwrap(), "a\n\nb\n\nc\n");
+
+        let mut writer = Vec::new();
+        let framing = BatchFraming {
+            collapse_trailing_delimiter: true,
+            ..Default::default()
+        };
+        let (written, _) =
+            encode_batch(&encoding, input(), &mut writer, &[], &[], framing).unwrap();
+        assert_eq!(String::from_utf8(writer).unwrap(), "a\nb\n\nc\n");
+        assert_eq!(written, 8);
+    }
+
+    #[test]
+    fn test_encode_batch_replaces_embedded_newlines() {
+        let encoding = (
+            Transformer::default(),
+            crate::codecs::Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::default().into(),
+                TextSerializerConfig::default().build().into(),
+            ),
+        );
+        let encode = |embedded_newlines| {
+            let mut writer = Vec::new();
+            let framing = BatchFraming {
+                embedded_newlines,
+                ..Default::default()
+            };
+            let input = ["a\nb", "c\r\nd"]
+                .map(|message| Event::Log(LogEvent::from(message)))
+                .to_vec();
+            encode_batch(&encoding, input, &mut writer, &[], &[], framing).unwrap();
+            String::from_utf8(writer).unwrap()
+        };
+
+        assert_eq!(encode(EmbeddedNewlines::Keep), "a\nb\nc\r\nd");
+        assert_eq!(encode(EmbeddedNewlines::Escape), "a\\nb\nc\\r\\nd");
+        assert_eq!(encode(EmbeddedNewlines::Strip), "ab\ncd");
+    }
+
+    #[test]
+    fn test_encode_batch_sanitizes_invalid_utf8() {
+        let encoding = (
+            Transformer::default(),
+            crate::codecs::Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::default().into(),
+                TextSerializerConfig::default().build().into(),
+            ),
+        );
+        let encode = |invalid_utf8| {
+            let mut writer = Vec::new();
+            let framing = BatchFraming {
+                invalid_utf8,
+                ..Default::default()
+            };
+            let input = [&b"a\xffb"[..], b"c", b"d\xe2\x82"]
+                .map(|message| Event::Log(LogEvent::from(Bytes::from_static(message))))
+                .to_vec();
+            let (_, json_size) =
+                encode_batch(&encoding, input, &mut writer, &[], &[], framing).unwrap();
+            (writer, json_size.size().unwrap().0)
+        };
+
+        assert_eq!(
+            encode(InvalidUtf8::Keep),
+            (b"a\xffb\nc\nd\xe2\x82".to_vec(), 3)
+        );
+        assert_eq!(
+            encode(InvalidUtf8::Replace),
+            ("a\u{FFFD}b\nc\nd\u{FFFD}".as_bytes().to_vec(), 3)
+        );
+        assert_eq!(encode(InvalidUtf8::Strip), (b"ab\nc\nd".to_vec(), 3));
+        assert_eq!(encode(InvalidUtf8::Error), (b"c".to_vec(), 1));
+    }
+
+    #[test]
+    fn test_encode_batch_drops_empty_events() {
+        let encoding = (
+            Transformer::default(),
+            crate::codecs::Encoder::<Framer>::new(
+                NewlineDelimitedEncoder::default().into(),
+                TextSerializerConfig::default().build().into(),
+            ),
+        );
+        let input = || {
+            ["a", "", "b", ""]
+                .map(|message| Event::Log(LogEvent::from(message)))
+                .to_vec()
+        };
+
+        let mut writer = Vec::new();
+        let (_, json_size) = encoding.encode_input(input(), &mut writer).unwrap();
+        assert_eq!(String::from_utf8(writer).unwrap(), "a\n\nb\n");
+        assert_eq!(json_size.size().unwrap().0, 4);
+
+        let mut writer = Vec::new();
+        let framing = BatchFraming {
+            drop_empty_events: true,
+            ..Default::default()
+        };
+        let (written, json_size) =
+            encode_batch(&encoding, input(), &mut writer, &[], &[], framing).unwrap();
+        assert_eq!(String::from_utf8(writer).unwrap(), "a\nb");
+        assert_eq!(written, 3);
+        assert_eq!(json_size.size().unwrap().0, 2);
+    }
+
     #[test]
     fn test_encode_event_json() {
         let encoding = (
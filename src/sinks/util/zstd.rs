@@ -1,5 +1,7 @@
 use std::{fmt::Display, io};
 
+use zstd::stream::raw::CParameter;
+
 use super::buffer::compression::CompressionLevel;
 
 #[derive(Debug)]
@@ -24,6 +26,24 @@ impl Display for ZstdCompressionLevel {
     }
 }
 
+/// The window size of deterministic frames, of 8 MiB, which is the largest window decoders are
+/// required to support.
+const DETERMINISTIC_WINDOW_LOG: u32 = 23;
+
+/// Compresses the data as a single frame with a fixed configuration, such that identical data
+/// compresses to identical bytes, however it was written.
+///
+/// The frame is compressed on the calling thread, records the size of the data, and has neither a
+/// content checksum nor a dictionary ID.
+pub fn compress_deterministic(data: &[u8], level: ZstdCompressionLevel) -> io::Result<Vec<u8>> {
+    let mut compressor = zstd::bulk::Compressor::new(level.0)?;
+    compressor.set_parameter(CParameter::ChecksumFlag(false))?;
+    compressor.set_parameter(CParameter::ContentSizeFlag(true))?;
+    compressor.set_parameter(CParameter::DictIdFlag(false))?;
+    compressor.set_parameter(CParameter::WindowLog(DETERMINISTIC_WINDOW_LOG))?;
+    compressor.compress(data)
+}
+
 pub struct ZstdEncoder<W: io::Write> {
     inner: zstd::Encoder<'static, W>,
 }
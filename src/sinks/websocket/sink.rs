@@ -236,11 +236,12 @@ impl WebSocketSink {
 
     const fn should_encode_as_binary(&self) -> bool {
         use vector_lib::codecs::encoding::Serializer::{
-            Avro, Csv, Gelf, Json, Logfmt, Native, NativeJson, Protobuf, RawMessage, Text,
+            Avro, Cbor, Csv, Gelf, Json, Logfmt, MessagePack, Native, NativeJson, Protobuf,
+            RawMessage, Text,
         };
 
         match self.encoder.serializer() {
-            RawMessage(_) | Avro(_) | Native(_) | Protobuf(_) => true,
+            RawMessage(_) | Avro(_) | Cbor(_) | MessagePack(_) | Native(_) | Protobuf(_) => true,
             Csv(_) | Logfmt(_) | Gelf(_) | Json(_) | Text(_) | NativeJson(_) => false,
         }
     }
@@ -3,8 +3,8 @@ use std::{borrow::Cow, convert::TryFrom, fmt, hash::Hash, path::PathBuf};
 
 use bytes::Bytes;
 use chrono::{
-    format::{strftime::StrftimeItems, Item},
-    FixedOffset, Utc,
+    format::{strftime::StrftimeItems, Fixed, Item, Numeric},
+    DateTime, FixedOffset, Utc,
 };
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -162,39 +162,68 @@ impl Template {
         if self.is_static {
             Ok(self.src.clone())
         } else {
-            self.render_event(event.into())
+            self.render_event(event.into(), None, &|_, _| None)
         }
     }
 
-    fn render_event(&self, event: EventRef<'_>) -> Result<String, TemplateRenderingError> {
+    /// Renders the given template with data from the event, passing the value of each field
+    /// through `normalize`.
+    ///
+    /// `normalize` is called with the path of the field and its value, if present. The value it
+    /// returns, if any, is rendered in place of the value of the field, which is then no longer
+    /// considered missing.
+    pub fn render_string_normalized<'a>(
+        &self,
+        event: impl Into<EventRef<'a>>,
+        normalize: &dyn Fn(&str, Option<&str>) -> Option<String>,
+    ) -> Result<String, TemplateRenderingError> {
+        if self.is_static {
+            Ok(self.src.clone())
+        } else {
+            self.render_event(event.into(), None, normalize)
+        }
+    }
+
+    /// Renders the given template with data from the event, like `render_string_normalized`, but
+    /// formatting times from the given timestamp rather than from the timestamp of the event.
+    pub fn render_string_at<'a>(
+        &self,
+        event: impl Into<EventRef<'a>>,
+        timestamp: DateTime<Utc>,
+        normalize: &dyn Fn(&str, Option<&str>) -> Option<String>,
+    ) -> Result<String, TemplateRenderingError> {
+        if self.is_static {
+            Ok(self.src.clone())
+        } else {
+            self.render_event(event.into(), Some(timestamp), normalize)
+        }
+    }
+
+    fn render_event(
+        &self,
+        event: EventRef<'_>,
+        timestamp: Option<DateTime<Utc>>,
+        normalize: &dyn Fn(&str, Option<&str>) -> Option<String>,
+    ) -> Result<String, TemplateRenderingError> {
         let mut missing_keys = Vec::new();
         let mut out = String::with_capacity(self.reserve_size);
         for part in &self.parts {
             match part {
                 Part::Literal(lit) => out.push_str(lit),
                 Part::Strftime(items) => {
-                    out.push_str(&render_timestamp(items, event, self.tz_offset))
+                    let timestamp = timestamp.unwrap_or_else(|| event_timestamp(event));
+                    out.push_str(&render_timestamp(items, timestamp, self.tz_offset))
                 }
                 Part::Reference(key) => {
-                    out.push_str(
-                        &match event {
-                            EventRef::Log(log) => log
-                                .parse_path_and_get_value(key)
-                                .ok()
-                                .and_then(|v| v.map(Value::to_string_lossy)),
-                            EventRef::Metric(metric) => {
-                                render_metric_field(key, metric).map(Cow::Borrowed)
-                            }
-                            EventRef::Trace(trace) => trace
-                                .parse_path_and_get_value(key)
-                                .ok()
-                                .and_then(|v| v.map(Value::to_string_lossy)),
-                        }
-                        .unwrap_or_else(|| {
+                    let value = render_field(key, event);
+                    let value = match normalize(key, value.as_deref()) {
+                        Some(normalized) => Cow::Owned(normalized),
+                        None => value.unwrap_or_else(|| {
                             missing_keys.push(key.to_owned());
                             Cow::Borrowed("")
                         }),
-                    );
+                    };
+                    out.push_str(&value);
                 }
             }
         }
@@ -221,6 +250,33 @@ impl Template {
         (!parts.is_empty()).then_some(parts)
     }
 
+    /// Returns `true` if rendering this template depends on the timestamp of the event.
+    pub fn renders_timestamp(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Strftime(_)))
+    }
+
+    /// Returns `true` if this template renders fractions of seconds of the timestamp of the event.
+    pub fn renders_subseconds(&self) -> bool {
+        self.parts.iter().any(|part| match part {
+            Part::Strftime(items) => items.as_items().any(|item| {
+                matches!(
+                    item,
+                    Item::Numeric(Numeric::Nanosecond, _)
+                        | Item::Fixed(
+                            Fixed::Nanosecond
+                                | Fixed::Nanosecond3
+                                | Fixed::Nanosecond6
+                                | Fixed::Nanosecond9
+                                | Fixed::Internal(_)
+                        )
+                )
+            }),
+            Part::Literal(_) | Part::Reference(_) => false,
+        })
+    }
+
     /// Returns a reference to the template string.
     pub fn get_ref(&self) -> &str {
         &self.src
@@ -346,6 +402,21 @@ fn parse_template(src: &str) -> Result<Vec<Part>, TemplateParseError> {
     Ok(parts)
 }
 
+/// Renders the value of the field at the given path of the event, as it is rendered in templates.
+pub fn render_field<'a>(key: &str, event: EventRef<'a>) -> Option<Cow<'a, str>> {
+    match event {
+        EventRef::Log(log) => log
+            .parse_path_and_get_value(key)
+            .ok()
+            .and_then(|v| v.map(Value::to_string_lossy)),
+        EventRef::Metric(metric) => render_metric_field(key, metric).map(Cow::Borrowed),
+        EventRef::Trace(trace) => trace
+            .parse_path_and_get_value(key)
+            .ok()
+            .and_then(|v| v.map(Value::to_string_lossy)),
+    }
+}
+
 fn render_metric_field<'a>(key: &str, metric: &'a Metric) -> Option<&'a str> {
     match key {
         "name" => Some(metric.name()),
@@ -355,12 +426,10 @@ fn render_metric_field<'a>(key: &str, metric: &'a Metric) -> Option<&'a str> {
     }
 }
 
-fn render_timestamp(
-    items: &ParsedStrftime,
-    event: EventRef<'_>,
-    tz_offset: Option<FixedOffset>,
-) -> String {
-    let timestamp = match event {
+/// Returns the timestamp templates render for the event, which is the current time for events
+/// without a timestamp.
+pub fn event_timestamp(event: EventRef<'_>) -> DateTime<Utc> {
+    match event {
         EventRef::Log(log) => log.get_timestamp().and_then(Value::as_timestamp).copied(),
         EventRef::Metric(metric) => metric.timestamp(),
         EventRef::Trace(trace) => {
@@ -374,8 +443,14 @@ fn render_timestamp(
                 })
         }
     }
-    .unwrap_or_else(Utc::now);
+    .unwrap_or_else(Utc::now)
+}
 
+fn render_timestamp(
+    items: &ParsedStrftime,
+    timestamp: DateTime<Utc>,
+    tz_offset: Option<FixedOffset>,
+) -> String {
     match tz_offset {
         Some(offset) => timestamp
             .with_timezone(&offset)
@@ -734,6 +809,17 @@ mod tests {
         ))
     }
 
+    #[test]
+    fn renders_timestamp() {
+        let template = |src| Template::try_from(src).unwrap();
+        assert!(!template("{{ foo }}/static").renders_timestamp());
+        assert!(template("{{ foo }}/%F").renders_timestamp());
+        assert!(!template("%F/%H").renders_subseconds());
+        for src in ["%s%.3f", "%f", "%3f"] {
+            assert!(template(src).renders_subseconds(), "{src}");
+        }
+    }
+
     #[test]
     fn strftime_error() {
         assert_eq!(
@@ -568,6 +568,7 @@ impl<'a> Builder<'a> {
             };
 
             let cx = SinkContext {
+                key: key.clone(),
                 healthcheck,
                 globals: self.config.global.clone(),
                 proxy: ProxyConfig::merge_with_env(&self.config.global.proxy, sink.proxy()),
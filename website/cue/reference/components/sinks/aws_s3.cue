@@ -198,6 +198,16 @@ components: sinks: aws_s3: components._aws & {
 		}
 	}
 
+	telemetry: metrics: {
+		adaptive_concurrency_current_limit:   components.sources.internal_metrics.output.metrics.adaptive_concurrency_current_limit
+		aws_s3_clock_skew_detected_total:     components.sources.internal_metrics.output.metrics.aws_s3_clock_skew_detected_total
+		aws_s3_compression_selected_total:    components.sources.internal_metrics.output.metrics.aws_s3_compression_selected_total
+		aws_s3_distinct_partition_keys:       components.sources.internal_metrics.output.metrics.aws_s3_distinct_partition_keys
+		aws_s3_object_upload_latency_seconds: components.sources.internal_metrics.output.metrics.aws_s3_object_upload_latency_seconds
+		aws_s3_slo_violations_total:          components.sources.internal_metrics.output.metrics.aws_s3_slo_violations_total
+		s3_key_prefix_fallback_events_total:  components.sources.internal_metrics.output.metrics.s3_key_prefix_fallback_events_total
+	}
+
 	permissions: iam: [
 		{
 			platform:      "aws"
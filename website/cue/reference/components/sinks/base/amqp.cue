@@ -66,6 +66,13 @@ base: components: sinks: amqp: configuration: {
 
 						[apache_avro]: https://avro.apache.org/
 						"""
+					cbor: """
+						Encodes an event as a [CBOR][cbor] data item.
+
+						Events are represented as with the `json` codec.
+
+						[cbor]: https://cbor.io/
+						"""
 					csv: """
 						Encodes an event as a CSV message.
 
@@ -99,6 +106,13 @@ base: components: sinks: amqp: configuration: {
 
 						[logfmt]: https://brandur.org/logfmt
 						"""
+					message_pack: """
+						Encodes an event as a [MessagePack][message_pack] object.
+
+						Events are represented as with the `json` codec.
+
+						[message_pack]: https://msgpack.org/
+						"""
 					native: """
 						Encodes an event in the [native Protocol Buffers format][vector_native_protobuf].
 
@@ -228,6 +242,29 @@ base: components: sinks: amqp: configuration: {
 				required:    false
 				type: array: items: type: string: {}
 			}
+			flatten: {
+				description: """
+					Whether to flatten nested fields into top-level fields.
+
+					The name of each flattened field joins the path of the field with `flatten_separator`,
+					with the index of array elements as a segment, such that `{"a": {"b": [1]}}` is encoded as
+					`{"a.b.0": 1}`. Fields are flattened after `only_fields` and `except_fields` are applied,
+					which refer to fields by their nested path. When the names of flattened fields collide,
+					such as for a field `a.b` alongside a field `a` containing `b`, the field whose name sorts
+					last is kept. Empty objects and arrays are kept as they are.
+					"""
+				required: false
+				type: bool: default: false
+			}
+			flatten_separator: {
+				description: """
+					The separator joining the segments of the names of flattened fields.
+
+					Defaults to `.`.
+					"""
+				required: false
+				type: string: {}
+			}
 			json: {
 				description:   "Options for the JsonSerializer."
 				relevant_when: "codec = \"json\""
@@ -139,6 +139,10 @@ base: components: sinks: aws_cloudwatch_logs: configuration: {
 
 					This is based on the uncompressed size of the batched events, before they are
 					serialized/compressed.
+
+					This can be set either to an integer number of bytes or to a string with a unit, such as
+					`128MiB` or `10MB`. Units ending in `iB` are powers of 1024, and the others are powers of
+					1000, such that `1MiB` is 1048576 bytes while `1MB` is 1000000 bytes.
 					"""
 				required: false
 				type: uint: {
@@ -242,6 +246,13 @@ base: components: sinks: aws_cloudwatch_logs: configuration: {
 
 						[apache_avro]: https://avro.apache.org/
 						"""
+					cbor: """
+						Encodes an event as a [CBOR][cbor] data item.
+
+						Events are represented as with the `json` codec.
+
+						[cbor]: https://cbor.io/
+						"""
 					csv: """
 						Encodes an event as a CSV message.
 
@@ -275,6 +286,13 @@ base: components: sinks: aws_cloudwatch_logs: configuration: {
 
 						[logfmt]: https://brandur.org/logfmt
 						"""
+					message_pack: """
+						Encodes an event as a [MessagePack][message_pack] object.
+
+						Events are represented as with the `json` codec.
+
+						[message_pack]: https://msgpack.org/
+						"""
 					native: """
 						Encodes an event in the [native Protocol Buffers format][vector_native_protobuf].
 
@@ -404,6 +422,29 @@ base: components: sinks: aws_cloudwatch_logs: configuration: {
 				required:    false
 				type: array: items: type: string: {}
 			}
+			flatten: {
+				description: """
+					Whether to flatten nested fields into top-level fields.
+
+					The name of each flattened field joins the path of the field with `flatten_separator`,
+					with the index of array elements as a segment, such that `{"a": {"b": [1]}}` is encoded as
+					`{"a.b.0": 1}`. Fields are flattened after `only_fields` and `except_fields` are applied,
+					which refer to fields by their nested path. When the names of flattened fields collide,
+					such as for a field `a.b` alongside a field `a` containing `b`, the field whose name sorts
+					last is kept. Empty objects and arrays are kept as they are.
+					"""
+				required: false
+				type: bool: default: false
+			}
+			flatten_separator: {
+				description: """
+					The separator joining the segments of the names of flattened fields.
+
+					Defaults to `.`.
+					"""
+				required: false
+				type: string: {}
+			}
 			json: {
 				description:   "Options for the JsonSerializer."
 				relevant_when: "codec = \"json\""
@@ -139,6 +139,10 @@ base: components: sinks: aws_cloudwatch_metrics: configuration: {
 
 					This is based on the uncompressed size of the batched events, before they are
 					serialized/compressed.
+
+					This can be set either to an integer number of bytes or to a string with a unit, such as
+					`128MiB` or `10MB`. Units ending in `iB` are powers of 1024, and the others are powers of
+					1000, such that `1MiB` is 1048576 bytes while `1MB` is 1000000 bytes.
 					"""
 				required: false
 				type: uint: unit: "bytes"
@@ -204,6 +204,32 @@ base: components: sinks: aws_s3: configuration: {
 			}
 		}
 	}
+	auto_region: {
+		description: """
+			Whether or not to send requests to the region of the bucket when it is in another region.
+
+			When S3 reports that the bucket is in another region than the configured `region`, the
+			request is sent again to the region of the bucket, as are all later requests. Otherwise,
+			the request fails with an error naming the region of the bucket.
+
+			Has no effect when `endpoint` is set.
+			"""
+		required: false
+		type: bool: default: false
+	}
+	avro_object_container_file: {
+		description: """
+			Whether or not to write objects encoded with the `avro` codec as Avro Object Container
+			Files.
+
+			When enabled, each object starts with a header embedding the schema, and each event is
+			written as a data block of its own, such that each object is a standard `.avro` file
+			readable by any Avro tool, uploaded with the `application/avro` content type and the `avro`
+			extension. Requires the `avro` codec, with either the `bytes` framing or no framing set.
+			"""
+		required: false
+		type: bool: default: false
+	}
 	batch: {
 		description: "Event batching behavior."
 		required:    false
@@ -214,6 +240,10 @@ base: components: sinks: aws_s3: configuration: {
 
 					This is based on the uncompressed size of the batched events, before they are
 					serialized/compressed.
+
+					This can be set either to an integer number of bytes or to a string with a unit, such as
+					`128MiB` or `10MB`. Units ending in `iB` are powers of 1024, and the others are powers of
+					1000, such that `1MiB` is 1048576 bytes while `1MB` is 1000000 bytes.
 					"""
 				required: false
 				type: uint: {
@@ -236,14 +266,197 @@ base: components: sinks: aws_s3: configuration: {
 			}
 		}
 	}
+	bloom_filter: {
+		description: """
+			Configuration for writing a companion bloom filter object for each object.
+
+			Once an object is uploaded, a JSON object is written to the same key with a `.bloom` suffix,
+			holding a bloom filter over the value rendered from each of its events. Point lookups can then
+			read the filters first, and skip the objects whose filter does not contain the value.
+
+			The filter object contains the `source` template, the `hash` used, which is always `md5`, the
+			`num_bits` and `num_hashes` of the filter, the `item_count` of values inserted, and the `bits`
+			of the filter encoded as base64. To test a value, compute its MD5 digest, and read its first
+			and last 8 bytes as the little-endian unsigned integers `h1` and `h2`. The value may be in the
+			object if, for each `i` from `0` to `num_hashes - 1`, the bit `(h1 + i * h2) mod num_bits` is
+			set, computed with wrapping 64-bit arithmetic, where bit `j` is the bit `j mod 8` of byte
+			`j / 8` of `bits`, counting from the least significant bit. Otherwise, the value is certainly
+			not in the object.
+			"""
+		required: false
+		type: object: options: {
+			expected_items: {
+				description: """
+					The number of distinct values objects are expected to contain.
+
+					Along with `false_positive_rate`, this sets the size of the filters. Objects containing
+					more values than expected have a higher false positive rate.
+					"""
+				required: false
+				type: uint: default: 10000
+			}
+			false_positive_rate: {
+				description: """
+					The rate at which the filter reports values missing from an object as possibly present,
+					when the object contains `expected_items` values.
+
+					Must be strictly between `0` and `1`.
+					"""
+				required: false
+				type: float: default: 0.01
+			}
+			source: {
+				description: """
+					The template rendering the value each event is indexed by.
+
+					Events whose value fails to render are left out of the filter.
+					"""
+				required: true
+				type: string: {
+					examples: ["{{ user_id }}"]
+					syntax: "template"
+				}
+			}
+		}
+	}
 	bucket: {
 		description: """
-			The S3 bucket name.
+			The S3 bucket name, or the ARN of an access point.
 
 			This must not include a leading `s3://` or a trailing `/`.
+
+			Both S3 access point ARNs, such as `arn:aws:s3:<region>:<account-id>:accesspoint/<name>`,
+			and S3 on Outposts access point ARNs, such as
+			`arn:aws:s3-outposts:<region>:<account-id>:outpost/<outpost-id>/accesspoint/<name>`, are
+			supported. Requests are then sent to the region of the ARN, and `auto_region` has no effect.
 			"""
 		required: true
-		type: string: examples: ["my-bucket"]
+		type: string: examples: ["my-bucket", "arn:aws:s3:us-east-1:123456789012:accesspoint/my-access-point"]
+	}
+	byte_order_mark: {
+		description: """
+			Whether to add or strip a UTF-8 byte order mark (BOM) at the start of objects.
+
+			Some consumers, such as spreadsheet applications opening CSV files, rely on the BOM to detect
+			that text is UTF-8, while others fail to parse text starting with one. This only applies to
+			text-based codecs.
+			"""
+		required: false
+		type: string: {
+			default: "none"
+			enum: {
+				add: """
+					A BOM is written at the start of objects, unless they already start with one.
+
+					When `framing_scope` is set to `stream`, only the first object of each partition starts
+					with a BOM, as the following objects continue it.
+					"""
+				none:  "Objects are written as encoded."
+				strip: "The BOM objects start with, if any, is removed."
+			}
+		}
+	}
+	cache_control: {
+		description: """
+			Specifies caching behavior for the object.
+
+			Directly comparable to the `Cache-Control` HTTP header, which S3 returns when the object is
+			retrieved, such as through CloudFront.
+			"""
+		required: false
+		type: string: examples: ["max-age=3600", "no-cache, no-store"]
+	}
+	checksum_footer: {
+		description: """
+			A footer line appended to each object, containing a checksum of the rest of the object.
+
+			The checksum covers all the bytes of the object preceding the footer, before compression. When
+			the object does not end with a newline, one is written before the footer and covered by the
+			checksum. Objects of a partition no longer concatenate into a single document when
+			`framing_scope` is set to `stream`, unless consumers remove the footers.
+			"""
+		required: false
+		type: object: options: {
+			algorithm: {
+				description: "The algorithm used to compute the checksum of objects."
+				required:    false
+				type: string: {
+					default: "crc32"
+					enum: {
+						crc32: "CRC-32, as used by gzip."
+						md5:   "MD5."
+					}
+				}
+			}
+			format: {
+				description: """
+					The format of the footer line, excluding its trailing newline.
+
+					The placeholders `{algorithm}` and `{checksum}` are replaced with the name of the algorithm
+					and the checksum in lowercase hexadecimal, respectively.
+					"""
+				required: false
+				type: string: {
+					default: "#{algorithm}:{checksum}"
+					examples: ["#{algorithm}:{checksum}"]
+				}
+			}
+		}
+	}
+	clock_skew_threshold_secs: {
+		description: """
+			The difference between the system clock and event timestamps, in seconds, above which clock
+			skew is reported.
+
+			When set, the latest event timestamp of each object is compared to the system clock when
+			the object is named, and objects for which they differ by more than this are counted by the
+			`aws_s3_clock_skew_detected_total` counter, with a warning. This surfaces hosts whose clock
+			would date keys in the wrong partition, though delayed events are reported as well. By
+			default, clock skew is not checked.
+			"""
+		required: false
+		type: uint: {
+			examples: [300]
+			unit: "seconds"
+		}
+	}
+	collapse_identical_events: {
+		description: """
+			Whether or not to collapse batches whose events are all encoded identically.
+
+			When set, a batch whose events all encode to the same bytes, such as repeated status
+			snapshots, is written as a single copy of its event, and the number of events it stands for
+			is set as the `collapsed_count` tag of the object. All of the events are still acknowledged
+			once the object is written, while the reported byte sizes are those of the collapsed
+			object. Each event is encoded once more to compare it, so this is best left unset unless
+			such batches are common.
+			"""
+		required: false
+		type: bool: default: false
+	}
+	collapse_trailing_delimiter: {
+		description: """
+			Whether or not to skip the delimiter after events which already end with it.
+
+			Some sources produce events whose encoded form already ends with a newline, which
+			newline-delimited framing follows with another one, leaving empty lines that strict NDJSON
+			readers reject. When set, events already ending with the delimiter of `framing` are not
+			followed by another one. This only applies to `newline_delimited` and `character_delimited`
+			framing.
+			"""
+		required: false
+		type: bool: default: false
+	}
+	component_id_tag: {
+		description: """
+			The key of a tag set on each object to the ID of the sink component that wrote it.
+
+			This attributes objects to the sink that wrote them, such as for cost allocation or
+			debugging in buckets written by several sinks or Vector instances. A tag of the same key
+			set in `tags` takes precedence.
+			"""
+		required: false
+		type: string: examples: ["vector_component_id"]
 	}
 	compression: {
 		description: """
@@ -282,6 +495,79 @@ base: components: sinks: aws_s3: configuration: {
 			}
 		}
 	}
+	compression_candidates: {
+		description: """
+			Compression algorithms to also compress each object with, uploading whichever of these and
+			`compression` makes the object the smallest.
+
+			The extension and `Content-Encoding` of objects are those of the selected compression,
+			unless `filename_extension` or `content_encoding` are set. This trades CPU for storage, as
+			each object is compressed once per algorithm, and the number of objects each algorithm was
+			selected for is counted by the `aws_s3_compression_selected_total` counter. By default,
+			objects are only compressed with `compression`.
+			"""
+		required: false
+		type: array: {
+			default: []
+			items: type: string: enum: {
+				gzip: """
+					[Gzip][gzip] compression.
+
+					[gzip]: https://www.gzip.org/
+					"""
+				none: "No compression."
+				snappy: """
+					[Snappy][snappy] compression.
+
+					[snappy]: https://github.com/google/snappy/blob/main/docs/README.md
+					"""
+				zlib: """
+					[Zlib][zlib] compression.
+
+					[zlib]: https://zlib.net/
+					"""
+				zstd: """
+					[Zstandard][zstd] compression.
+
+					[zstd]: https://facebook.github.io/zstd/
+					"""
+			}
+		}
+	}
+	compression_min_bytes: {
+		description: """
+			The size, in bytes, below which objects are uploaded uncompressed.
+
+			Compression formats add headers and footers of their own, so compressing very small objects
+			can make them larger than they were. Objects whose encoded size is below this threshold are
+			uploaded as is, without the extension and `Content-Encoding` of the compression, unless
+			`filename_extension` or `content_encoding` are set.
+
+			By default, all objects are compressed.
+			"""
+		required: false
+		type: uint: {
+			examples: [256]
+			unit: "bytes"
+		}
+	}
+	connection_retry_limit_secs: {
+		description: """
+			How long, in seconds, to keep retrying requests which fail to connect to the endpoint.
+
+			Failures to resolve the name of the endpoint, refused connections, and connection timeouts
+			are transient for custom endpoints, such as during a DNS failover, and are retried with
+			backoff. Once requests have been failing to connect for longer than this, they are no longer
+			retried, until a request succeeds.
+
+			By default, they are retried for as long as any other retriable error.
+			"""
+		required: false
+		type: uint: {
+			examples: [300]
+			unit: "seconds"
+		}
+	}
 	content_encoding: {
 		description: """
 			Overrides what content encoding has been applied to the object.
@@ -302,11 +588,85 @@ base: components: sinks: aws_s3: configuration: {
 			Directly comparable to the `Content-Type` HTTP header.
 
 			If not specified, the compression scheme used dictates this value.
-			When `compression` is set to `none`, the value `text/x-log` is used.
+			When `compression` is set to `none`, the value `text/x-log` is used. Objects encoded with
+			the `cbor` or `message_pack` codecs use `application/cbor` or `application/msgpack`.
 			"""
 		required: false
 		type: string: examples: ["application/gzip"]
 	}
+	deterministic_compression: {
+		description: """
+			Whether or not to compress zstd objects deterministically.
+
+			When set, objects compressed with `zstd`, including as one of the `compression_candidates`,
+			are compressed in one go, on a single thread, as a single frame with a fixed configuration,
+			such that identical objects are compressed to identical bytes. This suits content-addressed
+			storage and deduplication, at the cost of holding each object uncompressed in memory while it
+			is compressed. Frames record the size of the object, and carry no content checksum.
+
+			Gzip objects are always compressed deterministically, as their header carries no
+			modification time.
+			"""
+		required: false
+		type: bool: default: false
+	}
+	embedded_newlines: {
+		description: """
+			How line breaks within the string values of events are written.
+
+			With newline-delimited framing, an event whose encoded form contains a line break spans
+			several lines, such as with the `text` codec, breaking readers expecting one event per line.
+			This only applies to log and trace events, and to `newline_delimited` framing, or
+			`character_delimited` framing with a `\\n` delimiter.
+			"""
+		required: false
+		type: string: {
+			default: "keep"
+			enum: {
+				escape: "Line breaks are escaped as in JSON strings, by a backslash followed by `n` or `r`."
+				keep:   "Line breaks are written as is."
+				strip:  "Line breaks are removed."
+			}
+		}
+	}
+	emf_summary: {
+		description: """
+			Configuration for writing summaries of the activity of the sink as CloudWatch embedded metric
+			format (EMF) objects.
+
+			Every interval, an object is written containing the number of events and bytes written by the
+			sink, and the number of requests which failed, during the interval, as an EMF document with
+			the `ComponentId` and `Host` dimensions. Once ingested by CloudWatch, they are extracted as
+			metrics, which allows the sink to be monitored where the metrics of Vector can't be scraped.
+			"""
+		required: false
+		type: object: options: {
+			interval_secs: {
+				description: "How often to write a summary object, in seconds."
+				required:    false
+				type: uint: {
+					default: 60
+					unit:    "seconds"
+				}
+			}
+			key_prefix: {
+				description: """
+					The prefix of the keys of the summary objects.
+
+					Objects are named after the time they are written, in milliseconds since the Unix epoch,
+					such as `emf/1658176486000.json`. The prefix should be outside of `key_prefix`, so that the
+					summaries are not mistaken for objects of events.
+					"""
+				required: true
+				type: string: examples: ["emf/"]
+			}
+			namespace: {
+				description: "The CloudWatch namespace of the metrics."
+				required:    false
+				type: string: default: "Vector"
+			}
+		}
+	}
 	encoding: {
 		description: "Configures how events are encoded into raw bytes."
 		required:    true
@@ -330,6 +690,13 @@ base: components: sinks: aws_s3: configuration: {
 
 						[apache_avro]: https://avro.apache.org/
 						"""
+					cbor: """
+						Encodes an event as a [CBOR][cbor] data item.
+
+						Events are represented as with the `json` codec.
+
+						[cbor]: https://cbor.io/
+						"""
 					csv: """
 						Encodes an event as a CSV message.
 
@@ -363,6 +730,13 @@ base: components: sinks: aws_s3: configuration: {
 
 						[logfmt]: https://brandur.org/logfmt
 						"""
+					message_pack: """
+						Encodes an event as a [MessagePack][message_pack] object.
+
+						Events are represented as with the `json` codec.
+
+						[message_pack]: https://msgpack.org/
+						"""
 					native: """
 						Encodes an event in the [native Protocol Buffers format][vector_native_protobuf].
 
@@ -492,6 +866,29 @@ base: components: sinks: aws_s3: configuration: {
 				required:    false
 				type: array: items: type: string: {}
 			}
+			flatten: {
+				description: """
+					Whether to flatten nested fields into top-level fields.
+
+					The name of each flattened field joins the path of the field with `flatten_separator`,
+					with the index of array elements as a segment, such that `{"a": {"b": [1]}}` is encoded as
+					`{"a.b.0": 1}`. Fields are flattened after `only_fields` and `except_fields` are applied,
+					which refer to fields by their nested path. When the names of flattened fields collide,
+					such as for a field `a.b` alongside a field `a` containing `b`, the field whose name sorts
+					last is kept. Empty objects and arrays are kept as they are.
+					"""
+				required: false
+				type: bool: default: false
+			}
+			flatten_separator: {
+				description: """
+					The separator joining the segments of the names of flattened fields.
+
+					Defaults to `.`.
+					"""
+				required: false
+				type: string: {}
+			}
 			json: {
 				description:   "Options for the JsonSerializer."
 				relevant_when: "codec = \"json\""
@@ -568,6 +965,107 @@ base: components: sinks: aws_s3: configuration: {
 		required:    false
 		type: string: examples: ["http://127.0.0.0:5000/path/to/service"]
 	}
+	event_time_tags: {
+		description: """
+			Configuration for tagging objects with the range of the timestamps of their events.
+
+			Objects are tagged with the earliest and latest timestamps of their events, formatted as RFC
+			3339 to the second, under the `min_event_time` and `max_event_time` tags. Along with
+			tag-based lifecycle rules, this allows objects to be expired according to the time of their
+			events rather than the time they were uploaded. Events without a timestamp are left out of the
+			range, and objects without any event with a timestamp are not tagged.
+
+			As S3 allows at most 10 tags per object, Vector fails to start if these tags, along with the
+			other tags set by the sink, could exceed that limit.
+			"""
+		required: false
+		type: object: options: timestamp_field: {
+			description: """
+				The field holding the timestamp of log events.
+
+				By default, the timestamp of the event is used. Values of the field which are not
+				timestamps are ignored.
+				"""
+			required: false
+			type: string: examples: ["event_time"]
+		}
+	}
+	event_time_windowing: {
+		description: """
+			Partitioning of events by tumbling windows of their timestamp, with allowed lateness.
+
+			The sink tracks a watermark, the latest timestamp seen. Events within the window of the
+			watermark, or after it, are partitioned by `key_prefix` as usual. Events before it, but within
+			the allowed lateness of its start, are partitioned as if their timestamp were its start, so that
+			they are written to the current window rather than reopening a closed one. Events later than
+			that are handled according to `late_events`.
+
+			Windows are aligned to the Unix epoch, and the times of `key_prefix` should be no finer than
+			the windows. Events without a timestamp are partitioned as usual.
+			"""
+		required: false
+		type: object: options: {
+			allowed_lateness_secs: {
+				description: """
+					How late events can be, in seconds before the start of the current window, to still be
+					written to it.
+					"""
+				required: false
+				type: uint: {
+					default: 0
+					examples: [300]
+					unit: "seconds"
+				}
+			}
+			late_events: {
+				description: "How events later than the allowed lateness are handled."
+				required:    false
+				type: string: {
+					default: "redirect"
+					enum: {
+						drop:     "Drop late events, keeping closed windows complete at the cost of losing them."
+						redirect: "Write late events under `late_prefix`, keeping closed windows complete."
+						reopen:   "Write late events to their own window, as usual, reopening it with a new object."
+					}
+				}
+			}
+			late_prefix: {
+				description: "The prefix of the keys of events later than the allowed lateness."
+				required:    false
+				type: string: default: "_late/"
+			}
+			timestamp_field: {
+				description: """
+					The field holding the timestamp of the events.
+
+					By default, the timestamp of the event is used.
+					"""
+				required: false
+				type: string: examples: ["event_time"]
+			}
+			window_secs: {
+				description: "The length of the windows, in seconds."
+				required:    true
+				type: uint: {
+					examples: [3600]
+					unit: "seconds"
+				}
+			}
+		}
+	}
+	expires_secs: {
+		description: """
+			The time after an object is built at which it can no longer be cached, in seconds.
+
+			Sets the `Expires` HTTP header of the object, which S3 returns when the object is
+			retrieved, to the date at which this time elapses.
+			"""
+		required: false
+		type: uint: {
+			examples: [86400]
+			unit: "seconds"
+		}
+	}
 	filename_append_uuid: {
 		description: """
 			Whether or not to append a UUID v4 token to the end of the object key.
@@ -586,13 +1084,35 @@ base: components: sinks: aws_s3: configuration: {
 		description: """
 			The filename extension to use in the object key.
 
-			This overrides setting the extension based on the configured `compression`.
+			This overrides setting the extension based on the configured `compression`, and on the
+			codec when it is `cbor` or `message_pack`.
 			"""
 		required: false
 		type: string: examples: [
 			"json",
 		]
 	}
+	filename_sequence_start: {
+		description: """
+			The sequence number of the first object, when prefixing filenames with a sequence number.
+
+			When set, the filename of each object is prefixed with a sequence number, zero-padded to 20
+			digits, such that an object key of `date=2022-07-18/1658176486` becomes
+			`date=2022-07-18/00000000000000000042-1658176486`. Object keys within a key prefix then sort
+			in the order objects were built, regardless of clock skew, and retried uploads keep their
+			sequence number.
+
+			The sequence is kept in memory, restarting at this value whenever Vector restarts, and is
+			only monotonic for a single instance of this sink. Writing to the same key prefix from
+			several sinks or Vector instances requires keeping their sequences apart, for example with
+			distinct key prefixes. Concurrent requests may complete out of order, so set
+			`request.concurrency` to `1` if objects must also be written in sequence order.
+			"""
+		required: false
+		type: uint: examples: [
+			0,
+		]
+	}
 	filename_time_format: {
 		description: """
 			The timestamp format for the time component of the object key.
@@ -615,19 +1135,76 @@ base: components: sinks: aws_s3: configuration: {
 		required: false
 		type: string: default: "%s"
 	}
-	framing: {
-		description: "Framing configuration."
+	filename_time_source: {
+		description: "The time object filenames are named after."
 		required:    false
-		type: object: options: {
-			character_delimited: {
-				description:   "Options for the character delimited encoder."
-				relevant_when: "method = \"character_delimited\""
-				required:      true
-				type: object: options: delimiter: {
-					description: "The ASCII (7-bit) character that delimits byte sequences."
-					required:    true
-					type: ascii_char: {}
-				}
+		type: string: {
+			default: "clock"
+			enum: {
+				clock: "The system clock when the object is built."
+				latest_event: """
+					The system clock, clamped to the latest event timestamp of the object.
+
+					This prevents keys dated in the future on hosts whose clock is ahead of their events.
+					Objects without event timestamps use the system clock.
+					"""
+				monotonic: """
+					The system clock, never going back before the time of the previous object.
+
+					Filenames keep their order when the clock is stepped back, such as by NTP, at the cost of
+					reusing the last time until the clock catches up.
+					"""
+			}
+		}
+	}
+	flush_at_boundary_secs: {
+		description: """
+			The interval, in seconds, of the wall-clock boundaries at which all batches are flushed.
+
+			Boundaries fall on multiples of the interval since midnight, in the timezone of `timezone`,
+			such that an interval of `3600` flushes all batches at the start of every hour. This lets
+			consumers processing the objects of each hour once it has passed find them complete, when
+			`key_prefix` partitions events by hour. Batches are flushed regardless of
+			`min_events_per_object`.
+			"""
+		required: false
+		type: uint: {
+			examples: [3600]
+			unit: "seconds"
+		}
+	}
+	flush_signal: {
+		description: """
+			A signal which flushes the batches of all partitions when Vector receives it.
+
+			Batches are flushed at once, regardless of `batch.timeout_secs`, `min_events_per_object`,
+			`min_write_interval_secs`, and `startup_warmup_secs`, then batching resumes as usual. This sets
+			the boundaries of objects on demand, such as before taking a snapshot of the bucket. Objects
+			already being uploaded, multipart uploads included, are completed as usual.
+
+			Vector also reloads its configuration on `SIGHUP`, which leaves the sink running as it is when
+			its configuration is unchanged. Only supported on Unix.
+			"""
+		required: false
+		type: string: enum: {
+			sighup:  "Flush on `SIGHUP`."
+			sigusr1: "Flush on `SIGUSR1`."
+			sigusr2: "Flush on `SIGUSR2`."
+		}
+	}
+	framing: {
+		description: "Framing configuration."
+		required:    false
+		type: object: options: {
+			character_delimited: {
+				description:   "Options for the character delimited encoder."
+				relevant_when: "method = \"character_delimited\""
+				required:      true
+				type: object: options: delimiter: {
+					description: "The ASCII (7-bit) character that delimits byte sequences."
+					required:    true
+					type: ascii_char: {}
+				}
 			}
 			length_delimited: {
 				description:   "Options for the length delimited decoder."
@@ -672,6 +1249,55 @@ base: components: sinks: aws_s3: configuration: {
 			}
 		}
 	}
+	framing_scope: {
+		description: """
+			Whether the batch prefix and suffix of the encoding apply to each object, or to all the objects
+			of a partition.
+
+			This matters for encodings which enclose batches of events, such as JSON arrays when using the
+			`json` codec with the default framing, when the objects are concatenated by consumers.
+			"""
+		required: false
+		type: string: {
+			default: "per_object"
+			enum: {
+				per_object: "Each object is a complete document, enclosed by the batch prefix and suffix."
+				stream: """
+					The objects of each partition form a single document once concatenated in order.
+
+					Only the first object of a partition starts with the batch prefix, and following objects
+					start with the separator between events instead. Only the last object, written when Vector
+					shuts down, ends with the batch suffix. Partitions without events left to write on shutdown
+					are closed with an object containing only the batch suffix.
+
+					Objects must be concatenated in the order they were written, so this is best combined with
+					a `filename_time_format` which sorts in that order. The set of partitions which have
+					started a document is kept in memory until shutdown.
+					"""
+			}
+		}
+	}
+	generation_metadata: {
+		description: """
+			Ordering metadata written to each object, ordering objects across restarts of the sink.
+
+			The sink keeps a generation counter in a marker object, which is read and incremented on the
+			first upload after each start. Each object is then written with the generation and its sequence
+			number within the generation, starting from 0, as the `x-amz-meta-generation` and
+			`x-amz-meta-seq` user-defined metadata. Objects are ordered by their generation, then by their
+			sequence number, regardless of clock skew, as long as a single sink writes the marker object.
+
+			Sequence numbers are assigned as objects are built, so retries keep them, though concurrent
+			uploads can complete out of order. Objects uploaded again from `spill`, and companion objects,
+			have no ordering metadata.
+			"""
+		required: false
+		type: object: options: marker_key: {
+			description: "The key of the marker object holding the generation, in `bucket`."
+			required:    true
+			type: string: examples: ["vector/generation"]
+		}
+	}
 	grant_full_control: {
 		description: """
 			Grants `READ`, `READ_ACP`, and `WRITE_ACP` permissions on the created objects to the named [grantee].
@@ -695,41 +1321,816 @@ base: components: sinks: aws_s3: configuration: {
 		required: false
 		type: string: examples: ["79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be", "person@email.com", "http://acs.amazonaws.com/groups/global/AllUsers"]
 	}
-	grant_read_acp: {
+	grant_read_acp: {
+		description: """
+			Grants `READ_ACP` permissions on the created objects to the named [grantee].
+
+			This allows the grantee to read the ACL on the created objects.
+
+			[grantee]: https://docs.aws.amazon.com/AmazonS3/latest/dev/acl-overview.html#specifying-grantee
+			"""
+		required: false
+		type: string: examples: ["79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be", "person@email.com", "http://acs.amazonaws.com/groups/global/AllUsers"]
+	}
+	grant_write_acp: {
+		description: """
+			Grants `WRITE_ACP` permissions on the created objects to the named [grantee].
+
+			This allows the grantee to modify the ACL on the created objects.
+
+			[grantee]: https://docs.aws.amazon.com/AmazonS3/latest/dev/acl-overview.html#specifying-grantee
+			"""
+		required: false
+		type: string: examples: ["79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be", "person@email.com", "http://acs.amazonaws.com/groups/global/AllUsers"]
+	}
+	gzip_header_os: {
+		description: """
+			The value of the OS field of the header of gzip objects.
+
+			Some strict decompressors only accept given values, such as `255` (unknown) or `3` (Unix).
+			By default, the field is set to `255`. Objects left uncompressed by `compression_min_bytes`,
+			or compressed with another of the `compression_candidates`, are unaffected.
+
+			Requires `compression` to be set to `gzip`, and can't be combined with `write_gzip_index`.
+			"""
+		required: false
+		type: uint: examples: [3]
+	}
+	hash_partitioning: {
+		description: """
+			Partitioning of events into a fixed number of buckets by the hash of a key, for balanced read
+			parallelism.
+
+			Each event is assigned to the bucket `crc32(key) % buckets`, where `crc32` is the CRC-32 (IEEE
+			802.3, as used by gzip) of the UTF-8 bytes of the key rendered from the event, so that readers
+			can reproduce the assignment. Events are partitioned under `bucket=<number>`, the number
+			zero-padded to the width of the largest one, followed by a `/`, preceding the label of
+			`range_partitioning`, such that an object key of `date=2022-07-18/1658176486` becomes
+			`bucket=07/date=2022-07-18/1658176486`.
+
+			Events sharing a key are always in the same bucket, so buckets are balanced as long as the key
+			has many distinct values, each making up a small share of the events. Events whose key fails to
+			render are in bucket 0.
+			"""
+		required: false
+		type: object: options: {
+			buckets: {
+				description: "The number of buckets."
+				required:    true
+				type: uint: examples: [16]
+			}
+			key: {
+				description: "The key events are hashed by."
+				required:    true
+				type: string: {
+					examples: ["{{ user_id }}"]
+					syntax: "template"
+				}
+			}
+		}
+	}
+	heartbeat: {
+		description: """
+			Configuration for writing a heartbeat object periodically.
+
+			The heartbeat object is overwritten with each heartbeat, so its age shows whether the sink is
+			running even when no events flow through it.
+			"""
+		required: false
+		type: object: options: {
+			interval_secs: {
+				description: "How often to write the heartbeat object, in seconds."
+				required:    false
+				type: uint: {
+					default: 60
+					unit:    "seconds"
+				}
+			}
+			key: {
+				description: """
+					The key of the heartbeat object.
+
+					The object contains, as JSON, the `timestamp` it was written at, the `host` running Vector,
+					and the `component_id` of the sink. The key is used as is, and should be outside of
+					`key_prefix`, so that the heartbeat is not mistaken for an object of events.
+					"""
+				required: true
+				type: string: examples: ["heartbeats/vector.json"]
+			}
+		}
+	}
+	invalid_utf8: {
+		description: """
+			How invalid UTF-8 in the encoded form of events is handled.
+
+			Text codecs, such as `text`, write string values as they are, so that an event holding invalid
+			UTF-8 makes the whole object invalid for strict readers. This only applies to text codecs.
+			"""
+		required: false
+		type: string: {
+			default: "keep"
+			enum: {
+				error:   "Events encoded to invalid UTF-8 are rejected with an error, and dropped."
+				keep:    "Invalid UTF-8 is written as is."
+				replace: "Invalid sequences are replaced by the replacement character, `U+FFFD`."
+				strip:   "Invalid sequences are removed."
+			}
+		}
+	}
+	key_prefix: {
+		description: """
+			A prefix to apply to all object keys.
+
+			Prefixes are useful for partitioning objects, such as by creating an object key that
+			stores objects under a particular directory. If using a prefix for this purpose, it must end
+			in `/` to act as a directory path. A trailing `/` is **not** automatically added.
+			"""
+		required: false
+		type: string: {
+			default: "date=%F"
+			examples: ["date=%F/hour=%H", "year=%Y/month=%m/day=%d", "application_id={{ application_id }}/date=%F"]
+			syntax: "template"
+		}
+	}
+	key_prefix_fallbacks: {
+		description: """
+			Templates to render the key prefix of events with when `key_prefix` fails to render, or
+			renders empty.
+
+			The templates are tried in order, and the first to render a non-empty prefix is used, such
+			that events from sources with different schemas are each partitioned by the best field they
+			have. Events for which none of them render are handled according to `on_missing_key`.
+			"""
+		required: false
+		type: array: {
+			default: []
+			items: type: string: {
+				examples: ["app={{ app }}/"]
+				syntax: "template"
+			}
+		}
+	}
+	key_prefix_mapping: {
+		description: """
+			A table normalizing a value rendered from each event into a segment prefixed to `key_prefix`.
+
+			This keeps prefixes stable when the same value is represented inconsistently, such as a
+			severity of `ERROR`, `err` or `3`. The segment is followed by a `/`, and precedes the rendered
+			`key_prefix`, including the fallback prefix of `on_missing_key`.
+			"""
+		required: false
+		type: object: options: {
+			default: {
+				description: "The segment of values missing from `values`, or which fail to render."
+				required:    true
+				type: string: examples: ["other"]
+			}
+			source: {
+				description: "The template rendering the value to normalize."
+				required:    true
+				type: string: {
+					examples: ["{{ severity }}"]
+					syntax: "template"
+				}
+			}
+			values: {
+				description: "The segment each rendered value is normalized to."
+				required:    true
+				type: object: {
+					examples: [{
+						ERROR: "error"
+						WARN:  "warn"
+						err:   "error"
+					}]
+					options: "*": {
+						description: "The segment for the rendered value."
+						required:    true
+						type: string: {}
+					}
+				}
+			}
+		}
+	}
+	key_segment_normalizers: {
+		description: """
+			Normalizers for the values of fields rendered in `key_prefix`, by the path of the field.
+
+			This keeps partitions by enriched fields clean, such as a country code rendered as
+			`{{ geo.country }}`, so that `us` and `US` share a prefix and unexpected or missing values
+			don't create new ones. Each field must be referenced in `key_prefix`. Events missing a
+			normalized field are partitioned under its `invalid` value rather than being handled by
+			`on_missing_key`.
+			"""
+		required: false
+		type: object: options: "*": {
+			description: "The normalizer of the field."
+			required:    true
+			type: object: options: {
+				allowed: {
+					description: "The values allowed in the key prefix, such as a list of ISO country codes."
+					required:    true
+					type: array: items: type: string: examples: ["us", "fr", "de"]
+				}
+				invalid: {
+					description: "The value rendered in place of values which are missing or not allowed."
+					required:    false
+					type: string: default: "unknown"
+				}
+				lowercase: {
+					description: "Whether or not to lowercase values, and the allowed values, before comparing them."
+					required:    false
+					type: bool: default: true
+				}
+			}
+		}
+	}
+	lag_partitioning: {
+		description: """
+			Partitioning of events by their ingest lag, the time between their timestamp and when they are
+			received by the sink.
+
+			This isolates late-arriving events from on-time ones, such as to inspect backpressure. The
+			segment of the lag of each event is followed by a `/`, and precedes the segment of
+			`key_prefix_mapping` and the rendered `key_prefix`, such that an object key of
+			`date=2022-07-18/1658176486` becomes `late/date=2022-07-18/1658176486`. Events without a
+			timestamp, or with one in the future, are on time.
+			"""
+		required: false
+		type: object: options: {
+			on_time: {
+				description: "The segment of events lagging less than every threshold."
+				required:    false
+				type: string: default: "ontime"
+			}
+			thresholds: {
+				description: """
+					The segments of events lagging at least as much as each threshold.
+
+					Events are partitioned under the segment of the largest threshold their lag reaches.
+					"""
+				required: true
+				type: array: {
+					examples: [[{
+						min_lag_secs: 300
+						segment:      "late"
+					}, {
+						min_lag_secs: 3600
+						segment:      "very_late"
+					}]]
+					items: type: object: options: {
+						min_lag_secs: {
+							description: "The minimum lag of the events of the segment, in seconds."
+							required:    true
+							type: uint: unit: "seconds"
+						}
+						segment: {
+							description: "The segment of the events."
+							required:    true
+							type: string: {}
+						}
+					}
+				}
+			}
+			timestamp_field: {
+				description: """
+					The field holding the timestamp to measure the lag of.
+
+					By default, the timestamp of the event is used.
+					"""
+				required: false
+				type: string: examples: ["event_time"]
+			}
+		}
+	}
+	lineage: {
+		description: """
+			Configuration for recording the source offsets of the events of each object.
+
+			The companion metadata object written by `write_object_metadata` is given a `lineage` array
+			holding, for each source partition of the events of the object, the values of its
+			`partition_fields`, the `min_offset` and `max_offset` of its events, and their `event_count`.
+			This lets consumers trace an object back to the exact range of records it was built from, such
+			as the offsets of each Kafka topic partition, for auditing and replay.
+
+			Only log events holding an integer offset are recorded.
+			"""
+		required: false
+		type: object: options: {
+			offset_field: {
+				description: "The field of log events holding their offset within their source partition."
+				required:    false
+				type: string: {
+					default: "offset"
+					examples: ["offset"]
+				}
+			}
+			partition_fields: {
+				description: """
+					The fields of log events identifying their source partition.
+
+					Fields the events have no value for are left out of their partition.
+					"""
+				required: false
+				type: array: {
+					default: ["topic", "partition"]
+					items: type: string: examples: ["topic"]
+				}
+			}
+		}
+	}
+	log_object_versions: {
+		description: """
+			Whether or not to log the version ID of each object once it is uploaded.
+
+			On buckets with versioning enabled, each upload creates a new version of its object. When
+			set, the bucket, key, and version ID of each object are logged at the `info` level once it
+			is uploaded, for audit trails recording exactly which version each write created. Nothing
+			is logged for buckets without versioning.
+			"""
+		required: false
+		type: bool: default: false
+	}
+	lowercase_keys: {
+		description: """
+			Whether or not to convert the entire object key to lowercase.
+
+			This is useful when objects are consumed by tools which treat keys case-insensitively. The
+			`key_prefix` is also lowercased when partitioning events, so that events whose keys only
+			differ by case are batched into the same object rather than into colliding objects.
+			"""
+		required: false
+		type: bool: default: false
+	}
+	max_age_secs: {
+		description: """
+			The maximum age of a batch, in seconds, before it is flushed.
+
+			This bounds the latency introduced by `min_events_per_object`: once a batch is this old, it
+			is flushed even if it contains fewer than the minimum number of events. The age of a batch
+			is measured from when its first event is received.
+			"""
+		required: false
+		type: uint: {
+			examples: [900]
+			unit: "seconds"
+		}
+	}
+	max_concurrent_uploads_per_prefix: {
+		description: """
+			The maximum number of objects uploaded concurrently under each key prefix.
+
+			S3 limits the request rate of each prefix, such that uploading many objects to a single
+			prefix at once is throttled even when the overall concurrency is within limits. Prefixes
+			are those of the keys of the objects, up to their last `/`. Uploads beyond this wait for
+			another upload to the same prefix to complete, retries included, while holding on to the
+			overall concurrency set by `request.concurrency`.
+
+			By default, uploads to the same prefix are only limited by the overall concurrency.
+			"""
+		required: false
+		type: uint: examples: [4]
+	}
+	max_event_bytes: {
+		description: """
+			The maximum size of events, in bytes.
+
+			Events are measured by their size in memory, before they are encoded, and events larger than
+			this are rejected and counted as discarded rather than being encoded and batched. This keeps
+			a single malformed event, such as one hundreds of megabytes large, from exhausting the
+			memory of the sink.
+
+			By default, events of any size are accepted.
+			"""
+		required: false
+		type: uint: {
+			examples: [10485760]
+			unit: "bytes"
+		}
+	}
+	max_key_length: {
+		description: """
+			The maximum length of object keys, in bytes.
+
+			S3 rejects object keys longer than 1024 bytes. Keys exceeding this length are handled
+			according to `on_key_too_long`.
+			"""
+		required: false
+		type: uint: {
+			default: 1024
+			unit:    "bytes"
+		}
+	}
+	min_events_per_object: {
+		description: """
+			The minimum number of events an object should contain.
+
+			When set, a batch which reaches `batch.timeout_secs` with fewer events is held open until
+			it reaches this number of events, rather than being flushed as a small object. Batch size
+			limits still apply, so full batches are flushed regardless.
+
+			This trades freshness for fewer, larger objects, and requires `max_age_secs` to be set.
+			"""
+		required: false
+		type: uint: {
+			examples: [1000]
+			unit: "events"
+		}
+	}
+	min_write_interval_secs: {
+		description: """
+			The minimum interval, in seconds, between writes of objects of the same partition.
+
+			A batch which reaches `batch.timeout_secs` within this interval of the previous flush of
+			its partition is held open, and keeps accumulating events, until the interval has passed.
+			This coalesces frequent writes to the same object key, such as when `key_prefix` renders
+			a stable key that is rewritten on each flush, so that it is written at most once per
+			interval. Batch size limits, `max_age_secs`, and `flush_at_boundary_secs` still apply.
+			"""
+		required: false
+		type: uint: {
+			examples: [60]
+			unit: "seconds"
+		}
+	}
+	multipart_part_size_bytes: {
+		description: """
+			The size, in bytes, of the parts of multipart uploads.
+
+			Larger parts take fewer requests to upload an object, at the cost of more data uploaded
+			again when a part fails. The size must be between 5 MiB and 5 GiB. Objects too large to be
+			uploaded in 10,000 parts of this size, the most S3 allows, are uploaded in larger parts,
+			and a warning is logged.
+
+			By default, parts are 8 MiB.
+			"""
+		required: false
+		type: uint: {
+			examples: [67108864]
+			unit: "bytes"
+		}
+	}
+	multipart_threshold_bytes: {
+		description: """
+			The size, in bytes, above which objects are uploaded with a multipart upload.
+
+			Objects larger than this are uploaded in parts of `multipart_part_size_bytes`, which avoids
+			the 5 GiB limit on the size of a single upload, while smaller objects are still uploaded
+			with a single request. The size is that of the object once encoded and compressed. Parts
+			failing with a retriable error are retried on their own, up to 4 times in all. If a part
+			still fails to upload, the multipart upload is aborted and the whole object is uploaded
+			again.
+
+			Objects larger than 5 GiB are always uploaded with a multipart upload when this is set, and
+			batches are only split into several objects above 5 TiB, the largest object S3 allows, rather
+			than 5 GiB.
+
+			By default, all objects are uploaded with a single request.
+			"""
+		required: false
+		type: uint: {
+			examples: [104857600]
+			unit: "bytes"
+		}
+	}
+	on_empty_encode: {
+		description: """
+			The behavior when the serializer encodes an event to zero bytes.
+
+			This happens, for example, with metrics which have no value the codec can represent.
+			"""
+		required: false
+		type: string: {
+			default: "keep"
+			enum: {
+				drop: "Drop the event, counting it as discarded."
+				keep: """
+					Keep the event, writing an empty frame for it, such as an empty line with
+					newline-delimited framing.
+					"""
+			}
+		}
+	}
+	on_key_collision: {
+		description: """
+			The strategy to use when the key of an object is the same as the key of another object built by
+			this sink with the same timestamp.
+
+			Objects of different partitions can end up with the same key when it doesn't include a UUID or
+			sequence number, such as when their key prefixes only differ by case with `lowercase_keys`, or
+			once hashed by `on_key_too_long`. Keys are only compared with those of objects built since the
+			formatted `filename_time_format` last changed, and not with objects built before Vector
+			restarted or by other instances of the sink.
+			"""
+		required: false
+		type: string: {
+			default: "overwrite"
+			enum: {
+				append_counter: "Append a counter to the filename, such as `-1`, until the key is unique."
+				error:          "Reject the object, and log an error naming the colliding key."
+				overwrite: """
+					Upload the object, overwriting the object with the same key.
+
+					Keys are not tracked.
+					"""
+			}
+		}
+	}
+	on_key_too_long: {
+		description: "The strategy to use for object keys longer than `max_key_length`."
+		required:    false
+		type: string: {
+			default: "error"
+			enum: {
+				error:       "Reject the object, and log an error naming the oversized key."
+				hash_prefix: "Replace the key prefix with a hash of it, keeping the filename and extension."
+				truncate: """
+					Truncate the key, replacing its end with a hash of the full key.
+
+					The extension of the key is preserved, and the hash keeps truncated keys unique.
+					"""
+			}
+		}
+	}
+	on_missing_key: {
+		description: """
+			The behavior when the `key_prefix` of an event fails to render.
+
+			This happens when the event is missing a field referenced by the template.
+			"""
+		required: false
+		type: object: options: {
+			action: {
+				description: "The action to take when the `key_prefix` of an event fails to render."
+				required:    false
+				type: string: {
+					default: "drop"
+					enum: {
+						drop: "Drop the event."
+						fallback: """
+							Write the event under a fallback key prefix.
+
+							The `s3_key_prefix_fallback_events_total` counter tracks the number of events written under
+							the fallback prefix, which can be used to detect events missing fields upstream.
+							"""
+					}
+				}
+			}
+			prefix: {
+				description:   "The key prefix to write events under when their `key_prefix` fails to render."
+				relevant_when: "action = \"fallback\""
+				required:      true
+				type: string: examples: ["_unpartitioned/"]
+			}
+		}
+	}
+	on_persistent_error: {
+		description: "The behavior when requests keep failing once retries are exhausted."
+		required:    false
+		type: object: options: {
+			action: {
+				description: "The action to take when requests keep failing once retries are exhausted."
+				required:    false
+				type: string: {
+					default: "degrade"
+					enum: {
+						degrade: """
+							Keep accepting events, dropping the events of each failed request.
+
+							Dropped events are counted by the `component_discarded_events_total` counter.
+							"""
+						fail_fast: """
+							Stop the sink with an error once consecutive requests have failed.
+
+							This stops Vector, such that the failure is noticed by whatever supervises it rather than
+							events being dropped until it is. Events still buffered or in flight when the sink stops
+							are not acknowledged, so sources supporting end-to-end acknowledgements send them again.
+							"""
+					}
+				}
+			}
+			max_consecutive_failures: {
+				description: """
+					The number of consecutive requests which must fail for the sink to stop.
+
+					Any request which succeeds resets the count.
+					"""
+				relevant_when: "action = \"fail_fast\""
+				required:      false
+				type: uint: {
+					default:  10
+					examples: [10]
+				}
+			}
+		}
+	}
+	partition_case: {
+		description: """
+			The case the values of fields rendered in the key prefix are converted to.
+
+			Only the values of the fields are converted: the literal parts of the key prefix, such as the
+			names of Hive-style partitions, and the filename keep their case.
+			"""
+		required: false
+		type: string: {
+			default: "preserve"
+			enum: {
+				lower:    "Convert the values to lowercase."
+				preserve: "Keep the case of the values."
+				upper:    "Convert the values to uppercase."
+			}
+		}
+	}
+	partition_key_cache_size: {
+		description: """
+			The number of partition keys to cache, by the values of the fields they are rendered from.
+
+			When set, the partition key of each event, rendered from `key_prefix` along with
+			`ssekms_key_id`, `storage_class_template`, `key_prefix_mapping` and `region_routing`, is
+			reused for following events sharing the values of the fields they reference, rather than
+			rendered again. This saves CPU when many events share a partition, such as logs of the same
+			service and hour.
+			Keys rendered from a timestamp are cached for each second of it, or each timestamp if they
+			render fractions of seconds.
+
+			The least recently used keys are evicted once the cache is full. By default, keys are not
+			cached.
+			"""
+		required: false
+		type: uint: examples: [1024]
+	}
+	partition_tags: {
+		description: """
+			Tags to apply to the objects of partitions whose key prefix starts with a given prefix.
+
+			This keeps tags such as a governance classification in sync with the partition objects are
+			written under, such as a segment added by `key_prefix_mapping`. The tags of every entry
+			whose prefix matches the rendered key prefix are applied, in order, such that later entries
+			take precedence over earlier ones, and all of them over the tags set in `tags`.
+			"""
+		required: false
+		type: array: {
+			default: []
+			examples: [[{
+				prefix: "pii/"
+				tags: classification: "pii"
+			}]]
+			items: type: object: options: {
+				prefix: {
+					description: "The prefix of the rendered key prefix of the partitions to tag."
+					required:    true
+					type: string: {}
+				}
+				tags: {
+					description: "The tags to apply to the objects of the partitions."
+					required:    true
+					type: object: options: "*": {
+						description: "A single tag."
+						required:    true
+						type: string: {}
+					}
+				}
+			}
+		}
+	}
+	payload_signing: {
+		description: "How the payload of uploads is signed."
+		required:    false
+		type: string: {
+			default: "signed"
+			enum: {
+				signed: "The SHA-256 hash of the payload is included in the signature of the request."
+				unsigned: """
+					The payload is not included in the signature of the request, saving hashing it.
+
+					This saves a pass over the payload before each upload, but the signature no longer
+					protects the payload from being modified in transit. Its integrity then relies on TLS,
+					which must be used, and on the `Content-MD5` header sent with each object.
+					"""
+			}
+		}
+	}
+	prefix_time_format: {
+		description: """
+			The timestamp format for a date partition between the key prefix and the filename.
+
+			When set, the time the filename is formatted from is also formatted with this format, and
+			inserted between the rendered `key_prefix` and the filename, such that a format of
+			`year=%Y/month=%m/day=%d/` with a `filename_time_format` of `%H%M%S` results in object keys
+			such as `year=2024/month=01/day=15/143022`. Unlike specifiers in `key_prefix`, which are
+			rendered from the timestamps of events, the date partition is always consistent with the
+			filename, following `filename_time_source` and `timezone`.
+
+			Supports the same [`strftime`][chrono_strftime_specifiers] specifiers as
+			`filename_time_format`.
+
+			[chrono_strftime_specifiers]: https://docs.rs/chrono/latest/chrono/format/strftime/index.html#specifiers
+			"""
+		required: false
+		type: string: examples: ["year=%Y/month=%m/day=%d/"]
+	}
+	preserve_partition_order: {
 		description: """
-			Grants `READ_ACP` permissions on the created objects to the named [grantee].
+			Whether or not to upload the objects of each partition in the order they are built.
 
-			This allows the grantee to read the ACL on the created objects.
+			When set, an object is only uploaded once the previous object of its partition has been
+			uploaded, including any retries, while objects of different partitions are still uploaded
+			concurrently. This keeps the order of uploads consistent with the order of object keys,
+			such as with `filename_sequence_start`.
 
-			[grantee]: https://docs.aws.amazon.com/AmazonS3/latest/dev/acl-overview.html#specifying-grantee
+			Objects waiting for the previous object of their partition count towards
+			`request.concurrency`, and towards `request.timeout_secs` once their upload starts, such
+			that objects waiting for longer than the timeout are retried.
 			"""
 		required: false
-		type: string: examples: ["79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be", "person@email.com", "http://acs.amazonaws.com/groups/global/AllUsers"]
+		type: bool: default: false
 	}
-	grant_write_acp: {
+	profile_startup: {
 		description: """
-			Grants `WRITE_ACP` permissions on the created objects to the named [grantee].
-
-			This allows the grantee to modify the ACL on the created objects.
+			Whether or not to profile the encoding and compression of the first events.
 
-			[grantee]: https://docs.aws.amazon.com/AmazonS3/latest/dev/acl-overview.html#specifying-grantee
+			Once `profile_startup_events` events have been received, they are encoded and compressed
+			as a single object would be, and the achieved throughput and compression ratio are logged.
+			The events are sent on as usual without waiting for the profile.
 			"""
 		required: false
-		type: string: examples: ["79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be", "person@email.com", "http://acs.amazonaws.com/groups/global/AllUsers"]
+		type: bool: default: false
 	}
-	key_prefix: {
+	profile_startup_events: {
+		description: "The number of events to profile when `profile_startup` is enabled."
+		required:    false
+		type: uint: {
+			default: 10000
+			unit:    "events"
+		}
+	}
+	range_partitioning: {
 		description: """
-			A prefix to apply to all object keys.
+			Partitioning of events by ranges of the value of a numeric field.
 
-			Prefixes are useful for partitioning objects, such as by creating an object key that
-			stores objects under a particular directory. If using a prefix for this purpose, it must end
-			in `/` to act as a directory path. A trailing `/` is **not** automatically added.
+			Events are partitioned under the label of the first range their value falls in, followed by a
+			`/`, preceding the segment of `key_prefix_mapping` and the rendered `key_prefix`, such that an
+			object key of `date=2022-07-18/1658176486` becomes `100-500/date=2022-07-18/1658176486`. Unlike
+			rendering the value itself, this keeps a small, stable set of prefixes, which downstream
+			queries can select ranges of values by.
 			"""
 		required: false
-		type: string: {
-			default: "date=%F"
-			examples: ["date=%F/hour=%H", "year=%Y/month=%m/day=%d", "application_id={{ application_id }}/date=%F"]
-			syntax: "template"
+		type: object: options: {
+			default: {
+				description: """
+					The label of events whose value falls in none of the ranges, or which have no integer or
+					float value for the field.
+					"""
+				required: false
+				type: string: default: "other"
+			}
+			field: {
+				description: "The field holding the value of log events."
+				required:    true
+				type: string: examples: ["latency_ms"]
+			}
+			ranges: {
+				description: "The ranges of values, and their labels."
+				required:    true
+				type: array: {
+					examples: [[{
+						label: "0-100"
+						max:   100.0
+						min:   0.0
+					}, {
+						label: "100-500"
+						max:   500.0
+						min:   100.0
+					}, {
+						label: "500+"
+						min:   500.0
+					}]]
+					items: type: object: options: {
+						label: {
+							description: "The label of the events in the range."
+							required:    true
+							type: string: {}
+						}
+						max: {
+							description: """
+								The upper bound of the range, exclusive.
+
+								By default, the range is unbounded above.
+								"""
+							required: false
+							type: float: {}
+						}
+						min: {
+							description: """
+								The lower bound of the range, inclusive.
+
+								By default, the range is unbounded below.
+								"""
+							required: false
+							type: float: {}
+						}
+					}
+				}
+			}
 		}
 	}
 	region: {
@@ -741,6 +2142,57 @@ base: components: sinks: aws_s3: configuration: {
 		required: false
 		type: string: examples: ["us-east-1"]
 	}
+	region_routing: {
+		description: """
+			Routing of events to buckets in several regions, by a value rendered from each event.
+
+			This keeps events in the bucket of their region, such as for data residency. Events are
+			partitioned by the rendered value, so that objects never mix events bound for different
+			targets, and each object is uploaded to the region of its target by the same client. Objects
+			are only written to the buckets of `targets`, never to `bucket`, and `auto_region` has no
+			effect.
+			"""
+		required: false
+		type: object: options: {
+			source: {
+				description: """
+					The template rendering the value selecting the target of each event.
+
+					Events for which it fails to render are dropped.
+					"""
+				required: true
+				type: string: {
+					examples: ["{{ residency }}"]
+					syntax: "template"
+				}
+			}
+			targets: {
+				description: """
+					The target of each rendered value.
+
+					Batches whose value has no target are rejected rather than written elsewhere, with an error
+					which doesn't include the value.
+					"""
+				required: true
+				type: object: options: "*": {
+					description: "The target of the value."
+					required:    true
+					type: object: options: {
+						bucket: {
+							description: "The S3 bucket name."
+							required:    true
+							type: string: examples: ["my-eu-bucket"]
+						}
+						region: {
+							description: "The AWS region of the bucket."
+							required:    true
+							type: string: examples: ["eu-west-1"]
+						}
+					}
+				}
+			}
+		}
+	}
 	request: {
 		description: """
 			Middleware settings for outbound requests.
@@ -927,6 +2379,55 @@ base: components: sinks: aws_s3: configuration: {
 			}
 		}
 	}
+	retention: {
+		description: """
+			Limits on the objects kept under each key prefix, enforced by deleting the oldest of them.
+
+			**This deletes objects.** Once an object is uploaded, the objects directly under its key prefix,
+			up to the last `/` of its key, are listed, and the oldest of them, by their last modification
+			time, are deleted until the rest are within the limits. The uploaded object itself is never
+			deleted. This keeps a rolling buffer of the latest objects in S3 without lifecycle rules.
+
+			Every object under the prefix is counted and may be deleted, including objects written by other
+			writers, so the key prefix must be dedicated to the sink. As companion objects are written under
+			the same prefix, this can't be combined with `write_object_metadata`, `bloom_filter`,
+			`write_gzip_index`, `uncommitted_key_suffix`, or a `summary_object` without a `key`.
+
+			This requires the `s3:ListBucket` and `s3:DeleteObject` permissions, and costs a `ListObjectsV2`
+			request per uploaded object. Objects which fail to be deleted are retried on the next upload to
+			the same prefix.
+			"""
+		required: false
+		type: object: options: {
+			max_bytes: {
+				description: "The maximum total size of the objects to keep under each key prefix, in bytes."
+				required:    false
+				type: uint: {
+					examples: [10737418240]
+					unit: "bytes"
+				}
+			}
+			max_objects: {
+				description: "The maximum number of objects to keep under each key prefix."
+				required:    false
+				type: uint: examples: [100]
+			}
+		}
+	}
+	retry_extra_retryable_codes: {
+		description: """
+			Error codes to retry requests on, on top of the ones retried by default.
+
+			S3-compatible stores, such as MinIO or Wasabi, may respond with non-standard error codes
+			when throttling requests. Requests failing with an error response whose error code is
+			listed, or whose error message or body contains one of the listed values, are retried.
+			"""
+		required: false
+		type: array: {
+			default: []
+			items: type: string: examples: ["SlowDownRead", "XMinioServerNotInitialized"]
+		}
+	}
 	server_side_encryption: {
 		description: """
 			AWS S3 Server-Side Encryption algorithms.
@@ -949,6 +2450,105 @@ base: components: sinks: aws_s3: configuration: {
 				"""
 		}
 	}
+	sidecar_compression: {
+		description: """
+			The compression of the companion objects written by `write_object_metadata` and
+			`bloom_filter`.
+
+			Companion objects are compressed independently of `compression`, as some catalog tools
+			expect them uncompressed, and are given the extension and `Content-Encoding` of their
+			compression, such as `.meta.json.gz`. By default, they are uncompressed. Indexes written by
+			`write_gzip_index` are always uncompressed, as `bgzip` reads them as they are.
+			"""
+		required: false
+		type: string: {
+			default: "none"
+			enum: {
+				gzip: """
+					[Gzip][gzip] compression.
+
+					[gzip]: https://www.gzip.org/
+					"""
+				none: "No compression."
+				snappy: """
+					[Snappy][snappy] compression.
+
+					[snappy]: https://github.com/google/snappy/blob/main/docs/README.md
+					"""
+				zlib: """
+					[Zlib][zlib] compression.
+
+					[zlib]: https://zlib.net/
+					"""
+				zstd: """
+					[Zstandard][zstd] compression.
+
+					[zstd]: https://facebook.github.io/zstd/
+					"""
+			}
+		}
+	}
+	spill: {
+		description: """
+			Spills objects which fail to upload, once their retries are exhausted, to a local directory.
+
+			Spilled objects are reported as delivered, rather than being dropped or failing the pipeline,
+			and are uploaded again from the directory every retry interval, starting when the sink starts.
+			Objects are removed from the directory once uploaded. When the directory is full, its oldest
+			objects are dropped to make room for new ones; objects larger than the directory are not
+			spilled. Objects rejected without being uploaded, such as for an invalid key, are not spilled
+			either.
+
+			Spilled objects are uploaded without their companion metadata, bloom filter, or index objects.
+			"""
+		required: false
+		type: object: options: {
+			max_bytes: {
+				description: "The maximum total size of the spilled objects, in bytes."
+				required:    false
+				type: uint: {
+					default: 1073741824
+					unit:    "bytes"
+				}
+			}
+			path: {
+				description: """
+					The local directory to spill objects to.
+
+					The directory must not be shared with other sinks, or be the same as `staging_dir`.
+					"""
+				required: true
+				type: string: examples: ["/var/lib/vector/s3_spill"]
+			}
+			retry_interval_secs: {
+				description: "How often to upload the spilled objects again, in seconds."
+				required:    false
+				type: uint: {
+					default: 300
+					unit:    "seconds"
+				}
+			}
+		}
+	}
+	split_on_change: {
+		description: """
+			A template used to split objects whenever its rendered value changes.
+
+			When set, the template is rendered for each event and compared with the value rendered for
+			the previous event of the same partition. Whenever the value changes, the batch for that
+			partition is closed and a new one is started, such that each object contains a contiguous
+			run of events sharing the same value, such as a session ID.
+
+			Unlike partitioning with `key_prefix`, events sharing a value are not grouped together
+			if other values are interleaved between them. Batch size limits still apply, so a single run
+			may span multiple objects.
+			"""
+		required: false
+		type: string: {
+			examples: ["{{ session_id }}"]
+			syntax: "template"
+		}
+	}
 	ssekms_key_id: {
 		description: """
 			Specifies the ID of the AWS Key Management Service (AWS KMS) symmetrical customer managed
@@ -964,6 +2564,38 @@ base: components: sinks: aws_s3: configuration: {
 			syntax: "template"
 		}
 	}
+	staging_dir: {
+		description: """
+			A local directory to write objects to before uploading them.
+
+			When set, each object is written to this directory before it is uploaded, and removed from
+			it once uploaded. Objects left in the directory, such as when Vector crashes or an upload
+			fails permanently, are uploaded when the sink next starts, before any new events are
+			processed. This makes objects durable once they are built, but events which are still
+			being batched are not staged.
+
+			The directory must not be shared with other sinks.
+			"""
+		required: false
+		type: string: examples: ["/var/lib/vector/s3_staging"]
+	}
+	startup_warmup_secs: {
+		description: """
+			The period, in seconds, after the sink starts during which batches are not flushed for
+			reaching `batch.timeout_secs`.
+
+			Batches timing out during this period are held open, and keep accumulating events, until
+			it ends. This avoids writing a burst of small objects right after Vector starts, while
+			few events have arrived yet, so that the first objects are of the same size as the
+			following ones. Batch size limits, `max_age_secs`, and `flush_at_boundary_secs` still
+			apply.
+			"""
+		required: false
+		type: uint: {
+			examples: [300]
+			unit: "seconds"
+		}
+	}
 	storage_class: {
 		description: """
 			The storage class for the created objects.
@@ -987,6 +2619,66 @@ base: components: sinks: aws_s3: configuration: {
 			}
 		}
 	}
+	storage_class_template: {
+		description: """
+			A template for the storage class of the created objects.
+
+			Takes precedence over `storage_class`, and objects are partitioned by the rendered value.
+			It must render to one of the `storage_class` values. Events for which it cannot be
+			rendered, or renders to an invalid value, are written with `storage_class`.
+			"""
+		required: false
+		type: string: {
+			examples: ["{{ storage_class }}"]
+			syntax: "template"
+		}
+	}
+	summary_object: {
+		description: """
+			Configuration for writing a summary object aggregating the events of each window.
+
+			The events of each object are added to the running aggregate of its window, which is written,
+			once the object is uploaded, to the summary object of the window, overwriting its previous
+			version. Windows are identified by the key of their summary object, such that a key rendered
+			from the date gives an aggregate per day. This lets dashboards read a single object rather than
+			every object of a window.
+
+			Summary objects are JSON objects holding the time they were `updated_at`, the `count` of events
+			of the window, and, under `fields`, the `count`, `sum`, `min`, and `max` of the numeric values
+			of each aggregated field. Running aggregates start from zero when Vector starts, and only those
+			of the 1024 most recently updated windows are kept. As objects are uploaded concurrently, a
+			summary object may be briefly overwritten by an earlier version.
+			"""
+		required: false
+		type: object: options: {
+			fields: {
+				description: """
+					The fields of log events to aggregate.
+
+					Values of the fields which are not integers or floats are ignored.
+					"""
+				required: false
+				type: array: {
+					default: []
+					items: type: string: examples: ["duration_ms"]
+				}
+			}
+			key: {
+				description: """
+					The template of the key of the summary object of each object, rendered with its first
+					event.
+
+					By default, summary objects are written as `_summary.json` under the key prefix of the
+					objects, giving an aggregate per partition.
+					"""
+				required: false
+				type: string: {
+					examples: ["summaries/date=%F/_summary.json"]
+					syntax: "template"
+				}
+			}
+		}
+	}
 	tags: {
 		description: "The tag-set for the object."
 		required:    false
@@ -1108,4 +2800,174 @@ base: components: sinks: aws_s3: configuration: {
 			}
 		}
 	}
+	uncommitted_key_prefix: {
+		description: """
+			A prefix to upload objects under until they are committed.
+
+			When set, objects are first uploaded under this prefix, such that an object key of
+			`date=2022-07-18/1658176486.log.gz` is uploaded as
+			`_uncommitted/date=2022-07-18/1658176486.log.gz`. Once uploaded, the object is copied to its
+			key and deleted from under the prefix, and only then are its events acknowledged. Consumers
+			of the other keys never see objects which failed to upload.
+
+			This requires the `s3:GetObject` and `s3:DeleteObject` permissions on the uncommitted
+			objects, and objects larger than 5 GB cannot be copied. Objects left under the prefix by
+			failed uploads are not cleaned up, which can be done with a lifecycle rule.
+			"""
+		required: false
+		type: string: examples: ["_uncommitted/"]
+	}
+	uncommitted_key_suffix: {
+		description: """
+			A suffix to apply to object keys until they are committed.
+
+			When set, objects are first uploaded with this suffix, such that an object key of
+			`date=2022-07-18/1658176486.log.gz` is uploaded as `date=2022-07-18/1658176486.log.gz.tmp`,
+			then committed like with `uncommitted_key_prefix`, with which it can be combined. Consumers
+			watching the prefix of the objects can then skip the keys ending with the suffix, and only
+			ever read complete objects.
+
+			This requires the same permissions as `uncommitted_key_prefix`.
+			"""
+		required: false
+		type: string: examples: [".tmp"]
+	}
+	unique_suffix: {
+		description: """
+			When to append a UUID v4 token to the end of the object key.
+
+			Takes precedence over `filename_append_uuid` when set.
+			"""
+		required: false
+		type: string: enum: {
+			always: "Append a UUID to every object key."
+			never:  "Never append a UUID."
+			when_needed: """
+				Append a UUID only when the object key would otherwise be the same as the key of an
+				object already built by this sink with the same timestamp.
+
+				This keeps object keys predictable, such that a partition flushing at most one object per
+				timestamp overwrites the same object when events are sent again, while objects sharing a
+				partition and timestamp still get distinct keys. A UUID is never needed when
+				`filename_sequence_start` is set. Objects built before Vector restarted, or by other
+				instances of the sink, are not taken into account.
+				"""
+		}
+	}
+	upload_slo_secs: {
+		description: """
+			The maximum time for an object to be uploaded after its first event is batched, in seconds.
+
+			The time between the first event of each object entering its batch and the object being
+			uploaded is always recorded in the `aws_s3_object_upload_latency_seconds` histogram. When
+			set, objects taking longer than this are also counted by the `aws_s3_slo_violations_total`
+			counter.
+			"""
+		required: false
+		type: uint: {
+			examples: [300]
+			unit: "seconds"
+		}
+	}
+	user_agent_app_name: {
+		description: """
+			An application name to include in the `User-Agent` header of requests sent to S3.
+
+			This allows attributing requests to a specific Vector deployment in AWS CloudTrail and S3
+			server access logs, where it appears as `app/<name>` within the user agent.
+
+			The placeholders `{hostname}` and `{version}` are replaced with the host name of the system
+			and the version of Vector, respectively. Once rendered, the name may only contain
+			alphanumeric characters and any of the characters ``!#$%&'*+-.^_`|~``.
+			"""
+		required: false
+		type: string: examples: ["vector-{hostname}", "my-deployment-{version}"]
+	}
+	verify_event_counts: {
+		description: """
+			Whether or not to check the event count of each object against the records encoded in it.
+
+			When set, an error is logged for each object whose number of encoded records differs from
+			the number of events it is accounted for, which would otherwise skew the reported event
+			counts. This is meant to catch accounting regressions during development and canary
+			deployments.
+			"""
+		required: false
+		type: bool: default: false
+	}
+	verify_uploads: {
+		description: """
+			Configuration for reading back uploaded objects to verify them.
+
+			Once uploaded, objects are requested with `HeadObject`, and their size and ETag are compared to
+			the uploaded body, catching objects which S3 stored differently than they were sent. The ETag
+			is not compared for objects encrypted with AWS KMS, as it is not their MD5 digest.
+
+			This requires the `s3:GetObject` permission on the objects, and costs a request per verified
+			object, so only a sample of the objects can be verified. Failed verifications are counted by
+			the `aws_s3_upload_verification_failures_total` counter.
+			"""
+		required: false
+		type: object: options: {
+			on_mismatch: {
+				description: "The behavior when an uploaded object does not match its upload once read back."
+				required:    false
+				type: string: {
+					default: "retry"
+					enum: {
+						error: "Fail the request without retrying it, rejecting its events."
+						retry: "Upload the object again, according to the retry settings of the sink."
+					}
+				}
+			}
+			sample_rate: {
+				description: "Verify one in this many uploaded objects."
+				required:    false
+				type: uint: {
+					default: 1
+					examples: [100]
+				}
+			}
+		}
+	}
+	write_build_metadata: {
+		description: """
+			Whether or not to record the build of Vector which wrote each object in its metadata.
+
+			When set, the `x-amz-meta-vector-version` and `x-amz-meta-vector-build` user-defined metadata
+			of each object are set to the version of Vector and the short hash of the Git commit it was
+			built from, identifying the exact binary which encoded the object, such as to reproduce its
+			encoding when investigating an anomaly. Companion objects are not affected.
+			"""
+		required: false
+		type: bool: default: false
+	}
+	write_gzip_index: {
+		description: """
+			Whether or not to write a `bgzip` index for each object, for random access into it.
+
+			When set, objects are compressed in the blocked gzip format (BGZF) used by `bgzip`, which
+			any gzip decoder can read, and once an object is uploaded, the index of its blocks is
+			written to the same key with a `.gzi` suffix. Readers such as `bgzip` or `htslib` can then
+			decompress an object from a given uncompressed offset, without decompressing it from the
+			start. Objects left uncompressed by `compression_min_bytes` have no index.
+
+			Requires `compression` to be set to `gzip`.
+			"""
+		required: false
+		type: bool: default: false
+	}
+	write_object_metadata: {
+		description: """
+			Whether or not to write a companion metadata object for each object.
+
+			Once an object is uploaded, a JSON object is written to the same key with a `.meta.json`
+			suffix. It contains the number of events, the uncompressed and compressed sizes of the
+			object in bytes, the earliest and latest timestamps of the events, the rendered key
+			prefix, the codec, and the ETag and version ID of the object. Unlike object metadata
+			headers, this survives tools which strip S3 metadata.
+			"""
+		required: false
+		type: bool: default: false
+	}
 }
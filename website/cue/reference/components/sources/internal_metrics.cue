@@ -129,6 +129,12 @@ components: sources: internal_metrics: {
 			default_namespace: "vector"
 			tags:              _component_tags
 		}
+		adaptive_concurrency_current_limit: {
+			description:       "The concurrency limit that the adaptive concurrency feature has decided on for the last window."
+			type:              "gauge"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
 		adaptive_concurrency_in_flight: {
 			description:       "The number of outbound requests currently awaiting a response."
 			type:              "histogram"
@@ -718,6 +724,47 @@ components: sources: internal_metrics: {
 			default_namespace: "vector"
 			tags:              _component_tags
 		}
+		s3_key_prefix_fallback_events_total: {
+			description:       "The total number of events written under the fallback key prefix because their key prefix failed to render."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		aws_s3_clock_skew_detected_total: {
+			description:       "The total number of objects whose latest event timestamp differed from the system clock by more than `clock_skew_threshold_secs` when they were named, in the S3 sink."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		aws_s3_compression_selected_total: {
+			description:       "The total number of objects the S3 sink compressed with each compression, when trying `compression_candidates`."
+			type:              "counter"
+			default_namespace: "vector"
+			tags: _component_tags & {
+				compression: {
+					description: "The compression the object was compressed with, such as `zstd(3)`."
+					required:    true
+				}
+			}
+		}
+		aws_s3_distinct_partition_keys: {
+			description:       "The number of distinct partition keys the S3 sink rendered within the last minute."
+			type:              "gauge"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		aws_s3_object_upload_latency_seconds: {
+			description:       "The time between the first event of an object entering its batch and the object being uploaded to S3."
+			type:              "histogram"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
+		aws_s3_slo_violations_total: {
+			description:       "The total number of objects uploaded to S3 later than the configured `upload_slo_secs` after their first event."
+			type:              "counter"
+			default_namespace: "vector"
+			tags:              _component_tags
+		}
 		sqs_message_delete_succeeded_total: {
 			description:       "The total number of successful deletions of SQS messages."
 			type:              "counter"